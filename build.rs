@@ -0,0 +1,7 @@
+fn main() {
+    // The gRPC service is generated from its proto definitions, which needs
+    // `protoc` installed.
+    #[cfg(feature = "grpc")]
+    tonic_build::compile_protos("proto/triplicata.proto")
+        .expect("could not generate the gRPC service");
+}
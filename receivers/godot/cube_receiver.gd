@@ -0,0 +1,79 @@
+# Receives cube events from triplicata's UDP bridge. Add this node to a scene,
+# set `udp: Some("127.0.0.1:9000")` in triplicata's config and connect to the
+# signals.
+extends Node
+
+signal connected
+signal disconnected
+## `notation` is like "R'", `face` is U R F D L B as 0 to 5 and `direction`
+## is 1 clockwise or -1 counterclockwise.
+signal turned(notation: String, face: int, direction: int)
+## In the cube's own axes, map them to the scene as needed.
+signal oriented(orientation: Quaternion)
+signal battery_changed(percent: int)
+
+@export var port := 9000
+
+var _peer := PacketPeerUDP.new()
+
+
+func _ready() -> void:
+	var error := _peer.bind(port, "127.0.0.1")
+	if error != OK:
+		push_error("Could not listen for the cube on port %d: %s" % [port, error_string(error)])
+
+
+func _process(_delta: float) -> void:
+	while _peer.get_available_packet_count() > 0:
+		_handle(_peer.get_packet())
+
+
+func _handle(packet: PackedByteArray) -> void:
+	var reader := _OscReader.new(packet)
+	var address := reader.read_string()
+	var tags := reader.read_string()
+
+	match [address, tags]:
+		["/cube/connected", ","]:
+			connected.emit()
+		["/cube/disconnected", ","]:
+			disconnected.emit()
+		["/cube/move", ",sii"]:
+			turned.emit(reader.read_string(), reader.read_int(), reader.read_int())
+		["/cube/orientation", ",ffff"]:
+			var w := reader.read_float()
+			var x := reader.read_float()
+			var y := reader.read_float()
+			var z := reader.read_float()
+			oriented.emit(Quaternion(x, y, z, w))
+		["/cube/battery", ",i"]:
+			battery_changed.emit(reader.read_int())
+
+
+## Reads the big endian, four byte aligned values of an OSC message.
+class _OscReader:
+	var _packet: PackedByteArray
+	var _offset := 0
+
+	func _init(packet: PackedByteArray) -> void:
+		_packet = packet
+
+	func read_string() -> String:
+		var end := _packet.find(0, _offset)
+		if end == -1:
+			end = _packet.size()
+		var value := _packet.slice(_offset, end).get_string_from_utf8()
+		_offset = (end + 4) & ~3
+		return value
+
+	func read_int() -> int:
+		var bytes := _packet.slice(_offset, _offset + 4)
+		bytes.reverse()
+		_offset += 4
+		return bytes.decode_s32(0)
+
+	func read_float() -> float:
+		var bytes := _packet.slice(_offset, _offset + 4)
+		bytes.reverse()
+		_offset += 4
+		return bytes.decode_float(0)
@@ -0,0 +1,126 @@
+// Receives cube events from triplicata's UDP bridge. Add this component to a
+// GameObject, set `udp: Some("127.0.0.1:9000")` in triplicata's config and
+// subscribe to the events, which are raised on the main thread.
+using System;
+using System.Collections.Concurrent;
+using System.Net;
+using System.Net.Sockets;
+using System.Text;
+using UnityEngine;
+
+public class CubeReceiver : MonoBehaviour
+{
+    public int port = 9000;
+
+    public event Action Connected;
+    public event Action Disconnected;
+    /// <summary>
+    /// The notation like "R'", the face as U R F D L B from 0 to 5 and the
+    /// direction as 1 clockwise or -1 counterclockwise.
+    /// </summary>
+    public event Action<string, int, int> Turned;
+    public event Action<Quaternion> Oriented;
+    public event Action<int> BatteryChanged;
+
+    private UdpClient client;
+    private readonly ConcurrentQueue<byte[]> packets = new ConcurrentQueue<byte[]>();
+
+    private void OnEnable()
+    {
+        client = new UdpClient(new IPEndPoint(IPAddress.Loopback, port));
+        client.BeginReceive(Receive, null);
+    }
+
+    private void OnDisable()
+    {
+        client?.Close();
+        client = null;
+    }
+
+    private void Receive(IAsyncResult result)
+    {
+        try
+        {
+            IPEndPoint sender = null;
+            packets.Enqueue(client.EndReceive(result, ref sender));
+            client.BeginReceive(Receive, null);
+        }
+        catch (ObjectDisposedException)
+        {
+        }
+    }
+
+    private void Update()
+    {
+        while (packets.TryDequeue(out var packet))
+        {
+            Handle(new OscReader(packet));
+        }
+    }
+
+    private void Handle(OscReader reader)
+    {
+        switch (reader.ReadString(), reader.ReadString())
+        {
+            case ("/cube/connected", ","):
+                Connected?.Invoke();
+                break;
+            case ("/cube/disconnected", ","):
+                Disconnected?.Invoke();
+                break;
+            case ("/cube/move", ",sii"):
+                Turned?.Invoke(reader.ReadString(), reader.ReadInt(), reader.ReadInt());
+                break;
+            case ("/cube/orientation", ",ffff"):
+                var w = reader.ReadFloat();
+                var x = reader.ReadFloat();
+                var y = reader.ReadFloat();
+                var z = reader.ReadFloat();
+                // In the cube's own axes, map them to the scene as needed.
+                Oriented?.Invoke(new Quaternion(x, y, z, w));
+                break;
+            case ("/cube/battery", ",i"):
+                BatteryChanged?.Invoke(reader.ReadInt());
+                break;
+        }
+    }
+
+    /// <summary>
+    /// Reads the big endian, four byte aligned values of an OSC message.
+    /// </summary>
+    private class OscReader
+    {
+        private readonly byte[] packet;
+        private int offset;
+
+        public OscReader(byte[] packet)
+        {
+            this.packet = packet;
+        }
+
+        public string ReadString()
+        {
+            var end = Array.IndexOf(packet, (byte)0, offset);
+            if (end == -1)
+            {
+                end = packet.Length;
+            }
+            var value = Encoding.UTF8.GetString(packet, offset, end - offset);
+            offset = (end + 4) & ~3;
+            return value;
+        }
+
+        public int ReadInt()
+        {
+            var value = (packet[offset] << 24) | (packet[offset + 1] << 16)
+                | (packet[offset + 2] << 8) | packet[offset + 3];
+            offset += 4;
+            return value;
+        }
+
+        public float ReadFloat()
+        {
+            return BitConverter.Int32BitsToSingle(ReadInt());
+        }
+    }
+}
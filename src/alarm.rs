@@ -0,0 +1,84 @@
+//! Alarms that are dismissed by solving the cube: once the alarm sounds it
+//! only stops after the cube is scrambled with a generated scramble and then
+//! solved again.
+
+use rand::Rng;
+
+use crate::{
+    algorithm::Algorithm,
+    cube::{CubeState, Face, Move},
+    error::NotationError,
+    scramble,
+};
+
+pub const SCRAMBLE_LENGTH: usize = 20;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Stage {
+    /// Waiting for the cube to reach the scrambled state.
+    Scrambling,
+    Solving,
+    Dismissed,
+}
+
+/// Tracks the cube through the scramble and the solve that dismiss an alarm.
+#[derive(Debug, Clone)]
+pub struct Dismissal {
+    scramble: Algorithm,
+    scrambled: CubeState,
+    state: CubeState,
+    stage: Stage,
+}
+
+impl Dismissal {
+    pub fn random(rng: &mut impl Rng) -> Self {
+        Self::new(scramble::random_moves(SCRAMBLE_LENGTH, &Face::ALL, rng))
+            .expect("random scrambles only turn faces")
+    }
+
+    /// The scramble is applied from solved, so a cube left unsolved has to be
+    /// solved before it is scrambled. Any moves reaching the scrambled state
+    /// count, so a mistake while scrambling can be undone.
+    pub fn new(scramble: Algorithm) -> Result<Self, NotationError> {
+        let mut scrambled = CubeState::SOLVED;
+        for m in scramble.to_moves()? {
+            scrambled.apply(m);
+        }
+
+        Ok(Self {
+            scramble,
+            scrambled,
+            state: CubeState::SOLVED,
+            stage: Stage::Scrambling,
+        })
+    }
+
+    pub fn scramble(&self) -> &Algorithm {
+        &self.scramble
+    }
+
+    pub fn stage(&self) -> Stage {
+        self.stage
+    }
+
+    pub fn apply(&mut self, m: Move) -> Stage {
+        self.state.apply(m);
+        self.advance()
+    }
+
+    /// Replaces the tracked state with one the cube reported.
+    pub fn sync(&mut self, state: CubeState) -> Stage {
+        self.state = state;
+        self.advance()
+    }
+
+    fn advance(&mut self) -> Stage {
+        self.stage = match self.stage {
+            Stage::Scrambling if self.state == self.scrambled => Stage::Solving,
+            Stage::Solving if self.state.is_solved() => Stage::Dismissed,
+            stage => stage,
+        };
+
+        self.stage
+    }
+}
@@ -0,0 +1,302 @@
+use alloc::{
+    format,
+    string::{String, ToString},
+    vec::Vec,
+};
+use core::{fmt, ops::Add, str::FromStr};
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::{
+    cube::{Direction, Face, Move},
+    error::NotationError,
+};
+
+#[derive(PartialEq, Eq, Clone, Copy, Debug, Hash)]
+pub enum Slice {
+    M,
+    E,
+    S,
+}
+
+#[derive(PartialEq, Eq, Clone, Copy, Debug, Hash)]
+pub enum Axis {
+    X,
+    Y,
+    Z,
+}
+
+#[derive(PartialEq, Eq, Clone, Copy, Debug, Hash)]
+pub enum Layer {
+    Face(Face),
+    /// A wide turn of the outer `depth` layers on the given face.
+    Wide(Face, u8),
+    Slice(Slice),
+    Rotation(Axis),
+}
+
+/// A single token of an algorithm. `amount` is the number of clockwise
+/// quarter turns, negative for counter clockwise turns.
+#[derive(PartialEq, Eq, Clone, Copy, Debug, Hash)]
+pub struct Turn {
+    pub layer: Layer,
+    pub amount: i8,
+}
+
+#[derive(PartialEq, Eq, Clone, Debug, Default, Hash)]
+pub struct Algorithm(pub Vec<Turn>);
+
+impl Turn {
+    pub fn inverse(self) -> Self {
+        Self {
+            layer: self.layer,
+            amount: -self.amount,
+        }
+    }
+
+    fn amount_suffix(self) -> String {
+        match self.amount {
+            1 => String::new(),
+            -1 => "'".to_string(),
+            amount if amount < 0 => format!("{}'", -amount),
+            amount => amount.to_string(),
+        }
+    }
+
+    pub fn to_sign(self) -> String {
+        match self.layer {
+            Layer::Wide(face, 2) => format!(
+                "{}{}",
+                face.to_string().to_lowercase(),
+                self.amount_suffix()
+            ),
+            Layer::Wide(face, depth) => format!(
+                "{depth}{}{}",
+                face.to_string().to_lowercase(),
+                self.amount_suffix()
+            ),
+            _ => self.to_string(),
+        }
+    }
+}
+
+impl From<Move> for Turn {
+    fn from(value: Move) -> Self {
+        Self {
+            layer: Layer::Face(value.face()),
+            amount: match value.direction() {
+                Direction::Clockwise => 1,
+                Direction::CounterClockwise => -1,
+            },
+        }
+    }
+}
+
+impl fmt::Display for Turn {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.layer {
+            Layer::Face(face) => write!(f, "{face}")?,
+            Layer::Wide(face, 2) => write!(f, "{face}w")?,
+            Layer::Wide(face, depth) => write!(f, "{depth}{face}w")?,
+            Layer::Slice(slice) => write!(f, "{slice:?}")?,
+            Layer::Rotation(axis) => write!(f, "{}", format!("{axis:?}").to_lowercase())?,
+        }
+
+        f.write_str(&self.amount_suffix())
+    }
+}
+
+impl Algorithm {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub fn inverse(&self) -> Self {
+        Self(self.0.iter().rev().map(|turn| turn.inverse()).collect())
+    }
+
+    pub fn concat(&self, other: &Algorithm) -> Self {
+        Self(self.0.iter().chain(&other.0).copied().collect())
+    }
+
+    /// Expands the algorithm into the quarter turns a cube reports, failing on
+    /// wide, slice and rotation tokens which have no single face turn.
+    pub fn to_moves(&self) -> Result<Vec<Move>, NotationError> {
+        let mut moves = Vec::new();
+
+        for turn in &self.0 {
+            let Layer::Face(face) = turn.layer else {
+                return Err(NotationError::UnsupportedTurn(turn.to_string()));
+            };
+
+            let direction = if turn.amount < 0 {
+                Direction::CounterClockwise
+            } else {
+                Direction::Clockwise
+            };
+
+            moves.extend(core::iter::repeat_n(
+                Move::new(face, direction),
+                turn.amount.unsigned_abs() as usize,
+            ));
+        }
+
+        Ok(moves)
+    }
+
+    pub fn to_sign(&self) -> String {
+        self.0
+            .iter()
+            .map(|turn| turn.to_sign())
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+}
+
+impl From<Vec<Move>> for Algorithm {
+    fn from(value: Vec<Move>) -> Self {
+        value.into_iter().collect()
+    }
+}
+
+impl FromIterator<Move> for Algorithm {
+    fn from_iter<T: IntoIterator<Item = Move>>(iter: T) -> Self {
+        Self(iter.into_iter().map(Turn::from).collect())
+    }
+}
+
+impl FromIterator<Turn> for Algorithm {
+    fn from_iter<T: IntoIterator<Item = Turn>>(iter: T) -> Self {
+        Self(iter.into_iter().collect())
+    }
+}
+
+impl Add for Algorithm {
+    type Output = Algorithm;
+
+    fn add(mut self, rhs: Self) -> Self::Output {
+        self.0.extend(rhs.0);
+        self
+    }
+}
+
+impl fmt::Display for Algorithm {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, turn) in self.0.iter().enumerate() {
+            if i > 0 {
+                f.write_str(" ")?;
+            }
+            write!(f, "{turn}")?;
+        }
+
+        Ok(())
+    }
+}
+
+fn parse_number(chars: &mut core::iter::Peekable<core::str::Chars>) -> Option<u8> {
+    let mut number = None::<u8>;
+
+    while let Some(digit) = chars.peek().and_then(|c| c.to_digit(10)) {
+        chars.next();
+        number = Some(
+            number
+                .unwrap_or(0)
+                .saturating_mul(10)
+                .saturating_add(digit as u8),
+        );
+    }
+
+    number
+}
+
+impl FromStr for Algorithm {
+    type Err = NotationError;
+
+    /// Parses standard (`R U R' Rw2 3Fw'`) and SiGN (`RUR'r2 3f'`) notation,
+    /// including slices (`M E S`) and rotations (`x y z`).
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut turns = Vec::new();
+        let mut chars = s.chars().peekable();
+
+        loop {
+            while chars.next_if(|c| c.is_whitespace()).is_some() {}
+
+            let depth = parse_number(&mut chars);
+            if depth == Some(0) {
+                return Err(NotationError::InvalidMove(s.to_string()));
+            }
+
+            let Some(c) = chars.next() else {
+                if depth.is_some() {
+                    return Err(NotationError::InvalidMove(s.to_string()));
+                }
+                break;
+            };
+
+            let layer = match c {
+                'U' | 'R' | 'F' | 'D' | 'L' | 'B' => {
+                    let face = c.to_string().parse()?;
+                    if chars.next_if_eq(&'w').is_some() {
+                        Layer::Wide(face, depth.unwrap_or(2))
+                    } else if let Some(depth) = depth {
+                        // SiGN inner slices such as `3R` are not representable.
+                        return Err(NotationError::UnsupportedTurn(format!("{depth}{c}")));
+                    } else {
+                        Layer::Face(face)
+                    }
+                }
+                'u' | 'r' | 'f' | 'd' | 'l' | 'b' => Layer::Wide(
+                    c.to_ascii_uppercase().to_string().parse()?,
+                    depth.unwrap_or(2),
+                ),
+                'M' | 'E' | 'S' if depth.is_none() => Layer::Slice(match c {
+                    'M' => Slice::M,
+                    'E' => Slice::E,
+                    _ => Slice::S,
+                }),
+                'x' | 'y' | 'z' if depth.is_none() => Layer::Rotation(match c {
+                    'x' => Axis::X,
+                    'y' => Axis::Y,
+                    _ => Axis::Z,
+                }),
+                _ => return Err(NotationError::InvalidMove(c.to_string())),
+            };
+
+            let mut amount = i8::try_from(parse_number(&mut chars).unwrap_or(1))
+                .map_err(|_| NotationError::InvalidMove(s.to_string()))?;
+            if chars.next_if(|c| matches!(c, '\'' | '’')).is_some() {
+                amount = -amount;
+            }
+
+            if amount == 0 {
+                return Err(NotationError::InvalidMove(s.to_string()));
+            }
+
+            turns.push(Turn { layer, amount });
+        }
+
+        Ok(Self(turns))
+    }
+}
+
+impl Serialize for Algorithm {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for Algorithm {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer)?
+            .parse()
+            .map_err(serde::de::Error::custom)
+    }
+}
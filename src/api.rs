@@ -0,0 +1,255 @@
+//! A small HTTP API for Stream Deck buttons and `curl`, served on localhost:
+//!
+//! - `GET /status` answers `{"connected": true, "battery": 80, "paused": false}`.
+//! - `GET /state` answers the cube as `{"facelets": "UUUU...", "solved": true}`.
+//! - `GET /moves` answers the kept move history, oldest first, as
+//!   `[{"move": "R", "time_ms": 1718000000123}]`, or the last few with
+//!   `?count=5`. Needs `history` in the config.
+//! - `POST /binds/{name}` fires the bind with that name without turning its
+//!   trigger.
+//! - `POST /pause` pauses the binds, or resumes them if paused, answering
+//!   `{"paused": true}`.
+//!
+//! Browsers let any page send a bodiless `POST` to localhost, so requests
+//! made from pages not served from this machine are refused.
+
+use std::{
+    net::{IpAddr, SocketAddr},
+    sync::{Arc, Mutex},
+};
+
+use axum::{
+    Json, Router,
+    extract::{Path, Query, Request, State},
+    http::{StatusCode, header::ORIGIN},
+    middleware::{self, Next},
+    response::Response,
+    routing::{get, post},
+};
+use futures::{Stream, StreamExt};
+use serde::{Deserialize, Serialize};
+use tokio::{net::TcpListener, select};
+use tokio_util::sync::CancellationToken;
+use tracing::info;
+
+use crate::{
+    MoveInjector,
+    cube::{CubeEvent, CubeState},
+    error::ApiError,
+    history::MoveHistory,
+    state_machine::Pause,
+    status::CubeStatus,
+};
+
+/// What the endpoints act on.
+#[derive(Debug, Clone)]
+pub struct Api {
+    status: CubeStatus,
+    injector: MoveInjector,
+    pause: Pause,
+    history: Option<MoveHistory>,
+    /// The names of the binds, by index.
+    binds: Vec<Option<String>>,
+    state: Arc<Mutex<CubeState>>,
+}
+
+#[derive(Serialize, Debug)]
+struct StatusReply {
+    connected: bool,
+    battery: Option<u8>,
+    paused: bool,
+}
+
+#[derive(Serialize, Debug)]
+struct StateReply {
+    facelets: String,
+    solved: bool,
+}
+
+#[derive(Serialize, Debug)]
+struct MoveReply {
+    #[serde(rename = "move")]
+    m: String,
+    time_ms: u64,
+}
+
+#[derive(Serialize, Debug)]
+struct PauseReply {
+    paused: bool,
+}
+
+#[derive(Deserialize, Debug)]
+struct MovesQuery {
+    count: Option<usize>,
+}
+
+type Rejection = (StatusCode, String);
+
+impl Api {
+    pub fn new(
+        status: CubeStatus,
+        injector: MoveInjector,
+        pause: Pause,
+        binds: Vec<Option<String>>,
+    ) -> Self {
+        Self {
+            status,
+            injector,
+            pause,
+            history: None,
+            binds,
+            state: Arc::new(Mutex::new(CubeState::SOLVED)),
+        }
+    }
+
+    pub fn history(mut self, history: Option<MoveHistory>) -> Self {
+        self.history = history;
+        self
+    }
+}
+
+/// Serves the API at `http://{address}/` until `cancel` is cancelled,
+/// following the cube's state from `events`.
+pub async fn serve(
+    address: SocketAddr,
+    api: Api,
+    events: impl Stream<Item = CubeEvent> + Send + Unpin + 'static,
+    cancel: CancellationToken,
+) -> Result<(), ApiError> {
+    let listener = TcpListener::bind(address).await?;
+
+    tokio::spawn(follow_state(events, api.state.clone(), cancel.clone()));
+
+    let app = Router::new()
+        .route("/status", get(status))
+        .route("/state", get(state))
+        .route("/moves", get(moves))
+        .route("/binds/{name}", post(fire))
+        .route("/pause", post(pause))
+        .layer(middleware::from_fn(same_machine))
+        .with_state(api);
+
+    info!("Serving the API on http://{address}/");
+
+    axum::serve(listener, app)
+        .with_graceful_shutdown(cancel.cancelled_owned())
+        .await?;
+
+    Ok(())
+}
+
+/// Whether `origin`, as sent by browsers, is a page served from this machine.
+fn is_loopback_origin(origin: &str) -> bool {
+    let Some((_, authority)) = origin.split_once("://") else {
+        return false;
+    };
+    let host = match authority.strip_prefix('[') {
+        Some(rest) => rest.split_once(']').map_or(rest, |(host, _)| host),
+        None => authority
+            .split_once(':')
+            .map_or(authority, |(host, _)| host),
+    };
+
+    host.eq_ignore_ascii_case("localhost")
+        || host.parse::<IpAddr>().is_ok_and(|ip| ip.is_loopback())
+}
+
+/// Refuses requests made from web pages elsewhere, which would otherwise fire
+/// binds from any site the user opens. Requests without an `Origin`, such as
+/// from `curl`, are not made by a page.
+async fn same_machine(request: Request, next: Next) -> Result<Response, Rejection> {
+    if let Some(origin) = request.headers().get(ORIGIN)
+        && !origin.to_str().is_ok_and(is_loopback_origin)
+    {
+        return Err((
+            StatusCode::FORBIDDEN,
+            "requests from other sites are refused".to_string(),
+        ));
+    }
+
+    Ok(next.run(request).await)
+}
+
+async fn follow_state(
+    mut events: impl Stream<Item = CubeEvent> + Unpin,
+    state: Arc<Mutex<CubeState>>,
+    cancel: CancellationToken,
+) {
+    loop {
+        let event = select! {
+            event = events.next() => event,
+            _ = cancel.cancelled() => return,
+        };
+
+        match event {
+            Some(CubeEvent::Move(m)) => state.lock().unwrap().apply(m),
+            Some(CubeEvent::StateSync(synced)) => *state.lock().unwrap() = synced,
+            Some(_) => {}
+            None => return,
+        }
+    }
+}
+
+async fn status(State(api): State<Api>) -> Json<StatusReply> {
+    let status = api.status.get();
+    Json(StatusReply {
+        connected: status.connected,
+        battery: status.battery,
+        paused: api.pause.is_paused(),
+    })
+}
+
+async fn state(State(api): State<Api>) -> Json<StateReply> {
+    let state = *api.state.lock().unwrap();
+    Json(StateReply {
+        facelets: state
+            .facelets()
+            .iter()
+            .map(|face| face.to_string())
+            .collect(),
+        solved: state.is_solved(),
+    })
+}
+
+async fn moves(
+    State(api): State<Api>,
+    Query(query): Query<MovesQuery>,
+) -> Result<Json<Vec<MoveReply>>, Rejection> {
+    let history = api.history.as_ref().ok_or_else(|| {
+        (
+            StatusCode::NOT_FOUND,
+            "the move history is not enabled".to_string(),
+        )
+    })?;
+    let moves = match query.count {
+        Some(count) => history.recent(count),
+        None => history.moves(),
+    };
+
+    Ok(Json(
+        moves
+            .iter()
+            .map(|m| MoveReply {
+                m: m.m.to_string(),
+                time_ms: m.timestamp_ms(),
+            })
+            .collect(),
+    ))
+}
+
+async fn fire(State(api): State<Api>, Path(name): Path<String>) -> Result<StatusCode, Rejection> {
+    let bind = api
+        .binds
+        .iter()
+        .position(|bind| bind.as_deref() == Some(name.as_str()))
+        .ok_or_else(|| (StatusCode::NOT_FOUND, format!("no bind named `{name}`")))?;
+
+    api.injector.inject_event(CubeEvent::Fire(bind));
+    Ok(StatusCode::NO_CONTENT)
+}
+
+async fn pause(State(api): State<Api>) -> Json<PauseReply> {
+    let paused = api.pause.toggle();
+    info!(paused, "Pause toggled from the API");
+    Json(PauseReply { paused })
+}
@@ -0,0 +1,156 @@
+//! Blindfolded memo, traced from a cube state with Speffz letters. Edges are
+//! shot from the UF buffer and corners from the UFR buffer, so every letter
+//! is one swap with the buffer.
+
+use alloc::{string::String, vec::Vec};
+use core::fmt;
+
+use crate::cube::{CORNER_FACELETS, CubeState, EDGE_FACELETS};
+
+/// Kociemba index of the UF edge.
+pub const EDGE_BUFFER: usize = 1;
+
+/// Kociemba index of the UFR corner.
+pub const CORNER_BUFFER: usize = 0;
+
+#[derive(PartialEq, Eq, Clone, Debug, Default)]
+pub struct Memo {
+    pub edges: Vec<char>,
+    pub corners: Vec<char>,
+}
+
+impl Memo {
+    pub fn new(state: &CubeState) -> Self {
+        Self {
+            edges: trace(
+                state.edge_permutation,
+                state.edge_orientation,
+                EDGE_BUFFER,
+                &EDGE_FACELETS,
+            ),
+            corners: trace(
+                state.corner_permutation,
+                state.corner_orientation,
+                CORNER_BUFFER,
+                &CORNER_FACELETS,
+            ),
+        }
+    }
+
+    /// Whether an odd number of edge targets leaves UF and UR swapped, needing
+    /// a parity algorithm.
+    pub fn parity(&self) -> bool {
+        self.edges.len() % 2 == 1
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.edges.is_empty() && self.corners.is_empty()
+    }
+
+    pub fn edge_pairs(&self) -> Vec<String> {
+        letter_pairs(&self.edges)
+    }
+
+    pub fn corner_pairs(&self) -> Vec<String> {
+        letter_pairs(&self.corners)
+    }
+}
+
+impl fmt::Display for Memo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "edges: {}\ncorners: {}",
+            self.edge_pairs().join(" "),
+            self.corner_pairs().join(" ")
+        )?;
+
+        if self.parity() {
+            write!(f, "\nparity")?;
+        }
+
+        Ok(())
+    }
+}
+
+/// The number of edges and corners not in their solved position and
+/// orientation.
+pub fn unsolved_pieces(state: &CubeState) -> (usize, usize) {
+    let unsolved = |permutation: &[u8], orientation: &[u8]| {
+        (0..permutation.len())
+            .filter(|&i| permutation[i] as usize != i || orientation[i] != 0)
+            .count()
+    };
+
+    (
+        unsolved(&state.edge_permutation, &state.edge_orientation),
+        unsolved(&state.corner_permutation, &state.corner_orientation),
+    )
+}
+
+fn letter_pairs(letters: &[char]) -> Vec<String> {
+    letters
+        .chunks(2)
+        .map(|pair| pair.iter().collect())
+        .collect()
+}
+
+/// Swaps the buffer with each target in turn until every other piece is
+/// solved. When the buffer piece comes home early the cycle is broken into
+/// the first unsolved piece, preferring pieces that are out of place over
+/// ones that are only twisted or flipped.
+fn trace<const N: usize, const P: usize>(
+    mut permutation: [u8; P],
+    mut orientation: [u8; P],
+    buffer: usize,
+    facelets: &[[usize; N]; P],
+) -> Vec<char> {
+    let mut letters = Vec::new();
+
+    loop {
+        let piece = permutation[buffer] as usize;
+
+        let (target, sticker) = if piece != buffer {
+            // The sticker in the buffer's first facelet.
+            (piece, (N - orientation[buffer] as usize) % N)
+        } else {
+            let others = || (0..P).filter(|&i| i != buffer);
+            let Some(target) = others()
+                .find(|&i| permutation[i] as usize != i)
+                .or_else(|| others().find(|&i| orientation[i] != 0))
+            else {
+                break;
+            };
+            (target, 0)
+        };
+
+        letters.push(speffz(facelets[target][sticker]));
+
+        let (buffer_twist, target_twist) =
+            (orientation[buffer] as usize, orientation[target] as usize);
+        permutation.swap(buffer, target);
+        orientation[target] = ((sticker + buffer_twist) % N) as u8;
+        orientation[buffer] = ((target_twist + N - sticker) % N) as u8;
+    }
+
+    letters
+}
+
+/// The Speffz letter of a sticker. Each face is lettered clockwise from its
+/// top left corner and top edge, with faces in the order U, L, F, R, B, D.
+fn speffz(facelet: usize) -> char {
+    // Where each face of the facelet order U, R, F, D, L, B is in Speffz.
+    const FACE_ORDER: [u8; 6] = [0, 3, 2, 5, 1, 4];
+    const CORNERS: [usize; 4] = [0, 2, 8, 6];
+    const EDGES: [usize; 4] = [1, 5, 7, 3];
+
+    let (face, position) = (facelet / 9, facelet % 9);
+    let index = CORNERS
+        .iter()
+        .chain(&EDGES)
+        .position(|&p| p == position)
+        .expect("centers have no letter")
+        % 4;
+
+    (b'A' + FACE_ORDER[face] * 4 + index as u8) as char
+}
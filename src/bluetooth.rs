@@ -0,0 +1,730 @@
+use std::time::{Duration, Instant};
+
+use btleplug::{
+    api::{
+        BDAddr, Central, CentralEvent, Characteristic, Manager as _, Peripheral, ScanFilter,
+        WriteType,
+    },
+    platform::{Adapter, Manager, Peripheral as PlatformPeripheral, PeripheralId},
+};
+use futures::{Stream, StreamExt, stream};
+use tokio::{
+    select,
+    sync::broadcast::Receiver,
+    task::{JoinHandle, spawn_blocking},
+    time::{MissedTickBehavior, interval, sleep, timeout},
+};
+use tokio_util::sync::CancellationToken;
+use tracing::{Instrument, debug, info, info_span, warn};
+
+use crate::{
+    GAN_TIMER_SERVICE, GAN_TIMER_STATE_CHARACTERISTIC,
+    cube::CubeEvent,
+    error::{CubeError, ProtocolError},
+    metrics::{self, Stage},
+    persist::{StateKeeper, StateStore},
+    protocol::{
+        characteristics::{self, Generation, Identified, Role},
+        cipher::{CipherKeys, GAN_MANUFACTURER_ID, GANCubeVersion1Cipher, GANCubeVersion2Cipher},
+        gen1,
+        gen2::{Command, Decoder},
+        timer,
+    },
+    retry::RetryPolicy,
+    source::{CubeSource, HEARTBEAT_INTERVAL, SILENCE_TIMEOUT, silence_timeout},
+};
+
+#[derive(Debug, Default)]
+pub struct BluetoothCubeSource {
+    /// Scans on every adapter when not set.
+    adapter: Option<usize>,
+    keys: CipherKeys,
+    retry: RetryPolicy,
+    store: Option<StateStore>,
+    battery_interval: Option<Duration>,
+}
+
+/// A GAN Smart Timer or Halo timer, connected alongside the cube. Its state
+/// changes are sent as [`CubeEvent::Timer`].
+#[derive(Debug, Default)]
+pub struct GanTimerSource {
+    adapter: usize,
+}
+
+impl BluetoothCubeSource {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Only scans on the adapter at `adapter` in [`adapters`], rather than on
+    /// all of them.
+    pub fn adapter(mut self, adapter: usize) -> Self {
+        self.adapter = Some(adapter);
+        self
+    }
+
+    /// Derives the cube's cipher from other base keys than GAN's.
+    pub fn keys(mut self, keys: CipherKeys) -> Self {
+        self.keys = keys;
+        self
+    }
+
+    /// Times out and retries connecting, discovering services and
+    /// subscribing as `retry` says.
+    pub fn retry(mut self, retry: RetryPolicy) -> Self {
+        self.retry = retry;
+        self
+    }
+
+    /// Saves the cube's state to `store` and restores it on connecting again.
+    pub fn state_store(mut self, store: Option<StateStore>) -> Self {
+        self.store = store;
+        self
+    }
+
+    /// Asks the cube for its battery level every `interval`, or every
+    /// [`HEARTBEAT_INTERVAL`] when `None`.
+    pub fn battery_interval(mut self, interval: Option<Duration>) -> Self {
+        self.battery_interval = interval;
+        self
+    }
+}
+
+impl GanTimerSource {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn adapter(mut self, adapter: usize) -> Self {
+        self.adapter = adapter;
+        self
+    }
+}
+
+pub(crate) async fn select_adapter(index: usize) -> Result<Adapter, CubeError> {
+    let manager = Manager::new().await?;
+
+    let mut adapter_list = manager.adapters().await?;
+
+    if index >= adapter_list.len() {
+        return Err(CubeError::NoAdapter);
+    }
+
+    let adapter = adapter_list.swap_remove(index);
+
+    info!("Using adapter: {}", adapter.adapter_info().await?);
+
+    Ok(adapter)
+}
+
+/// A description of every bluetooth adapter, in the order `adapter` indexes
+/// them.
+pub async fn adapters() -> Result<Vec<String>, CubeError> {
+    let manager = Manager::new().await?;
+
+    let mut adapters = Vec::new();
+    for adapter in manager.adapters().await? {
+        adapters.push(adapter.adapter_info().await?);
+    }
+
+    Ok(adapters)
+}
+
+impl BluetoothCubeSource {
+    async fn move_stream_v2(
+        self,
+        device: impl Peripheral + 'static,
+        read: Characteristic,
+        write: Characteristic,
+        cancel: CancellationToken,
+    ) -> Result<Receiver<CubeEvent>, CubeError> {
+        let properties = device
+            .properties()
+            .await?
+            .ok_or(CubeError::MissingProperties)?;
+        let data = properties
+            .manufacturer_data
+            .get(&GAN_MANUFACTURER_ID)
+            .ok_or(ProtocolError::MissingDeviceIdentifier)?;
+
+        let salt = GANCubeVersion2Cipher::salt_from_manufacturer_data(data)?;
+        let cipher = GANCubeVersion2Cipher::from_salt_with(self.keys, salt);
+        let mut decoder = Decoder::new(cipher);
+        let encoder = decoder.clone();
+        let heartbeat_encoder = decoder.clone();
+        let mut keeper = self
+            .store
+            .map(|store| StateKeeper::new(store, device.id().to_string()));
+        let battery_interval = self.battery_interval.unwrap_or(HEARTBEAT_INTERVAL);
+        let silence = silence_timeout(battery_interval);
+
+        let mut notificaitons = device.notifications().await?;
+        let span = info_span!("cube", id = %device.address(), local_name = properties.local_name);
+
+        // The notifications are buffered from here, so none are missed while
+        // subscribing, and the silence watchdog only starts once subscribed, as
+        // subscribing may take longer than the cube is allowed to stay silent.
+        self.retry
+            .run("subscribe to the cube", || device.subscribe(&read))
+            .await?;
+
+        let (tx, rx) = tokio::sync::broadcast::channel::<CubeEvent>(10);
+
+        let event_sender = tx.clone();
+        let peripheral = device.clone();
+        let heartbeat_write = write.clone();
+
+        let notify = async move {
+            let mut heartbeat = interval(HEARTBEAT_INTERVAL);
+            let mut battery_poll = interval(battery_interval);
+            let mut last_heard = Instant::now();
+            // The state being saved in the background, to wait for before
+            // saving it once more on disconnecting.
+            let mut saving: Option<JoinHandle<()>> = None;
+
+            loop {
+                let value = select! {
+                    value = notificaitons.next() => value,
+                    _ = heartbeat.tick() => {
+                        if last_heard.elapsed() >= silence {
+                            warn!("Cube went silent, disconnecting");
+                            let _ = peripheral.disconnect().await;
+                            break;
+                        }
+
+                        if let Some(keeper) = &mut keeper
+                            && saving.as_ref().is_none_or(JoinHandle::is_finished)
+                            && let Some(save) = keeper.pending(decoder.move_count())
+                        {
+                            saving = Some(spawn_blocking(move || save.write()));
+                        }
+                        continue;
+                    }
+                    _ = battery_poll.tick() => {
+                        if let Ok(packet) = heartbeat_encoder.encode(Command::RequestBattery) {
+                            let write =
+                                peripheral.write(&heartbeat_write, &packet, WriteType::WithResponse);
+                            let _ = timeout(HEARTBEAT_INTERVAL, write).await;
+                        }
+                        continue;
+                    }
+                    _ = cancel.cancelled() => {
+                        let _ = peripheral.disconnect().await;
+                        break;
+                    }
+                };
+
+                let Some(value) = value else {
+                    break;
+                };
+
+                let received = Instant::now();
+                last_heard = received;
+                let events = match decoder.decode(&value.value) {
+                    Ok(events) => events,
+                    Err(error) => {
+                        debug!(%error, bytes = value.value.len(), "Could not decode packet");
+                        metrics::decrypt_failure();
+                        continue;
+                    }
+                };
+                metrics::stage_latency(Stage::Decode, received.elapsed());
+
+                for event in events {
+                    match event {
+                        CubeEvent::Move(m) => {
+                            debug!(counter = decoder.move_count(), %m, "Move");
+                            metrics::move_received();
+                            let _ = tx.send(CubeEvent::Received(received));
+                        }
+                        CubeEvent::Battery(level) => metrics::battery_level(level),
+                        _ => {}
+                    }
+
+                    let event = match &mut keeper {
+                        Some(keeper) => keeper.event(event, decoder.move_count()),
+                        None => event,
+                    };
+
+                    if tx.send(event).is_err() {
+                        warn!("Nothing is listening for cube events, disconnecting");
+                        let _ = peripheral.disconnect().await;
+                        if let Some(keeper) = &mut keeper {
+                            if let Some(saving) = saving.take() {
+                                let _ = saving.await;
+                            }
+                            keeper.save(decoder.move_count());
+                        }
+                        return;
+                    }
+                }
+            }
+
+            if let Some(keeper) = &mut keeper {
+                if let Some(saving) = saving.take() {
+                    let _ = saving.await;
+                }
+                keeper.save(decoder.move_count());
+            }
+            let _ = tx.send(CubeEvent::Disconnected);
+        };
+        tokio::spawn(notify.instrument(span));
+
+        let _ = event_sender.send(CubeEvent::Connected);
+
+        for command in [Command::RequestState, Command::RequestBattery] {
+            device
+                .write(&write, &encoder.encode(command)?, WriteType::WithResponse)
+                .await?;
+        }
+
+        Ok(rx)
+    }
+}
+
+/// How often a first generation cube's last moves are read.
+const V1_POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// Follows a first generation cube by reading its last moves over and over, as
+/// it does not notify, and its battery level every `battery_interval`. Its
+/// state is not read, so it is neither synced nor saved.
+pub async fn move_stream_v1(
+    device: impl Peripheral + 'static,
+    mut identified: Identified<Characteristic>,
+    retry: RetryPolicy,
+    battery_interval: Duration,
+    cancel: CancellationToken,
+) -> Result<Receiver<CubeEvent>, CubeError> {
+    let last_moves = identified.take(Role::V1LastMoves)?;
+    let battery = identified.take(Role::V1Battery).ok();
+
+    // Without its version there is no telling whether the cube encrypts, so
+    // it is taken for early firmware, which does not.
+    let cipher = match identified.take(Role::V1Version) {
+        Ok(version) => {
+            let version = retry
+                .run("read the cube's firmware version", || device.read(&version))
+                .await?;
+            let hardware = match identified.take(Role::V1Hardware) {
+                Ok(hardware) => {
+                    retry
+                        .run("read the cube's hardware id", || device.read(&hardware))
+                        .await?
+                }
+                Err(_) => Vec::new(),
+            };
+            GANCubeVersion1Cipher::for_firmware(&version, &hardware)?
+        }
+        Err(_) => None,
+    };
+    let mut decoder = gen1::Decoder::new(cipher);
+
+    let local_name = device
+        .properties()
+        .await?
+        .and_then(|properties| properties.local_name);
+    let span = info_span!("cube", id = %device.address(), local_name);
+
+    let (tx, rx) = tokio::sync::broadcast::channel::<CubeEvent>(10);
+    let _ = tx.send(CubeEvent::Connected);
+
+    let poll = async move {
+        let mut poll = interval(V1_POLL_INTERVAL);
+        poll.set_missed_tick_behavior(MissedTickBehavior::Delay);
+        let mut heartbeat = interval(HEARTBEAT_INTERVAL);
+        let mut battery_poll = interval(battery_interval);
+        let mut last_heard = Instant::now();
+
+        loop {
+            let value = select! {
+                _ = poll.tick() => timeout(HEARTBEAT_INTERVAL, device.read(&last_moves)).await,
+                _ = heartbeat.tick() => {
+                    if last_heard.elapsed() >= SILENCE_TIMEOUT {
+                        warn!("Cube went silent, disconnecting");
+                        let _ = device.disconnect().await;
+                        break;
+                    }
+                    continue;
+                }
+                _ = battery_poll.tick() => {
+                    if let Some(battery) = &battery
+                        && let Ok(Ok(value)) = timeout(HEARTBEAT_INTERVAL, device.read(battery)).await
+                    {
+                        match decoder.decode_battery(&value) {
+                            Ok(level) => {
+                                metrics::battery_level(level);
+                                let _ = tx.send(CubeEvent::Battery(level));
+                            }
+                            Err(error) => debug!(%error, "Could not decode the battery level"),
+                        }
+                    }
+                    continue;
+                }
+                _ = cancel.cancelled() => {
+                    let _ = device.disconnect().await;
+                    break;
+                }
+            };
+
+            // A cube that cannot be read is as good as silent.
+            let Ok(Ok(value)) = value else {
+                continue;
+            };
+
+            let received = Instant::now();
+            last_heard = received;
+            let events = match decoder.decode_moves(&value) {
+                Ok(events) => events,
+                Err(error) => {
+                    debug!(%error, bytes = value.len(), "Could not decode last moves");
+                    metrics::decrypt_failure();
+                    continue;
+                }
+            };
+            metrics::stage_latency(Stage::Decode, received.elapsed());
+
+            for event in events {
+                if let CubeEvent::Move(m) = event {
+                    debug!(counter = decoder.move_count(), %m, "Move");
+                    metrics::move_received();
+                    let _ = tx.send(CubeEvent::Received(received));
+                }
+
+                if tx.send(event).is_err() {
+                    warn!("Nothing is listening for cube events, disconnecting");
+                    let _ = device.disconnect().await;
+                    return;
+                }
+            }
+        }
+
+        let _ = tx.send(CubeEvent::Disconnected);
+    };
+    tokio::spawn(poll.instrument(span));
+
+    Ok(rx)
+}
+
+async fn scan_for_cubes(adapter: &Adapter) -> Result<PeripheralId, CubeError> {
+    let mut events = adapter.events().await?;
+    adapter.start_scan(ScanFilter::default()).await?;
+
+    info!("Scanning for devices...");
+
+    while let Some(event) = events.next().await {
+        if let CentralEvent::DeviceDiscovered(id) = event {
+            let peripheral = adapter.peripheral(&id).await?;
+            let properties = peripheral.properties().await?;
+            let Some(name) = properties.and_then(|p| p.local_name) else {
+                continue;
+            };
+
+            if name.starts_with("GAN") {
+                return Ok(id);
+            }
+        }
+    }
+
+    Err(CubeError::NotFound)
+}
+
+/// How long every adapter keeps scanning after one finds the cube, for the
+/// others to report how well they hear it.
+const ADAPTER_SETTLE: Duration = Duration::from_millis(1500);
+
+/// A cube heard by one of several adapters.
+struct Sighting {
+    adapter: usize,
+    id: PeripheralId,
+    address: BDAddr,
+    rssi: Option<i16>,
+}
+
+async fn sightings(
+    index: usize,
+    adapter: Adapter,
+) -> Result<impl Stream<Item = Sighting> + Send + Unpin, CubeError> {
+    let events = adapter.events().await?;
+    adapter.start_scan(ScanFilter::default()).await?;
+
+    Ok(events
+        .filter_map(move |event| {
+            let adapter = adapter.clone();
+            async move {
+                let (CentralEvent::DeviceDiscovered(id) | CentralEvent::DeviceUpdated(id)) = event
+                else {
+                    return None;
+                };
+
+                let properties = adapter
+                    .peripheral(&id)
+                    .await
+                    .ok()?
+                    .properties()
+                    .await
+                    .ok()??;
+                let name = properties.local_name?;
+
+                name.starts_with("GAN").then_some(Sighting {
+                    adapter: index,
+                    id,
+                    address: properties.address,
+                    rssi: properties.rssi,
+                })
+            }
+        })
+        .boxed())
+}
+
+/// Scans on every adapter at once and picks the one hearing the first cube
+/// found with the strongest signal.
+async fn scan_all_adapters() -> Result<(Adapter, PeripheralId), CubeError> {
+    let manager = Manager::new().await?;
+    let mut adapters = manager.adapters().await?;
+
+    if adapters.len() <= 1 {
+        let adapter = adapters.pop().ok_or(CubeError::NoAdapter)?;
+        info!("Using adapter: {}", adapter.adapter_info().await?);
+        let id = scan_for_cubes(&adapter).await?;
+        return Ok((adapter, id));
+    }
+
+    let mut streams = Vec::new();
+    for (index, adapter) in adapters.iter().enumerate() {
+        streams.push(sightings(index, adapter.clone()).await?);
+    }
+    let mut sightings = stream::select_all(streams);
+
+    info!("Scanning for devices on {} adapters...", adapters.len());
+
+    let first = sightings.next().await.ok_or(CubeError::NotFound)?;
+    let address = first.address;
+    let mut best = first;
+
+    let settle = sleep(ADAPTER_SETTLE);
+    tokio::pin!(settle);
+    loop {
+        let sighting = select! {
+            sighting = sightings.next() => sighting,
+            _ = &mut settle => break,
+        };
+
+        let Some(sighting) = sighting else {
+            break;
+        };
+
+        debug!(adapter = sighting.adapter, rssi = ?sighting.rssi, "Heard the cube");
+        if sighting.address == address && sighting.rssi > best.rssi {
+            best = sighting;
+        }
+    }
+
+    for (index, adapter) in adapters.iter().enumerate() {
+        if index != best.adapter {
+            let _ = adapter.stop_scan().await;
+        }
+    }
+
+    let adapter = adapters.swap_remove(best.adapter);
+    info!(
+        rssi = ?best.rssi,
+        "Using adapter: {}",
+        adapter.adapter_info().await?
+    );
+
+    Ok((adapter, best.id))
+}
+
+impl CubeSource for BluetoothCubeSource {
+    async fn connect(self, cancel: CancellationToken) -> Result<Receiver<CubeEvent>, CubeError> {
+        let (adapter, cube_id) = match self.adapter {
+            Some(index) => {
+                let adapter = select_adapter(index).await?;
+                let id = scan_for_cubes(&adapter).await?;
+                (adapter, id)
+            }
+            None => scan_all_adapters().await?,
+        };
+        let cube = adapter.peripheral(&cube_id).await?;
+
+        info!(
+            "Found cube: {}",
+            cube.properties()
+                .await?
+                .and_then(|p| p.local_name)
+                .unwrap_or_default()
+        );
+
+        self.retry
+            .run("connect to the cube", || cube.connect())
+            .await?;
+        let mut identified = identify_characteristics(&cube, self.retry).await?;
+
+        for uuid in &identified.unknown {
+            warn!("Unknown characteristic: {uuid}");
+        }
+
+        match identified.generation {
+            Generation::V1 => {
+                for known in identified.missing() {
+                    warn!("Cube has no {} characteristic", known.purpose);
+                }
+                let battery_interval = self.battery_interval.unwrap_or(HEARTBEAT_INTERVAL);
+                move_stream_v1(cube, identified, self.retry, battery_interval, cancel).await
+            }
+            Generation::V2 => {
+                let write = identified.take(Role::V2Command)?;
+                let read = identified.take(Role::V2State)?;
+                self.move_stream_v2(cube, read, write, cancel).await
+            }
+        }
+    }
+}
+
+/// Discovers the cube's services until they identify a known generation, as
+/// some platforms report an empty or stale list at first. Before the last
+/// attempt the cube is reconnected, which makes the platform look its services
+/// up again instead of answering from its cache.
+async fn identify_characteristics(
+    cube: &PlatformPeripheral,
+    retry: RetryPolicy,
+) -> Result<characteristics::Identified<Characteristic>, CubeError> {
+    let attempts = retry.attempts.max(1);
+    let mut tried = 0;
+
+    loop {
+        tried += 1;
+
+        retry
+            .run("discover the cube's services", || cube.discover_services())
+            .await?;
+
+        let found = cube.characteristics();
+        debug!(
+            "Discovered characteristics: {:?}",
+            found.iter().map(|c| c.uuid).collect::<Vec<_>>()
+        );
+
+        let error = match characteristics::identify(found.into_iter().map(|c| (c.uuid, c))) {
+            Ok(identified) => return Ok(identified),
+            Err(e @ ProtocolError::UnrecognizedCharacteristics(_)) if tried < attempts => e,
+            Err(e) => return Err(e.into()),
+        };
+
+        warn!("Discovering services again, attempt {tried} of {attempts}: {error}");
+        sleep(retry.backoff()).await;
+
+        if tried + 1 == attempts {
+            info!("Reconnecting to refresh the cube's services");
+            cube.disconnect().await?;
+            retry.run("connect to the cube", || cube.connect()).await?;
+        }
+    }
+}
+
+/// Accessories are named like cubes, so candidates are connected to and kept
+/// only if `is_match` accepts them once their services are discovered.
+/// Devices that are already connected, such as the cube, are left alone.
+pub(crate) async fn scan_for_accessory(
+    adapter: &Adapter,
+    kind: &str,
+    is_match: impl Fn(&PlatformPeripheral) -> bool,
+) -> Result<PlatformPeripheral, CubeError> {
+    let mut events = adapter.events().await?;
+    adapter.start_scan(ScanFilter::default()).await?;
+
+    info!("Scanning for a {kind}...");
+
+    while let Some(event) = events.next().await {
+        let CentralEvent::DeviceDiscovered(id) = event else {
+            continue;
+        };
+
+        let peripheral = adapter.peripheral(&id).await?;
+        let Some(name) = peripheral.properties().await?.and_then(|p| p.local_name) else {
+            continue;
+        };
+
+        if !name.starts_with("GAN") || peripheral.is_connected().await? {
+            continue;
+        }
+
+        peripheral.connect().await?;
+        peripheral.discover_services().await?;
+
+        if is_match(&peripheral) {
+            info!("Found {kind}: {name}");
+            return Ok(peripheral);
+        }
+
+        peripheral.disconnect().await?;
+    }
+
+    Err(CubeError::NotFound)
+}
+
+impl CubeSource for GanTimerSource {
+    async fn connect(self, cancel: CancellationToken) -> Result<Receiver<CubeEvent>, CubeError> {
+        let adapter = select_adapter(self.adapter).await?;
+        let device = scan_for_accessory(&adapter, "timer", |peripheral| {
+            peripheral
+                .services()
+                .iter()
+                .any(|service| service.uuid == GAN_TIMER_SERVICE)
+        })
+        .await?;
+
+        let state = device
+            .characteristics()
+            .into_iter()
+            .find(|c| c.uuid == GAN_TIMER_STATE_CHARACTERISTIC)
+            .ok_or(ProtocolError::UnknownVersion)?;
+
+        let mut notifications = device.notifications().await?;
+
+        let (tx, rx) = tokio::sync::broadcast::channel::<CubeEvent>(10);
+
+        let event_sender = tx.clone();
+        let peripheral = device.clone();
+        let span = info_span!("timer", id = ?device.id());
+
+        let notify = async move {
+            loop {
+                let value = select! {
+                    value = notifications.next() => value,
+                    _ = cancel.cancelled() => {
+                        let _ = peripheral.disconnect().await;
+                        break;
+                    }
+                };
+
+                let Some(value) = value else {
+                    break;
+                };
+
+                let event = match timer::decode(&value.value) {
+                    Ok(event) => event,
+                    Err(e) => {
+                        warn!("Could not decode timer packet: {e}");
+                        continue;
+                    }
+                };
+
+                if tx.send(CubeEvent::Timer(event)).is_err() {
+                    warn!("Nothing is listening for timer events, disconnecting");
+                    let _ = peripheral.disconnect().await;
+                    return;
+                }
+            }
+
+            let _ = tx.send(CubeEvent::Disconnected);
+        };
+        tokio::spawn(notify.instrument(span));
+
+        device.subscribe(&state).await?;
+
+        let _ = event_sender.send(CubeEvent::Connected);
+
+        Ok(rx)
+    }
+}
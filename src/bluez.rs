@@ -0,0 +1,484 @@
+use std::time::{Duration, Instant};
+
+use bluer::{AdapterEvent, Device, Session, gatt::remote::Characteristic};
+use futures::{StreamExt, pin_mut};
+use tokio::{
+    select,
+    sync::broadcast::Receiver,
+    task::{JoinHandle, spawn_blocking},
+    time::{MissedTickBehavior, interval, sleep, timeout},
+};
+use tokio_util::sync::CancellationToken;
+use tracing::{Instrument, debug, info, info_span, warn};
+use uuid::Uuid;
+
+use crate::{
+    DEVICE_INFORMATION_SERVICE, GAN_GEN1_SERVICE, GAN_GEN2_SERVICE,
+    cube::CubeEvent,
+    error::{CubeError, ProtocolError},
+    metrics::{self, Stage},
+    persist::{StateKeeper, StateStore},
+    protocol::{
+        characteristics::{self, Generation, Role},
+        cipher::{CipherKeys, GAN_MANUFACTURER_ID, GANCubeVersion1Cipher, GANCubeVersion2Cipher},
+        gen1,
+        gen2::{Command, Decoder},
+    },
+    retry::RetryPolicy,
+    source::{CubeSource, HEARTBEAT_INTERVAL, SILENCE_TIMEOUT, silence_timeout},
+};
+
+/// A cube source talking to BlueZ directly over D-Bus, for systems where
+/// btleplug drops notifications.
+#[derive(Debug, Default)]
+pub struct BluezCubeSource {
+    adapter: Option<String>,
+    keys: CipherKeys,
+    retry: RetryPolicy,
+    store: Option<StateStore>,
+    battery_interval: Option<Duration>,
+}
+
+impl BluezCubeSource {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Uses the adapter with the given name, such as `hci1`, instead of the
+    /// default adapter.
+    pub fn adapter(mut self, adapter: impl Into<String>) -> Self {
+        self.adapter = Some(adapter.into());
+        self
+    }
+
+    /// Derives the cube's cipher from other base keys than GAN's.
+    pub fn keys(mut self, keys: CipherKeys) -> Self {
+        self.keys = keys;
+        self
+    }
+
+    /// Times out and retries connecting, finding the characteristics and
+    /// subscribing as `retry` says.
+    pub fn retry(mut self, retry: RetryPolicy) -> Self {
+        self.retry = retry;
+        self
+    }
+
+    /// Saves the cube's state to `store` and restores it on connecting again.
+    pub fn state_store(mut self, store: Option<StateStore>) -> Self {
+        self.store = store;
+        self
+    }
+
+    /// Asks the cube for its battery level every `interval`, or every
+    /// [`HEARTBEAT_INTERVAL`] when `None`.
+    pub fn battery_interval(mut self, interval: Option<Duration>) -> Self {
+        self.battery_interval = interval;
+        self
+    }
+}
+
+async fn scan_for_cubes(adapter: &bluer::Adapter) -> Result<Device, CubeError> {
+    let events = adapter.discover_devices().await?;
+    pin_mut!(events);
+
+    info!("Scanning for devices...");
+
+    while let Some(event) = events.next().await {
+        if let AdapterEvent::DeviceAdded(address) = event {
+            let device = adapter.device(address)?;
+            let Some(name) = device.name().await? else {
+                continue;
+            };
+
+            if name.starts_with("GAN") {
+                return Ok(device);
+            }
+        }
+    }
+
+    Err(CubeError::NotFound)
+}
+
+/// The services holding the characteristics of every known generation.
+const CUBE_SERVICES: [Uuid; 3] = [
+    GAN_GEN2_SERVICE,
+    GAN_GEN1_SERVICE,
+    DEVICE_INFORMATION_SERVICE,
+];
+
+async fn find_characteristics(device: &Device) -> Result<Vec<(Uuid, Characteristic)>, CubeError> {
+    let mut characteristics = Vec::new();
+
+    for service in device.services().await? {
+        if !CUBE_SERVICES.contains(&service.uuid().await?) {
+            continue;
+        }
+
+        for characteristic in service.characteristics().await? {
+            characteristics.push((characteristic.uuid().await?, characteristic));
+        }
+    }
+
+    Ok(characteristics)
+}
+
+/// Finds the cube's characteristics until they identify a known generation,
+/// as BlueZ may list none or stale ones before it has resolved the services.
+/// Before the last attempt the cube is reconnected, which makes BlueZ resolve
+/// them again instead of answering from its cache.
+async fn identify_characteristics(
+    device: &Device,
+    retry: RetryPolicy,
+) -> Result<characteristics::Identified<Characteristic>, CubeError> {
+    let attempts = retry.attempts.max(1);
+    let mut tried = 0;
+
+    loop {
+        tried += 1;
+
+        let found = retry
+            .run("find the cube's characteristics", || {
+                find_characteristics(device)
+            })
+            .await?;
+        debug!(
+            "Found characteristics: {:?}",
+            found.iter().map(|(uuid, _)| uuid).collect::<Vec<_>>()
+        );
+
+        let error = match characteristics::identify(found) {
+            Ok(identified) => return Ok(identified),
+            Err(e @ ProtocolError::UnrecognizedCharacteristics(_)) if tried < attempts => e,
+            Err(e) => return Err(e.into()),
+        };
+
+        warn!("Finding the characteristics again, attempt {tried} of {attempts}: {error}");
+        sleep(retry.backoff()).await;
+
+        if tried + 1 == attempts {
+            info!("Reconnecting to refresh the cube's services");
+            device.disconnect().await?;
+            retry
+                .run("connect to the cube", || device.connect())
+                .await?;
+        }
+    }
+}
+
+impl CubeSource for BluezCubeSource {
+    async fn connect(self, cancel: CancellationToken) -> Result<Receiver<CubeEvent>, CubeError> {
+        let session = Session::new().await?;
+
+        let adapter = match self.adapter {
+            Some(name) => session.adapter(&name)?,
+            None => session.default_adapter().await?,
+        };
+        adapter.set_powered(true).await?;
+
+        info!("Using adapter: {}", adapter.name());
+
+        let device = scan_for_cubes(&adapter).await?;
+
+        let name = device.name().await?.unwrap_or_default();
+        info!("Found cube: {name}");
+
+        self.retry
+            .run("connect to the cube", || device.connect())
+            .await?;
+
+        let mut identified = identify_characteristics(&device, self.retry).await?;
+
+        for uuid in &identified.unknown {
+            warn!("Unknown characteristic: {uuid}");
+        }
+
+        match identified.generation {
+            Generation::V1 => {
+                for known in identified.missing() {
+                    warn!("Cube has no {} characteristic", known.purpose);
+                }
+                let battery_interval = self.battery_interval.unwrap_or(HEARTBEAT_INTERVAL);
+                move_stream_v1(
+                    device,
+                    name,
+                    identified,
+                    self.retry,
+                    battery_interval,
+                    cancel,
+                )
+                .await
+            }
+            Generation::V2 => {
+                let read = identified.take(Role::V2State)?;
+                let write = identified.take(Role::V2Command)?;
+                self.move_stream_v2(device, name, read, write, cancel).await
+            }
+        }
+    }
+}
+
+impl BluezCubeSource {
+    async fn move_stream_v2(
+        self,
+        device: Device,
+        name: String,
+        read: Characteristic,
+        write: Characteristic,
+        cancel: CancellationToken,
+    ) -> Result<Receiver<CubeEvent>, CubeError> {
+        let data = device
+            .manufacturer_data()
+            .await?
+            .and_then(|mut data| data.remove(&GAN_MANUFACTURER_ID))
+            .ok_or(ProtocolError::MissingDeviceIdentifier)?;
+
+        let salt = GANCubeVersion2Cipher::salt_from_manufacturer_data(&data)?;
+        let cipher = GANCubeVersion2Cipher::from_salt_with(self.keys, salt);
+        let mut decoder = Decoder::new(cipher);
+        let encoder = decoder.clone();
+        let heartbeat_encoder = decoder.clone();
+        let mut keeper = self
+            .store
+            .map(|store| StateKeeper::new(store, device.address().to_string()));
+        let battery_interval = self.battery_interval.unwrap_or(HEARTBEAT_INTERVAL);
+        let silence = silence_timeout(battery_interval);
+
+        let notifications = self
+            .retry
+            .run("subscribe to the cube", || read.notify())
+            .await?;
+
+        let (tx, rx) = tokio::sync::broadcast::channel::<CubeEvent>(10);
+
+        let event_sender = tx.clone();
+        let heartbeat_write = write.clone();
+        let span = info_span!("cube", id = %device.address(), local_name = name);
+
+        let notify = async move {
+            pin_mut!(notifications);
+
+            let mut heartbeat = interval(HEARTBEAT_INTERVAL);
+            let mut battery_poll = interval(battery_interval);
+            let mut last_heard = Instant::now();
+            // The state being saved in the background, to wait for before
+            // saving it once more on disconnecting.
+            let mut saving: Option<JoinHandle<()>> = None;
+
+            loop {
+                let value = select! {
+                    value = notifications.next() => value,
+                    _ = heartbeat.tick() => {
+                        if last_heard.elapsed() >= silence {
+                            warn!("Cube went silent, disconnecting");
+                            let _ = device.disconnect().await;
+                            break;
+                        }
+
+                        if let Some(keeper) = &mut keeper
+                            && saving.as_ref().is_none_or(JoinHandle::is_finished)
+                            && let Some(save) = keeper.pending(decoder.move_count())
+                        {
+                            saving = Some(spawn_blocking(move || save.write()));
+                        }
+                        continue;
+                    }
+                    _ = battery_poll.tick() => {
+                        if let Ok(packet) = heartbeat_encoder.encode(Command::RequestBattery) {
+                            let write = heartbeat_write.write(&packet);
+                            let _ = timeout(HEARTBEAT_INTERVAL, write).await;
+                        }
+                        continue;
+                    }
+                    _ = cancel.cancelled() => {
+                        let _ = device.disconnect().await;
+                        break;
+                    }
+                };
+
+                let Some(value) = value else {
+                    break;
+                };
+
+                let received = Instant::now();
+                last_heard = received;
+                let events = match decoder.decode(&value) {
+                    Ok(events) => events,
+                    Err(error) => {
+                        debug!(%error, bytes = value.len(), "Could not decode packet");
+                        metrics::decrypt_failure();
+                        continue;
+                    }
+                };
+                metrics::stage_latency(Stage::Decode, received.elapsed());
+
+                for event in events {
+                    match event {
+                        CubeEvent::Move(m) => {
+                            debug!(counter = decoder.move_count(), %m, "Move");
+                            metrics::move_received();
+                            let _ = tx.send(CubeEvent::Received(received));
+                        }
+                        CubeEvent::Battery(level) => metrics::battery_level(level),
+                        _ => {}
+                    }
+
+                    let event = match &mut keeper {
+                        Some(keeper) => keeper.event(event, decoder.move_count()),
+                        None => event,
+                    };
+
+                    if tx.send(event).is_err() {
+                        warn!("Nothing is listening for cube events, disconnecting");
+                        let _ = device.disconnect().await;
+                        if let Some(keeper) = &mut keeper {
+                            if let Some(saving) = saving.take() {
+                                let _ = saving.await;
+                            }
+                            keeper.save(decoder.move_count());
+                        }
+                        return;
+                    }
+                }
+            }
+
+            if let Some(keeper) = &mut keeper {
+                if let Some(saving) = saving.take() {
+                    let _ = saving.await;
+                }
+                keeper.save(decoder.move_count());
+            }
+            let _ = tx.send(CubeEvent::Disconnected);
+        };
+        tokio::spawn(notify.instrument(span));
+
+        let _ = event_sender.send(CubeEvent::Connected);
+
+        for command in [Command::RequestState, Command::RequestBattery] {
+            write.write(&encoder.encode(command)?).await?;
+        }
+
+        Ok(rx)
+    }
+}
+
+/// How often a first generation cube's last moves are read.
+const V1_POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// Follows a first generation cube by reading its last moves over and over, as
+/// it does not notify, and its battery level every `battery_interval`. Its
+/// state is not read, so it is neither synced nor saved.
+async fn move_stream_v1(
+    device: Device,
+    name: String,
+    mut identified: characteristics::Identified<Characteristic>,
+    retry: RetryPolicy,
+    battery_interval: Duration,
+    cancel: CancellationToken,
+) -> Result<Receiver<CubeEvent>, CubeError> {
+    let last_moves = identified.take(Role::V1LastMoves)?;
+    let battery = identified.take(Role::V1Battery).ok();
+
+    // Without its version there is no telling whether the cube encrypts, so
+    // it is taken for early firmware, which does not.
+    let cipher = match identified.take(Role::V1Version) {
+        Ok(version) => {
+            let version = retry
+                .run("read the cube's firmware version", || version.read())
+                .await?;
+            let hardware = match identified.take(Role::V1Hardware) {
+                Ok(hardware) => {
+                    retry
+                        .run("read the cube's hardware id", || hardware.read())
+                        .await?
+                }
+                Err(_) => Vec::new(),
+            };
+            GANCubeVersion1Cipher::for_firmware(&version, &hardware)?
+        }
+        Err(_) => None,
+    };
+    let mut decoder = gen1::Decoder::new(cipher);
+
+    let (tx, rx) = tokio::sync::broadcast::channel::<CubeEvent>(10);
+    let _ = tx.send(CubeEvent::Connected);
+
+    let span = info_span!("cube", id = %device.address(), local_name = name);
+
+    let poll = async move {
+        let mut poll = interval(V1_POLL_INTERVAL);
+        poll.set_missed_tick_behavior(MissedTickBehavior::Delay);
+        let mut heartbeat = interval(HEARTBEAT_INTERVAL);
+        let mut battery_poll = interval(battery_interval);
+        let mut last_heard = Instant::now();
+
+        loop {
+            let value = select! {
+                _ = poll.tick() => timeout(HEARTBEAT_INTERVAL, last_moves.read()).await,
+                _ = heartbeat.tick() => {
+                    if last_heard.elapsed() >= SILENCE_TIMEOUT {
+                        warn!("Cube went silent, disconnecting");
+                        let _ = device.disconnect().await;
+                        break;
+                    }
+                    continue;
+                }
+                _ = battery_poll.tick() => {
+
+                    if let Some(battery) = &battery
+                        && let Ok(Ok(value)) = timeout(HEARTBEAT_INTERVAL, battery.read()).await
+                    {
+                        match decoder.decode_battery(&value) {
+                            Ok(level) => {
+                                metrics::battery_level(level);
+                                let _ = tx.send(CubeEvent::Battery(level));
+                            }
+                            Err(error) => debug!(%error, "Could not decode the battery level"),
+                        }
+                    }
+                    continue;
+                }
+                _ = cancel.cancelled() => {
+                    let _ = device.disconnect().await;
+                    break;
+                }
+            };
+
+            // A cube that cannot be read is as good as silent.
+            let Ok(Ok(value)) = value else {
+                continue;
+            };
+
+            let received = Instant::now();
+            last_heard = received;
+            let events = match decoder.decode_moves(&value) {
+                Ok(events) => events,
+                Err(error) => {
+                    debug!(%error, bytes = value.len(), "Could not decode last moves");
+                    metrics::decrypt_failure();
+                    continue;
+                }
+            };
+            metrics::stage_latency(Stage::Decode, received.elapsed());
+
+            for event in events {
+                if let CubeEvent::Move(m) = event {
+                    debug!(counter = decoder.move_count(), %m, "Move");
+                    metrics::move_received();
+                    let _ = tx.send(CubeEvent::Received(received));
+                }
+
+                if tx.send(event).is_err() {
+                    warn!("Nothing is listening for cube events, disconnecting");
+                    let _ = device.disconnect().await;
+                    return;
+                }
+            }
+        }
+
+        let _ = tx.send(CubeEvent::Disconnected);
+    };
+    tokio::spawn(poll.instrument(span));
+
+    Ok(rx)
+}
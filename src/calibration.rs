@@ -0,0 +1,122 @@
+//! Calibrating the cube's orientation: after a gesture, the cube is held
+//! still in the position taken as neutral, and orientations are reported
+//! relative to it from then on, so it does not matter which way up the cube
+//! was picked up.
+
+use std::time::Duration;
+
+use tracing::info;
+
+use crate::cube::{CubeEvent, Move, Quaternion};
+
+/// How far the cube may drift, in radians, and still count as held still.
+pub const STILL_TOLERANCE: f32 = 0.09;
+
+/// Follows the cube's events, calibrating when the gesture is turned.
+#[derive(Debug, Clone)]
+pub struct Calibrator {
+    gesture: Vec<Move>,
+    hold: Duration,
+    /// The last moves turned, matched against the gesture.
+    recent: Vec<Move>,
+    /// Whether the gesture was turned and the cube is to be held still.
+    waiting: bool,
+    /// Since when and where the cube has been held still.
+    still: Option<(Duration, Quaternion)>,
+    reference: Option<Quaternion>,
+}
+
+impl Calibrator {
+    /// Calibrates when `gesture` is turned and the cube is then held still
+    /// for `hold`.
+    pub fn new(gesture: Vec<Move>, hold: Duration) -> Self {
+        Self {
+            gesture,
+            hold,
+            recent: Vec::new(),
+            waiting: false,
+            still: None,
+            reference: None,
+        }
+    }
+
+    /// The neutral orientation, as the cube reports it.
+    pub fn reference(&self) -> Option<Quaternion> {
+        self.reference
+    }
+
+    /// Starts from a neutral orientation calibrated before.
+    pub fn set_reference(&mut self, reference: Option<Quaternion>) {
+        self.reference = reference;
+    }
+
+    /// Follows `event`, received at `at` since a start that is the same for
+    /// every call, returning it with its orientation made relative to the
+    /// neutral one.
+    pub fn push(&mut self, event: CubeEvent, at: Duration) -> CubeEvent {
+        match event {
+            CubeEvent::Move(m) if !self.gesture.is_empty() => {
+                self.recent.push(m);
+                if self.recent.len() > self.gesture.len() {
+                    self.recent.remove(0);
+                }
+                if self.recent == self.gesture {
+                    info!("Hold the cube still in its neutral position to calibrate");
+                    self.recent.clear();
+                    self.waiting = true;
+                    self.still = None;
+                }
+                event
+            }
+            CubeEvent::Orientation(q) => {
+                if self.waiting {
+                    self.hold_still(q, at);
+                }
+                match self.reference {
+                    Some(reference) => CubeEvent::Orientation(multiply(conjugate(reference), q)),
+                    None => event,
+                }
+            }
+            _ => event,
+        }
+    }
+
+    fn hold_still(&mut self, q: Quaternion, at: Duration) {
+        match self.still {
+            Some((since, anchor)) if angle_between(anchor, q) <= STILL_TOLERANCE => {
+                if at.saturating_sub(since) >= self.hold {
+                    info!("Calibrated the cube's orientation");
+                    self.reference = Some(q);
+                    self.waiting = false;
+                    self.still = None;
+                }
+            }
+            _ => self.still = Some((at, q)),
+        }
+    }
+}
+
+fn conjugate(q: Quaternion) -> Quaternion {
+    Quaternion {
+        x: -q.x,
+        y: -q.y,
+        z: -q.z,
+        w: q.w,
+    }
+}
+
+/// The rotation `b` followed by `a`.
+fn multiply(a: Quaternion, b: Quaternion) -> Quaternion {
+    Quaternion {
+        x: a.w * b.x + a.x * b.w + a.y * b.z - a.z * b.y,
+        y: a.w * b.y - a.x * b.z + a.y * b.w + a.z * b.x,
+        z: a.w * b.z + a.x * b.y - a.y * b.x + a.z * b.w,
+        w: a.w * b.w - a.x * b.x - a.y * b.y - a.z * b.z,
+    }
+}
+
+/// The angle of the rotation from `a` to `b`, in radians.
+fn angle_between(a: Quaternion, b: Quaternion) -> f32 {
+    let dot = a.x * b.x + a.y * b.y + a.z * b.z + a.w * b.w;
+    2.0 * dot.abs().min(1.0).acos()
+}
@@ -0,0 +1,402 @@
+//! Recognizing the OLL and PLL case of each solve from the tracked state, and
+//! timing how long each case took to recognize and execute.
+
+use std::{
+    collections::BTreeMap,
+    fmt,
+    sync::OnceLock,
+    time::{Duration, Instant},
+};
+
+use futures::{Stream, StreamExt};
+use tokio::select;
+use tokio_util::sync::CancellationToken;
+use tracing::info;
+
+use crate::{
+    algorithm::{Algorithm, Layer, Turn},
+    cube::{CubeEvent, Face},
+    facelet::FaceletCube,
+    solve::{Solve, SolveRecorder},
+};
+
+/// An algorithm solving each OLL case, by its number.
+const OLL: [(&str, &str); 57] = [
+    ("1", "R U2 R2 F R F' U2 R' F R F'"),
+    ("2", "F R U R' U' F' f R U R' U' f'"),
+    ("3", "f R U R' U' f' U' F R U R' U' F'"),
+    ("4", "f R U R' U' f' U F R U R' U' F'"),
+    ("5", "l' U2 L U L' U l"),
+    ("6", "r U2 R' U' R U' r'"),
+    ("7", "r U R' U R U2 r'"),
+    ("8", "l' U' L U' L' U2 l"),
+    ("9", "R U R' U' R' F R2 U R' U' F'"),
+    ("10", "R U R' U R' F R F' R U2 R'"),
+    ("11", "r U R' U R' F R F' R U2 r'"),
+    ("12", "M' R' U' R U' R' U2 R U' R r'"),
+    ("13", "F U R U' R2 F' R U R U' R'"),
+    ("14", "R' F R U R' F' R F U' F'"),
+    ("15", "l' U' l L' U' L U l' U l"),
+    ("16", "r U r' R U R' U' r U' r'"),
+    ("17", "R U R' U R' F R F' U2 R' F R F'"),
+    ("18", "r U R' U R U2 r2 U' R U' R' U2 r"),
+    ("19", "r' R U R U R' U' M' R' F R F'"),
+    ("20", "r U R' U' M2 U R U' R' U' M'"),
+    ("21", "R U2 R' U' R U R' U' R U' R'"),
+    ("22", "R U2 R2 U' R2 U' R2 U2 R"),
+    ("23", "R2 D' R U2 R' D R U2 R"),
+    ("24", "r U R' U' r' F R F'"),
+    ("25", "F' r U R' U' r' F R"),
+    ("26", "R U2 R' U' R U' R'"),
+    ("27", "R U R' U R U2 R'"),
+    ("28", "r U R' U' r' R U R U' R'"),
+    ("29", "R U R' U' R U' R' F' U' F R U R'"),
+    ("30", "F R' F R2 U' R' U' R U R' F2"),
+    ("31", "R' U' F U R U' R' F' R"),
+    ("32", "L U F' U' L' U L F L'"),
+    ("33", "R U R' U' R' F R F'"),
+    ("34", "R U R2 U' R' F R U R U' F'"),
+    ("35", "R U2 R2 F R F' R U2 R'"),
+    ("36", "L' U' L U' L' U L U L F' L' F"),
+    ("37", "F R' F' R U R U' R'"),
+    ("38", "R U R' U R U' R' U' R' F R F'"),
+    ("39", "L F' L' U' L U F U' L'"),
+    ("40", "R' F R U R' U' F' U R"),
+    ("41", "R U R' U R U2 R' F R U R' U' F'"),
+    ("42", "R' U' R U' R' U2 R F R U R' U' F'"),
+    ("43", "F' U' L' U L F"),
+    ("44", "F U R U' R' F'"),
+    ("45", "F R U R' U' F'"),
+    ("46", "R' U' R' F R F' U R"),
+    ("47", "R' U' R' F R F' R' F R F' U R"),
+    ("48", "F R U R' U' R U R' U' F'"),
+    ("49", "r U' r2 U r2 U r2 U' r"),
+    ("50", "r' U r2 U' r2 U' r2 U r'"),
+    ("51", "F U R U' R' U R U' R' F'"),
+    ("52", "R U R' U R U' B U' B' R'"),
+    ("53", "l' U2 L U L' U' L U L' U l"),
+    ("54", "r U2 R' U' R U R' U' R U' r'"),
+    ("55", "R' F R U R U' R2 F' R2 U' R' U R U R'"),
+    ("56", "r' U' r U' R' U R U' R' U R r' U r"),
+    ("57", "R U R' U' M' U R U' r'"),
+];
+
+/// An algorithm solving each PLL case, by its name.
+const PLL: [(&str, &str); 21] = [
+    ("Aa", "x R' U R' D2 R U' R' D2 R2 x'"),
+    ("Ab", "x R2 D2 R U R' D2 R U' R x'"),
+    ("E", "x' R U' R' D R U R' D' R U R' D R U' R' D' x"),
+    ("F", "R' U' F' R U R' U' R' F R2 U' R' U' R U R' U R"),
+    ("Ga", "R2 U R' U R' U' R U' R2 U' D R' U R D'"),
+    ("Gb", "R' U' R U D' R2 U R' U R U' R U' R2 D"),
+    ("Gc", "R2 U' R U' R U R' U R2 U D' R U' R' D"),
+    ("Gd", "R U R' U' D R2 U' R U' R' U R' U R2 D'"),
+    ("H", "M2 U M2 U2 M2 U M2"),
+    ("Ja", "x R2 F R F' R U2 r' U r U2 x'"),
+    ("Jb", "R U R' F' R U R' U' R' F R2 U' R'"),
+    (
+        "Na",
+        "R U R' U R U R' F' R U R' U' R' F R2 U' R' U2 R U' R'",
+    ),
+    ("Nb", "R' U R U' R' F' U' F R U R' F R' F' R U' R"),
+    ("Ra", "R U' R' U' R U R D R' U' R D' R' U2 R'"),
+    ("Rb", "R2 F R U R U' R' F' R U2 R' U2 R"),
+    ("T", "R U R' U' R' F R2 U' R' U' R U R' F'"),
+    ("Ua", "M2 U M U2 M' U M2"),
+    ("Ub", "M2 U' M U2 M' U' M2"),
+    ("V", "R' U R' U' y R' F' R2 U' R' U R' F R F"),
+    ("Y", "F R U' R' U' R U R' F' R U R' U' R' F R F'"),
+    ("Z", "M' U M2 U M2 U M' U2 M2"),
+];
+
+/// The U facelets followed by the top row of the F, R, B and L faces, the
+/// stickers the last layer cases are told apart by.
+const LAST_LAYER: [usize; 21] = [
+    0, 1, 2, 3, 4, 5, 6, 7, 8, 18, 19, 20, 9, 10, 11, 45, 46, 47, 36, 37, 38,
+];
+
+#[derive(PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Debug)]
+pub enum Step {
+    Oll,
+    Pll,
+}
+
+#[derive(PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Debug)]
+pub struct Case {
+    pub step: Step,
+    /// The case number or name, `skip` when the step was already solved or
+    /// `unknown` if it could not be recognized.
+    pub name: &'static str,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct CaseTime {
+    pub case: Case,
+    /// From the move finishing the previous step to the next move.
+    pub recognition: Duration,
+    /// From the first move of the step to the move finishing it.
+    pub execution: Duration,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct LastLayer {
+    pub oll: CaseTime,
+    pub pll: CaseTime,
+}
+
+/// Totals for every case seen over a session.
+#[derive(Debug, Default, Clone)]
+pub struct CaseReport {
+    cases: BTreeMap<Case, CaseStats>,
+}
+
+#[derive(Debug, Default, Clone, Copy)]
+pub struct CaseStats {
+    pub count: u32,
+    pub recognition: Duration,
+    pub execution: Duration,
+}
+
+type Patterns = Vec<(&'static str, Vec<[Face; 21]>)>;
+
+impl fmt::Display for Step {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Step::Oll => "OLL",
+            Step::Pll => "PLL",
+        })
+    }
+}
+
+impl fmt::Display for Case {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {}", self.step, self.name)
+    }
+}
+
+impl Case {
+    fn skip(step: Step) -> Self {
+        Self { step, name: "skip" }
+    }
+}
+
+impl CaseStats {
+    pub fn mean(&self) -> Duration {
+        (self.recognition + self.execution) / self.count.max(1)
+    }
+}
+
+/// The last layer stickers of `cube` seen from `top`, with stickers that do
+/// not matter for `step` blanked out. For OLL only whether a sticker is the
+/// top color matters.
+fn pattern(cube: &FaceletCube, top: Face, step: Step) -> [Face; 21] {
+    let cube = cube.with_on_top(top);
+    LAST_LAYER.map(|i| match step {
+        Step::Oll if cube.0[i] == Face::U => Face::U,
+        Step::Oll => Face::D,
+        Step::Pll => cube.0[i],
+    })
+}
+
+/// Every pattern each case can show, with any turn of U before and after.
+fn patterns(step: Step) -> &'static Patterns {
+    static OLL_PATTERNS: OnceLock<Patterns> = OnceLock::new();
+    static PLL_PATTERNS: OnceLock<Patterns> = OnceLock::new();
+
+    let (cell, algorithms) = match step {
+        Step::Oll => (&OLL_PATTERNS, &OLL[..]),
+        Step::Pll => (&PLL_PATTERNS, &PLL[..]),
+    };
+
+    cell.get_or_init(|| {
+        algorithms
+            .iter()
+            .map(|&(name, algorithm)| {
+                let inverse = algorithm
+                    .parse::<Algorithm>()
+                    .expect("case algorithms are valid")
+                    .inverse();
+
+                let mut patterns = Vec::new();
+                for before in 0..4 {
+                    for after in 0..4 {
+                        let mut cube = FaceletCube::SOLVED;
+                        cube.apply(u_turn(after));
+                        cube.apply_algorithm(&inverse);
+                        cube.apply(u_turn(before));
+                        patterns.push(pattern(&cube, Face::U, step));
+                    }
+                }
+
+                (name, patterns)
+            })
+            .collect()
+    })
+}
+
+fn u_turn(amount: i8) -> Turn {
+    Turn {
+        layer: Layer::Face(Face::U),
+        amount,
+    }
+}
+
+/// The case `cube` is in for `step`, with the first two layers solved under
+/// `top`.
+pub fn recognize(cube: &FaceletCube, top: Face, step: Step) -> Case {
+    let solved = match step {
+        Step::Oll => cube.face_solved(top),
+        Step::Pll => Face::ALL.iter().all(|&face| cube.face_solved(face)),
+    };
+    if solved {
+        return Case::skip(step);
+    }
+
+    let observed = pattern(cube, top, step);
+    let name = patterns(step)
+        .iter()
+        .find(|(_, patterns)| patterns.contains(&observed))
+        .map_or("unknown", |&(name, _)| name);
+
+    Case { step, name }
+}
+
+/// Finds where the first two layers and OLL were finished in `solve` and the
+/// cases that came up. Returns `None` for solves that were not solved layer
+/// by layer.
+pub fn last_layer(solve: &Solve) -> Option<LastLayer> {
+    let mut state = solve.scramble;
+    let mut f2l = None;
+    let mut oll = None;
+
+    for (i, m) in solve.moves.iter().enumerate() {
+        state.apply(m.m);
+        let cube = FaceletCube::from(&state);
+
+        if f2l.is_none()
+            && let Some(top) = Face::ALL.into_iter().find(|&f| cube.solved_below(f))
+        {
+            f2l = Some((i, top, recognize(&cube, top, Step::Oll)));
+        }
+
+        if let (Some((_, top, _)), None) = (f2l, oll)
+            && cube.solved_below(top)
+            && cube.face_solved(top)
+        {
+            oll = Some((i, recognize(&cube, top, Step::Pll)));
+        }
+    }
+
+    let (f2l, _, oll_case) = f2l?;
+    let (oll, pll_case) = oll?;
+
+    let offset = |i: usize| solve.moves[i].offset;
+    let next = |i: usize| solve.moves.get(i + 1).map_or(solve.duration, |m| m.offset);
+
+    let time = |case: Case, done: usize, finished: Duration| {
+        if case.name == "skip" {
+            CaseTime {
+                case,
+                recognition: Duration::ZERO,
+                execution: Duration::ZERO,
+            }
+        } else {
+            CaseTime {
+                case,
+                recognition: next(done) - offset(done),
+                execution: finished.saturating_sub(next(done)),
+            }
+        }
+    };
+
+    Some(LastLayer {
+        oll: time(oll_case, f2l, offset(oll)),
+        pll: time(pll_case, oll, solve.duration),
+    })
+}
+
+impl CaseReport {
+    pub fn add(&mut self, last_layer: &LastLayer) {
+        for time in [last_layer.oll, last_layer.pll] {
+            if time.case.name == "skip" {
+                continue;
+            }
+
+            let stats = self.cases.entry(time.case).or_default();
+            stats.count += 1;
+            stats.recognition += time.recognition;
+            stats.execution += time.execution;
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.cases.is_empty()
+    }
+
+    /// Every case seen, slowest on average first.
+    pub fn slowest(&self) -> Vec<(Case, CaseStats)> {
+        let mut cases: Vec<_> = self.cases.iter().map(|(&c, &s)| (c, s)).collect();
+        cases.sort_by_key(|(_, stats)| std::cmp::Reverse(stats.mean()));
+        cases
+    }
+}
+
+impl fmt::Display for CaseReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "case      count  recognition  execution")?;
+
+        for (case, stats) in self.slowest() {
+            let count = stats.count.max(1);
+            writeln!(
+                f,
+                "{:<9} {:>5}  {:>10.2}s  {:>8.2}s",
+                case.to_string(),
+                stats.count,
+                (stats.recognition / count).as_secs_f64(),
+                (stats.execution / count).as_secs_f64()
+            )?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Logs the last layer cases of every solve in `events` until `cancel` is
+/// cancelled, returning the report for the session.
+pub async fn log_cases(
+    mut events: impl Stream<Item = CubeEvent> + Unpin,
+    cancel: CancellationToken,
+) -> CaseReport {
+    let mut recorder = SolveRecorder::new();
+    let mut report = CaseReport::default();
+
+    loop {
+        let event = select! {
+            event = events.next() => event,
+            _ = cancel.cancelled() => return report,
+        };
+
+        let Some(event) = event else {
+            return report;
+        };
+
+        let Some(last_layer) = recorder
+            .push(&event, Instant::now())
+            .as_ref()
+            .and_then(last_layer)
+        else {
+            continue;
+        };
+
+        for time in [last_layer.oll, last_layer.pll] {
+            info!(
+                "{}: recognized in {:.2}s, executed in {:.2}s",
+                time.case,
+                time.recognition.as_secs_f64(),
+                time.execution.as_secs_f64()
+            );
+        }
+
+        report.add(&last_layer);
+    }
+}
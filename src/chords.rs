@@ -0,0 +1,142 @@
+//! Typing text with two-move chords, turning the cube into a chorded keyboard.
+//! The chords become binds in their own group, so they can be switched on and
+//! off like any other group.
+//!
+//! The built-in layout picks a set of four keys with the first move and a key
+//! from the set with the second, `U`, `U'`, `D` or `D'`:
+//!
+//! | First | `U` | `U'` | `D` | `D'` |
+//! |-------|-----|------|-----|------|
+//! | `R`   | a   | b    | c   | d    |
+//! | `R'`  | e   | f    | g   | h    |
+//! | `L`   | i   | j    | k   | l    |
+//! | `L'`  | m   | n    | o   | p    |
+//! | `F`   | q   | r    | s   | t    |
+//! | `F'`  | u   | v    | w   | x    |
+//! | `B`   | y   | z    | space | backspace |
+//! | `B'`  | enter | .  | ,   | '    |
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    config::{Action, Bind, Key, deserialize_trigger, serialize_trigger},
+    cube::Move,
+};
+
+/// The first moves of the built-in layout, each picking a row of keys.
+const ROWS: [Move; 8] = [
+    Move::R,
+    Move::Rp,
+    Move::L,
+    Move::Lp,
+    Move::F,
+    Move::Fp,
+    Move::B,
+    Move::Bp,
+];
+
+/// The second moves of the built-in layout, each picking a key of the row.
+const COLUMNS: [Move; 4] = [Move::U, Move::Up, Move::D, Move::Dp];
+
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct TextEntry {
+    /// The group of the chord binds.
+    #[serde(default = "default_group")]
+    pub group: String,
+    /// A trigger switching typing on and off, which should not start like a
+    /// chord. Typing starts off when set, and is always on otherwise.
+    #[serde(
+        default,
+        deserialize_with = "deserialize_toggle",
+        serialize_with = "serialize_toggle",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub toggle: Option<Vec<Move>>,
+    /// Chords replacing the built-in layout.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub layout: Vec<Chord>,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct Chord {
+    #[serde(
+        deserialize_with = "deserialize_trigger",
+        serialize_with = "serialize_trigger"
+    )]
+    pub trigger: Vec<Move>,
+    #[serde(deserialize_with = "crate::keys::deserialize_key")]
+    pub key: Key,
+}
+
+fn default_group() -> String {
+    "text".to_string()
+}
+
+fn deserialize_toggle<'de, D: serde::Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Option<Vec<Move>>, D::Error> {
+    deserialize_trigger(deserializer).map(Some)
+}
+
+fn serialize_toggle<S: serde::Serializer>(
+    toggle: &Option<Vec<Move>>,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    serialize_trigger(toggle.as_deref().unwrap_or_default(), serializer)
+}
+
+/// The chords of the built-in layout, in the order of the table above.
+pub fn builtin_layout() -> Vec<Chord> {
+    let letters = ('a'..='z').map(Key::Unicode);
+    let rest = [
+        Key::Space,
+        Key::Backspace,
+        Key::Return,
+        Key::Unicode('.'),
+        Key::Unicode(','),
+        Key::Unicode('\''),
+    ];
+
+    ROWS.iter()
+        .flat_map(|&first| COLUMNS.iter().map(move |&second| vec![first, second]))
+        .zip(letters.chain(rest))
+        .map(|(trigger, key)| Chord { trigger, key })
+        .collect()
+}
+
+impl TextEntry {
+    /// The layout's chords, the built-in one unless another is given.
+    pub fn chords(&self) -> Vec<Chord> {
+        if self.layout.is_empty() {
+            builtin_layout()
+        } else {
+            self.layout.clone()
+        }
+    }
+
+    /// A bind typing each chord's key, and one for the toggle if set.
+    pub fn binds(&self) -> Vec<Bind> {
+        let mut binds: Vec<Bind> = self
+            .chords()
+            .into_iter()
+            .map(|chord| Bind {
+                name: Some(format!("Type {:?}", chord.key)),
+                trigger: chord.trigger,
+                actions: vec![Action::Click(chord.key)],
+                groups: vec![self.group.clone()],
+                ..Bind::default()
+            })
+            .collect();
+
+        if let Some(toggle) = &self.toggle {
+            binds.push(Bind {
+                name: Some(format!("Toggle {}", self.group)),
+                trigger: toggle.clone(),
+                actions: vec![Action::ToggleGroup(self.group.clone())],
+                ..Bind::default()
+            });
+        }
+
+        binds
+    }
+}
@@ -1,24 +1,822 @@
-use enigo::Key;
-use serde::Deserialize;
+use std::{
+    collections::HashMap,
+    fmt, fs,
+    net::SocketAddr,
+    path::{Path, PathBuf},
+    str::FromStr,
+    time::Duration,
+};
 
-use crate::cube::Move;
+use serde::{
+    Deserialize, Deserializer, Serialize, Serializer,
+    de::{self, SeqAccess, Visitor},
+};
 
-#[derive(Deserialize, Debug)]
+use crate::{
+    algorithm::Algorithm,
+    calibration::Calibrator,
+    cube::Move,
+    error::ConfigError,
+    modifiers::Modifier,
+    phase::Phase,
+    protocol::{cipher::CipherKeys, timer::TimerState},
+    rate_limit::RateLimit,
+    retry::RetryPolicy,
+    smoothing::Smoothing,
+    strict,
+};
+
+#[cfg(not(target_arch = "wasm32"))]
+pub use enigo::Key;
+
+#[cfg(all(feature = "focus", not(target_arch = "wasm32")))]
+use crate::focus::Focus;
+#[cfg(feature = "hue")]
+use crate::hue::{HueBridge, HueCommand};
+#[cfg(feature = "spotify")]
+use crate::spotify::{Spotify, SpotifyCommand};
+#[cfg(feature = "twitch")]
+use crate::twitch::{Twitch, TwitchCommand};
+#[cfg(feature = "webhook")]
+use crate::webhook::Webhook;
+#[cfg(not(target_arch = "wasm32"))]
+use crate::{
+    chords::TextEntry, dial::Dial, morse::Morse, movement::Movement, numbers::NumberEntry,
+};
+#[cfg(all(feature = "input", not(target_arch = "wasm32")))]
+use crate::{hotkeys::Hotkeys, switches::Switches};
+
+/// Keys cannot be injected from a browser, so on the web they are kept as the
+/// raw config value for display.
+#[cfg(target_arch = "wasm32")]
+pub type Key = ron::Value;
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
 pub struct Config {
     pub timeout: u64,
     pub binds: Vec<Bind>,
+    /// Address to serve Prometheus metrics on, e.g. `Some("127.0.0.1:9898")`.
+    #[serde(default, skip_serializing_if = "is_default")]
+    pub metrics: Option<SocketAddr>,
+    /// Address to serve the streaming overlay on, e.g. `Some("127.0.0.1:9899")`.
+    #[serde(default, skip_serializing_if = "is_default")]
+    pub overlay: Option<SocketAddr>,
+    /// Address to serve the HTTP API on, for Stream Deck buttons and scripts,
+    /// e.g. `Some("127.0.0.1:9900")`.
+    #[serde(default, skip_serializing_if = "is_default")]
+    pub api: Option<SocketAddr>,
+    /// Allow serving the API on an address other machines can reach. It has
+    /// no authentication and can fire binds that run commands.
+    #[serde(default, skip_serializing_if = "is_default")]
+    pub api_remote: bool,
+    /// Address to serve the gRPC service on, e.g. `Some("127.0.0.1:9901")`.
+    /// Requires the `grpc` feature.
+    #[serde(default, skip_serializing_if = "is_default")]
+    pub grpc: Option<SocketAddr>,
+    /// Pop up a desktop notification with the name and moves of each bind
+    /// as it fires. Uses `notify-send` on Linux.
+    #[serde(default, skip_serializing_if = "is_default")]
+    pub osd: bool,
+    /// Address to send moves and orientation to as OSC messages over UDP,
+    /// for games, e.g. `Some("127.0.0.1:9000")`.
+    #[serde(default, skip_serializing_if = "is_default")]
+    pub udp: Option<SocketAddr>,
+    /// Smooths the cube's orientation before anything sees it, e.g.
+    /// `Some(OneEuro(min_cutoff: 1.0, beta: 0.01))`.
+    #[serde(default, skip_serializing_if = "is_default")]
+    pub smoothing: Option<Smoothing>,
+    #[serde(default, skip_serializing_if = "is_default")]
+    pub backend: Backend,
+    /// Disconnect from the cube after a while without moves so it can sleep,
+    /// e.g. `Some((minutes: 10, rescan: Some(15)))`.
+    #[serde(default, skip_serializing_if = "is_default")]
+    pub idle: Option<Idle>,
+    /// Path of a Unix socket to take commands on, such as `wake` to connect
+    /// to an idle cube again, e.g. `Some("/tmp/triplicata.sock")`.
+    #[serde(default, skip_serializing_if = "is_default")]
+    pub control: Option<PathBuf>,
+    /// Number of recent moves to keep for the `history` and `since` commands
+    /// on the control socket and the API, e.g. `Some(100)`.
+    #[serde(default, skip_serializing_if = "is_default")]
+    pub history: Option<usize>,
+    /// Minutes between logging the cube's battery level, e.g. `Some(10)`.
+    /// The level is always answered by the `status` command and exported
+    /// as a metric.
+    #[serde(default, skip_serializing_if = "is_default")]
+    pub battery_report: Option<u64>,
+    /// Seconds between asking the cube for its battery level, e.g. `Some(60)`,
+    /// rather than every half second. Asking less often spares the cube's
+    /// battery, but a dead connection takes longer to notice.
+    #[serde(default, skip_serializing_if = "is_default")]
+    pub battery_poll: Option<u64>,
+    /// File to save the state of each cube to, restored when it connects
+    /// again if the cube forgot its state, e.g. `Some("cube-state.json")`.
+    #[serde(default, skip_serializing_if = "is_default")]
+    pub cube_state: Option<PathBuf>,
+    /// Calibrate the cube's orientation by turning a gesture then holding
+    /// the cube still in its neutral position, e.g. `Some((gesture: "D D D D"))`.
+    /// Orientations are then reported relative to it. Saved with each cube's
+    /// state, so it needs `cube_state`.
+    #[serde(default, skip_serializing_if = "is_default")]
+    pub calibration: Option<Calibration>,
+    /// Base key and IV for the cube's cipher, for firmware that does not use
+    /// GAN's, e.g. `(key: "01024228...", iv: "11033228...")`.
+    #[serde(default, skip_serializing_if = "is_default")]
+    pub cipher: CipherKeys,
+    /// Timeouts and retries for each step of connecting to the cube, e.g.
+    /// `(timeout: 10000, attempts: 3, backoff: 1000)` in milliseconds.
+    #[serde(default, skip_serializing_if = "is_default")]
+    pub connection: RetryPolicy,
+    /// Advertise as a GAN cube so csTimer can connect through triplicata,
+    /// Linux only and requires the `cstimer` feature.
+    #[serde(default, skip_serializing_if = "is_default")]
+    pub cstimer: bool,
+    /// Connect to a GAN Smart Timer as well as the cube.
+    #[serde(default, skip_serializing_if = "is_default")]
+    pub smart_timer: bool,
+    /// Start WCA inspection by tapping the smart timer, and judge each solve
+    /// with +2 and DNF penalties.
+    #[serde(default, skip_serializing_if = "is_default")]
+    pub inspection: bool,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub timer_binds: Vec<TimerBind>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub redemption_binds: Vec<RedemptionBind>,
+    /// Bind groups that start disabled.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub disabled_groups: Vec<String>,
+    /// A trigger that stops everything, e.g. `"L L' L L'"`: held keys are
+    /// released and the binds paused. It works even while paused or typing,
+    /// for when a bind misbehaves, and the binds stay paused until resumed.
+    #[serde(
+        default,
+        skip_serializing_if = "Vec::is_empty",
+        deserialize_with = "deserialize_trigger",
+        serialize_with = "serialize_trigger"
+    )]
+    pub kill_gesture: Vec<Move>,
+    /// How often binds may fire altogether, on top of each bind's own
+    /// `rate_limit`, e.g. `Some((max: 10, per: Second))`.
+    #[serde(default, skip_serializing_if = "is_default")]
+    pub rate_limit: Option<RateLimit>,
+    /// Cues for binds without their own, by group, e.g.
+    /// `{"media": Tone(660), "editor": Sound("click.wav")}`.
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub cues: HashMap<String, Cue>,
+    /// Switch bind groups as the focused window changes, e.g.
+    /// `Some((profiles: [(name: "Code", windows: ["code"], groups: ["editor"])]))`.
+    /// Uses `xdotool` on Linux.
+    #[cfg(all(feature = "focus", not(target_arch = "wasm32")))]
+    #[serde(default, skip_serializing_if = "is_default")]
+    pub focus: Option<Focus>,
+    /// Type with two-move chords, e.g. `Some((toggle: "D"))` for the built-in
+    /// layout, switched on and off by turning D.
+    #[cfg(not(target_arch = "wasm32"))]
+    #[serde(default, skip_serializing_if = "is_default")]
+    pub text_entry: Option<TextEntry>,
+    /// Type Morse code with quick flicks, e.g. `Some((dot: U))` for U as a dot
+    /// and U' as a dash. Turned on and off through its group, `morse` unless
+    /// set, which can be listed in `disabled_groups`.
+    #[cfg(not(target_arch = "wasm32"))]
+    #[serde(default, skip_serializing_if = "is_default")]
+    pub morse: Option<Morse>,
+    /// Enter numbers with a digit on each quarter turn, e.g.
+    /// `Some((toggle: "D D", on_confirm: [Run("wmctrl -s {value}")]))`.
+    #[cfg(not(target_arch = "wasm32"))]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub number_entry: Option<NumberEntry>,
+    /// Turn a face like a dial, e.g. `Some((face: R, target: Volume))`.
+    #[cfg(not(target_arch = "wasm32"))]
+    #[serde(default, skip_serializing_if = "is_default")]
+    pub dial: Option<Dial>,
+    /// Hold movement keys down with the faces, WASD on U, L, D and R unless
+    /// set, e.g. `Some((stop: "F F'"))`. Turned on and off through its
+    /// group, `movement` unless set.
+    #[cfg(not(target_arch = "wasm32"))]
+    #[serde(default, skip_serializing_if = "is_default")]
+    pub movement: Option<Movement>,
+    /// Directory to write each solve to as JSON and SRT move lists, e.g.
+    /// `Some("solves")`.
+    #[serde(default, skip_serializing_if = "is_default")]
+    pub solves: Option<PathBuf>,
+    /// SQLite database to save every solve to with its scramble, moves and
+    /// splits, each run as a session, e.g. `Some("solves.db")`.
+    #[serde(default, skip_serializing_if = "is_default")]
+    pub database: Option<PathBuf>,
+    /// File to record the session's cube events to, replaced on each start,
+    /// to play back with `triplicata replay`, e.g. `Some("session.txt")`.
+    #[serde(default, skip_serializing_if = "is_default")]
+    pub session: Option<PathBuf>,
+    /// Phases to split exported solves into, like csTimer's multi-phase
+    /// timing, e.g. `[(name: "Cross", end: Milestone(Cross)), ...]`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub phases: Vec<Phase>,
+    /// Log the OLL and PLL case of each solve, and print the slowest cases
+    /// of the session on exit.
+    #[serde(default, skip_serializing_if = "is_default")]
+    pub cases: bool,
+    /// Type `Unicode` keys as text through the active keyboard layout rather
+    /// than clicking the key enigo picks for the character, for layouts
+    /// where clicks come out as the wrong character.
+    #[serde(default, skip_serializing_if = "is_default")]
+    pub type_unicode: bool,
+    /// Type through a virtual evdev keyboard named `triplicata`, which
+    /// remappers such as keyd and games reading evdev see as a real keyboard.
+    /// Linux only, and needs write access to `/dev/uinput`.
+    #[serde(default, skip_serializing_if = "is_default")]
+    pub evdev: bool,
+    /// Milliseconds after a key is typed on a keyboard during which moves are
+    /// ignored, so the cube on the desk cannot type into a document being
+    /// written, e.g. `Some(2000)`. Read through evdev, Linux only.
+    #[serde(default, skip_serializing_if = "is_default")]
+    pub typing_quiet: Option<u64>,
+    /// The assistive switches that `Switch` actions press, e.g.
+    /// `Some((keys: [space, enter]))`.
+    #[cfg(all(feature = "input", not(target_arch = "wasm32")))]
+    #[serde(default, skip_serializing_if = "is_default")]
+    pub switches: Option<Switches>,
+    /// Keyboard shortcuts that pause, switch profile, resync, stop everything
+    /// or quit, e.g. `Some((pause: Some((held: [Control, Alt], key: p))))`.
+    /// Read through evdev, Linux only.
+    #[cfg(all(feature = "input", not(target_arch = "wasm32")))]
+    #[serde(default, skip_serializing_if = "is_default")]
+    pub hotkeys: Option<Hotkeys>,
+    /// The Hue bridge that `Hue` actions control.
+    #[cfg(feature = "hue")]
+    #[serde(default, skip_serializing_if = "is_default")]
+    pub hue: Option<HueBridge>,
+    /// The Spotify account that `Spotify` actions control.
+    #[cfg(feature = "spotify")]
+    #[serde(default, skip_serializing_if = "is_default")]
+    pub spotify: Option<Spotify>,
+    /// The Twitch account that `Twitch` actions and redemption binds use.
+    #[cfg(feature = "twitch")]
+    #[serde(default, skip_serializing_if = "is_default")]
+    pub twitch: Option<Twitch>,
+    /// URLs to POST events to as JSON, e.g.
+    /// `[(url: "https://example.com/hook", events: [Solved])]`.
+    #[cfg(feature = "webhook")]
+    #[serde(default, skip_serializing_if = "is_default")]
+    pub webhooks: Vec<Webhook>,
+}
+
+/// The bluetooth stack used to talk to the cube.
+#[derive(Deserialize, Serialize, Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum Backend {
+    #[default]
+    Btleplug,
+    /// BlueZ over D-Bus, Linux only and requires the `bluez` feature.
+    Bluez,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Idle {
+    /// Minutes without a move before disconnecting.
+    pub minutes: u64,
+    /// Minutes after disconnecting to scan for the cube again, long enough
+    /// for it to fall asleep so it reconnects once picked up. Without it the
+    /// cube is only connected again by a `wake` command on the control socket.
+    #[serde(default, skip_serializing_if = "is_default")]
+    pub rescan: Option<u64>,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct Calibration {
+    #[serde(
+        deserialize_with = "deserialize_trigger",
+        serialize_with = "serialize_trigger"
+    )]
+    pub gesture: Vec<Move>,
+    /// Milliseconds the cube is held still for.
+    #[serde(default = "default_hold")]
+    pub hold: u64,
+}
+
+fn default_hold() -> u64 {
+    2000
+}
+
+impl Calibration {
+    pub fn calibrator(&self) -> Calibrator {
+        Calibrator::new(self.gesture.clone(), Duration::from_millis(self.hold))
+    }
+}
+
+impl Config {
+    /// Replaces every `Platform` action with the actions of the current
+    /// platform.
+    pub fn resolve_platform_actions(&mut self) {
+        let lists = self
+            .binds
+            .iter_mut()
+            .map(|bind| &mut bind.actions)
+            .chain(self.timer_binds.iter_mut().map(|bind| &mut bind.actions))
+            .chain(
+                self.redemption_binds
+                    .iter_mut()
+                    .map(|bind| &mut bind.actions),
+            );
+        #[cfg(not(target_arch = "wasm32"))]
+        let lists = lists.chain(
+            self.number_entry
+                .iter_mut()
+                .map(|entry| &mut entry.on_confirm),
+        );
+
+        for actions in lists {
+            *actions = resolve_actions(std::mem::take(actions));
+        }
+    }
+
+    /// Adds the mirror of every bind marked `mirror`, unless its trigger is
+    /// already bound.
+    pub fn add_mirrored_binds(&mut self) {
+        let mirrored: Vec<_> = self
+            .binds
+            .iter()
+            .filter(|bind| bind.mirror)
+            .map(Bind::mirrored)
+            .collect();
+
+        for bind in mirrored {
+            if !self.binds.iter().any(|b| b.trigger == bind.trigger) {
+                self.binds.push(bind);
+            }
+        }
+    }
+
+    /// Adds the binds of the text entry chords, which start disabled if they
+    /// can be toggled.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn add_chord_binds(&mut self) {
+        let Some(text_entry) = &self.text_entry else {
+            return;
+        };
+
+        if text_entry.toggle.is_some() && !self.disabled_groups.contains(&text_entry.group) {
+            self.disabled_groups.push(text_entry.group.clone());
+        }
+
+        let binds = text_entry.binds();
+        self.binds.extend(binds);
+    }
+
+    /// Adds the binds of number entry, which start disabled if it has a
+    /// toggle.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn add_number_binds(&mut self) {
+        let Some(number_entry) = &self.number_entry else {
+            return;
+        };
+
+        if number_entry.toggle.is_some() && !self.disabled_groups.contains(&number_entry.group) {
+            self.disabled_groups.push(number_entry.group.clone());
+        }
+
+        let binds = number_entry.binds();
+        self.binds.extend(binds);
+    }
+
+    /// Adds the binds of the dial.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn add_dial_binds(&mut self) {
+        if let Some(dial) = &self.dial {
+            let binds = dial.binds();
+            self.binds.extend(binds);
+        }
+    }
+
+    /// Adds the binds of the movement keys.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn add_movement_binds(&mut self) {
+        if let Some(movement) = &self.movement {
+            let binds = movement.binds();
+            self.binds.extend(binds);
+        }
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, ConfigError> {
+        fs::read_to_string(path)?.parse()
+    }
+
+    /// Like [`Config::load`], but rejects fields that do not exist instead of
+    /// ignoring them, and suggests the nearest valid name for typos.
+    pub fn load_strict(path: impl AsRef<Path>) -> Result<Self, ConfigError> {
+        Self::from_str_strict(&fs::read_to_string(path)?)
+    }
+
+    pub fn from_str_strict(s: &str) -> Result<Self, ConfigError> {
+        // Fields first, so a misspelled required field is reported as a typo
+        // rather than as missing.
+        strict::check_fields(s)?;
+        ron::from_str(s).map_err(strict::suggest_variant)
+    }
+
+    /// Writes the config back out. Comments and formatting in an existing
+    /// file are not kept.
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), ConfigError> {
+        fs::write(path, to_ron(self)?)?;
+        Ok(())
+    }
+
+    /// The binds as a table with a column each for the name, trigger,
+    /// actions, groups and description, to keep as a cheat sheet.
+    pub fn bind_table(&self) -> String {
+        let header = ["name", "trigger", "actions", "groups", "description"];
+        let rows = self
+            .binds
+            .iter()
+            .map(|bind| {
+                let actions = bind.actions.iter().map(ToString::to_string);
+                [
+                    bind.name.clone().unwrap_or_default(),
+                    if bind.mirror {
+                        let mirror = Algorithm::from(bind.mirrored().trigger);
+                        format!("{} | {mirror}", Algorithm::from(bind.trigger.clone()))
+                    } else {
+                        Algorithm::from(bind.trigger.clone()).to_string()
+                    },
+                    actions.collect::<Vec<_>>().join(", "),
+                    bind.groups.join(", "),
+                    bind.description.clone().unwrap_or_default(),
+                ]
+            })
+            .collect::<Vec<_>>();
+
+        let mut widths = header.map(str::len);
+        for row in &rows {
+            for (width, cell) in widths.iter_mut().zip(row) {
+                *width = (*width).max(cell.chars().count());
+            }
+        }
+
+        let line = |row: [&str; 5]| {
+            let line = row
+                .iter()
+                .zip(widths)
+                .map(|(cell, width)| format!("{cell:<width$}"))
+                .collect::<Vec<_>>()
+                .join("  ");
+            line.trim_end().to_string()
+        };
+
+        let mut lines = vec![line(header)];
+        lines.extend(
+            rows.iter()
+                .map(|row| line(row.each_ref().map(String::as_str))),
+        );
+        lines.join("\n")
+    }
 }
 
-#[derive(Deserialize, Debug)]
+pub(crate) fn to_ron(value: &impl Serialize) -> Result<String, ConfigError> {
+    Ok(ron::ser::to_string_pretty(
+        value,
+        ron::ser::PrettyConfig::default(),
+    )?)
+}
+
+fn is_default<T: Default + PartialEq>(value: &T) -> bool {
+    *value == T::default()
+}
+
+impl FromStr for Config {
+    type Err = ConfigError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(ron::from_str(s)?)
+    }
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, Default)]
 pub struct Bind {
+    #[serde(default, skip_serializing_if = "is_default")]
+    pub name: Option<String>,
+    #[serde(default, skip_serializing_if = "is_default")]
+    pub description: Option<String>,
+    #[serde(
+        deserialize_with = "deserialize_trigger",
+        serialize_with = "serialize_trigger"
+    )]
     pub trigger: Vec<Move>,
     pub actions: Vec<Action>,
+    /// Groups the bind belongs to. The bind is off while any of them is
+    /// disabled.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub groups: Vec<String>,
+    /// Also trigger on the left-right mirror of the trigger, so a gesture
+    /// works with either hand.
+    #[serde(default, skip_serializing_if = "is_default")]
+    pub mirror: bool,
+    /// How many stray moves or turns in the wrong direction the trigger
+    /// still matches with. Stray moves are only allowed between moves of the
+    /// trigger, not before or after it.
+    #[serde(default, skip_serializing_if = "is_default")]
+    pub tolerance: usize,
+    /// Milliseconds the whole trigger has to be turned within.
+    #[serde(default, skip_serializing_if = "is_default")]
+    pub within: Option<u64>,
+    /// Played when the bind fires, instead of the cue of its group.
+    #[serde(default, skip_serializing_if = "is_default")]
+    pub cue: Option<Cue>,
+    /// Keyboard modifiers that have to be held for the bind to fire, e.g.
+    /// `[Shift]`. Read from the keyboards through evdev, Linux only.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub held: Vec<Modifier>,
+    /// How often the bind may fire, e.g.
+    /// `Some((max: 2, per: Second, policy: Queue))`.
+    #[serde(default, skip_serializing_if = "is_default")]
+    pub rate_limit: Option<RateLimit>,
+    /// What happens when the bind fires while actions sent before it are
+    /// still playing.
+    #[serde(default, skip_serializing_if = "is_default")]
+    pub overlap: Overlap,
+}
+
+#[derive(Deserialize, Serialize, Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum Overlap {
+    /// Plays the actions once everything before them has played.
+    #[default]
+    Queue,
+    /// Drops the firing while the bind's last firing is still playing, so
+    /// firings in quick succession play once.
+    Coalesce,
+    /// Skips whatever is still waiting to play, from any bind, and plays the
+    /// actions right after the action playing now.
+    Replace,
+}
+
+/// A sound confirming that a bind fired.
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, Eq)]
+pub enum Cue {
+    /// A sound file, which must be WAV on Windows.
+    Sound(PathBuf),
+    /// A short beep at a pitch in hertz, e.g. `Tone(880)`.
+    Tone(u32),
+}
+
+impl Bind {
+    /// The bind triggered by the mirror of this bind's trigger.
+    pub fn mirrored(&self) -> Self {
+        Self {
+            trigger: self.trigger.iter().map(|m| m.mirror()).collect(),
+            mirror: false,
+            ..self.clone()
+        }
+    }
+
+    /// The bind's name, or its trigger for unnamed binds.
+    pub fn label(&self) -> String {
+        match &self.name {
+            Some(name) => name.clone(),
+            None => Algorithm::from(self.trigger.clone()).to_string(),
+        }
+    }
+
+    /// The number of mistakes in `moves` if they are the whole trigger.
+    pub fn matches(&self, moves: &[Move]) -> Option<usize> {
+        self.alignment(moves)[self.trigger.len()]
+    }
+
+    /// Whether `moves` could be the start of the trigger.
+    pub fn starts_with(&self, moves: &[Move]) -> bool {
+        self.alignment(moves).iter().any(Option::is_some)
+    }
+
+    /// The fewest mistakes needed to line `moves` up with each prefix of the
+    /// trigger, `None` where that takes more than the tolerance.
+    fn alignment(&self, moves: &[Move]) -> Vec<Option<usize>> {
+        let mut mistakes = vec![None; self.trigger.len() + 1];
+        mistakes[0] = Some(0);
+
+        for &m in moves {
+            let mut next = vec![None; self.trigger.len() + 1];
+
+            for (i, &target) in self.trigger.iter().enumerate() {
+                let Some(so_far) = mistakes[i] else {
+                    continue;
+                };
+
+                let turned = if m == target {
+                    Some(so_far)
+                } else if m == target.inverse() {
+                    Some(so_far + 1)
+                } else {
+                    None
+                };
+                // A stray move between two moves of the trigger.
+                let stray = (i > 0).then_some(so_far + 1);
+
+                for (j, count) in [(i + 1, turned), (i, stray)] {
+                    if let Some(count) = count.filter(|&count| count <= self.tolerance) {
+                        next[j] = Some(next[j].map_or(count, |best: usize| best.min(count)));
+                    }
+                }
+            }
+
+            mistakes = next;
+        }
+
+        mistakes
+    }
 }
 
-#[derive(Deserialize, Debug, Clone, Copy)]
+/// Actions played when a smart timer enters `state`, e.g.
+/// `(state: Running, actions: [Click(Space)])`.
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct TimerBind {
+    pub state: TimerState,
+    pub actions: Vec<Action>,
+}
+
+/// Actions played when a Twitch viewer redeems the channel point reward titled
+/// `reward`, e.g. `(reward: "Scramble me", actions: [Twitch(Scramble)])`.
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct RedemptionBind {
+    pub reward: String,
+    pub actions: Vec<Action>,
+}
+
+/// Keys can also be given as an alias or a single character, e.g.
+/// `Click(esc)` or `Click(a)`, see [`KEY_ALIASES`](crate::keys::KEY_ALIASES).
+#[derive(Deserialize, Serialize, Debug, Clone)]
 pub enum Action {
-    Press(Key),
-    Release(Key),
-    Click(Key),
+    Press(
+        #[cfg_attr(
+            not(target_arch = "wasm32"),
+            serde(deserialize_with = "crate::keys::deserialize_key")
+        )]
+        Key,
+    ),
+    Release(
+        #[cfg_attr(
+            not(target_arch = "wasm32"),
+            serde(deserialize_with = "crate::keys::deserialize_key")
+        )]
+        Key,
+    ),
+    Click(
+        #[cfg_attr(
+            not(target_arch = "wasm32"),
+            serde(deserialize_with = "crate::keys::deserialize_key")
+        )]
+        Key,
+    ),
+    /// Presses, releases or clicks a key by its raw code rather than the
+    /// character it types, for games reading raw input and layouts other
+    /// than QWERTY. The code is a scancode on Windows, a virtual key code on
+    /// macOS, an X11 keycode on Linux, or an evdev code with `evdev` on.
+    PressScancode(u16),
+    ReleaseScancode(u16),
+    ClickScancode(u16),
     Delay(u64),
+    /// Scrolls by this many lines, down when positive.
+    Scroll(i32),
+    /// Presses an assistive switch, counting from 1.
+    Switch(u8),
+    /// Runs a command line through the shell, without waiting for it.
+    Run(String),
+    EnableGroup(String),
+    DisableGroup(String),
+    /// Enables the group if it is disabled and disables it otherwise.
+    ToggleGroup(String),
+    /// Adds a digit to the number being entered.
+    Digit(u8),
+    /// Removes the last digit of the number being entered.
+    EraseDigit,
+    /// Runs the number entry's `on_confirm` actions with the number entered.
+    ConfirmNumber,
+    /// Releases every key pressed and not released yet.
+    ReleaseAll,
+    /// Actions of which only the current platform's are played, so one config
+    /// works across machines, e.g.
+    /// `Platform(linux: [Run("xdg-open .")], windows: [Run("explorer .")])`.
+    Platform {
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        linux: Vec<Action>,
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        windows: Vec<Action>,
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        macos: Vec<Action>,
+    },
+    #[cfg(feature = "hue")]
+    Hue(HueCommand),
+    #[cfg(feature = "spotify")]
+    Spotify(SpotifyCommand),
+    #[cfg(feature = "twitch")]
+    Twitch(TwitchCommand),
+}
+
+impl fmt::Display for Action {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Action::Press(key) => write!(f, "press {key:?}"),
+            Action::Release(key) => write!(f, "release {key:?}"),
+            Action::Click(key) => write!(f, "{key:?}"),
+            Action::PressScancode(code) => write!(f, "press scancode {code}"),
+            Action::ReleaseScancode(code) => write!(f, "release scancode {code}"),
+            Action::ClickScancode(code) => write!(f, "scancode {code}"),
+            Action::Delay(delay) => write!(f, "wait {delay}ms"),
+            Action::Scroll(lines) => write!(f, "scroll {lines}"),
+            Action::Switch(switch) => write!(f, "switch {switch}"),
+            Action::Run(command) => write!(f, "run {command:?}"),
+            Action::EnableGroup(group) => write!(f, "enable {group}"),
+            Action::DisableGroup(group) => write!(f, "disable {group}"),
+            Action::ToggleGroup(group) => write!(f, "toggle {group}"),
+            Action::Digit(digit) => write!(f, "digit {digit}"),
+            Action::EraseDigit => write!(f, "erase digit"),
+            Action::ConfirmNumber => write!(f, "confirm number"),
+            Action::ReleaseAll => write!(f, "release all"),
+            Action::Platform { .. } => {
+                let actions = self.clone().resolve();
+                if actions.is_empty() {
+                    return write!(f, "nothing on this platform");
+                }
+                for (i, action) in actions.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{action}")?;
+                }
+                Ok(())
+            }
+            #[cfg(feature = "hue")]
+            Action::Hue(command) => write!(f, "hue {command}"),
+            #[cfg(feature = "spotify")]
+            Action::Spotify(command) => write!(f, "spotify {command}"),
+            #[cfg(feature = "twitch")]
+            Action::Twitch(command) => write!(f, "twitch {command}"),
+        }
+    }
+}
+
+impl Action {
+    /// The actions played for this one on the current platform, which are
+    /// those of the platform for `Platform` and the action itself otherwise.
+    pub fn resolve(self) -> Vec<Action> {
+        match self {
+            Action::Platform {
+                linux,
+                windows,
+                macos,
+            } => {
+                let actions = if cfg!(target_os = "linux") {
+                    linux
+                } else if cfg!(target_os = "windows") {
+                    windows
+                } else if cfg!(target_os = "macos") {
+                    macos
+                } else {
+                    Vec::new()
+                };
+                resolve_actions(actions)
+            }
+            action => vec![action],
+        }
+    }
+}
+
+/// `actions` with each `Platform` action replaced by the current platform's.
+pub fn resolve_actions(actions: Vec<Action>) -> Vec<Action> {
+    actions.into_iter().flat_map(Action::resolve).collect()
+}
+
+/// Triggers are written back as algorithm strings, the more readable form.
+pub(crate) fn serialize_trigger<S: Serializer>(
+    trigger: &[Move],
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    serializer.collect_str(&Algorithm::from(trigger.to_vec()))
+}
+
+/// Triggers are either a list of moves (`[R, Up]`) or an algorithm string
+/// (`"R U'"`, `"R2 U"`).
+pub(crate) fn deserialize_trigger<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Vec<Move>, D::Error> {
+    struct TriggerVisitor;
+
+    impl<'de> Visitor<'de> for TriggerVisitor {
+        type Value = Vec<Move>;
+
+        fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+            formatter.write_str("a list of moves or an algorithm")
+        }
+
+        fn visit_str<E: de::Error>(self, v: &str) -> Result<Self::Value, E> {
+            v.parse::<Algorithm>()
+                .and_then(|algorithm| algorithm.to_moves())
+                .map_err(E::custom)
+        }
+
+        fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+            let mut moves = Vec::new();
+            while let Some(m) = seq.next_element()? {
+                moves.push(m);
+            }
+            Ok(moves)
+        }
+    }
+
+    deserializer.deserialize_any(TriggerVisitor)
 }
@@ -0,0 +1,575 @@
+//! A Unix socket for controlling the running daemon. Each line sent is a
+//! command, answered with one line, `ok` or the answer to a query, or `error: `
+//! and the reason:
+//!
+//! - `status` answers whether the cube is connected and its battery level,
+//!   e.g. `connected, battery 80%`.
+//! - `channels` answers how many messages each internal channel carried and
+//!   how many its receivers missed for falling behind, e.g.
+//!   `actions: 12 sent, 0 dropped in 0 lags; event_stream: 96 sent, 3 dropped in 1 lag`.
+//! - `wake` connects to a cube that was disconnected for being idle.
+//! - `history [count]` answers with the last moves kept, or the last `count`,
+//!   oldest first as `<milliseconds since the UNIX epoch>:<move>` separated by
+//!   spaces, e.g. `1718000000123:R 1718000000456:U'`.
+//! - `since <milliseconds>` answers with every move made after a time since
+//!   the UNIX epoch, like `history`, or fails if the history no longer goes
+//!   back that far.
+//!
+//! Lines starting with `{` are instead [JSON-RPC 2.0] requests, answered with
+//! one line each, for programs that should not parse the text answers. The
+//! API is versioned by [`API_VERSION`], which only changes when a method or
+//! event is changed or removed. The methods are:
+//!
+//! - `version` answers `{"api": 1, "triplicata": "0.1.0"}`.
+//! - `status` answers `{"connected": true, "battery": 80}`, with a `null`
+//!   battery until the cube reports it.
+//! - `channels` answers
+//!   `{"event_stream": {"sent": 96, "dropped": 3, "lags": 1}, ...}`.
+//! - `reload` reads the config again and restarts with it, reconnecting to
+//!   the cube, and answers `null`. An invalid config is logged and the old
+//!   one kept.
+//! - `switch_profile` with `{"name": "Code"}` switches to a profile of the
+//!   `focus` config until another window takes focus, and answers `null`.
+//! - `inject_move` with `{"moves": "R U'"}` feeds moves into the daemon as
+//!   if they were turned, and answers `null`.
+//! - `subscribe` answers `null`, after which every cube event is sent as an
+//!   `event` notification, e.g.
+//!   `{"jsonrpc": "2.0", "method": "event", "params": {"type": "move", "move": "R'"}}`.
+//!   Event types are `move`, `state` with `solved`, `battery` with `level`,
+//!   `orientation` with `x`, `y`, `z` and `w`, `connected`, `disconnected`,
+//!   `lagged` with `dropped`, `timer` with `state` and `time_ms`,
+//!   `redemption` with `bind`, `profile` with `profile` and `fire` with
+//!   `bind`.
+//!
+//! Errors use the standard JSON-RPC codes, and `-32000` for methods whose
+//! feature is not enabled or which failed.
+//!
+//! [JSON-RPC 2.0]: https://www.jsonrpc.org/specification
+
+#[cfg(feature = "history")]
+use std::time::{Duration, UNIX_EPOCH};
+use std::{io, path::PathBuf, sync::Arc};
+
+use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
+use tokio::{
+    io::{AsyncBufReadExt, AsyncWriteExt, BufReader},
+    net::{UnixListener, UnixStream},
+    select,
+    sync::{
+        Notify,
+        broadcast::{self, error::RecvError},
+    },
+};
+use tokio_util::sync::CancellationToken;
+use tracing::{debug, info};
+
+#[cfg(feature = "history")]
+use crate::history::{HistoryMove, MoveHistory};
+#[cfg(feature = "idle")]
+use crate::idle::IdleWaker;
+#[cfg(feature = "input")]
+use crate::{MoveInjector, algorithm::Algorithm, error::NotationError};
+use crate::{
+    cube::{CubeEvent, Quaternion},
+    error::ControlError,
+    metrics::{self, Channel},
+    protocol::timer::TimerState,
+    status::CubeStatus,
+};
+
+/// The version of the JSON-RPC methods and events.
+pub const API_VERSION: u32 = 1;
+
+const PARSE_ERROR: i64 = -32700;
+const INVALID_REQUEST: i64 = -32600;
+const METHOD_NOT_FOUND: i64 = -32601;
+#[cfg(feature = "input")]
+const INVALID_PARAMS: i64 = -32602;
+const SERVER_ERROR: i64 = -32000;
+
+/// The events sent to a connection after `subscribe`.
+type Subscription = broadcast::Receiver<CubeEvent>;
+
+/// Asks the daemon to read its config again and restart with it, such as from
+/// the `reload` method.
+#[derive(Debug, Clone, Default)]
+pub struct Reloader {
+    notify: Arc<Notify>,
+}
+
+impl Reloader {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Requests a reload, which is kept until [`Reloader::requested`] is
+    /// waited on.
+    pub fn reload(&self) {
+        self.notify.notify_one();
+    }
+
+    pub async fn requested(&self) {
+        self.notify.notified().await;
+    }
+}
+
+/// What the commands act on, each missing unless it is enabled.
+#[derive(Debug, Clone, Default)]
+pub struct Control {
+    status: Option<CubeStatus>,
+    #[cfg(feature = "idle")]
+    waker: Option<IdleWaker>,
+    #[cfg(feature = "history")]
+    history: Option<MoveHistory>,
+    #[cfg(feature = "input")]
+    injector: Option<MoveInjector>,
+    reloader: Option<Reloader>,
+    /// The names of the profiles, by index.
+    profiles: Vec<String>,
+}
+
+#[derive(Deserialize, Debug)]
+struct Request {
+    jsonrpc: String,
+    /// Missing for notifications, which are not answered.
+    #[serde(default)]
+    id: Option<Value>,
+    method: String,
+    #[serde(default)]
+    params: Value,
+}
+
+#[derive(Serialize, Debug)]
+struct RpcError {
+    code: i64,
+    message: String,
+}
+
+impl RpcError {
+    fn new(code: i64, message: impl Into<String>) -> Self {
+        Self {
+            code,
+            message: message.into(),
+        }
+    }
+
+    fn disabled(message: &str) -> Self {
+        Self::new(SERVER_ERROR, message)
+    }
+}
+
+/// A cube event as sent to subscribers.
+#[derive(Serialize, Debug, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum EventMessage {
+    Move {
+        #[serde(rename = "move")]
+        m: String,
+    },
+    State {
+        solved: bool,
+    },
+    Battery {
+        level: u8,
+    },
+    Orientation(Quaternion),
+    Connected,
+    Disconnected,
+    Lagged {
+        dropped: u64,
+    },
+    Timer {
+        state: TimerState,
+        time_ms: Option<u128>,
+    },
+    Redemption {
+        bind: usize,
+    },
+    Profile {
+        profile: usize,
+    },
+    Fire {
+        bind: usize,
+    },
+}
+
+impl EventMessage {
+    /// The message for `event`, or `None` for when a move was received, which
+    /// only the pipeline follows.
+    pub fn new(event: &CubeEvent) -> Option<Self> {
+        let message = match *event {
+            CubeEvent::Move(m) => EventMessage::Move { m: m.to_string() },
+            CubeEvent::StateSync(state) => EventMessage::State {
+                solved: state.is_solved(),
+            },
+            CubeEvent::Battery(level) => EventMessage::Battery { level },
+            CubeEvent::Orientation(quaternion) => EventMessage::Orientation(quaternion),
+            CubeEvent::Connected => EventMessage::Connected,
+            CubeEvent::Disconnected => EventMessage::Disconnected,
+            CubeEvent::Lagged(dropped) => EventMessage::Lagged { dropped },
+            CubeEvent::Timer(timer) => EventMessage::Timer {
+                state: timer.state,
+                time_ms: timer.time.map(|time| time.as_millis()),
+            },
+            CubeEvent::Redemption(bind) => EventMessage::Redemption { bind },
+            CubeEvent::Profile(profile) => EventMessage::Profile { profile },
+            CubeEvent::Fire(bind) => EventMessage::Fire { bind },
+            CubeEvent::Received(_) => return None,
+        };
+
+        Some(message)
+    }
+}
+
+impl Control {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn status(mut self, status: Option<CubeStatus>) -> Self {
+        self.status = status;
+        self
+    }
+
+    #[cfg(feature = "idle")]
+    pub fn waker(mut self, waker: Option<IdleWaker>) -> Self {
+        self.waker = waker;
+        self
+    }
+
+    #[cfg(feature = "history")]
+    pub fn history(mut self, history: Option<MoveHistory>) -> Self {
+        self.history = history;
+        self
+    }
+
+    /// Where `inject_move` feeds moves and `subscribe` follows events from.
+    #[cfg(feature = "input")]
+    pub fn injector(mut self, injector: Option<MoveInjector>) -> Self {
+        self.injector = injector;
+        self
+    }
+
+    pub fn reloader(mut self, reloader: Option<Reloader>) -> Self {
+        self.reloader = reloader;
+        self
+    }
+
+    /// The names of the profiles `switch_profile` can switch to, in the order
+    /// of the config.
+    pub fn profiles(mut self, profiles: Vec<String>) -> Self {
+        self.profiles = profiles;
+        self
+    }
+
+    /// Runs one command, returning the line to answer with.
+    pub fn handle(&self, command: &str) -> Result<String, String> {
+        let mut words = command.split_whitespace();
+        let command = words.next().unwrap_or_default();
+
+        match command {
+            "status" => {
+                let status = self.status.as_ref().ok_or("the status is not followed")?;
+                Ok(status.get().to_string())
+            }
+            "channels" => Ok(metrics::channels()
+                .into_iter()
+                .map(|(channel, stats)| format!("{channel}: {stats}"))
+                .collect::<Vec<_>>()
+                .join("; ")),
+            #[cfg(feature = "idle")]
+            "wake" => {
+                let waker = self
+                    .waker
+                    .as_ref()
+                    .ok_or("idle disconnecting is not enabled")?;
+                waker.wake();
+                Ok("ok".to_string())
+            }
+            #[cfg(feature = "history")]
+            "history" => {
+                let history = self
+                    .history
+                    .as_ref()
+                    .ok_or("the move history is not enabled")?;
+                let moves = match words.next() {
+                    Some(count) => history.recent(
+                        count
+                            .parse()
+                            .map_err(|_| format!("invalid count `{count}`"))?,
+                    ),
+                    None => history.moves(),
+                };
+                Ok(list_moves(&moves))
+            }
+            #[cfg(feature = "history")]
+            "since" => {
+                let history = self
+                    .history
+                    .as_ref()
+                    .ok_or("the move history is not enabled")?;
+                let since = words.next().ok_or("missing time")?;
+                let since = since
+                    .parse()
+                    .map_err(|_| format!("invalid time `{since}`"))?;
+                let moves = history
+                    .since(UNIX_EPOCH + Duration::from_millis(since))
+                    .ok_or("the history no longer goes back that far")?;
+                Ok(list_moves(&moves))
+            }
+            "" => Err("no command".to_string()),
+            command => Err(format!("unknown command `{command}`")),
+        }
+    }
+}
+
+impl Control {
+    /// Runs one JSON-RPC request, returning the line to answer with, if any,
+    /// and the events to send to the connection after a `subscribe`.
+    pub fn call(&self, request: &str) -> (Option<String>, Option<Subscription>) {
+        let request: Request = match serde_json::from_str(request) {
+            Ok(request) => request,
+            Err(e) => {
+                let code = if e.is_syntax() || e.is_eof() {
+                    PARSE_ERROR
+                } else {
+                    INVALID_REQUEST
+                };
+                return (
+                    Some(error_response(
+                        Value::Null,
+                        RpcError::new(code, e.to_string()),
+                    )),
+                    None,
+                );
+            }
+        };
+
+        let id = request.id.clone();
+        let result = if request.jsonrpc == "2.0" {
+            self.dispatch(&request.method, request.params)
+        } else {
+            Err(RpcError::new(
+                INVALID_REQUEST,
+                "only JSON-RPC 2.0 is supported",
+            ))
+        };
+
+        let Some(id) = id else {
+            return (None, result.ok().and_then(|(_, events)| events));
+        };
+
+        match result {
+            Ok((result, events)) => (
+                Some(json!({"jsonrpc": "2.0", "id": id, "result": result}).to_string()),
+                events,
+            ),
+            Err(error) => (Some(error_response(id, error)), None),
+        }
+    }
+
+    #[cfg_attr(not(feature = "input"), allow(unused_variables))]
+    fn dispatch(
+        &self,
+        method: &str,
+        params: Value,
+    ) -> Result<(Value, Option<Subscription>), RpcError> {
+        let result = match method {
+            "version" => json!({
+                "api": API_VERSION,
+                "triplicata": env!("CARGO_PKG_VERSION"),
+            }),
+            "status" => {
+                let status = self
+                    .status
+                    .as_ref()
+                    .ok_or_else(|| RpcError::disabled("the status is not followed"))?;
+                json!(status.get())
+            }
+            "channels" => json!(metrics::channels()),
+            "reload" => {
+                let reloader = self
+                    .reloader
+                    .as_ref()
+                    .ok_or_else(|| RpcError::disabled("reloading is not enabled"))?;
+                reloader.reload();
+                Value::Null
+            }
+            #[cfg(feature = "input")]
+            "switch_profile" => {
+                #[derive(Deserialize)]
+                struct Params {
+                    name: String,
+                }
+
+                let Params { name } = params_of(params)?;
+                let profile = self
+                    .profiles
+                    .iter()
+                    .position(|profile| *profile == name)
+                    .ok_or_else(|| {
+                        RpcError::new(INVALID_PARAMS, format!("unknown profile `{name}`"))
+                    })?;
+                self.running_injector()?
+                    .inject_event(CubeEvent::Profile(profile));
+                Value::Null
+            }
+            #[cfg(feature = "input")]
+            "inject_move" => {
+                #[derive(Deserialize)]
+                struct Params {
+                    moves: String,
+                }
+
+                let Params { moves } = params_of(params)?;
+                let algorithm: Algorithm = moves
+                    .parse()
+                    .map_err(|e: NotationError| RpcError::new(INVALID_PARAMS, e.to_string()))?;
+                self.running_injector()?
+                    .inject_algorithm(&algorithm)
+                    .map_err(|e| RpcError::new(INVALID_PARAMS, e.to_string()))?;
+                Value::Null
+            }
+            #[cfg(feature = "input")]
+            "subscribe" => return Ok((Value::Null, Some(self.running_injector()?.subscribe()))),
+            _ => {
+                return Err(RpcError::new(
+                    METHOD_NOT_FOUND,
+                    format!("unknown method `{method}`"),
+                ));
+            }
+        };
+
+        Ok((result, None))
+    }
+
+    #[cfg(feature = "input")]
+    fn running_injector(&self) -> Result<&MoveInjector, RpcError> {
+        self.injector
+            .as_ref()
+            .ok_or_else(|| RpcError::disabled("the daemon is not running"))
+    }
+}
+
+#[cfg(feature = "input")]
+fn params_of<T: for<'de> Deserialize<'de>>(params: Value) -> Result<T, RpcError> {
+    serde_json::from_value(params).map_err(|e| RpcError::new(INVALID_PARAMS, e.to_string()))
+}
+
+fn error_response(id: Value, error: RpcError) -> String {
+    json!({"jsonrpc": "2.0", "id": id, "error": error}).to_string()
+}
+
+/// The notification sent to subscribers for `event`, if they are sent one.
+pub fn event_notification(event: &CubeEvent) -> Option<String> {
+    let notification = json!({
+        "jsonrpc": "2.0",
+        "method": "event",
+        "params": EventMessage::new(event)?,
+    });
+
+    Some(notification.to_string())
+}
+
+#[cfg(feature = "history")]
+fn list_moves(moves: &[HistoryMove]) -> String {
+    moves
+        .iter()
+        .map(|m| format!("{}:{}", m.timestamp_ms(), m.m))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Listens on `path` until `cancel` is cancelled, replacing a socket left
+/// behind by a previous run and removing it afterwards.
+pub async fn serve(
+    path: PathBuf,
+    control: Control,
+    cancel: CancellationToken,
+) -> Result<(), ControlError> {
+    match std::fs::remove_file(&path) {
+        Err(e) if e.kind() != io::ErrorKind::NotFound => return Err(e.into()),
+        _ => {}
+    }
+
+    let listener = UnixListener::bind(&path)?;
+    info!("Listening for commands on {}", path.display());
+
+    loop {
+        let stream = select! {
+            accepted = listener.accept() => accepted?.0,
+            _ = cancel.cancelled() => break,
+        };
+
+        let control = control.clone();
+        tokio::spawn(async move {
+            if let Err(error) = answer(stream, &control).await {
+                debug!(%error, "Control connection failed");
+            }
+        });
+    }
+
+    let _ = std::fs::remove_file(&path);
+
+    Ok(())
+}
+
+async fn answer(stream: UnixStream, control: &Control) -> io::Result<()> {
+    let (read, mut write) = stream.into_split();
+    let mut lines = BufReader::new(read).lines();
+    let mut events: Option<Subscription> = None;
+
+    loop {
+        let next = async {
+            match &mut events {
+                Some(events) => events.recv().await,
+                None => std::future::pending().await,
+            }
+        };
+
+        let reply = select! {
+            line = lines.next_line() => {
+                let Some(line) = line? else {
+                    break;
+                };
+
+                if line.trim_start().starts_with('{') {
+                    let (reply, subscribed) = control.call(&line);
+                    if subscribed.is_some() {
+                        events = subscribed;
+                    }
+                    match reply {
+                        Some(reply) => reply,
+                        None => continue,
+                    }
+                } else {
+                    match control.handle(&line) {
+                        Ok(reply) => reply,
+                        Err(reason) => format!("error: {reason}"),
+                    }
+                }
+            }
+            event = next => {
+                let event = match event {
+                    Ok(event) => event,
+                    Err(RecvError::Lagged(dropped)) => {
+                        metrics::channel_drops(Channel::EventStream, dropped);
+                        CubeEvent::Lagged(dropped)
+                    }
+                    Err(RecvError::Closed) => {
+                        events = None;
+                        continue;
+                    }
+                };
+                match event_notification(&event) {
+                    Some(notification) => notification,
+                    None => continue,
+                }
+            }
+        };
+        write.write_all(format!("{reply}\n").as_bytes()).await?;
+    }
+
+    Ok(())
+}
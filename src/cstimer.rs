@@ -0,0 +1,224 @@
+//! Presents triplicata as a GAN v2 cube over BlueZ, so timers such as csTimer
+//! can connect to it with Web Bluetooth while binds keep working.
+
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    sync::{Arc, Mutex},
+    time::Instant,
+};
+
+use bluer::{
+    Session,
+    adv::Advertisement,
+    gatt::local::{
+        Application, Characteristic, CharacteristicNotify, CharacteristicNotifyMethod,
+        CharacteristicWrite, CharacteristicWriteMethod, Service,
+    },
+};
+use futures::{Stream, StreamExt};
+use tokio::{select, sync::broadcast};
+use tokio_util::sync::CancellationToken;
+use tracing::{info, warn};
+
+use crate::{
+    GAN_GEN2_COMMAND_CHARACTERISTIC, GAN_GEN2_SERVICE, GAN_GEN2_STATE_CHARACTERISTIC,
+    cube::{CubeEvent, CubeState},
+    error::CubeError,
+    protocol::{
+        cipher::{GAN_MANUFACTURER_ID, GANCubeVersion2Cipher},
+        gen2::{Command, Emulator, PACKET_LENGTH},
+    },
+};
+
+#[derive(Debug)]
+pub struct CsTimerBridge {
+    adapter: Option<String>,
+    name: String,
+    salt: [u8; 6],
+}
+
+struct Emulated {
+    emulator: Emulator,
+    state: CubeState,
+    battery: u8,
+    last_move: Instant,
+}
+
+impl Default for CsTimerBridge {
+    fn default() -> Self {
+        Self {
+            adapter: None,
+            name: "GAN-triplicata".to_string(),
+            salt: [0; 6],
+        }
+    }
+}
+
+impl CsTimerBridge {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn adapter(mut self, adapter: impl Into<String>) -> Self {
+        self.adapter = Some(adapter.into());
+        self
+    }
+
+    /// The advertised name, csTimer only lists devices starting with `GAN`.
+    pub fn name(mut self, name: impl Into<String>) -> Self {
+        self.name = name.into();
+        self
+    }
+
+    pub fn salt(mut self, salt: [u8; 6]) -> Self {
+        self.salt = salt;
+        self
+    }
+
+    /// Advertises the emulated cube and relays `events` to connected clients
+    /// until `cancel` is cancelled or the events end.
+    pub async fn serve(
+        self,
+        mut events: impl Stream<Item = CubeEvent> + Unpin,
+        cancel: CancellationToken,
+    ) -> Result<(), CubeError> {
+        let session = Session::new().await?;
+        let adapter = match &self.adapter {
+            Some(name) => session.adapter(name)?,
+            None => session.default_adapter().await?,
+        };
+        adapter.set_powered(true).await?;
+
+        let emulated = Arc::new(Mutex::new(Emulated {
+            emulator: Emulator::new(GANCubeVersion2Cipher::from_salt(self.salt)),
+            state: CubeState::SOLVED,
+            battery: 100,
+            last_move: Instant::now(),
+        }));
+        let (packets, _) = broadcast::channel::<[u8; PACKET_LENGTH]>(16);
+
+        let notify_packets = packets.clone();
+        let write_packets = packets.clone();
+        let write_emulated = emulated.clone();
+
+        let application = Application {
+            services: vec![Service {
+                uuid: GAN_GEN2_SERVICE,
+                primary: true,
+                characteristics: vec![
+                    Characteristic {
+                        uuid: GAN_GEN2_STATE_CHARACTERISTIC,
+                        notify: Some(CharacteristicNotify {
+                            notify: true,
+                            method: CharacteristicNotifyMethod::Fun(Box::new(
+                                move |mut notifier| {
+                                    let mut packets = notify_packets.subscribe();
+                                    Box::pin(async move {
+                                        info!("Client subscribed to the emulated cube");
+                                        while let Ok(packet) = packets.recv().await {
+                                            if notifier.is_stopped()
+                                                || notifier.notify(packet.to_vec()).await.is_err()
+                                            {
+                                                break;
+                                            }
+                                        }
+                                    })
+                                },
+                            )),
+                            ..Default::default()
+                        }),
+                        ..Default::default()
+                    },
+                    Characteristic {
+                        uuid: GAN_GEN2_COMMAND_CHARACTERISTIC,
+                        write: Some(CharacteristicWrite {
+                            write: true,
+                            write_without_response: true,
+                            method: CharacteristicWriteMethod::Fun(Box::new(move |value, _| {
+                                let emulated = write_emulated.clone();
+                                let packets = write_packets.clone();
+                                Box::pin(async move {
+                                    let emulated = emulated.lock().unwrap();
+                                    let packet = match emulated.emulator.command(&value) {
+                                        Ok(Some(Command::RequestState)) => {
+                                            emulated.emulator.state_packet(&emulated.state)
+                                        }
+                                        Ok(Some(Command::RequestBattery)) => {
+                                            emulated.emulator.battery_packet(emulated.battery)
+                                        }
+                                        _ => return Ok(()),
+                                    };
+
+                                    if let Ok(packet) = packet {
+                                        let _ = packets.send(packet);
+                                    }
+
+                                    Ok(())
+                                })
+                            })),
+                            ..Default::default()
+                        }),
+                        ..Default::default()
+                    },
+                ],
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+
+        let mut manufacturer_data = vec![0; 3];
+        manufacturer_data.extend(self.salt);
+
+        let advertisement = Advertisement {
+            service_uuids: BTreeSet::from([GAN_GEN2_SERVICE]),
+            manufacturer_data: BTreeMap::from([(GAN_MANUFACTURER_ID, manufacturer_data)]),
+            discoverable: Some(true),
+            local_name: Some(self.name.clone()),
+            ..Default::default()
+        };
+
+        let _application = adapter.serve_gatt_application(application).await?;
+        let _advertisement = adapter.advertise(advertisement).await?;
+
+        info!("Emulating {} for csTimer", self.name);
+
+        loop {
+            let event = select! {
+                event = events.next() => event,
+                _ = cancel.cancelled() => break,
+            };
+
+            let Some(event) = event else {
+                break;
+            };
+
+            let mut emulated = emulated.lock().unwrap();
+            let packet = match event {
+                CubeEvent::Move(m) => {
+                    let since_last = emulated.last_move.elapsed();
+                    emulated.last_move = Instant::now();
+                    emulated.state.apply(m);
+                    emulated.emulator.move_packet(m, since_last)
+                }
+                CubeEvent::StateSync(state) => {
+                    emulated.state = state;
+                    emulated.emulator.state_packet(&state)
+                }
+                CubeEvent::Battery(level) => {
+                    emulated.battery = level;
+                    emulated.emulator.battery_packet(level)
+                }
+                _ => continue,
+            };
+
+            match packet {
+                Ok(packet) => {
+                    let _ = packets.send(packet);
+                }
+                Err(e) => warn!("Could not encode emulated packet: {e}"),
+            }
+        }
+
+        Ok(())
+    }
+}
@@ -1,215 +1,424 @@
-use aes::{
-    Aes128, Block,
-    cipher::{BlockDecrypt, BlockEncrypt, KeyInit, generic_array::GenericArray},
-};
-use anyhow::bail;
-use btleplug::api::{Characteristic, Peripheral};
-use futures::StreamExt;
-use serde::Deserialize;
-
-#[derive(Clone)]
-struct GANCubeVersion2Cipher {
-    device_key: [u8; 16],
-    device_iv: [u8; 16],
-}
-
-impl GANCubeVersion2Cipher {
-    fn decrypt(&self, value: &[u8]) -> anyhow::Result<Vec<u8>> {
-        if value.len() <= 16 {
-            bail!("Packet size less than expected length");
+use alloc::string::{String, ToString};
+use core::{fmt, str::FromStr};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{error::NotationError, protocol::timer::TimerEvent};
+
+#[derive(PartialEq, Eq, Clone, Copy, Debug, Deserialize, Serialize)]
+pub enum Move {
+    U,
+    Up,
+    R,
+    Rp,
+    F,
+    Fp,
+    D,
+    Dp,
+    L,
+    Lp,
+    B,
+    Bp,
+}
+
+#[derive(PartialEq, Eq, Clone, Copy, Debug, Hash, Deserialize, Serialize)]
+pub enum Face {
+    U,
+    R,
+    F,
+    D,
+    L,
+    B,
+}
+
+#[derive(PartialEq, Eq, Clone, Copy, Debug, Hash, Deserialize, Serialize)]
+pub enum Direction {
+    Clockwise,
+    CounterClockwise,
+}
+
+impl Move {
+    /// All moves in the order GAN cubes number them.
+    pub const ALL: [Move; 12] = [
+        Move::U,
+        Move::Up,
+        Move::R,
+        Move::Rp,
+        Move::F,
+        Move::Fp,
+        Move::D,
+        Move::Dp,
+        Move::L,
+        Move::Lp,
+        Move::B,
+        Move::Bp,
+    ];
+
+    pub fn new(face: Face, direction: Direction) -> Self {
+        match (face, direction) {
+            (Face::U, Direction::Clockwise) => Move::U,
+            (Face::U, Direction::CounterClockwise) => Move::Up,
+            (Face::R, Direction::Clockwise) => Move::R,
+            (Face::R, Direction::CounterClockwise) => Move::Rp,
+            (Face::F, Direction::Clockwise) => Move::F,
+            (Face::F, Direction::CounterClockwise) => Move::Fp,
+            (Face::D, Direction::Clockwise) => Move::D,
+            (Face::D, Direction::CounterClockwise) => Move::Dp,
+            (Face::L, Direction::Clockwise) => Move::L,
+            (Face::L, Direction::CounterClockwise) => Move::Lp,
+            (Face::B, Direction::Clockwise) => Move::B,
+            (Face::B, Direction::CounterClockwise) => Move::Bp,
         }
+    }
 
-        // Packets are larger than block size. First decrypt the last 16 bytes
-        // of the packet in place.
-        let mut value = value.to_vec();
-        let aes = Aes128::new(GenericArray::from_slice(&self.device_key));
-        let offset = value.len() - 16;
-        let end_cipher = &value[offset..];
-        let mut end_plain = Block::clone_from_slice(end_cipher);
-        aes.decrypt_block(&mut end_plain);
-        for i in 0..16 {
-            end_plain[i] ^= self.device_iv[i];
-            value[offset + i] = end_plain[i];
+    pub fn face(self) -> Face {
+        match self {
+            Move::U | Move::Up => Face::U,
+            Move::R | Move::Rp => Face::R,
+            Move::F | Move::Fp => Face::F,
+            Move::D | Move::Dp => Face::D,
+            Move::L | Move::Lp => Face::L,
+            Move::B | Move::Bp => Face::B,
         }
+    }
 
-        // Decrypt the first 16 bytes of the packet in place. This will overlap
-        // with the decrypted block above.
-        let start_cipher = &value[0..16];
-        let mut start_plain = Block::clone_from_slice(start_cipher);
-        aes.decrypt_block(&mut start_plain);
-        for i in 0..16 {
-            start_plain[i] ^= self.device_iv[i];
-            value[i] = start_plain[i];
+    pub fn direction(self) -> Direction {
+        match self {
+            Move::U | Move::R | Move::F | Move::D | Move::L | Move::B => Direction::Clockwise,
+            _ => Direction::CounterClockwise,
         }
+    }
 
-        Ok(value)
+    pub fn inverse(self) -> Self {
+        Self::new(self.face(), self.direction().inverse())
     }
 
-    fn encrypt(&self, value: &[u8]) -> anyhow::Result<Vec<u8>> {
-        if value.len() <= 16 {
-            bail!("Packet size less than expected length");
-        }
+    /// The move mirrored left to right, as a left handed turn of the same
+    /// gesture: R becomes L', and every other face keeps its face but turns
+    /// the other way.
+    pub fn mirror(self) -> Self {
+        let face = match self.face() {
+            Face::R => Face::L,
+            Face::L => Face::R,
+            face => face,
+        };
+        Self::new(face, self.direction().inverse())
+    }
+
+    pub fn to_notation(self) -> String {
+        self.to_string()
+    }
+
+    pub fn from_notation(notation: &str) -> Result<Self, NotationError> {
+        notation.parse()
+    }
+}
 
-        // Packets are larger than block size. First encrypt the first 16 bytes
-        // of the packet in place.
-        let mut value = value.to_vec();
-        for i in 0..16 {
-            value[i] ^= self.device_iv[i];
+impl Face {
+    pub const ALL: [Face; 6] = [Face::U, Face::R, Face::F, Face::D, Face::L, Face::B];
+
+    pub fn opposite(self) -> Self {
+        match self {
+            Face::U => Face::D,
+            Face::R => Face::L,
+            Face::F => Face::B,
+            Face::D => Face::U,
+            Face::L => Face::R,
+            Face::B => Face::F,
         }
-        let mut cipher = Block::clone_from_slice(&value[0..16]);
-        let aes = Aes128::new(GenericArray::from_slice(&self.device_key));
-        aes.encrypt_block(&mut cipher);
-        for i in 0..16 {
-            value[i] = cipher[i];
+    }
+}
+
+impl Direction {
+    pub fn inverse(self) -> Self {
+        match self {
+            Direction::Clockwise => Direction::CounterClockwise,
+            Direction::CounterClockwise => Direction::Clockwise,
         }
+    }
+}
+
+impl fmt::Display for Face {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let face = match self {
+            Face::U => "U",
+            Face::R => "R",
+            Face::F => "F",
+            Face::D => "D",
+            Face::L => "L",
+            Face::B => "B",
+        };
 
-        // Decrypt the last 16 bytes of the packet in place. This will overlap
-        // with the decrypted block above.
-        let offset = value.len() - 16;
-        for i in 0..16 {
-            value[offset + i] ^= self.device_iv[i];
+        f.write_str(face)
+    }
+}
+
+impl FromStr for Face {
+    type Err = NotationError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "U" => Ok(Face::U),
+            "R" => Ok(Face::R),
+            "F" => Ok(Face::F),
+            "D" => Ok(Face::D),
+            "L" => Ok(Face::L),
+            "B" => Ok(Face::B),
+            _ => Err(NotationError::InvalidMove(s.to_string())),
         }
-        let mut cipher = Block::clone_from_slice(&value[offset..]);
-        aes.encrypt_block(&mut cipher);
-        for i in 0..16 {
-            value[offset + i] = cipher[i];
+    }
+}
+
+impl fmt::Display for Move {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.direction() {
+            Direction::Clockwise => write!(f, "{}", self.face()),
+            Direction::CounterClockwise => write!(f, "{}'", self.face()),
         }
+    }
+}
+
+impl FromStr for Move {
+    type Err = NotationError;
 
-        Ok(value)
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        let invalid = || NotationError::InvalidMove(s.to_string());
+
+        let (face, direction) = match s.strip_suffix(['\'', '’']) {
+            Some(face) => (face, Direction::CounterClockwise),
+            None => (s, Direction::Clockwise),
+        };
+
+        Ok(Move::new(face.parse().map_err(|_| invalid())?, direction))
     }
 }
 
-#[derive(PartialEq, Eq, Clone, Copy, Debug, Deserialize)]
-pub enum Move {
-    U,
-    Up,
-    R,
-    Rp,
-    F,
-    Fp,
-    D,
-    Dp,
-    L,
-    Lp,
-    B,
-    Bp,
+#[derive(PartialEq, Eq, Clone, Copy, Debug, Deserialize, Serialize)]
+pub struct CubeState {
+    pub corner_permutation: [u8; 8],
+    pub corner_orientation: [u8; 8],
+    pub edge_permutation: [u8; 12],
+    pub edge_orientation: [u8; 12],
 }
 
-const CUBE_MOVE_MESSAGE: u8 = 2;
-const CUBE_STATE_MESSAGE: u8 = 4;
-const CUBE_BATTERY_STATE_MESSAGE: u8 = 9;
-
-pub async fn move_stream_v2(
-    device: impl Peripheral,
-    read: Characteristic,
-    write: Characteristic,
-) -> anyhow::Result<tokio::sync::broadcast::Receiver<Move>> {
-    let device_key: [u8; 6] = if let Some(data) = device
-        .properties()
-        .await?
-        .ok_or(anyhow::anyhow!("could not get device properties"))?
-        .manufacturer_data
-        .get(&36097)
-    {
-        if data.len() >= 9 {
-            let mut result = [0; 6];
-            result.copy_from_slice(&data[3..9]);
-            result
-        } else {
-            bail!("Device identifier invalid")
-        }
-    } else {
-        bail!("Manufacturer data missing device identifier")
+/// Clockwise face turns as cubie permutations, in the order of [`Face::ALL`].
+const FACE_TURNS: [CubeState; 6] = [
+    CubeState {
+        corner_permutation: [3, 0, 1, 2, 4, 5, 6, 7],
+        corner_orientation: [0; 8],
+        edge_permutation: [3, 0, 1, 2, 4, 5, 6, 7, 8, 9, 10, 11],
+        edge_orientation: [0; 12],
+    },
+    CubeState {
+        corner_permutation: [4, 1, 2, 0, 7, 5, 6, 3],
+        corner_orientation: [2, 0, 0, 1, 1, 0, 0, 2],
+        edge_permutation: [8, 1, 2, 3, 11, 5, 6, 7, 4, 9, 10, 0],
+        edge_orientation: [0; 12],
+    },
+    CubeState {
+        corner_permutation: [1, 5, 2, 3, 0, 4, 6, 7],
+        corner_orientation: [1, 2, 0, 0, 2, 1, 0, 0],
+        edge_permutation: [0, 9, 2, 3, 4, 8, 6, 7, 1, 5, 10, 11],
+        edge_orientation: [0, 1, 0, 0, 0, 1, 0, 0, 1, 1, 0, 0],
+    },
+    CubeState {
+        corner_permutation: [0, 1, 2, 3, 5, 6, 7, 4],
+        corner_orientation: [0; 8],
+        edge_permutation: [0, 1, 2, 3, 5, 6, 7, 4, 8, 9, 10, 11],
+        edge_orientation: [0; 12],
+    },
+    CubeState {
+        corner_permutation: [0, 2, 6, 3, 4, 1, 5, 7],
+        corner_orientation: [0, 1, 2, 0, 0, 2, 1, 0],
+        edge_permutation: [0, 1, 10, 3, 4, 5, 9, 7, 8, 2, 6, 11],
+        edge_orientation: [0; 12],
+    },
+    CubeState {
+        corner_permutation: [0, 1, 3, 7, 4, 5, 2, 6],
+        corner_orientation: [0, 0, 1, 2, 0, 0, 2, 1],
+        edge_permutation: [0, 1, 2, 11, 4, 5, 6, 10, 8, 9, 3, 7],
+        edge_orientation: [0, 0, 0, 1, 0, 0, 0, 1, 0, 0, 1, 1],
+    },
+];
+
+/// Facelet indices of each corner position, clockwise from its U or D facelet.
+/// Facelets are numbered row by row on the U, R, F, D, L, B faces in turn.
+pub(crate) const CORNER_FACELETS: [[usize; 3]; 8] = [
+    [8, 9, 20],
+    [6, 18, 38],
+    [0, 36, 47],
+    [2, 45, 11],
+    [29, 26, 15],
+    [27, 44, 24],
+    [33, 53, 42],
+    [35, 17, 51],
+];
+
+pub(crate) const EDGE_FACELETS: [[usize; 2]; 12] = [
+    [5, 10],
+    [7, 19],
+    [3, 37],
+    [1, 46],
+    [32, 16],
+    [28, 25],
+    [30, 43],
+    [34, 52],
+    [23, 12],
+    [21, 41],
+    [50, 39],
+    [48, 14],
+];
+
+const CORNER_COLORS: [[Face; 3]; 8] = [
+    [Face::U, Face::R, Face::F],
+    [Face::U, Face::F, Face::L],
+    [Face::U, Face::L, Face::B],
+    [Face::U, Face::B, Face::R],
+    [Face::D, Face::F, Face::R],
+    [Face::D, Face::L, Face::F],
+    [Face::D, Face::B, Face::L],
+    [Face::D, Face::R, Face::B],
+];
+
+const EDGE_COLORS: [[Face; 2]; 12] = [
+    [Face::U, Face::R],
+    [Face::U, Face::F],
+    [Face::U, Face::L],
+    [Face::U, Face::B],
+    [Face::D, Face::R],
+    [Face::D, Face::F],
+    [Face::D, Face::L],
+    [Face::D, Face::B],
+    [Face::F, Face::R],
+    [Face::F, Face::L],
+    [Face::B, Face::L],
+    [Face::B, Face::R],
+];
+
+impl CubeState {
+    /// Corners are numbered URF, UFL, ULB, UBR, DFR, DLF, DBL, DRB and edges
+    /// UR, UF, UL, UB, DR, DF, DL, DB, FR, FL, BL, BR, as GAN cubes report
+    /// them.
+    pub const SOLVED: CubeState = CubeState {
+        corner_permutation: [0, 1, 2, 3, 4, 5, 6, 7],
+        corner_orientation: [0; 8],
+        edge_permutation: [0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11],
+        edge_orientation: [0; 12],
     };
 
-    const GAN_V2_KEY: [u8; 16] = [
-        0x01, 0x02, 0x42, 0x28, 0x31, 0x91, 0x16, 0x07, 0x20, 0x05, 0x18, 0x54, 0x42, 0x11, 0x12,
-        0x53,
-    ];
-    const GAN_V2_IV: [u8; 16] = [
-        0x11, 0x03, 0x32, 0x28, 0x21, 0x01, 0x76, 0x27, 0x20, 0x95, 0x78, 0x14, 0x32, 0x12, 0x02,
-        0x43,
-    ];
+    pub fn is_solved(&self) -> bool {
+        *self == Self::SOLVED
+    }
+
+    fn multiply(&self, other: &CubeState) -> CubeState {
+        let mut result = *self;
+
+        for (i, &from) in other.corner_permutation.iter().enumerate() {
+            let from = from as usize;
+            result.corner_permutation[i] = self.corner_permutation[from];
+            result.corner_orientation[i] =
+                (self.corner_orientation[from] + other.corner_orientation[i]) % 3;
+        }
+
+        for (i, &from) in other.edge_permutation.iter().enumerate() {
+            let from = from as usize;
+            result.edge_permutation[i] = self.edge_permutation[from];
+            result.edge_orientation[i] =
+                (self.edge_orientation[from] + other.edge_orientation[i]) % 2;
+        }
 
-    let mut key = GAN_V2_KEY.clone();
-    let mut iv = GAN_V2_IV.clone();
-    for (idx, byte) in device_key.iter().enumerate() {
-        key[idx] = ((key[idx] as u16 + *byte as u16) % 255) as u8;
-        iv[idx] = ((iv[idx] as u16 + *byte as u16) % 255) as u8;
+        result
     }
 
-    let cipher = GANCubeVersion2Cipher {
-        device_key: key,
-        device_iv: iv,
-    };
+    pub fn apply(&mut self, m: Move) {
+        let turn = &FACE_TURNS[m.face() as usize];
+        let turns = match m.direction() {
+            Direction::Clockwise => 1,
+            Direction::CounterClockwise => 3,
+        };
+
+        for _ in 0..turns {
+            *self = self.multiply(turn);
+        }
+    }
+
+    /// The color of every sticker, named after the face it is solved on, in
+    /// the order U1..U9, R1..R9, F1..F9, D1..D9, L1..L9, B1..B9.
+    pub fn facelets(&self) -> [Face; 54] {
+        let mut facelets = [Face::U; 54];
+
+        for (i, face) in Face::ALL.into_iter().enumerate() {
+            facelets[i * 9 + 4] = face;
+        }
 
-    let mut notificaitons = device.notifications().await?;
-
-    let (tx, rx) = tokio::sync::broadcast::channel::<Move>(10);
-
-    tokio::spawn(async move {
-        let mut last_move_count = None;
-        while let Some(value) = notificaitons.next().await {
-            if let Ok(value) = cipher.decrypt(&value.value) {
-                let message_type = extract_bits(&value, 0, 4) as u8;
-
-                match message_type {
-                    CUBE_MOVE_MESSAGE => {
-                        let current_move_count = extract_bits(&value, 4, 8) as u8;
-
-                        let Some(last) = last_move_count.as_mut() else {
-                            last_move_count = Some(current_move_count);
-                            continue;
-                        };
-
-                        let move_count = current_move_count - *last;
-                        *last = current_move_count;
-
-                        for j in 0..(move_count as usize) {
-                            let i = (move_count as usize - 1) - j;
-
-                            let move_num = extract_bits(&value, 12 + i * 5, 5) as usize;
-                            const MOVES: &[Move] = &[
-                                Move::U,
-                                Move::Up,
-                                Move::R,
-                                Move::Rp,
-                                Move::F,
-                                Move::Fp,
-                                Move::D,
-                                Move::Dp,
-                                Move::L,
-                                Move::Lp,
-                                Move::B,
-                                Move::Bp,
-                            ];
-
-                            if move_num >= MOVES.len() {
-                                continue;
-                            }
-
-                            tx.send(MOVES[move_num]).expect("could not broadcast move");
-                        }
-                    }
-                    _ => {}
-                }
+        for (i, positions) in CORNER_FACELETS.iter().enumerate() {
+            let colors = CORNER_COLORS[self.corner_permutation[i] as usize];
+            let orientation = self.corner_orientation[i] as usize;
+            for (n, color) in colors.into_iter().enumerate() {
+                facelets[positions[(n + orientation) % 3]] = color;
             }
         }
-    });
 
-    device.subscribe(&read).await?;
+        for (i, positions) in EDGE_FACELETS.iter().enumerate() {
+            let colors = EDGE_COLORS[self.edge_permutation[i] as usize];
+            let orientation = self.edge_orientation[i] as usize;
+            for (n, color) in colors.into_iter().enumerate() {
+                facelets[positions[(n + orientation) % 2]] = color;
+            }
+        }
 
-    Ok(rx)
+        facelets
+    }
 }
 
-fn extract_bits(data: &[u8], start: usize, count: usize) -> u32 {
-    let mut result = 0;
-    for i in 0..count {
-        let bit = start + i;
-        result <<= 1;
-        if data[bit / 8] & (1 << (7 - (bit % 8))) != 0 {
-            result |= 1;
-        }
+impl Default for CubeState {
+    fn default() -> Self {
+        Self::SOLVED
+    }
+}
+
+#[derive(PartialEq, Clone, Copy, Debug, Deserialize, Serialize)]
+pub struct Quaternion {
+    pub x: f32,
+    pub y: f32,
+    pub z: f32,
+    pub w: f32,
+}
+
+/// When an event was received, on a clock that also runs in browsers.
+#[cfg(all(feature = "runtime", not(target_arch = "wasm32")))]
+pub type Instant = std::time::Instant;
+#[cfg(all(feature = "runtime", target_arch = "wasm32"))]
+pub type Instant = web_time::Instant;
+
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub enum CubeEvent {
+    Move(Move),
+    StateSync(CubeState),
+    Battery(u8),
+    Orientation(Quaternion),
+    Connected,
+    Disconnected,
+    /// A receiver fell behind and missed this many events.
+    Lagged(u64),
+    /// A GAN Smart Timer connected alongside the cube changed state.
+    Timer(TimerEvent),
+    /// A Twitch viewer redeemed the reward of the redemption bind at this
+    /// index.
+    Redemption(usize),
+    /// The focused window settled on the profile at this index.
+    Profile(usize),
+    /// Fires the bind at this index without turning its trigger.
+    Fire(usize),
+    /// The next move was received from the cube at this instant, so its
+    /// latency is measured from there through every stage.
+    #[cfg(feature = "runtime")]
+    Received(Instant),
+}
+
+impl From<Move> for CubeEvent {
+    fn from(value: Move) -> Self {
+        CubeEvent::Move(value)
     }
-    result
 }
@@ -0,0 +1,135 @@
+//! Events shaped like the ones cubing.js `BluetoothPuzzle` listeners receive,
+//! so twizzle and other cubing.js based visualizers can follow a triplicata
+//! feed without an adapter.
+
+use alloc::{
+    string::{String, ToString},
+    vec,
+    vec::Vec,
+};
+
+use serde::Serialize;
+
+use crate::cube::{CubeEvent, CubeState, Direction, Quaternion};
+
+/// Kociemba indices of the edges in cubing.js order: UF, UR, UB, UL, DF, DR,
+/// DB, DL, FR, FL, BR, BL. Both orders only swap pairs, so this also maps
+/// cubing.js indices back.
+const EDGE_ORDER: [usize; 12] = [1, 0, 3, 2, 5, 4, 7, 6, 8, 9, 11, 10];
+
+/// Kociemba indices of the corners in cubing.js order: UFR, URB, UBL, ULF,
+/// DRF, DFL, DLB, DBR, which likewise maps back.
+const CORNER_ORDER: [usize; 8] = [0, 3, 2, 1, 4, 5, 6, 7];
+
+const CENTER_COUNT: u8 = 6;
+
+#[derive(Serialize, Clone, Debug, PartialEq)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum CubingEvent {
+    #[serde(rename_all = "camelCase")]
+    Move {
+        latest_move: CubingMove,
+        time_stamp: f64,
+        state: KPatternData,
+    },
+    #[serde(rename_all = "camelCase")]
+    Orientation {
+        quaternion: Quaternion,
+        time_stamp: f64,
+    },
+    #[serde(rename_all = "camelCase")]
+    State {
+        state: KPatternData,
+        time_stamp: f64,
+    },
+}
+
+/// A move as cubing.js `Move` fields, `R'` being family `R` and amount `-1`.
+#[derive(Serialize, Clone, Debug, PartialEq, Eq)]
+pub struct CubingMove {
+    pub family: String,
+    pub amount: i8,
+}
+
+/// A `KPattern` of the `3x3x3` puzzle.
+#[derive(Serialize, Clone, Debug, PartialEq, Eq)]
+#[serde(rename_all = "UPPERCASE")]
+pub struct KPatternData {
+    pub edges: Orbit,
+    pub corners: Orbit,
+    pub centers: Orbit,
+}
+
+#[derive(Serialize, Clone, Debug, PartialEq, Eq)]
+pub struct Orbit {
+    pub pieces: Vec<u8>,
+    pub orientation: Vec<u8>,
+}
+
+impl CubingEvent {
+    /// Converts `event`, with `state` being the cube after it was applied and
+    /// `time_stamp` in milliseconds. Events cubing.js has no equivalent for
+    /// are skipped.
+    pub fn new(event: &CubeEvent, state: &CubeState, time_stamp: f64) -> Option<Self> {
+        match event {
+            CubeEvent::Move(m) => Some(CubingEvent::Move {
+                latest_move: CubingMove {
+                    family: m.face().to_string(),
+                    amount: match m.direction() {
+                        Direction::Clockwise => 1,
+                        Direction::CounterClockwise => -1,
+                    },
+                },
+                time_stamp,
+                state: state.into(),
+            }),
+            CubeEvent::StateSync(state) => Some(CubingEvent::State {
+                state: state.into(),
+                time_stamp,
+            }),
+            CubeEvent::Orientation(quaternion) => Some(CubingEvent::Orientation {
+                quaternion: *quaternion,
+                time_stamp,
+            }),
+            _ => None,
+        }
+    }
+}
+
+impl From<&CubeState> for KPatternData {
+    fn from(state: &CubeState) -> Self {
+        let edges = Orbit {
+            pieces: EDGE_ORDER
+                .iter()
+                .map(|&i| EDGE_ORDER[state.edge_permutation[i] as usize] as u8)
+                .collect(),
+            orientation: EDGE_ORDER
+                .iter()
+                .map(|&i| state.edge_orientation[i])
+                .collect(),
+        };
+
+        let corners = Orbit {
+            pieces: CORNER_ORDER
+                .iter()
+                .map(|&i| CORNER_ORDER[state.corner_permutation[i] as usize] as u8)
+                .collect(),
+            orientation: CORNER_ORDER
+                .iter()
+                .map(|&i| state.corner_orientation[i])
+                .collect(),
+        };
+
+        // Face turns never move centers.
+        let centers = Orbit {
+            pieces: (0..CENTER_COUNT).collect(),
+            orientation: vec![0; CENTER_COUNT as usize],
+        };
+
+        KPatternData {
+            edges,
+            corners,
+            centers,
+        }
+    }
+}
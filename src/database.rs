@@ -0,0 +1,474 @@
+//! Solves saved to a SQLite database with their scramble, moves and phase
+//! splits, so statistics survive restarts and progress can be followed over
+//! months. Each run of triplicata is a session.
+//!
+//! The tables are `sessions (id, started_ms)`, `solves (id, session,
+//! started_ms, duration_ms, scramble, penalty)` with the scramble as a
+//! facelet string and the penalty `PlusTwo`, `Dnf` or null,
+//! `moves (solve, position, notation, offset_ms)` and `splits (solve,
+//! position, name, duration_ms, moves)`.
+
+use std::{
+    fmt::{self, Display},
+    path::Path,
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+use futures::{Stream, StreamExt};
+use rusqlite::{Connection, OptionalExtension, Row, params};
+use tokio::select;
+use tokio_util::sync::CancellationToken;
+use tracing::info;
+
+use crate::{
+    cube::{CubeEvent, Move},
+    error::DatabaseError,
+    facelet::FaceletCube,
+    phase::Phase,
+    solve::{Solve, SolveRecorder, Split, TimedMove},
+};
+
+const SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS sessions (
+        id INTEGER PRIMARY KEY,
+        started_ms INTEGER NOT NULL
+    );
+    CREATE TABLE IF NOT EXISTS solves (
+        id INTEGER PRIMARY KEY,
+        session INTEGER NOT NULL REFERENCES sessions (id),
+        started_ms INTEGER NOT NULL,
+        duration_ms INTEGER NOT NULL,
+        scramble TEXT NOT NULL,
+        penalty TEXT
+    );
+    CREATE TABLE IF NOT EXISTS moves (
+        solve INTEGER NOT NULL REFERENCES solves (id),
+        position INTEGER NOT NULL,
+        notation TEXT NOT NULL,
+        offset_ms INTEGER NOT NULL,
+        PRIMARY KEY (solve, position)
+    );
+    CREATE TABLE IF NOT EXISTS splits (
+        solve INTEGER NOT NULL REFERENCES solves (id),
+        position INTEGER NOT NULL,
+        name TEXT NOT NULL,
+        duration_ms INTEGER NOT NULL,
+        moves INTEGER NOT NULL,
+        PRIMARY KEY (solve, position)
+    );
+    CREATE INDEX IF NOT EXISTS solves_by_start ON solves (started_ms);
+";
+
+/// A solve read back from the database.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StoredSolve {
+    pub id: i64,
+    pub session: i64,
+    pub started: SystemTime,
+    pub duration: Duration,
+    /// The scrambled cube as a facelet string, `UUUUUUUUURRR...`.
+    pub scramble: String,
+    pub moves: Vec<TimedMove>,
+    pub splits: Vec<Split>,
+    pub penalty: Option<Penalty>,
+}
+
+/// A penalty given to a solve after the fact, as in competition.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Penalty {
+    /// Two seconds added to the time.
+    PlusTwo,
+    /// Did not finish, the solve has no time.
+    Dnf,
+}
+
+impl Penalty {
+    fn as_str(self) -> &'static str {
+        match self {
+            Penalty::PlusTwo => "PlusTwo",
+            Penalty::Dnf => "Dnf",
+        }
+    }
+
+    fn parse(s: &str) -> Option<Self> {
+        match s {
+            "PlusTwo" => Some(Penalty::PlusTwo),
+            "Dnf" => Some(Penalty::Dnf),
+            _ => None,
+        }
+    }
+}
+
+impl Display for Penalty {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Penalty::PlusTwo => write!(f, "+2"),
+            Penalty::Dnf => write!(f, "DNF"),
+        }
+    }
+}
+
+impl StoredSolve {
+    /// The time counted for the solve, with two seconds added for a `PlusTwo`
+    /// and none for a `Dnf`.
+    pub fn time(&self) -> Option<Duration> {
+        match self.penalty {
+            None => Some(self.duration),
+            Some(Penalty::PlusTwo) => Some(self.duration + Duration::from_secs(2)),
+            Some(Penalty::Dnf) => None,
+        }
+    }
+
+    /// The moves of each split, by the split's name, with any moves after
+    /// the last split on their own under an empty name.
+    pub fn reconstruction(&self) -> Vec<(&str, &[TimedMove])> {
+        let mut rest = self.moves.as_slice();
+        let mut phases = Vec::with_capacity(self.splits.len() + 1);
+
+        for split in &self.splits {
+            let (moves, after) = rest.split_at(split.moves.min(rest.len()));
+            phases.push((split.name.as_str(), moves));
+            rest = after;
+        }
+
+        if !rest.is_empty() {
+            phases.push(("", rest));
+        }
+
+        phases
+    }
+}
+
+/// The order solves are read in.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum Sort {
+    #[default]
+    Newest,
+    Oldest,
+    /// By time counted, DNFs last.
+    Fastest,
+    /// By time counted, DNFs first.
+    Slowest,
+}
+
+impl Sort {
+    fn order_by(self) -> &'static str {
+        match self {
+            Sort::Newest => "started_ms DESC, id DESC",
+            Sort::Oldest => "started_ms, id",
+            Sort::Fastest => "penalty IS 'Dnf', duration_ms + (penalty IS 'PlusTwo') * 2000, id",
+            Sort::Slowest => {
+                "penalty IS 'Dnf' DESC, duration_ms + (penalty IS 'PlusTwo') * 2000 DESC, id DESC"
+            }
+        }
+    }
+}
+
+/// Which solves to read and in which order, newest first unless sorted
+/// otherwise, e.g. `SolveQuery::new().session(3).limit(12)`.
+#[derive(Debug, Clone, Default)]
+pub struct SolveQuery {
+    session: Option<i64>,
+    since: Option<SystemTime>,
+    limit: Option<usize>,
+    sort: Sort,
+}
+
+impl SolveQuery {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn session(mut self, session: i64) -> Self {
+        self.session = Some(session);
+        self
+    }
+
+    /// Only solves started at or after `since`.
+    pub fn since(mut self, since: SystemTime) -> Self {
+        self.since = Some(since);
+        self
+    }
+
+    /// At most the first `limit` solves in the order sorted.
+    pub fn limit(mut self, limit: usize) -> Self {
+        self.limit = Some(limit);
+        self
+    }
+
+    pub fn sort(mut self, sort: Sort) -> Self {
+        self.sort = sort;
+        self
+    }
+}
+
+pub struct SolveDatabase {
+    connection: Connection,
+}
+
+impl SolveDatabase {
+    /// Opens the database at `path`, creating it and its tables if needed.
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, DatabaseError> {
+        Self::with_connection(Connection::open(path)?)
+    }
+
+    /// A database kept in memory, gone once dropped.
+    pub fn in_memory() -> Result<Self, DatabaseError> {
+        Self::with_connection(Connection::open_in_memory()?)
+    }
+
+    fn with_connection(connection: Connection) -> Result<Self, DatabaseError> {
+        connection.execute_batch(SCHEMA)?;
+        Ok(Self { connection })
+    }
+
+    /// Starts a session at `started`, returning its id.
+    pub fn start_session(&self, started: SystemTime) -> Result<i64, DatabaseError> {
+        self.connection.execute(
+            "INSERT INTO sessions (started_ms) VALUES (?1)",
+            params![millis(started)],
+        )?;
+        Ok(self.connection.last_insert_rowid())
+    }
+
+    /// The id and start of every session, oldest first.
+    pub fn sessions(&self) -> Result<Vec<(i64, SystemTime)>, DatabaseError> {
+        let mut statement = self
+            .connection
+            .prepare("SELECT id, started_ms FROM sessions ORDER BY id")?;
+        let sessions = statement
+            .query_map([], |row| Ok((row.get(0)?, time(row.get(1)?))))?
+            .collect::<Result<_, _>>()?;
+        Ok(sessions)
+    }
+
+    /// Saves `solve` in `session` with its splits for `phases`, returning its
+    /// id.
+    pub fn insert(
+        &mut self,
+        session: i64,
+        solve: &Solve,
+        phases: &[Phase],
+    ) -> Result<i64, DatabaseError> {
+        let transaction = self.connection.transaction()?;
+
+        transaction.execute(
+            "INSERT INTO solves (session, started_ms, duration_ms, scramble)
+             VALUES (?1, ?2, ?3, ?4)",
+            params![
+                session,
+                millis(solve.started),
+                solve.duration.as_millis() as i64,
+                facelets(&FaceletCube::from(&solve.scramble)),
+            ],
+        )?;
+        let id = transaction.last_insert_rowid();
+
+        for (position, m) in solve.moves.iter().enumerate() {
+            transaction.execute(
+                "INSERT INTO moves (solve, position, notation, offset_ms)
+                 VALUES (?1, ?2, ?3, ?4)",
+                params![
+                    id,
+                    position as i64,
+                    m.m.to_string(),
+                    m.offset.as_millis() as i64
+                ],
+            )?;
+        }
+
+        for (position, split) in solve.splits(phases).iter().enumerate() {
+            transaction.execute(
+                "INSERT INTO splits (solve, position, name, duration_ms, moves)
+                 VALUES (?1, ?2, ?3, ?4, ?5)",
+                params![
+                    id,
+                    position as i64,
+                    split.name,
+                    split.duration.as_millis() as i64,
+                    split.moves as i64
+                ],
+            )?;
+        }
+
+        transaction.commit()?;
+        Ok(id)
+    }
+
+    /// The solves `query` picks, in its order.
+    pub fn solves(&self, query: &SolveQuery) -> Result<Vec<StoredSolve>, DatabaseError> {
+        let mut statement = self.connection.prepare(&format!(
+            "SELECT {COLUMNS} FROM solves
+             WHERE (?1 IS NULL OR session = ?1) AND (?2 IS NULL OR started_ms >= ?2)
+             ORDER BY {}
+             LIMIT ?3",
+            query.sort.order_by()
+        ))?;
+        let limit = query.limit.map_or(-1, |limit| limit as i64);
+        let rows = statement
+            .query_map(
+                params![query.session, query.since.map(millis), limit],
+                stored_solve,
+            )?
+            .collect::<Result<Vec<_>, _>>()?;
+
+        rows.into_iter()
+            .map(|solve| self.with_moves(solve))
+            .collect()
+    }
+
+    /// The solve with the id `id`, if there is one.
+    pub fn solve(&self, id: i64) -> Result<Option<StoredSolve>, DatabaseError> {
+        let solve = self
+            .connection
+            .query_row(
+                &format!("SELECT {COLUMNS} FROM solves WHERE id = ?1"),
+                [id],
+                stored_solve,
+            )
+            .optional()?;
+        solve.map(|solve| self.with_moves(solve)).transpose()
+    }
+
+    /// The fastest of the solves `query` picks by time counted, leaving out
+    /// DNFs.
+    pub fn best(&self, query: &SolveQuery) -> Result<Option<StoredSolve>, DatabaseError> {
+        let query = query.clone().sort(Sort::Fastest).limit(1);
+        let solves = self.solves(&query)?;
+        Ok(solves.into_iter().find(|solve| solve.time().is_some()))
+    }
+
+    /// Gives the solve `id` a penalty, or takes it away with `None`,
+    /// returning whether there was such a solve.
+    pub fn penalize(&self, id: i64, penalty: Option<Penalty>) -> Result<bool, DatabaseError> {
+        let changed = self.connection.execute(
+            "UPDATE solves SET penalty = ?2 WHERE id = ?1",
+            params![id, penalty.map(Penalty::as_str)],
+        )?;
+        Ok(changed > 0)
+    }
+
+    /// Deletes the solve `id` with its moves and splits, returning whether
+    /// there was such a solve.
+    pub fn delete(&mut self, id: i64) -> Result<bool, DatabaseError> {
+        let transaction = self.connection.transaction()?;
+        transaction.execute("DELETE FROM moves WHERE solve = ?1", [id])?;
+        transaction.execute("DELETE FROM splits WHERE solve = ?1", [id])?;
+        let deleted = transaction.execute("DELETE FROM solves WHERE id = ?1", [id])?;
+        transaction.commit()?;
+        Ok(deleted > 0)
+    }
+
+    fn with_moves(&self, mut solve: StoredSolve) -> Result<StoredSolve, DatabaseError> {
+        solve.moves = self.moves(solve.id)?;
+        solve.splits = self.splits(solve.id)?;
+        Ok(solve)
+    }
+
+    fn moves(&self, solve: i64) -> Result<Vec<TimedMove>, DatabaseError> {
+        let mut statement = self.connection.prepare_cached(
+            "SELECT notation, offset_ms FROM moves WHERE solve = ?1 ORDER BY position",
+        )?;
+        let rows = statement
+            .query_map([solve], |row| {
+                Ok((row.get::<_, String>(0)?, row.get::<_, i64>(1)?))
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+
+        rows.into_iter()
+            .map(|(notation, offset)| {
+                Ok(TimedMove {
+                    m: notation.parse::<Move>()?,
+                    offset: Duration::from_millis(offset as u64),
+                })
+            })
+            .collect()
+    }
+
+    fn splits(&self, solve: i64) -> Result<Vec<Split>, DatabaseError> {
+        let mut statement = self.connection.prepare_cached(
+            "SELECT name, duration_ms, moves FROM splits WHERE solve = ?1 ORDER BY position",
+        )?;
+        let splits = statement
+            .query_map([solve], |row| {
+                Ok(Split {
+                    name: row.get(0)?,
+                    duration: Duration::from_millis(row.get::<_, i64>(1)? as u64),
+                    moves: row.get::<_, i64>(2)? as usize,
+                })
+            })?
+            .collect::<Result<_, _>>()?;
+        Ok(splits)
+    }
+}
+
+const COLUMNS: &str = "id, session, started_ms, duration_ms, scramble, penalty";
+
+/// A solve from a row of `COLUMNS`, without its moves and splits.
+fn stored_solve(row: &Row) -> rusqlite::Result<StoredSolve> {
+    Ok(StoredSolve {
+        id: row.get(0)?,
+        session: row.get(1)?,
+        started: time(row.get(2)?),
+        duration: Duration::from_millis(row.get::<_, i64>(3)? as u64),
+        scramble: row.get(4)?,
+        moves: Vec::new(),
+        splits: Vec::new(),
+        penalty: row
+            .get::<_, Option<String>>(5)?
+            .as_deref()
+            .and_then(Penalty::parse),
+    })
+}
+
+fn millis(time: SystemTime) -> i64 {
+    time.duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis() as i64
+}
+
+fn time(millis: i64) -> SystemTime {
+    UNIX_EPOCH + Duration::from_millis(millis as u64)
+}
+
+/// The cube as a facelet string, each sticker as the face whose center it
+/// matches.
+fn facelets(cube: &FaceletCube) -> String {
+    cube.0.iter().map(|face| format!("{face:?}")).collect()
+}
+
+/// Saves every solve in `events` to the database at `path`, with splits for
+/// `phases`, as a new session until `cancel` is cancelled.
+pub async fn store_solves(
+    path: impl AsRef<Path>,
+    phases: Vec<Phase>,
+    mut events: impl Stream<Item = CubeEvent> + Unpin,
+    cancel: CancellationToken,
+) -> Result<(), DatabaseError> {
+    let mut database = SolveDatabase::open(path)?;
+    let session = database.start_session(SystemTime::now())?;
+    let mut recorder = SolveRecorder::new();
+
+    loop {
+        let event = select! {
+            event = events.next() => event,
+            _ = cancel.cancelled() => return Ok(()),
+        };
+
+        let Some(event) = event else {
+            return Ok(());
+        };
+
+        let Some(solve) = recorder.push(&event, Instant::now()) else {
+            continue;
+        };
+
+        let id = database.insert(session, &solve, &phases)?;
+        info!(
+            id,
+            session,
+            "Saved a {:.2}s solve",
+            solve.duration.as_secs_f64()
+        );
+    }
+}
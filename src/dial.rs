@@ -0,0 +1,85 @@
+//! Turning one face like a dial, scrolling, changing the volume or zooming
+//! with each quarter turn. The cube only reports whole quarter turns, so the
+//! dial steps once per turn rather than following the face's angle.
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    config::{Action, Bind, Key},
+    cube::{Direction, Face, Move},
+};
+
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum DialTarget {
+    #[default]
+    Scroll,
+    Volume,
+    /// Scrolls with control held, which zooms in most applications.
+    Zoom,
+}
+
+/// A face used as a dial, e.g. `(face: R, target: Volume, sensitivity: 2)`.
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, Eq)]
+#[serde(default)]
+pub struct Dial {
+    /// The group of the dial binds.
+    pub group: String,
+    pub face: Face,
+    pub target: DialTarget,
+    /// Scroll lines or volume steps per quarter turn.
+    pub sensitivity: u32,
+}
+
+impl Default for Dial {
+    fn default() -> Self {
+        Self {
+            group: "dial".to_string(),
+            face: Face::U,
+            target: DialTarget::Scroll,
+            sensitivity: 1,
+        }
+    }
+}
+
+impl Dial {
+    /// The actions of one quarter turn. Clockwise scrolls down, turns the
+    /// volume up and zooms in.
+    pub fn step(&self, direction: Direction) -> Vec<Action> {
+        let steps = self.sensitivity as i32;
+        let clockwise = direction == Direction::Clockwise;
+
+        match self.target {
+            DialTarget::Scroll => vec![Action::Scroll(if clockwise { steps } else { -steps })],
+            DialTarget::Volume => {
+                let key = if clockwise {
+                    Key::VolumeUp
+                } else {
+                    Key::VolumeDown
+                };
+                vec![Action::Click(key); self.sensitivity as usize]
+            }
+            DialTarget::Zoom => vec![
+                Action::Press(Key::Control),
+                Action::Scroll(if clockwise { -steps } else { steps }),
+                Action::Release(Key::Control),
+            ],
+        }
+    }
+
+    /// A bind for each direction of the face.
+    pub fn binds(&self) -> Vec<Bind> {
+        [Direction::Clockwise, Direction::CounterClockwise]
+            .into_iter()
+            .map(|direction| {
+                let m = Move::new(self.face, direction);
+                Bind {
+                    name: Some(format!("Dial {m}")),
+                    trigger: vec![m],
+                    actions: self.step(direction),
+                    groups: vec![self.group.clone()],
+                    ..Bind::default()
+                }
+            })
+            .collect()
+    }
+}
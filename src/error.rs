@@ -0,0 +1,323 @@
+use alloc::{
+    string::{String, ToString},
+    vec::Vec,
+};
+
+use thiserror::Error;
+use uuid::Uuid;
+
+#[cfg(feature = "std")]
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error(transparent)]
+    Cube(#[from] CubeError),
+    #[cfg(feature = "config")]
+    #[error(transparent)]
+    Config(#[from] ConfigError),
+    #[cfg(feature = "input")]
+    #[error(transparent)]
+    Output(#[from] OutputError),
+    #[cfg(feature = "metrics")]
+    #[error(transparent)]
+    Metrics(#[from] MetricsError),
+    #[cfg(feature = "overlay")]
+    #[error(transparent)]
+    Overlay(#[from] OverlayError),
+    #[cfg(feature = "api")]
+    #[error(transparent)]
+    Api(#[from] ApiError),
+    #[cfg(feature = "robot")]
+    #[error(transparent)]
+    Robot(#[from] RobotError),
+    #[cfg(feature = "solves")]
+    #[error(transparent)]
+    Solve(#[from] SolveError),
+    #[error("the {0} task stopped unexpectedly")]
+    TaskStopped(&'static str),
+}
+
+#[cfg(feature = "std")]
+#[derive(Debug, Error)]
+pub enum CubeError {
+    #[cfg(feature = "bluetooth")]
+    #[error("bluetooth error: {0}")]
+    Bluetooth(#[from] btleplug::Error),
+    #[cfg(all(feature = "bluez", target_os = "linux"))]
+    #[error("bluez error: {0}")]
+    Bluez(#[from] bluer::Error),
+    #[error("could not find bluetooth adapter")]
+    NoAdapter,
+    #[error("could not find a GAN cube")]
+    NotFound,
+    #[cfg(feature = "web")]
+    #[error("web bluetooth error: {0}")]
+    Web(String),
+    #[error("could not get device properties")]
+    MissingProperties,
+    #[error("device disconnected")]
+    Disconnected,
+    #[error("timed out after {timeout:?}")]
+    TimedOut {
+        step: &'static str,
+        timeout: std::time::Duration,
+    },
+    #[error(
+        "could not {step} after {attempts} attempt{}: {last}",
+        if *attempts == 1 { "" } else { "s" }
+    )]
+    GaveUp {
+        step: &'static str,
+        attempts: u32,
+        last: Box<CubeError>,
+    },
+    #[error(transparent)]
+    Protocol(#[from] ProtocolError),
+}
+
+#[derive(Debug, Error)]
+pub enum ProtocolError {
+    #[error("packet size {0} less than expected length")]
+    PacketTooShort(usize),
+    #[error("packet size {0} greater than expected length")]
+    PacketTooLong(usize),
+    #[error("manufacturer data missing device identifier")]
+    MissingDeviceIdentifier,
+    #[error("device identifier invalid")]
+    InvalidDeviceIdentifier,
+    #[error("unknown protocol version")]
+    UnknownVersion,
+    #[error("unknown protocol version, the cube exposes {}", list_characteristics(.0))]
+    UnrecognizedCharacteristics(Vec<Uuid>),
+    #[error("packet has a bad header or checksum")]
+    InvalidChecksum,
+    #[error("unknown timer state {0}")]
+    UnknownTimerState(u8),
+}
+
+fn list_characteristics(found: &[Uuid]) -> String {
+    if found.is_empty() {
+        return "no characteristics".into();
+    }
+
+    found
+        .iter()
+        .map(Uuid::to_string)
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum NotationError {
+    #[error("invalid move `{0}`")]
+    InvalidMove(String),
+    #[error("`{0}` cannot be performed as face turns")]
+    UnsupportedTurn(String),
+}
+
+#[cfg(feature = "config")]
+#[derive(Debug, Error)]
+pub enum ConfigError {
+    #[error("could not read config: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("could not parse config: {0}")]
+    Parse(#[from] ron::error::SpannedError),
+    #[error("could not write config: {0}")]
+    Serialize(#[from] ron::Error),
+    #[error("could not parse config: {error}, did you mean `{suggestion}`?")]
+    UnknownVariant {
+        error: ron::error::SpannedError,
+        suggestion: &'static str,
+    },
+    #[error(
+        "unknown field `{field}` in {location}{}",
+        .suggestion.map(|s| format!(", did you mean `{s}`?")).unwrap_or_default()
+    )]
+    UnknownField {
+        field: String,
+        location: &'static str,
+        suggestion: Option<&'static str>,
+    },
+    #[error("bind pack version {0} is newer than this triplicata supports")]
+    UnsupportedPackVersion(u32),
+    #[error("no config provided")]
+    Missing,
+}
+
+#[cfg(feature = "input")]
+#[derive(Debug, Error)]
+pub enum OutputError {
+    #[error("could not create input connection: {0}")]
+    Connection(#[from] enigo::NewConError),
+    #[error("could not simulate input: {0}")]
+    Input(#[from] enigo::InputError),
+    #[error("could not run the command: {0}")]
+    Run(#[from] std::io::Error),
+    #[cfg(all(feature = "evdev", target_os = "linux"))]
+    #[error("could not use the virtual keyboard: {0}")]
+    Evdev(std::io::Error),
+    #[cfg(all(feature = "evdev", target_os = "linux"))]
+    #[error("the virtual keyboard has no key for {0:?}")]
+    Unmapped(enigo::Key),
+    #[error("there is no switch {0}")]
+    NoSwitch(u8),
+    #[cfg(feature = "hue")]
+    #[error("could not control Hue lights: {0}")]
+    Hue(#[from] HueError),
+    #[cfg(feature = "spotify")]
+    #[error("could not control Spotify: {0}")]
+    Spotify(#[from] SpotifyError),
+    #[cfg(feature = "twitch")]
+    #[error("could not run the Twitch command: {0}")]
+    Twitch(#[from] TwitchError),
+}
+
+#[cfg(feature = "hue")]
+#[derive(Debug, Error)]
+pub enum HueError {
+    #[error("could not reach the bridge: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("the bridge refused the command: {0}")]
+    Refused(String),
+    #[error("no Hue bridge is configured")]
+    NotConfigured,
+}
+
+#[cfg(feature = "spotify")]
+#[derive(Debug, Error)]
+pub enum SpotifyError {
+    #[error("could not reach Spotify: {0}")]
+    Http(#[from] Box<ureq::Error>),
+    #[error("could not read the response from Spotify: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Spotify refused the request: {0}")]
+    Refused(String),
+    #[error("no Spotify device named `{0}` is available")]
+    NoDevice(String),
+    #[error("nothing is playing")]
+    NothingPlaying,
+    #[error("no Spotify account is configured")]
+    NotConfigured,
+}
+
+#[cfg(feature = "twitch")]
+#[derive(Debug, Error)]
+pub enum TwitchError {
+    #[error("could not reach Twitch: {0}")]
+    Http(#[from] Box<ureq::Error>),
+    #[error("could not follow EventSub: {0}")]
+    WebSocket(#[from] Box<tokio_tungstenite::tungstenite::Error>),
+    #[error("could not parse an EventSub message: {0}")]
+    Json(#[from] serde_json::Error),
+    #[error("Twitch refused the request: {0}")]
+    Refused(String),
+    #[error("unexpected response from Twitch: {0}")]
+    Unexpected(String),
+    #[error("EventSub closed the connection")]
+    Closed,
+    #[error("no Twitch account is configured")]
+    NotConfigured,
+}
+
+#[cfg(feature = "metrics")]
+#[derive(Debug, Error)]
+pub enum MetricsError {
+    #[error("could not start metrics exporter: {0}")]
+    Exporter(#[from] metrics_exporter_prometheus::BuildError),
+}
+
+#[cfg(feature = "overlay")]
+#[derive(Debug, Error)]
+pub enum OverlayError {
+    #[error("could not serve overlay: {0}")]
+    Io(#[from] std::io::Error),
+}
+
+#[cfg(feature = "api")]
+#[derive(Debug, Error)]
+pub enum ApiError {
+    #[error("could not serve the API: {0}")]
+    Io(#[from] std::io::Error),
+}
+
+#[cfg(feature = "grpc")]
+#[derive(Debug, Error)]
+pub enum GrpcError {
+    #[error("could not serve gRPC: {0}")]
+    Transport(#[from] tonic::transport::Error),
+}
+
+#[cfg(feature = "udp")]
+#[derive(Debug, Error)]
+pub enum UdpError {
+    #[error("could not send events over UDP: {0}")]
+    Io(#[from] std::io::Error),
+}
+
+#[cfg(feature = "webhook")]
+#[derive(Debug, Error)]
+pub enum WebhookError {
+    #[error("could not reach the webhook: {0}")]
+    Http(#[from] Box<ureq::Error>),
+    #[error("the webhook refused the event: {0}")]
+    Refused(String),
+}
+
+#[cfg(feature = "control")]
+#[derive(Debug, Error)]
+pub enum ControlError {
+    #[error("could not serve the control socket: {0}")]
+    Io(#[from] std::io::Error),
+}
+
+#[cfg(feature = "robot")]
+#[derive(Debug, Error)]
+pub enum RobotError {
+    #[error(transparent)]
+    Connection(#[from] CubeError),
+    #[error("bluetooth error: {0}")]
+    Bluetooth(#[from] btleplug::Error),
+    #[error(transparent)]
+    Protocol(#[from] ProtocolError),
+    #[error(transparent)]
+    Notation(#[from] NotationError),
+    #[error("robot did not finish its moves in time")]
+    Timeout,
+}
+
+#[cfg(feature = "solves")]
+#[derive(Debug, Error)]
+pub enum SolveError {
+    #[error("could not write solve: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("could not serialize solve: {0}")]
+    Json(#[from] serde_json::Error),
+}
+
+#[cfg(feature = "database")]
+#[derive(Debug, Error)]
+pub enum DatabaseError {
+    #[error("database error: {0}")]
+    Sqlite(#[from] rusqlite::Error),
+    #[error("stored move is invalid: {0}")]
+    Notation(#[from] NotationError),
+    #[error("unknown column `{0}`")]
+    UnknownColumn(String),
+}
+
+#[cfg(feature = "persist")]
+#[derive(Debug, Error)]
+pub enum PersistError {
+    #[error("could not access saved cube state: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("could not parse saved cube state: {0}")]
+    Json(#[from] serde_json::Error),
+}
+
+#[cfg(feature = "simulator")]
+#[derive(Debug, Error)]
+pub enum ScenarioError {
+    #[error("could not read scenario: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("line {line} of the scenario: {reason}")]
+    Invalid { line: usize, reason: String },
+}
@@ -0,0 +1,210 @@
+//! A cube tracked sticker by sticker. Unlike [`CubeState`] the centers move,
+//! so slices, wide turns and rotations can be applied as well as face turns.
+
+use crate::{
+    algorithm::{Algorithm, Axis, Layer, Slice, Turn},
+    cube::{CubeState, Face},
+};
+
+/// A position or direction, with -1, 0 and 1 on each axis for the layers.
+type Vector = [i8; 3];
+
+/// Every sticker's color, in the order of [`CubeState::facelets`].
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub struct FaceletCube(pub [Face; 54]);
+
+impl FaceletCube {
+    pub const SOLVED: Self = {
+        let mut facelets = [Face::U; 54];
+        let mut i = 0;
+        while i < 54 {
+            facelets[i] = Face::ALL[i / 9];
+            i += 1;
+        }
+        Self(facelets)
+    };
+
+    pub fn center(&self, face: Face) -> Face {
+        self.0[face as usize * 9 + 4]
+    }
+
+    /// Recolors every sticker after the center it matches, so a rotated cube
+    /// reads as if it was held in the standard orientation.
+    pub fn relative(&self) -> Self {
+        let mut relative = *self;
+        for color in &mut relative.0 {
+            *color = Face::ALL
+                .into_iter()
+                .find(|&face| self.center(face) == *color)
+                .unwrap_or(*color);
+        }
+        relative
+    }
+
+    /// Whether every sticker of `face` matches its center.
+    pub fn face_solved(&self, face: Face) -> bool {
+        let stickers = &self.0[face as usize * 9..face as usize * 9 + 9];
+        stickers.iter().all(|&color| color == self.center(face))
+    }
+
+    /// Whether the edges around `face` are solved, along with the stickers
+    /// next to them on the adjacent faces.
+    pub fn cross_solved(&self, face: Face) -> bool {
+        [1, 3, 5, 7].into_iter().all(|i| {
+            let facelet = face as usize * 9 + i;
+            let (position, normal) = geometry(facelet);
+            let side =
+                core::array::from_fn(|axis| if normal[axis] == 0 { position[axis] } else { 0 });
+            let adjacent = index(position, side);
+
+            self.0[facelet] == self.center(face)
+                && self.0[adjacent] == self.center(Face::ALL[adjacent / 9])
+        })
+    }
+
+    /// Whether every sticker outside the outer layer under `face` matches its
+    /// center, as after the first two layers when `face` is the last layer.
+    pub fn solved_below(&self, face: Face) -> bool {
+        let (axis, sign) = axis(face);
+
+        self.0.iter().enumerate().all(|(i, &color)| {
+            geometry(i).0[axis] * sign == 1 || color == self.center(Face::ALL[i / 9])
+        })
+    }
+
+    /// The cube turned so `face` is on top, recolored with
+    /// [`FaceletCube::relative`].
+    pub fn with_on_top(&self, face: Face) -> Self {
+        let rotation = match face {
+            Face::U => None,
+            Face::R => Some((Axis::Z, -1)),
+            Face::F => Some((Axis::X, 1)),
+            Face::D => Some((Axis::X, 2)),
+            Face::L => Some((Axis::Z, 1)),
+            Face::B => Some((Axis::X, -1)),
+        };
+
+        let mut cube = *self;
+        if let Some((axis, amount)) = rotation {
+            cube.apply(Turn {
+                layer: Layer::Rotation(axis),
+                amount,
+            });
+        }
+        cube.relative()
+    }
+
+    pub fn apply(&mut self, turn: Turn) {
+        let (axis, sign, layers) = match turn.layer {
+            Layer::Face(face) => (axis(face).0, axis(face).1, 1..=1),
+            Layer::Wide(face, depth) => {
+                let (axis, sign) = axis(face);
+                (axis, sign, (2 - depth.min(3) as i8)..=1)
+            }
+            Layer::Slice(slice) => {
+                let face = match slice {
+                    Slice::M => Face::L,
+                    Slice::E => Face::D,
+                    Slice::S => Face::F,
+                };
+                (axis(face).0, axis(face).1, 0..=0)
+            }
+            Layer::Rotation(rotation) => {
+                let face = match rotation {
+                    Axis::X => Face::R,
+                    Axis::Y => Face::U,
+                    Axis::Z => Face::F,
+                };
+                (axis(face).0, axis(face).1, -1..=1)
+            }
+        };
+
+        // Clockwise seen from the face is counter clockwise about the axis
+        // pointing out of the opposite side.
+        let quarters = (turn.amount as i32 * -(sign as i32)).rem_euclid(4);
+
+        let previous = self.0;
+        for (i, &color) in previous.iter().enumerate() {
+            let (mut position, mut normal) = geometry(i);
+            if !layers.contains(&(position[axis] * sign)) {
+                continue;
+            }
+
+            for _ in 0..quarters {
+                position = quarter(position, axis);
+                normal = quarter(normal, axis);
+            }
+
+            self.0[index(position, normal)] = color;
+        }
+    }
+
+    pub fn apply_algorithm(&mut self, algorithm: &Algorithm) {
+        for &turn in &algorithm.0 {
+            self.apply(turn);
+        }
+    }
+}
+
+impl Default for FaceletCube {
+    fn default() -> Self {
+        Self::SOLVED
+    }
+}
+
+impl From<&CubeState> for FaceletCube {
+    fn from(state: &CubeState) -> Self {
+        Self(state.facelets())
+    }
+}
+
+/// The axis a face is on and whether it is on the positive side. X points to
+/// R, Y to U and Z to F.
+fn axis(face: Face) -> (usize, i8) {
+    match face {
+        Face::U => (1, 1),
+        Face::R => (0, 1),
+        Face::F => (2, 1),
+        Face::D => (1, -1),
+        Face::L => (0, -1),
+        Face::B => (2, -1),
+    }
+}
+
+/// A quarter turn counter clockwise about `axis`.
+fn quarter([x, y, z]: Vector, axis: usize) -> Vector {
+    match axis {
+        0 => [x, -z, y],
+        1 => [z, y, -x],
+        _ => [-y, x, z],
+    }
+}
+
+/// The position and outward normal of a facelet. Faces are read row by row
+/// as they appear on the usual unfolded net.
+fn geometry(facelet: usize) -> (Vector, Vector) {
+    let (face, row, column) = (facelet / 9, (facelet % 9 / 3) as i8, (facelet % 3) as i8);
+
+    match Face::ALL[face] {
+        Face::U => ([column - 1, 1, row - 1], [0, 1, 0]),
+        Face::R => ([1, 1 - row, 1 - column], [1, 0, 0]),
+        Face::F => ([column - 1, 1 - row, 1], [0, 0, 1]),
+        Face::D => ([column - 1, -1, 1 - row], [0, -1, 0]),
+        Face::L => ([-1, 1 - row, column - 1], [-1, 0, 0]),
+        Face::B => ([1 - column, 1 - row, -1], [0, 0, -1]),
+    }
+}
+
+/// The inverse of [`geometry`].
+fn index([x, y, z]: Vector, normal: Vector) -> usize {
+    let (face, row, column) = match normal {
+        [0, 1, 0] => (Face::U, z + 1, x + 1),
+        [1, 0, 0] => (Face::R, 1 - y, 1 - z),
+        [0, 0, 1] => (Face::F, 1 - y, x + 1),
+        [0, -1, 0] => (Face::D, 1 - z, x + 1),
+        [-1, 0, 0] => (Face::L, 1 - y, z + 1),
+        _ => (Face::B, 1 - y, 1 - x),
+    };
+
+    face as usize * 9 + row as usize * 3 + column as usize
+}
@@ -0,0 +1,244 @@
+//! Switching between profiles of bind groups as the focused window changes,
+//! so a game and an editor can each have their own binds on the same
+//! triggers.
+
+use std::{collections::HashSet, io, time::Duration};
+
+use serde::{Deserialize, Serialize};
+use tokio::{
+    process::Command,
+    select,
+    time::{Instant, MissedTickBehavior, interval},
+};
+use tokio_util::sync::CancellationToken;
+use tracing::{debug, info};
+
+use crate::{MoveInjector, cube::CubeEvent};
+
+/// Profiles to switch between, e.g.
+/// `(profiles: [(name: "Code", windows: ["code"], groups: ["editor"])])`.
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct Focus {
+    /// Checked in order, switching to the first matching the focused window.
+    /// Windows matching none keep the current profile, and a profile with an
+    /// empty pattern matches every window.
+    pub profiles: Vec<Profile>,
+    /// Milliseconds a window has to keep focus before its profile is
+    /// switched to, so alt-tabbing past windows does not switch.
+    #[serde(default = "default_settle")]
+    pub settle: u64,
+    /// Milliseconds between checks of the focused window.
+    #[serde(default = "default_poll")]
+    pub poll: u64,
+}
+
+fn default_settle() -> u64 {
+    750
+}
+
+fn default_poll() -> u64 {
+    250
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct Profile {
+    pub name: String,
+    /// Matched against the title and class of the focused window, ignoring
+    /// case. The window matches if either contains any of the patterns.
+    pub windows: Vec<String>,
+    /// Groups enabled while the profile is active. The groups of every other
+    /// profile are disabled.
+    pub groups: Vec<String>,
+}
+
+/// The focused window. The class is the process name on Windows and the
+/// application name on macOS.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Window {
+    pub title: String,
+    pub class: String,
+}
+
+impl Profile {
+    pub fn matches(&self, window: &Window) -> bool {
+        let title = window.title.to_lowercase();
+        let class = window.class.to_lowercase();
+
+        self.windows.iter().any(|pattern| {
+            let pattern = pattern.to_lowercase();
+            title.contains(&pattern) || class.contains(&pattern)
+        })
+    }
+}
+
+impl Focus {
+    /// The index of the first profile matching `window`.
+    pub fn profile_of(&self, window: &Window) -> Option<usize> {
+        self.profiles
+            .iter()
+            .position(|profile| profile.matches(window))
+    }
+
+    /// Enables the groups of the profile at `profile` and disables those of
+    /// the others. A group in both stays enabled.
+    pub fn switch(&self, profile: usize, disabled_groups: &mut HashSet<String>) {
+        let Some(active) = self.profiles.get(profile) else {
+            return;
+        };
+
+        for other in &self.profiles {
+            disabled_groups.extend(other.groups.iter().cloned());
+        }
+        for group in &active.groups {
+            disabled_groups.remove(group);
+        }
+    }
+}
+
+/// Settles on the profile of the focused window, only switching once a
+/// different profile has matched for the whole settle time. The first profile
+/// matched is switched to straight away.
+#[derive(Debug, Clone)]
+pub struct ProfileSwitcher {
+    settle: Duration,
+    current: Option<usize>,
+    /// The profile waiting to be switched to and when it first matched.
+    pending: Option<(usize, Instant)>,
+}
+
+impl ProfileSwitcher {
+    pub fn new(settle: Duration) -> Self {
+        Self {
+            settle,
+            current: None,
+            pending: None,
+        }
+    }
+
+    pub fn current(&self) -> Option<usize> {
+        self.current
+    }
+
+    /// Takes the profile matching the window focused at `now`, returning the
+    /// profile to switch to if it has settled.
+    pub fn observe(&mut self, profile: Option<usize>, now: Instant) -> Option<usize> {
+        let profile = match profile {
+            Some(profile) if Some(profile) != self.current => profile,
+            _ => {
+                self.pending = None;
+                return None;
+            }
+        };
+
+        let since = match self.pending {
+            Some((pending, since)) if pending == profile => since,
+            _ => now,
+        };
+
+        if self.current.is_some() && now.duration_since(since) < self.settle {
+            self.pending = Some((profile, since));
+            return None;
+        }
+
+        self.pending = None;
+        self.current = Some(profile);
+        Some(profile)
+    }
+}
+
+/// Follows the focused window until `cancel` is cancelled, injecting
+/// [`CubeEvent::Profile`] as profiles are switched to. Fails if the focused
+/// window cannot be queried at all, such as without `xdotool` on Linux.
+pub async fn follow_focus(
+    focus: Focus,
+    injector: MoveInjector,
+    cancel: CancellationToken,
+) -> io::Result<()> {
+    let mut switcher = ProfileSwitcher::new(Duration::from_millis(focus.settle));
+    let mut poll = interval(Duration::from_millis(focus.poll));
+    poll.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
+    loop {
+        select! {
+            _ = poll.tick() => {}
+            _ = cancel.cancelled() => return Ok(()),
+        }
+
+        let Some(window) = focused_window().await? else {
+            continue;
+        };
+
+        if let Some(profile) = switcher.observe(focus.profile_of(&window), Instant::now()) {
+            info!(
+                window = %window.title,
+                "Switching to profile {}", focus.profiles[profile].name
+            );
+            injector.inject_event(CubeEvent::Profile(profile));
+        }
+    }
+}
+
+/// The focused window, or `None` if no window has focus.
+pub async fn focused_window() -> io::Result<Option<Window>> {
+    let output = focused_window_command().output().await?;
+
+    if !output.status.success() {
+        debug!(status = %output.status, "No focused window");
+        return Ok(None);
+    }
+
+    let output = String::from_utf8_lossy(&output.stdout);
+    let mut lines = output.lines();
+    let class = lines.next().unwrap_or_default().trim().to_string();
+    let title = lines.next().unwrap_or_default().trim().to_string();
+
+    Ok(Some(Window { title, class }))
+}
+
+/// Prints the class of the focused window, then its title.
+#[cfg(not(any(target_os = "macos", target_os = "windows")))]
+fn focused_window_command() -> Command {
+    let mut command = Command::new("xdotool");
+    command.args(["getactivewindow", "getwindowclassname", "getwindowname"]);
+    command
+}
+
+#[cfg(target_os = "macos")]
+fn focused_window_command() -> Command {
+    let mut command = Command::new("osascript");
+    command.args([
+        "-e",
+        "tell application \"System Events\"",
+        "-e",
+        "set p to first application process whose frontmost is true",
+        "-e",
+        "set t to \"\"",
+        "-e",
+        "try",
+        "-e",
+        "set t to name of front window of p",
+        "-e",
+        "end try",
+        "-e",
+        "return (name of p) & linefeed & t",
+        "-e",
+        "end tell",
+    ]);
+    command
+}
+
+#[cfg(target_os = "windows")]
+fn focused_window_command() -> Command {
+    let script = "Add-Type -Name Window -Namespace Triplicata -MemberDefinition '\
+                  [DllImport(\"user32.dll\")] public static extern IntPtr GetForegroundWindow(); \
+                  [DllImport(\"user32.dll\")] public static extern int GetWindowThreadProcessId(IntPtr w, out int p);'; \
+                  $p = 0; \
+                  [void][Triplicata.Window]::GetWindowThreadProcessId([Triplicata.Window]::GetForegroundWindow(), [ref]$p); \
+                  $process = Get-Process -Id $p; \
+                  $process.ProcessName; \
+                  $process.MainWindowTitle";
+
+    let mut command = Command::new("powershell");
+    command.args(["-NoProfile", "-WindowStyle", "Hidden", "-Command", script]);
+    command
+}
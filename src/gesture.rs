@@ -0,0 +1,101 @@
+//! Binds learned from a few demonstrations of a gesture. The trigger is what
+//! every demonstration has in common, with enough tolerance and time for each
+//! of them to have matched.
+
+use std::time::Duration;
+
+use crate::{config::Bind, cube::Move};
+
+/// How much longer than the slowest demonstration the gesture may take.
+const TIME_MARGIN: f64 = 1.5;
+
+/// The fewest demonstrations a gesture is learned from, as a single one shows
+/// none of the ways the gesture varies between performances.
+pub const MIN_DEMONSTRATIONS: usize = 2;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Demonstration {
+    pub moves: Vec<Move>,
+    /// From the first move to the last.
+    pub duration: Duration,
+}
+
+/// A bind without actions for the gesture shown in `demonstrations`, or
+/// `None` if there are fewer than [`MIN_DEMONSTRATIONS`] or they have no
+/// moves in common.
+pub fn learn(demonstrations: &[Demonstration]) -> Option<Bind> {
+    if demonstrations.len() < MIN_DEMONSTRATIONS {
+        return None;
+    }
+
+    let (first, rest) = demonstrations.split_first()?;
+    let trigger = rest
+        .iter()
+        .fold(first.moves.clone(), |common, demonstration| {
+            longest_common_subsequence(&common, &demonstration.moves)
+        });
+
+    if trigger.is_empty() {
+        return None;
+    }
+
+    let mut bind = Bind {
+        tolerance: trigger.len(),
+        trigger,
+        ..Bind::default()
+    };
+
+    // Demonstrations with strays before or after the common moves cannot
+    // match whatever the tolerance, so they are left out.
+    bind.tolerance = demonstrations
+        .iter()
+        .filter_map(|demonstration| bind.matches(&demonstration.moves))
+        .max()
+        .unwrap_or(0);
+
+    let slowest = demonstrations
+        .iter()
+        .map(|demonstration| demonstration.duration)
+        .max()
+        .unwrap_or_default();
+    bind.within = Some((slowest.as_millis() as f64 * TIME_MARGIN).ceil() as u64);
+
+    Some(bind)
+}
+
+/// The bind in `binds` that already fires on the learned `bind`'s trigger, so
+/// the same gesture is not bound twice.
+pub fn already_bound<'a>(binds: &'a [Bind], bind: &Bind) -> Option<&'a Bind> {
+    binds
+        .iter()
+        .find(|existing| existing.matches(&bind.trigger).is_some())
+}
+
+fn longest_common_subsequence(a: &[Move], b: &[Move]) -> Vec<Move> {
+    // lengths[i][j] is the length for a[i..] and b[j..].
+    let mut lengths = vec![vec![0; b.len() + 1]; a.len() + 1];
+    for i in (0..a.len()).rev() {
+        for j in (0..b.len()).rev() {
+            lengths[i][j] = if a[i] == b[j] {
+                lengths[i + 1][j + 1] + 1
+            } else {
+                lengths[i + 1][j].max(lengths[i][j + 1])
+            };
+        }
+    }
+
+    let mut common = Vec::with_capacity(lengths[0][0]);
+    let (mut i, mut j) = (0, 0);
+    while i < a.len() && j < b.len() {
+        if a[i] == b[j] {
+            common.push(a[i]);
+            (i, j) = (i + 1, j + 1);
+        } else if lengths[i + 1][j] >= lengths[i][j + 1] {
+            i += 1;
+        } else {
+            j += 1;
+        }
+    }
+
+    common
+}
@@ -0,0 +1,186 @@
+//! A gRPC service streaming cube events and taking control calls, defined in
+//! `proto/triplicata.proto` so typed clients can be generated for any
+//! language. Building it needs `protoc`.
+
+use std::{net::SocketAddr, pin::Pin};
+
+use futures::{Stream, StreamExt, future};
+use tokio_stream::wrappers::{BroadcastStream, errors::BroadcastStreamRecvError};
+use tokio_util::sync::CancellationToken;
+use tonic::{Request, Response, Status, transport::Server};
+use tracing::info;
+
+use crate::{
+    MoveInjector,
+    algorithm::Algorithm,
+    cube::CubeEvent,
+    error::GrpcError,
+    metrics::{self, Channel},
+    state_machine::Pause,
+    status::CubeStatus,
+};
+
+/// The messages and service generated from the proto definitions.
+pub mod proto {
+    tonic::include_proto!("triplicata.v1");
+}
+
+use proto::{
+    EventsRequest, FireBindReply, FireBindRequest, GetStatusRequest, InjectMovesReply,
+    InjectMovesRequest, SetPausedReply, SetPausedRequest, cube_event,
+    triplicata_server::{Triplicata, TriplicataServer},
+};
+
+/// What the calls act on.
+#[derive(Debug, Clone)]
+pub struct GrpcService {
+    status: CubeStatus,
+    injector: MoveInjector,
+    pause: Pause,
+    /// The names of the binds, by index.
+    binds: Vec<Option<String>>,
+}
+
+impl GrpcService {
+    pub fn new(
+        status: CubeStatus,
+        injector: MoveInjector,
+        pause: Pause,
+        binds: Vec<Option<String>>,
+    ) -> Self {
+        Self {
+            status,
+            injector,
+            pause,
+            binds,
+        }
+    }
+}
+
+impl From<CubeEvent> for proto::CubeEvent {
+    fn from(event: CubeEvent) -> Self {
+        let event = match event {
+            CubeEvent::Move(m) => cube_event::Event::Move(m.to_string()),
+            CubeEvent::StateSync(state) => cube_event::Event::StateSolved(state.is_solved()),
+            CubeEvent::Battery(level) => cube_event::Event::Battery(level.into()),
+            CubeEvent::Orientation(q) => cube_event::Event::Orientation(proto::Quaternion {
+                x: q.x,
+                y: q.y,
+                z: q.z,
+                w: q.w,
+            }),
+            CubeEvent::Connected => cube_event::Event::Connected(true),
+            CubeEvent::Disconnected => cube_event::Event::Disconnected(true),
+            CubeEvent::Lagged(dropped) => cube_event::Event::Lagged(dropped),
+            CubeEvent::Timer(timer) => cube_event::Event::Timer(proto::Timer {
+                state: format!("{:?}", timer.state),
+                time_ms: timer.time.map(|time| time.as_millis() as u64),
+            }),
+            CubeEvent::Redemption(bind) => cube_event::Event::Redemption(bind as u64),
+            CubeEvent::Profile(profile) => cube_event::Event::Profile(profile as u64),
+            CubeEvent::Fire(bind) => cube_event::Event::Fire(bind as u64),
+            CubeEvent::Received(_) => return Self { event: None },
+        };
+
+        Self { event: Some(event) }
+    }
+}
+
+type EventStream = Pin<Box<dyn Stream<Item = Result<proto::CubeEvent, Status>> + Send>>;
+
+#[tonic::async_trait]
+impl Triplicata for GrpcService {
+    type EventsStream = EventStream;
+
+    async fn events(
+        &self,
+        _request: Request<EventsRequest>,
+    ) -> Result<Response<Self::EventsStream>, Status> {
+        let events = BroadcastStream::new(self.injector.subscribe())
+            // When moves were received is only followed by the pipeline.
+            .filter(|event| future::ready(!matches!(event, Ok(CubeEvent::Received(_)))))
+            .map(|event| {
+                let event = match event {
+                    Ok(event) => event,
+                    Err(BroadcastStreamRecvError::Lagged(count)) => {
+                        metrics::channel_drops(Channel::EventStream, count);
+                        CubeEvent::Lagged(count)
+                    }
+                };
+                Ok(event.into())
+            });
+
+        Ok(Response::new(Box::pin(events)))
+    }
+
+    async fn get_status(
+        &self,
+        _request: Request<GetStatusRequest>,
+    ) -> Result<Response<proto::Status>, Status> {
+        let status = self.status.get();
+
+        Ok(Response::new(proto::Status {
+            connected: status.connected,
+            battery: status.battery.map(u32::from),
+            paused: self.pause.is_paused(),
+        }))
+    }
+
+    async fn inject_moves(
+        &self,
+        request: Request<InjectMovesRequest>,
+    ) -> Result<Response<InjectMovesReply>, Status> {
+        let algorithm = request
+            .into_inner()
+            .moves
+            .parse::<Algorithm>()
+            .map_err(|e| Status::invalid_argument(e.to_string()))?;
+        self.injector
+            .inject_algorithm(&algorithm)
+            .map_err(|e| Status::invalid_argument(e.to_string()))?;
+
+        Ok(Response::new(InjectMovesReply {}))
+    }
+
+    async fn fire_bind(
+        &self,
+        request: Request<FireBindRequest>,
+    ) -> Result<Response<FireBindReply>, Status> {
+        let name = request.into_inner().name;
+        let bind = self
+            .binds
+            .iter()
+            .position(|bind| bind.as_deref() == Some(name.as_str()))
+            .ok_or_else(|| Status::not_found(format!("no bind named `{name}`")))?;
+        self.injector.inject_event(CubeEvent::Fire(bind));
+
+        Ok(Response::new(FireBindReply {}))
+    }
+
+    async fn set_paused(
+        &self,
+        request: Request<SetPausedRequest>,
+    ) -> Result<Response<SetPausedReply>, Status> {
+        let paused = request.into_inner().paused;
+        self.pause.set(paused);
+        info!(paused, "Pause set over gRPC");
+
+        Ok(Response::new(SetPausedReply { paused }))
+    }
+}
+
+/// Serves `service` on `address` until `cancel` is cancelled.
+pub async fn serve(
+    address: SocketAddr,
+    service: GrpcService,
+    cancel: CancellationToken,
+) -> Result<(), GrpcError> {
+    info!("Serving gRPC on {address}");
+
+    Server::builder()
+        .add_service(TriplicataServer::new(service))
+        .serve_with_shutdown(address, cancel.cancelled_owned())
+        .await?;
+
+    Ok(())
+}
@@ -0,0 +1,64 @@
+//! Scripted move sequences run through the state machine against a config, so
+//! a gesture set can keep regression tests. A test file is a list of tests,
+//! e.g. `[(moves: "R U R' U'", fires: ["Copy"]), (moves: "R U", fires: [])]`.
+
+use std::{fs, path::Path};
+
+use futures::stream;
+use serde::{Deserialize, Serialize};
+use tokio::sync::mpsc;
+use tokio_util::sync::CancellationToken;
+
+use crate::{
+    algorithm::Algorithm,
+    config::{Config, deserialize_trigger, serialize_trigger},
+    cube::Move,
+    error::ConfigError,
+    state_machine::StateMachine,
+};
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct BindTest {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    /// Turned in one go, followed by a pause.
+    #[serde(
+        deserialize_with = "deserialize_trigger",
+        serialize_with = "serialize_trigger"
+    )]
+    pub moves: Vec<Move>,
+    /// The binds expected to fire, in order, by name or by trigger for
+    /// unnamed binds.
+    pub fires: Vec<String>,
+}
+
+impl BindTest {
+    pub fn load_all(path: impl AsRef<Path>) -> Result<Vec<Self>, ConfigError> {
+        Ok(ron::from_str(&fs::read_to_string(path)?)?)
+    }
+
+    /// The test's name, or its moves for unnamed tests.
+    pub fn label(&self) -> String {
+        match &self.name {
+            Some(name) => name.clone(),
+            None => Algorithm::from(self.moves.clone()).to_string(),
+        }
+    }
+
+    /// The binds `config` fires for the moves, labelled as in `fires`.
+    pub async fn run(&self, config: Config) -> Vec<String> {
+        let (actions, _played) = mpsc::unbounded_channel();
+        let (fired, mut binds) = mpsc::unbounded_channel();
+
+        StateMachine::new(stream::iter(self.moves.clone()), config)
+            .report_fired(fired)
+            .run(actions, CancellationToken::new())
+            .await;
+
+        let mut labels = Vec::new();
+        while let Ok(fired) = binds.try_recv() {
+            labels.push(fired.bind.label());
+        }
+        labels
+    }
+}
@@ -0,0 +1,133 @@
+//! The last moves made, kept in memory so overlays and debugging tools can
+//! show what just happened without following the events from the start.
+
+use std::{
+    collections::VecDeque,
+    sync::{Arc, Mutex},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use futures::{Stream, StreamExt};
+use tokio::select;
+use tokio_util::sync::CancellationToken;
+
+use crate::cube::{CubeEvent, Move};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HistoryMove {
+    pub m: Move,
+    pub at: SystemTime,
+}
+
+impl HistoryMove {
+    /// Milliseconds since the UNIX epoch.
+    pub fn timestamp_ms(&self) -> u64 {
+        self.at
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_millis() as u64
+    }
+}
+
+#[derive(Debug, Default)]
+struct Moves {
+    kept: VecDeque<HistoryMove>,
+    /// When the newest move pushed out of the buffer was made.
+    forgotten: Option<SystemTime>,
+}
+
+/// A ring buffer of the last `capacity` moves, shared between its clones.
+#[derive(Debug, Clone)]
+pub struct MoveHistory {
+    moves: Arc<Mutex<Moves>>,
+    capacity: usize,
+}
+
+impl MoveHistory {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            moves: Arc::new(Mutex::new(Moves {
+                kept: VecDeque::with_capacity(capacity),
+                forgotten: None,
+            })),
+            capacity,
+        }
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Adds `m` as made at `at`, forgetting the oldest move once full.
+    pub fn push(&self, m: Move, at: SystemTime) {
+        if self.capacity == 0 {
+            return;
+        }
+
+        let mut moves = self.moves.lock().expect("history lock poisoned");
+        if moves.kept.len() == self.capacity {
+            moves.forgotten = moves.kept.pop_front().map(|m| m.at);
+        }
+        moves.kept.push_back(HistoryMove { m, at });
+    }
+
+    /// Every move kept, oldest first.
+    pub fn moves(&self) -> Vec<HistoryMove> {
+        self.recent(self.capacity)
+    }
+
+    /// The last `count` moves, oldest first.
+    pub fn recent(&self, count: usize) -> Vec<HistoryMove> {
+        let moves = self.moves.lock().expect("history lock poisoned");
+        moves
+            .kept
+            .iter()
+            .skip(moves.kept.len().saturating_sub(count))
+            .copied()
+            .collect()
+    }
+
+    /// The moves made after `since`, oldest first, or `None` if some of them
+    /// were already pushed out of the buffer.
+    pub fn since(&self, since: SystemTime) -> Option<Vec<HistoryMove>> {
+        let moves = self.moves.lock().expect("history lock poisoned");
+        if moves.forgotten.is_some_and(|forgotten| forgotten > since) {
+            return None;
+        }
+
+        Some(
+            moves
+                .kept
+                .iter()
+                .filter(|m| m.at > since)
+                .copied()
+                .collect(),
+        )
+    }
+
+    pub fn clear(&self) {
+        let mut moves = self.moves.lock().expect("history lock poisoned");
+        moves.kept.clear();
+        moves.forgotten = None;
+    }
+
+    /// Records each move in `events` until they end or `cancel` is cancelled.
+    pub async fn record(
+        &self,
+        mut events: impl Stream<Item = CubeEvent> + Unpin,
+        cancel: CancellationToken,
+    ) {
+        loop {
+            let event = select! {
+                event = events.next() => event,
+                _ = cancel.cancelled() => return,
+            };
+
+            match event {
+                Some(CubeEvent::Move(m)) => self.push(m, SystemTime::now()),
+                Some(_) => {}
+                None => return,
+            }
+        }
+    }
+}
@@ -0,0 +1,106 @@
+//! Global hotkeys controlling triplicata itself, read from the keyboards so
+//! it can be paused or stopped even when the cube is what misbehaves.
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    config::Key,
+    modifiers::{Modifier, Modifiers},
+};
+
+/// The hotkeys, each optional, e.g.
+/// `(pause: Some((held: [Control, Alt], key: p)), quit: Some((held: [Control, Alt], key: q)))`.
+#[derive(Deserialize, Serialize, Debug, Clone, Default, PartialEq, Eq)]
+#[serde(default)]
+pub struct Hotkeys {
+    /// Pauses the binds, or resumes them if paused.
+    pub pause: Option<Hotkey>,
+    /// Switches to the next bind group profile.
+    pub next_profile: Option<Hotkey>,
+    /// Drops the moves turned so far towards a trigger, as when moves were
+    /// missed.
+    pub resync: Option<Hotkey>,
+    /// Releases every held key and pauses the binds at once, for when a bind
+    /// misbehaves. Resumed with `pause`.
+    pub kill: Option<Hotkey>,
+    pub quit: Option<Hotkey>,
+}
+
+/// A key pressed while modifiers are held. Keys can be given as an alias or a
+/// single character, as in actions.
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct Hotkey {
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub held: Vec<Modifier>,
+    #[serde(deserialize_with = "crate::keys::deserialize_key")]
+    pub key: Key,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HotkeyAction {
+    Pause,
+    NextProfile,
+    Resync,
+    Kill,
+    Quit,
+}
+
+impl Hotkeys {
+    /// The hotkeys that are set, with what they do.
+    pub fn bound(&self) -> impl Iterator<Item = (HotkeyAction, &Hotkey)> {
+        [
+            (HotkeyAction::Pause, &self.pause),
+            (HotkeyAction::NextProfile, &self.next_profile),
+            (HotkeyAction::Resync, &self.resync),
+            (HotkeyAction::Kill, &self.kill),
+            (HotkeyAction::Quit, &self.quit),
+        ]
+        .into_iter()
+        .filter_map(|(action, hotkey)| Some((action, hotkey.as_ref()?)))
+    }
+}
+
+/// Picks the hotkeys pressed out of what is held, each once until it is let
+/// go.
+#[derive(Debug, Default)]
+pub struct HotkeyWatcher {
+    hotkeys: Vec<(HotkeyAction, Hotkey)>,
+    /// Whether each hotkey was down at the last update.
+    down: Vec<bool>,
+}
+
+impl HotkeyWatcher {
+    pub fn new(hotkeys: &Hotkeys) -> Self {
+        let hotkeys: Vec<_> = hotkeys
+            .bound()
+            .map(|(action, hotkey)| (action, hotkey.clone()))
+            .collect();
+        let down = vec![false; hotkeys.len()];
+
+        Self { hotkeys, down }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.hotkeys.is_empty()
+    }
+
+    /// The hotkeys pressed since the last update, given the `modifiers` held
+    /// and whether each key `is_down`.
+    pub fn update(
+        &mut self,
+        modifiers: &Modifiers,
+        is_down: impl Fn(Key) -> bool,
+    ) -> Vec<HotkeyAction> {
+        let mut pressed = Vec::new();
+
+        for ((action, hotkey), was_down) in self.hotkeys.iter().zip(&mut self.down) {
+            let down = modifiers.are_held(&hotkey.held) && is_down(hotkey.key);
+            if down && !*was_down {
+                pressed.push(*action);
+            }
+            *was_down = down;
+        }
+
+        pressed
+    }
+}
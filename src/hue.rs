@@ -0,0 +1,138 @@
+//! Philips Hue lights, controlled through the bridge's local HTTP API.
+
+use std::{
+    fmt,
+    io::{Read, Write},
+    net::{IpAddr, SocketAddr, TcpStream},
+    time::Duration,
+};
+
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+use crate::error::HueError;
+
+/// How long to wait for the bridge before giving up on a request.
+const TIMEOUT: Duration = Duration::from_secs(2);
+
+/// A bridge and the application key (username) it gave triplicata, e.g.
+/// `(address: "192.168.1.2", token: "...")`.
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct HueBridge {
+    pub address: IpAddr,
+    pub token: String,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, Eq)]
+pub enum HueCommand {
+    /// Recalls a scene in a room or zone, group `"0"` being every light.
+    Scene {
+        group: String,
+        scene: String,
+    },
+    /// Turns a light on in a color.
+    Color {
+        light: String,
+        red: u8,
+        green: u8,
+        blue: u8,
+    },
+    Off {
+        light: String,
+    },
+}
+
+impl HueBridge {
+    pub fn send(&self, command: &HueCommand) -> Result<(), HueError> {
+        match command {
+            HueCommand::Scene { group, scene } => {
+                self.put(&format!("groups/{group}/action"), json!({ "scene": scene }))
+            }
+            HueCommand::Color {
+                light,
+                red,
+                green,
+                blue,
+            } => {
+                let ([x, y], brightness) = xy_brightness(*red, *green, *blue);
+                self.put(
+                    &format!("lights/{light}/state"),
+                    json!({ "on": true, "xy": [x, y], "bri": brightness }),
+                )
+            }
+            HueCommand::Off { light } => {
+                self.put(&format!("lights/{light}/state"), json!({ "on": false }))
+            }
+        }
+    }
+
+    fn put(&self, path: &str, body: serde_json::Value) -> Result<(), HueError> {
+        let body = body.to_string();
+
+        let mut stream = TcpStream::connect_timeout(&SocketAddr::new(self.address, 80), TIMEOUT)?;
+        stream.set_read_timeout(Some(TIMEOUT))?;
+        stream.set_write_timeout(Some(TIMEOUT))?;
+
+        write!(
+            stream,
+            "PUT /api/{}/{path} HTTP/1.1\r\nHost: {}\r\nContent-Type: application/json\r\n\
+             Content-Length: {}\r\nConnection: close\r\n\r\n{body}",
+            self.token,
+            self.address,
+            body.len()
+        )?;
+
+        let mut response = String::new();
+        stream.read_to_string(&mut response)?;
+
+        // The bridge answers 200 even for failed commands, listing the errors.
+        let (status, body) = response.split_once("\r\n\r\n").unwrap_or((&response, ""));
+        if !status.starts_with("HTTP/1.1 200") || body.contains("\"error\"") {
+            return Err(HueError::Refused(body.trim().to_string()));
+        }
+
+        Ok(())
+    }
+}
+
+impl fmt::Display for HueCommand {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            HueCommand::Scene { group, scene } => write!(f, "scene {scene} in group {group}"),
+            HueCommand::Color {
+                light,
+                red,
+                green,
+                blue,
+            } => write!(f, "light {light} to #{red:02x}{green:02x}{blue:02x}"),
+            HueCommand::Off { light } => write!(f, "light {light} off"),
+        }
+    }
+}
+
+/// The CIE xy coordinates and Hue brightness of an sRGB color. Brightness
+/// follows the brightest channel so saturated colors are not dimmed.
+fn xy_brightness(red: u8, green: u8, blue: u8) -> ([f64; 2], u8) {
+    let linear = |channel: u8| {
+        let channel = channel as f64 / 255.0;
+        if channel > 0.04045 {
+            ((channel + 0.055) / 1.055).powf(2.4)
+        } else {
+            channel / 12.92
+        }
+    };
+    let (r, g, b) = (linear(red), linear(green), linear(blue));
+
+    // Wide gamut D65 conversion, as recommended for Hue lights.
+    let x = r * 0.664511 + g * 0.154324 + b * 0.162028;
+    let y = r * 0.283881 + g * 0.668433 + b * 0.047685;
+    let z = r * 0.000088 + g * 0.072310 + b * 0.986039;
+
+    let sum = x + y + z;
+    if sum == 0.0 {
+        return ([0.3127, 0.3290], 0);
+    }
+
+    let brightness = red.max(green).max(blue) as f64 / 255.0 * 254.0;
+    ([x / sum, y / sum], brightness.round() as u8)
+}
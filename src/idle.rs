@@ -0,0 +1,195 @@
+//! Disconnecting from an idle cube so it can sleep and save its battery, and
+//! connecting to it again once it is likely to be used.
+
+use std::{sync::Arc, time::Duration};
+
+use tokio::{
+    select,
+    sync::{
+        Notify,
+        broadcast::{self, Receiver, Sender, error::RecvError},
+    },
+    time::{Instant, sleep, sleep_until},
+};
+use tokio_util::sync::CancellationToken;
+use tracing::{Instrument, info, info_span, warn};
+
+use crate::{
+    cube::CubeEvent,
+    error::CubeError,
+    metrics::{self, Channel},
+    source::CubeSource,
+};
+
+/// How long to wait before trying again when connecting fails.
+pub const RETRY_DELAY: Duration = Duration::from_secs(5);
+
+/// Connects an idle source to its cube again, such as from a control socket
+/// command. Does nothing unless the cube was disconnected for being idle.
+#[derive(Debug, Clone, Default)]
+pub struct IdleWaker {
+    notify: Arc<Notify>,
+}
+
+impl IdleWaker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn wake(&self) {
+        self.notify.notify_waiters();
+    }
+}
+
+/// Wraps the sources made by `connect`, disconnecting once no move has been
+/// made for a while and connecting a new source later. The cube's own
+/// [`CubeEvent::Connected`] and [`CubeEvent::Disconnected`] are passed on, so
+/// consumers see the cube come and go, and the events only end if the cube
+/// disconnects by itself.
+pub struct IdleCubeSource<F> {
+    connect: F,
+    idle: Duration,
+    rescan: Option<Duration>,
+    waker: IdleWaker,
+}
+
+impl<F, S> IdleCubeSource<F>
+where
+    F: FnMut() -> S + Send + 'static,
+    S: CubeSource + 'static,
+{
+    pub fn new(connect: F, idle: Duration) -> Self {
+        Self {
+            connect,
+            idle,
+            rescan: None,
+            waker: IdleWaker::new(),
+        }
+    }
+
+    /// Connects again on its own this long after disconnecting, which should
+    /// be long enough for the cube to fall asleep so it is found once it is
+    /// picked up. Without it only the waker connects again.
+    pub fn rescan(mut self, rescan: Option<Duration>) -> Self {
+        self.rescan = rescan;
+        self
+    }
+
+    pub fn waker(mut self, waker: IdleWaker) -> Self {
+        self.waker = waker;
+        self
+    }
+
+    /// Forwards events until `cancel` is cancelled or the cube disconnects by
+    /// itself.
+    async fn supervise(
+        mut self,
+        mut events: Receiver<CubeEvent>,
+        mut connection: CancellationToken,
+        tx: Sender<CubeEvent>,
+        cancel: CancellationToken,
+    ) {
+        loop {
+            let idle = select! {
+                idle = forward(&mut events, &tx, self.idle) => idle,
+                _ = cancel.cancelled() => return,
+            };
+
+            if !idle {
+                return;
+            }
+
+            info!(
+                "No moves for {:?}, disconnecting so the cube can sleep",
+                self.idle
+            );
+            // Created first so a wake while disconnecting is not missed.
+            let woken = self.waker.notify.notified();
+            connection.cancel();
+            // The source's own disconnection is still passed on.
+            while let Ok(event) = events.recv().await {
+                let _ = tx.send(event);
+            }
+
+            let rescan = async {
+                match self.rescan {
+                    Some(rescan) => sleep(rescan).await,
+                    None => std::future::pending().await,
+                }
+            };
+            select! {
+                _ = woken => info!("Woken, connecting to the cube"),
+                _ = rescan => info!("Scanning for the cube again"),
+                _ = cancel.cancelled() => return,
+            }
+
+            (events, connection) = loop {
+                let connection = cancel.child_token();
+                let connected = select! {
+                    connected = (self.connect)().connect(connection.clone()) => connected,
+                    _ = cancel.cancelled() => return,
+                };
+
+                match connected {
+                    Ok(events) => break (events, connection),
+                    Err(e) => {
+                        warn!("Could not connect to the cube, trying again: {e}");
+                        select! {
+                            _ = sleep(RETRY_DELAY) => {}
+                            _ = cancel.cancelled() => return,
+                        }
+                    }
+                }
+            };
+        }
+    }
+}
+
+/// Forwards events until no move has been made for `idle`, returning `true`,
+/// or until the events end.
+async fn forward(events: &mut Receiver<CubeEvent>, tx: &Sender<CubeEvent>, idle: Duration) -> bool {
+    let mut last_move = Instant::now();
+
+    loop {
+        let event = select! {
+            event = events.recv() => event,
+            _ = sleep_until(last_move + idle) => return true,
+        };
+
+        match event {
+            Ok(event) => {
+                if let CubeEvent::Move(_) = event {
+                    last_move = Instant::now();
+                }
+                let _ = tx.send(event);
+            }
+            Err(RecvError::Lagged(count)) => {
+                metrics::channel_drops(Channel::CubeEvents, count);
+                let _ = tx.send(CubeEvent::Lagged(count));
+            }
+            Err(RecvError::Closed) => return false,
+        }
+    }
+}
+
+impl<F, S> CubeSource for IdleCubeSource<F>
+where
+    F: FnMut() -> S + Send + 'static,
+    S: CubeSource + 'static,
+{
+    async fn connect(
+        mut self,
+        cancel: CancellationToken,
+    ) -> Result<Receiver<CubeEvent>, CubeError> {
+        let connection = cancel.child_token();
+        let events = (self.connect)().connect(connection.clone()).await?;
+
+        let (tx, rx) = broadcast::channel(16);
+        tokio::spawn(
+            self.supervise(events, connection, tx, cancel)
+                .instrument(info_span!("idle")),
+        );
+
+        Ok(rx)
+    }
+}
@@ -0,0 +1,193 @@
+//! WCA style inspection for solves timed with a smart timer. Tapping the
+//! timer pads with the cube scrambled starts the 15 seconds of inspection,
+//! and penalties are given as a judge would under regulations A3 and A4.
+
+use std::{
+    fmt,
+    time::{Duration, Instant},
+};
+
+use futures::{Stream, StreamExt};
+use tokio::{select, time::sleep_until};
+use tokio_util::sync::CancellationToken;
+use tracing::info;
+
+use crate::{
+    cube::{CubeEvent, CubeState},
+    protocol::timer::TimerState,
+};
+
+/// Inspection without a penalty.
+pub const INSPECTION: Duration = Duration::from_secs(15);
+
+/// Starting later than this is a DNF, and between this and [`INSPECTION`] a
+/// +2.
+pub const INSPECTION_LIMIT: Duration = Duration::from_secs(17);
+
+/// When the judge calls out the time spent inspecting.
+pub const CALLS: [Duration; 2] = [Duration::from_secs(8), Duration::from_secs(12)];
+
+#[derive(PartialEq, Eq, Clone, Copy, Debug, PartialOrd, Ord)]
+pub enum Penalty {
+    PlusTwo,
+    Dnf,
+}
+
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub struct Attempt {
+    /// The time on the timer.
+    pub time: Duration,
+    /// How long inspection lasted, `None` when the solve was started without
+    /// inspecting.
+    pub inspection: Option<Duration>,
+    pub penalty: Option<Penalty>,
+}
+
+/// Follows cube and timer events and returns each timed attempt with its
+/// penalty.
+#[derive(Debug, Default)]
+pub struct Judge {
+    state: CubeState,
+    /// When inspection started.
+    inspecting: Option<Instant>,
+    /// Whether the cube was turned during inspection.
+    turned: bool,
+    /// How long inspection lasted for the solve on the timer.
+    inspected: Option<Duration>,
+}
+
+impl Attempt {
+    /// The time counted for the attempt, `None` for a DNF.
+    pub fn result(&self) -> Option<Duration> {
+        match self.penalty {
+            None => Some(self.time),
+            Some(Penalty::PlusTwo) => Some(self.time + Duration::from_secs(2)),
+            Some(Penalty::Dnf) => None,
+        }
+    }
+}
+
+impl fmt::Display for Attempt {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let time = self.time.as_secs_f64();
+        match self.penalty {
+            None => write!(f, "{time:.2}"),
+            Some(Penalty::PlusTwo) => write!(f, "{:.2}+", time + 2.0),
+            Some(Penalty::Dnf) => write!(f, "DNF({time:.2})"),
+        }
+    }
+}
+
+impl Judge {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// When the current inspection started.
+    pub fn inspecting_since(&self) -> Option<Instant> {
+        self.inspecting
+    }
+
+    /// Updates the judge with `event`, received at `at`.
+    pub fn push(&mut self, event: &CubeEvent, at: Instant) -> Option<Attempt> {
+        match *event {
+            CubeEvent::Move(m) => {
+                self.state.apply(m);
+                self.turned |= self.inspecting.is_some();
+                None
+            }
+            CubeEvent::StateSync(state) => {
+                self.state = state;
+                None
+            }
+            CubeEvent::Timer(timer) => match timer.state {
+                TimerState::HandsOff if self.inspecting.is_none() && !self.state.is_solved() => {
+                    self.inspecting = Some(at);
+                    self.turned = false;
+                    None
+                }
+                TimerState::Running => {
+                    self.inspected = self
+                        .inspecting
+                        .take()
+                        .map(|started| at.duration_since(started));
+                    None
+                }
+                TimerState::Stopped => {
+                    let time = timer.time?;
+                    let inspection = self.inspected.take();
+
+                    let late = inspection.and_then(|inspection| {
+                        if inspection > INSPECTION_LIMIT {
+                            Some(Penalty::Dnf)
+                        } else if inspection > INSPECTION {
+                            Some(Penalty::PlusTwo)
+                        } else {
+                            None
+                        }
+                    });
+                    let turned = (inspection.is_some() && self.turned).then_some(Penalty::Dnf);
+
+                    Some(Attempt {
+                        time,
+                        inspection,
+                        penalty: late.max(turned),
+                    })
+                }
+                TimerState::Idle | TimerState::Disconnect => {
+                    self.inspecting = None;
+                    self.inspected = None;
+                    None
+                }
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+}
+
+/// Judges every attempt in `events` until `cancel` is cancelled, logging the
+/// calls during inspection and the result of each attempt.
+pub async fn judge(mut events: impl Stream<Item = CubeEvent> + Unpin, cancel: CancellationToken) {
+    let mut judge = Judge::new();
+    let mut calls = CALLS.iter();
+
+    loop {
+        let call = judge
+            .inspecting_since()
+            .zip(calls.clone().next())
+            .map(|(started, &call)| started + call);
+
+        let event = select! {
+            event = events.next() => event,
+            _ = sleep_until(call.unwrap_or_else(Instant::now).into()), if call.is_some() => {
+                if let Some(call) = calls.next() {
+                    info!("{} seconds", call.as_secs());
+                }
+                continue;
+            }
+            _ = cancel.cancelled() => return,
+        };
+
+        let Some(event) = event else {
+            return;
+        };
+
+        let inspecting = judge.inspecting_since().is_some();
+        let attempt = judge.push(&event, Instant::now());
+
+        if !inspecting && judge.inspecting_since().is_some() {
+            info!("Inspecting");
+            calls = CALLS.iter();
+        }
+
+        if let Some(attempt) = attempt {
+            match attempt.inspection {
+                Some(inspection) => {
+                    info!("{attempt}, inspected for {:.2}s", inspection.as_secs_f64())
+                }
+                None => info!("{attempt}"),
+            }
+        }
+    }
+}
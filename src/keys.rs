@@ -0,0 +1,138 @@
+//! Short names for keys in the config, so actions can be written as
+//! `Click(esc)` or `Press(ctrl)` as well as with enigo's key names.
+
+use std::fmt;
+
+use serde::{
+    Deserialize, Deserializer,
+    de::{
+        self, DeserializeSeed, EnumAccess, IntoDeserializer, Visitor, value::EnumAccessDeserializer,
+    },
+};
+
+use crate::config::Key;
+
+/// Matched ignoring case. A name of a single character is also the key for
+/// that character.
+pub const KEY_ALIASES: &[(&str, Key)] = &[
+    ("esc", Key::Escape),
+    ("escape", Key::Escape),
+    ("enter", Key::Return),
+    ("return", Key::Return),
+    ("tab", Key::Tab),
+    ("space", Key::Space),
+    ("backspace", Key::Backspace),
+    ("del", Key::Delete),
+    ("delete", Key::Delete),
+    ("ctrl", Key::Control),
+    ("control", Key::Control),
+    ("alt", Key::Alt),
+    ("shift", Key::Shift),
+    ("meta", Key::Meta),
+    ("super", Key::Meta),
+    ("win", Key::Meta),
+    ("cmd", Key::Meta),
+    ("up", Key::UpArrow),
+    ("down", Key::DownArrow),
+    ("left", Key::LeftArrow),
+    ("right", Key::RightArrow),
+    ("home", Key::Home),
+    ("end", Key::End),
+    ("pageup", Key::PageUp),
+    ("pagedown", Key::PageDown),
+    ("capslock", Key::CapsLock),
+];
+
+pub fn alias(name: &str) -> Option<Key> {
+    KEY_ALIASES
+        .iter()
+        .find(|(alias, _)| alias.eq_ignore_ascii_case(name))
+        .map(|&(_, key)| key)
+}
+
+/// Keys are an enigo key (`Space`, `Unicode('a')`), an alias (`esc`) or a
+/// single character (`a`), written bare: RON only reads an identifier where
+/// the key's variant goes, so `"a"` is rejected. A bare capital letter (`A`)
+/// is the letter's key, not the capital.
+pub(crate) fn deserialize_key<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Key, D::Error> {
+    struct KeyVisitor;
+
+    impl<'de> Visitor<'de> for KeyVisitor {
+        type Value = Key;
+
+        fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+            formatter.write_str("a key, key alias or character")
+        }
+
+        fn visit_str<E: de::Error>(self, v: &str) -> Result<Self::Value, E> {
+            let mut chars = v.chars();
+            match (chars.next(), chars.next()) {
+                (Some(c), None) => Ok(Key::Unicode(c)),
+                _ => alias(v).ok_or_else(|| E::custom(format!("unknown key {v:?}"))),
+            }
+        }
+
+        fn visit_enum<A: EnumAccess<'de>>(self, data: A) -> Result<Self::Value, A::Error> {
+            let (Variant(name), variant) = data.variant()?;
+
+            if let Some(key) = alias(&name) {
+                de::VariantAccess::unit_variant(variant)?;
+                return Ok(key);
+            }
+
+            // Single capital letters are left to enigo on Windows, which has
+            // them as virtual keys. Elsewhere they press the letter's key, so
+            // `Click(A)` works the same everywhere.
+            let mut chars = name.chars();
+            if let (Some(c), None) = (chars.next(), chars.next())
+                && !(cfg!(windows) && c.is_ascii_uppercase())
+            {
+                de::VariantAccess::unit_variant(variant)?;
+                return Ok(Key::Unicode(c.to_ascii_lowercase()));
+            }
+
+            Key::deserialize(EnumAccessDeserializer::new(Named { name, variant }))
+        }
+    }
+
+    deserializer.deserialize_enum("Key", &[], KeyVisitor)
+}
+
+/// A variant name, read as an identifier so bare names are accepted.
+struct Variant(String);
+
+impl<'de> Deserialize<'de> for Variant {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct VariantVisitor;
+
+        impl Visitor<'_> for VariantVisitor {
+            type Value = Variant;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                formatter.write_str("a key name")
+            }
+
+            fn visit_str<E: de::Error>(self, v: &str) -> Result<Self::Value, E> {
+                Ok(Variant(v.to_string()))
+            }
+        }
+
+        deserializer.deserialize_identifier(VariantVisitor)
+    }
+}
+
+/// Hands an already read variant name back to enigo's deserializer.
+struct Named<V> {
+    name: String,
+    variant: V,
+}
+
+impl<'de, V: de::VariantAccess<'de>> EnumAccess<'de> for Named<V> {
+    type Error = V::Error;
+    type Variant = V;
+
+    fn variant_seed<S: DeserializeSeed<'de>>(self, seed: S) -> Result<(S::Value, V), V::Error> {
+        let name = seed.deserialize(self.name.into_deserializer())?;
+        Ok((name, self.variant))
+    }
+}
@@ -1,9 +1,153 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+
+extern crate alloc;
+
+#[cfg(feature = "alarm")]
+pub mod alarm;
+pub mod algorithm;
+#[cfg(feature = "api")]
+pub mod api;
+pub mod bld;
+#[cfg(feature = "bluetooth")]
+pub mod bluetooth;
+#[cfg(all(feature = "bluez", target_os = "linux"))]
+pub mod bluez;
+#[cfg(feature = "std")]
+pub mod calibration;
+#[cfg(feature = "solves")]
+pub mod cfop;
+#[cfg(all(feature = "config", not(target_arch = "wasm32")))]
+pub mod chords;
+#[cfg(feature = "config")]
 pub mod config;
+#[cfg(all(feature = "control", unix))]
+pub mod control;
+#[cfg(all(feature = "cstimer", target_os = "linux"))]
+pub mod cstimer;
 pub mod cube;
+pub mod cubing;
+#[cfg(all(feature = "database", not(target_arch = "wasm32")))]
+pub mod database;
+#[cfg(all(feature = "config", not(target_arch = "wasm32")))]
+pub mod dial;
+pub mod error;
+pub mod facelet;
+#[cfg(all(feature = "focus", not(target_arch = "wasm32")))]
+pub mod focus;
+#[cfg(feature = "config")]
+pub mod gesture;
+#[cfg(feature = "grpc")]
+pub mod grpc;
+#[cfg(all(feature = "runtime", feature = "config"))]
+pub mod harness;
+#[cfg(feature = "history")]
+pub mod history;
+#[cfg(all(feature = "input", not(target_arch = "wasm32")))]
+pub mod hotkeys;
+#[cfg(feature = "hue")]
+pub mod hue;
+#[cfg(feature = "idle")]
+pub mod idle;
+#[cfg(feature = "inspection")]
+pub mod inspection;
+#[cfg(all(feature = "config", not(target_arch = "wasm32")))]
+pub mod keys;
+#[cfg(all(feature = "std", not(target_arch = "wasm32")))]
+pub mod log_file;
+#[cfg(feature = "runtime")]
+pub mod metrics;
+#[cfg(feature = "std")]
+pub mod metronome;
+#[cfg(feature = "config")]
+pub mod modifiers;
+#[cfg(all(feature = "config", not(target_arch = "wasm32")))]
+pub mod morse;
+#[cfg(all(feature = "config", not(target_arch = "wasm32")))]
+pub mod movement;
+pub mod net;
+#[cfg(all(feature = "config", not(target_arch = "wasm32")))]
+pub mod numbers;
+#[cfg(all(feature = "osd", not(target_arch = "wasm32")))]
+pub mod osd;
+#[cfg(feature = "input")]
+pub mod output;
+#[cfg(feature = "overlay")]
+pub mod overlay;
+#[cfg(feature = "config")]
+pub mod pack;
+#[cfg(feature = "persist")]
+pub mod persist;
+pub mod phase;
+#[cfg(all(feature = "runtime", feature = "input"))]
+pub mod pipeline;
+#[cfg(feature = "presets")]
+pub mod presets;
+pub mod protocol;
+#[cfg(feature = "config")]
+pub mod rate_limit;
+pub mod retry;
+#[cfg(feature = "robot")]
+pub mod robot;
+#[cfg(feature = "scramble")]
+pub mod scramble;
+#[cfg(feature = "simulator")]
+pub mod simulator;
+#[cfg(feature = "config")]
+pub mod smoothing;
+#[cfg(feature = "solves")]
+pub mod solve;
+#[cfg(all(feature = "sound", not(target_arch = "wasm32")))]
+pub mod sound;
+#[cfg(feature = "runtime")]
+pub mod source;
+#[cfg(feature = "spotify")]
+pub mod spotify;
+#[cfg(all(feature = "runtime", feature = "config"))]
 pub mod state_machine;
+#[cfg(all(feature = "database", not(target_arch = "wasm32")))]
+pub mod stats;
+#[cfg(feature = "runtime")]
+pub mod status;
+#[cfg(feature = "config")]
+mod strict;
+#[cfg(all(feature = "input", not(target_arch = "wasm32")))]
+pub mod switches;
+#[cfg(feature = "twitch")]
+pub mod twitch;
+#[cfg(feature = "udp")]
+pub mod udp;
+#[cfg(all(feature = "evdev", target_os = "linux"))]
+pub mod uinput;
+#[cfg(feature = "web")]
+pub mod web;
+#[cfg(feature = "webhook")]
+pub mod webhook;
+
+#[cfg(all(feature = "runtime", feature = "input"))]
+pub use pipeline::{MoveInjector, Triplicata, TriplicataBuilder};
 
 use uuid::{Uuid, uuid};
 
+pub const GAN_GEN1_SERVICE: Uuid = uuid!("0000fff0-0000-1000-8000-00805f9b34fb");
 pub const GAN_GEN2_SERVICE: Uuid = uuid!("6e400001-b5a3-f393-e0a9-e50e24dc4179");
 pub const GAN_GEN3_SERVICE: Uuid = uuid!("8653000a-43e6-47b7-9cb0-5fc21d4ae340");
 pub const GAN_GEN4_SERVICE: Uuid = uuid!("00000010-0000-fff7-fff6-fff5fff4fff0");
+
+pub const DEVICE_INFORMATION_SERVICE: Uuid = uuid!("0000180a-0000-1000-8000-00805f9b34fb");
+
+pub const GAN_GEN1_VERSION_CHARACTERISTIC: Uuid = uuid!("00002a28-0000-1000-8000-00805f9b34fb");
+pub const GAN_GEN1_HARDWARE_CHARACTERISTIC: Uuid = uuid!("00002a23-0000-1000-8000-00805f9b34fb");
+pub const GAN_GEN1_CUBE_STATE_CHARACTERISTIC: Uuid = uuid!("0000fff2-0000-1000-8000-00805f9b34fb");
+pub const GAN_GEN1_LAST_MOVES_CHARACTERISTIC: Uuid = uuid!("0000fff5-0000-1000-8000-00805f9b34fb");
+pub const GAN_GEN1_TIMING_CHARACTERISTIC: Uuid = uuid!("0000fff6-0000-1000-8000-00805f9b34fb");
+pub const GAN_GEN1_BATTERY_CHARACTERISTIC: Uuid = uuid!("0000fff7-0000-1000-8000-00805f9b34fb");
+
+pub const GAN_GEN2_COMMAND_CHARACTERISTIC: Uuid = uuid!("28be4a4a-cd67-11e9-a32f-2a2ae2dbcce4");
+pub const GAN_GEN2_STATE_CHARACTERISTIC: Uuid = uuid!("28be4cb6-cd67-11e9-a32f-2a2ae2dbcce4");
+
+pub const GAN_TIMER_SERVICE: Uuid = uuid!("0000fff0-0000-1000-8000-00805f9b34fb");
+pub const GAN_TIMER_TIME_CHARACTERISTIC: Uuid = uuid!("0000fff2-0000-1000-8000-00805f9b34fb");
+pub const GAN_TIMER_STATE_CHARACTERISTIC: Uuid = uuid!("0000fff5-0000-1000-8000-00805f9b34fb");
+
+pub const GAN_ROBOT_STATUS_CHARACTERISTIC: Uuid = uuid!("0000fff2-0000-1000-8000-00805f9b34fb");
+pub const GAN_ROBOT_MOVE_CHARACTERISTIC: Uuid = uuid!("0000fff3-0000-1000-8000-00805f9b34fb");
@@ -0,0 +1,148 @@
+//! A log file rotated by time and size, for running triplicata unattended
+//! and looking back at what went wrong, such as a cube dropping out
+//! overnight. Older logs are kept beside it as `triplicata.log.1`,
+//! `triplicata.log.2` and so on, the highest the oldest.
+
+use std::{
+    fs::{self, File, OpenOptions},
+    io::{self, Write},
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+/// When to start a new log file regardless of its size.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum Rotation {
+    Never,
+    Hourly,
+    #[default]
+    Daily,
+}
+
+impl Rotation {
+    /// Which hour or day `time` falls in, in UTC.
+    fn period(self, time: SystemTime) -> u64 {
+        let seconds = time
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+        match self {
+            Rotation::Never => 0,
+            Rotation::Hourly => seconds / 3600,
+            Rotation::Daily => seconds / 86400,
+        }
+    }
+}
+
+pub struct RotatingFile {
+    path: PathBuf,
+    rotation: Rotation,
+    max_size: Option<u64>,
+    keep: usize,
+    file: File,
+    size: u64,
+    /// When the log was last written to.
+    written: SystemTime,
+}
+
+impl RotatingFile {
+    /// Appends to the log at `path`, starting a new one every day and keeping
+    /// the last five.
+    pub fn open(path: impl Into<PathBuf>) -> io::Result<Self> {
+        let path = path.into();
+        let file = append(&path)?;
+        let metadata = file.metadata()?;
+
+        Ok(Self {
+            path,
+            rotation: Rotation::default(),
+            max_size: None,
+            keep: 5,
+            file,
+            size: metadata.len(),
+            written: metadata.modified().unwrap_or_else(|_| SystemTime::now()),
+        })
+    }
+
+    pub fn rotation(mut self, rotation: Rotation) -> Self {
+        self.rotation = rotation;
+        self
+    }
+
+    /// Starts a new log once the current one would grow past `max_size`
+    /// bytes.
+    pub fn max_size(mut self, max_size: Option<u64>) -> Self {
+        self.max_size = max_size;
+        self
+    }
+
+    /// How many old logs to keep beside the current one.
+    pub fn keep(mut self, keep: usize) -> Self {
+        self.keep = keep;
+        self
+    }
+
+    /// Writes `buf` as if at `now`, rotating first if it is time to.
+    pub fn write_at(&mut self, buf: &[u8], now: SystemTime) -> io::Result<usize> {
+        let stale = self.rotation.period(now) != self.rotation.period(self.written);
+        let full = self
+            .max_size
+            .is_some_and(|max_size| self.size + buf.len() as u64 > max_size);
+
+        // An empty log is kept however old, and a line longer than the most
+        // a log may hold gets one to itself.
+        if (stale || full) && self.size > 0 {
+            self.rotate()?;
+        }
+
+        let written = self.file.write(buf)?;
+        self.size += written as u64;
+        self.written = now;
+        Ok(written)
+    }
+
+    fn rotate(&mut self) -> io::Result<()> {
+        self.file.flush()?;
+
+        if self.keep == 0 {
+            fs::remove_file(&self.path)?;
+        } else {
+            let _ = fs::remove_file(numbered(&self.path, self.keep));
+            for n in (1..self.keep).rev() {
+                let from = numbered(&self.path, n);
+                if from.exists() {
+                    fs::rename(from, numbered(&self.path, n + 1))?;
+                }
+            }
+            fs::rename(&self.path, numbered(&self.path, 1))?;
+        }
+
+        self.file = append(&self.path)?;
+        self.size = 0;
+        Ok(())
+    }
+}
+
+impl Write for RotatingFile {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.write_at(buf, SystemTime::now())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.file.flush()
+    }
+}
+
+fn append(path: &Path) -> io::Result<File> {
+    if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+        fs::create_dir_all(dir)?;
+    }
+    OpenOptions::new().create(true).append(true).open(path)
+}
+
+/// The `n`th oldest log beside `path`, `path.n`.
+fn numbered(path: &Path, n: usize) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(format!(".{n}"));
+    PathBuf::from(name)
+}
@@ -1,135 +1,2217 @@
 use std::{
+    fmt::Display,
     fs,
-    iter::repeat_n,
-    process::ExitCode,
-    thread::sleep,
-    time::{Duration, Instant},
+    io::Write,
+    path::{Path, PathBuf},
+    sync::Mutex,
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
 };
 
-use anyhow::bail;
-use btleplug::{
-    api::{Central, CentralEvent, Manager as _, Peripheral, ScanFilter},
-    platform::{Adapter, Manager, PeripheralId},
+use chrono::{DateTime, Local, NaiveTime};
+use clap::{Parser, Subcommand, ValueEnum};
+use tokio::sync::broadcast::{Receiver, error::RecvError};
+use tokio_util::sync::CancellationToken;
+use tracing::{info, warn};
+use tracing_subscriber::{EnvFilter, Layer, layer::SubscriberExt, util::SubscriberInitExt};
+#[cfg(unix)]
+use triplicata::control::{Control, Reloader};
+#[cfg(feature = "spotify")]
+use triplicata::spotify::SpotifyClient;
+#[cfg(feature = "twitch")]
+use triplicata::twitch::TwitchClient;
+use triplicata::{
+    MoveInjector, Triplicata, TriplicataBuilder,
+    alarm::{Dismissal, Stage},
+    algorithm::Algorithm,
+    bld::{Memo, unsolved_pieces},
+    bluetooth::{self, BluetoothCubeSource, GanTimerSource},
+    config::{Action, Backend, Bind, Config, Idle, Key},
+    cube::{CubeEvent, CubeState},
+    gesture::{self, Demonstration},
+    harness::BindTest,
+    history::MoveHistory,
+    hotkeys::HotkeyAction,
+    idle::{IdleCubeSource, IdleWaker},
+    keys,
+    log_file::{RotatingFile, Rotation},
+    metronome::{RhythmScore, Session},
+    net::Net,
+    output::EnigoOutput,
+    pack::BindPack,
+    persist::StateStore,
+    presets::{PRESETS, Preset},
+    robot::GanRobot,
+    simulator::{Playback, Scenario, SimulatedCubeSource},
+    sound::play_command,
+    source::{CubeEventStream, CubeSource},
+    state_machine::StateMachine,
+    status::CubeStatus,
 };
-use enigo::{Direction, Enigo, Keyboard, Settings};
-use futures::StreamExt;
-use tracing::{debug, error, info, warn};
-use tracing_subscriber::EnvFilter;
-use triplicata::{config::Config, cube::move_stream_v2, state_machine::StateMachine};
-
-async fn scan_for_cubes(adapter: &Adapter) -> anyhow::Result<PeripheralId> {
-    let mut events = adapter.events().await?;
-    adapter.start_scan(ScanFilter::default()).await?;
-
-    info!("Scanning for devices...");
-
-    while let Some(event) = events.next().await {
-        if let CentralEvent::DeviceDiscovered(id) = event {
-            let peripheral = adapter.peripheral(&id).await?;
-            let properties = peripheral.properties().await?;
-            let Some(name) = properties.and_then(|p| p.local_name) else {
-                continue;
+#[cfg(all(feature = "evdev", target_os = "linux"))]
+use triplicata::{hotkeys::HotkeyWatcher, switches::VirtualSwitches, uinput::VirtualKeyboard};
+
+#[derive(Parser)]
+#[command(version, about)]
+struct Cli {
+    /// The config file to read and write
+    #[arg(long, short, global = true, default_value = "config.ron")]
+    config: PathBuf,
+    /// Reject unknown fields in the config instead of ignoring them
+    #[arg(long, global = true)]
+    strict: bool,
+    /// How log lines are written, filtered with `RUST_LOG`
+    #[arg(long, global = true, value_enum, default_value_t = LogFormat::Text)]
+    log_format: LogFormat,
+    /// A file to also write the logs to as JSON lines, rotated as it grows
+    /// old or large
+    #[arg(long, global = true)]
+    log_file: Option<PathBuf>,
+    /// When to start a new log file
+    #[arg(long, global = true, value_enum, default_value_t = LogRotation::Daily)]
+    log_rotation: LogRotation,
+    /// Start a new log file once it would grow past this many megabytes
+    #[arg(long, global = true)]
+    log_max_size: Option<u64>,
+    /// How many old log files to keep
+    #[arg(long, global = true, default_value_t = 5)]
+    log_keep: usize,
+    /// Run every task on one thread, for small boards such as a Raspberry Pi
+    /// Zero acting as a dedicated bridge
+    #[arg(long, global = true)]
+    low_resource: bool,
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum LogFormat {
+    Text,
+    /// One JSON object per line, with the fields of every enclosing span
+    Json,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum LogRotation {
+    Never,
+    Hourly,
+    Daily,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Connect to the cube and play binds, the default
+    Run {
+        /// Play a scenario file on a simulated cube instead of connecting
+        #[arg(long, value_name = "SCENARIO")]
+        simulate: Option<PathBuf>,
+    },
+    /// Write a new config file from a preset
+    Init {
+        #[arg(long, default_value = "starter")]
+        preset: String,
+        /// Replace an existing config
+        #[arg(long)]
+        force: bool,
+    },
+    /// Browse the bundled presets
+    #[command(subcommand)]
+    Preset(PresetCommand),
+    /// Write the config's binds as a bind pack to share
+    ExportBinds {
+        #[arg(long)]
+        name: String,
+        #[arg(long)]
+        author: Option<String>,
+        #[arg(long)]
+        description: Option<String>,
+        /// Where to write the pack, standard output if not given
+        #[arg(long, short)]
+        output: Option<PathBuf>,
+    },
+    /// Add the binds from a bind pack to the config
+    ImportBinds {
+        pack: PathBuf,
+        /// Replace binds with the same trigger instead of skipping them
+        #[arg(long)]
+        replace: bool,
+    },
+    /// Print every bind as a table, to keep as a cheat sheet
+    ListBinds,
+    /// Learn a bind from a few demonstrations of a gesture and add it to the
+    /// config
+    Record {
+        #[arg(long)]
+        name: Option<String>,
+        /// How many times to perform the gesture
+        #[arg(long, default_value_t = 3)]
+        count: usize,
+        /// The actions of the bind, e.g. `"[Click(space)]"`
+        #[arg(long, default_value = "[]")]
+        actions: String,
+    },
+    /// Add a bind step by step: perform the gesture, pick its actions and try
+    /// it out
+    AddBind,
+    /// Check which binds fire for the scripted moves in a test file
+    Test { tests: PathBuf },
+    /// Play a recorded session back through the binds, printing the binds
+    /// that fire and their actions instead of playing them
+    Replay {
+        /// A session recorded with `session` in the config, or any scenario
+        session: PathBuf,
+        /// How fast to play it, e.g. 0.5 for half speed or 4 for four times,
+        /// from 0.01 to 100
+        #[arg(long, default_value_t = 1.0)]
+        speed: f64,
+        /// Start paused, to step through one event at a time
+        #[arg(long)]
+        step: bool,
+        /// Play the actions too
+        #[arg(long)]
+        execute: bool,
+    },
+    /// Drive a GAN Robot
+    #[command(subcommand)]
+    Robot(RobotCommand),
+    /// Practice blindfolded solves, showing the memo once the cube is put
+    /// down scrambled and checking the solve
+    Bld {
+        /// Read the memo aloud
+        #[arg(long)]
+        speak: bool,
+        /// Seconds without a move after which a solve is given up on
+        #[arg(long, default_value_t = 15)]
+        give_up: u64,
+    },
+    /// Practice turning in time with a metronome
+    Metronome {
+        /// Turns per second to keep time with
+        #[arg(long, default_value_t = 4.0)]
+        tps: f64,
+        /// How long to practice in seconds
+        #[arg(long, default_value_t = 30)]
+        length: u64,
+        /// Where to keep the history of past sessions
+        #[arg(long, default_value = "metronome.ron")]
+        history: PathBuf,
+    },
+    /// Show the tracked cube state as a net after every move
+    Show {
+        /// Print face letters instead of colors
+        #[arg(long)]
+        plain: bool,
+    },
+    /// Sound an alarm that only stops once the cube is scrambled with a
+    /// generated scramble and solved again
+    Alarm {
+        /// When to sound, as `HH:MM` in local time
+        #[arg(value_parser = parse_time)]
+        at: NaiveTime,
+        /// A sound file to play over and over instead of ringing the terminal
+        /// bell
+        #[arg(long)]
+        sound: Option<PathBuf>,
+    },
+    /// Check bluetooth access, input permissions and the config, and suggest
+    /// fixes for what is wrong
+    Doctor,
+    /// Browse the solve database: list sessions and solves, show a solve's
+    /// reconstruction and penalize or delete solves
+    #[cfg(feature = "database")]
+    Solves {
+        /// The database to browse, the config's `database` if not given
+        database: Option<PathBuf>,
+    },
+    /// Write the stored solves and their splits as CSV, oldest first
+    #[cfg(feature = "database")]
+    ExportStats {
+        /// The database to read, the config's `database` if not given
+        database: Option<PathBuf>,
+        /// Which columns to write, from id, session, started, time,
+        /// duration, penalty, moves, tps, scramble and splits
+        #[arg(long, value_delimiter = ',')]
+        columns: Option<Vec<triplicata::stats::Column>>,
+        /// Only the solves of this session
+        #[arg(long)]
+        session: Option<i64>,
+        /// Where to write the CSV, standard output if not given
+        #[arg(long, short)]
+        output: Option<PathBuf>,
+    },
+}
+
+#[derive(Subcommand)]
+enum PresetCommand {
+    List,
+    /// Replace the config with a preset, keeping the old one as a `.bak` file
+    Apply {
+        name: String,
+    },
+}
+
+#[derive(Subcommand)]
+enum RobotCommand {
+    /// Scramble the cube with a random scramble
+    Scramble {
+        #[arg(long, default_value_t = 20)]
+        length: usize,
+    },
+    /// Perform an algorithm, which must not turn U or use wide turns, slices
+    /// or rotations
+    Perform { algorithm: Algorithm },
+}
+
+/// Blocking work is the output thread and the occasional prompt or HTTP
+/// request, which a few threads cover.
+const LOW_RESOURCE_BLOCKING_THREADS: usize = 4;
+
+fn main() -> anyhow::Result<()> {
+    let cli = Cli::parse();
+
+    let logs = tracing_subscriber::fmt::layer();
+    let logs = match cli.log_format {
+        LogFormat::Text => logs.boxed(),
+        LogFormat::Json => logs.json().boxed(),
+    };
+    let log_file = match &cli.log_file {
+        Some(path) => {
+            let rotation = match cli.log_rotation {
+                LogRotation::Never => Rotation::Never,
+                LogRotation::Hourly => Rotation::Hourly,
+                LogRotation::Daily => Rotation::Daily,
+            };
+            let file = RotatingFile::open(path)?
+                .rotation(rotation)
+                .max_size(cli.log_max_size.map(|megabytes| megabytes * 1024 * 1024))
+                .keep(cli.log_keep);
+            Some(
+                tracing_subscriber::fmt::layer()
+                    .json()
+                    .with_ansi(false)
+                    .with_writer(Mutex::new(file)),
+            )
+        }
+        None => None,
+    };
+    tracing_subscriber::registry()
+        .with(EnvFilter::from_default_env())
+        .with(logs)
+        .with(log_file)
+        .init();
+
+    let runtime = if cli.low_resource {
+        tokio::runtime::Builder::new_current_thread()
+            .max_blocking_threads(LOW_RESOURCE_BLOCKING_THREADS)
+            .enable_all()
+            .build()?
+    } else {
+        tokio::runtime::Builder::new_multi_thread()
+            .enable_all()
+            .build()?
+    };
+
+    runtime.block_on(dispatch(cli))
+}
+
+async fn dispatch(cli: Cli) -> anyhow::Result<()> {
+    match cli.command.unwrap_or(Command::Run { simulate: None }) {
+        Command::Run { simulate } => daemon(&cli.config, cli.strict, simulate.as_deref()).await,
+        Command::Init { preset, force } => init(&cli.config, &preset, force),
+        Command::Preset(PresetCommand::List) => {
+            for preset in PRESETS {
+                println!("{:<10} {}", preset.name, preset.description);
+            }
+            Ok(())
+        }
+        Command::Preset(PresetCommand::Apply { name }) => apply(&cli.config, &name),
+        Command::ExportBinds {
+            name,
+            author,
+            description,
+            output,
+        } => {
+            let mut pack = BindPack::new(name, Config::load(&cli.config)?.binds);
+            pack.author = author;
+            pack.description = description;
+
+            let pack = pack.to_ron()?;
+            match output {
+                Some(output) => fs::write(output, pack)?,
+                None => println!("{pack}"),
+            }
+            Ok(())
+        }
+        Command::ImportBinds { pack, replace } => import(&cli.config, &pack, replace),
+        Command::ListBinds => list_binds(&cli.config),
+        Command::Record {
+            name,
+            count,
+            actions,
+        } => record(&cli.config, name, count, &actions).await,
+        Command::AddBind => add_bind(&cli.config).await,
+        Command::Test { tests } => test(&cli.config, &tests).await,
+        Command::Replay {
+            session,
+            speed,
+            step,
+            execute,
+        } => replay(&cli.config, &session, speed, step, execute).await,
+        Command::Robot(command) => robot(command).await,
+        Command::Bld { speak, give_up } => bld(speak, Duration::from_secs(give_up)).await,
+        Command::Metronome {
+            tps,
+            length,
+            history,
+        } => metronome(tps, Duration::from_secs(length), &history).await,
+        Command::Show { plain } => show(plain).await,
+        Command::Alarm { at, sound } => alarm(at, sound).await,
+        Command::Doctor => doctor(&cli.config).await,
+        #[cfg(feature = "database")]
+        Command::Solves { database } => browse_solves(&cli.config, database),
+        #[cfg(feature = "database")]
+        Command::ExportStats {
+            database,
+            columns,
+            session,
+            output,
+        } => {
+            use triplicata::{
+                database::{SolveQuery, Sort},
+                stats::{Column, to_csv},
             };
 
-            if name.starts_with("GAN") {
-                return Ok(id);
+            let database = open_database(&cli.config, database)?;
+            let mut query = SolveQuery::new().sort(Sort::Oldest);
+            if let Some(session) = session {
+                query = query.session(session);
+            }
+
+            let columns = columns.unwrap_or_else(|| Column::DEFAULT.to_vec());
+            let csv = to_csv(&database.solves(&query)?, &columns);
+            match output {
+                Some(output) => fs::write(output, csv)?,
+                None => print!("{csv}"),
             }
+            Ok(())
         }
     }
+}
 
-    bail!("Could not connect to GAN cube");
+fn find_preset(name: &str) -> anyhow::Result<&'static Preset> {
+    Preset::find(name)
+        .ok_or_else(|| anyhow::anyhow!("no preset named `{name}`, see `triplicata preset list`"))
 }
 
-#[tokio::main]
-async fn main() -> anyhow::Result<ExitCode> {
-    tracing_subscriber::fmt()
-        .with_env_filter(EnvFilter::from_default_env())
-        .init();
+fn init(path: &Path, preset: &str, force: bool) -> anyhow::Result<()> {
+    let preset = find_preset(preset)?;
+
+    if path.exists() && !force {
+        anyhow::bail!(
+            "{} already exists, pass --force to replace it",
+            path.display()
+        );
+    }
+
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    fs::write(path, preset.source)?;
 
-    let config: Config = ron::from_str(&fs::read_to_string("config.ron")?)?;
+    println!("Wrote the {} preset to {}", preset.name, path.display());
 
-    info!("Parsed config with {} binds", config.binds.len());
+    Ok(())
+}
+
+fn backup(path: &Path) -> anyhow::Result<()> {
+    if path.exists() {
+        let backup = path.with_extension("ron.bak");
+        fs::copy(path, &backup)?;
+        println!("Saved the previous config to {}", backup.display());
+    }
+
+    Ok(())
+}
+
+fn apply(path: &Path, preset: &str) -> anyhow::Result<()> {
+    let preset = find_preset(preset)?;
+
+    backup(path)?;
+    init(path, preset.name, true)
+}
+
+fn import(path: &Path, pack: &Path, replace: bool) -> anyhow::Result<()> {
+    let pack = BindPack::load(pack)?;
+    let mut config = Config::load(path)?;
+
+    let name = pack.name.clone();
+    let summary = pack.merge_into(&mut config, replace);
+
+    backup(path)?;
+    config.save(path)?;
+
+    println!(
+        "Imported {name}: {} added, {} replaced, {} skipped as already bound",
+        summary.added, summary.replaced, summary.skipped
+    );
+
+    Ok(())
+}
+
+fn list_binds(path: &Path) -> anyhow::Result<()> {
+    let config = Config::load(path)?;
+    println!("{}", config.bind_table());
+    Ok(())
+}
+
+async fn record(
+    path: &Path,
+    name: Option<String>,
+    count: usize,
+    actions: &str,
+) -> anyhow::Result<()> {
+    anyhow::ensure!(
+        count >= gesture::MIN_DEMONSTRATIONS,
+        "a gesture is learned from at least {} demonstrations",
+        gesture::MIN_DEMONSTRATIONS
+    );
+
+    let actions: Vec<Action> = ron::from_str(actions)?;
+    let mut config = Config::load(path)?;
+    let gap = Duration::from_millis(config.timeout);
+
+    let cancel = CancellationToken::new();
+    let mut events = BluetoothCubeSource::new()
+        .keys(config.cipher)
+        .retry(config.connection)
+        .connect(cancel.clone())
+        .await?;
+
+    let mut demonstrations = Vec::with_capacity(count);
+    while demonstrations.len() < count {
+        println!("Perform the gesture ({}/{count})", demonstrations.len() + 1);
+
+        let Some(demonstration) = perform_gesture(&mut events, gap).await? else {
+            return Ok(());
+        };
+        demonstrations.push(demonstration);
+    }
+
+    cancel.cancel();
+
+    let Some(mut bind) = gesture::learn(&demonstrations) else {
+        anyhow::bail!("the demonstrations have no moves in common");
+    };
+    if let Some(existing) = gesture::already_bound(&config.binds, &bind) {
+        anyhow::bail!(
+            "{} is already bound by {}",
+            Algorithm::from(bind.trigger),
+            existing.label()
+        );
+    }
+    bind.name = name;
+    bind.actions = actions;
+
+    println!(
+        "Learned {} with a tolerance of {} within {}ms",
+        Algorithm::from(bind.trigger.clone()),
+        bind.tolerance,
+        bind.within.unwrap_or_default()
+    );
+
+    backup(path)?;
+    config.binds.push(bind);
+    config.save(path)?;
+
+    Ok(())
+}
+
+/// Records one performance of a gesture, which ends once the cube has been
+/// still for `gap`. `None` if interrupted with Ctrl-C.
+async fn perform_gesture(
+    events: &mut Receiver<CubeEvent>,
+    gap: Duration,
+) -> anyhow::Result<Option<Demonstration>> {
+    let mut moves = Vec::new();
+    let mut turned: Option<(Instant, Instant)> = None;
+
+    loop {
+        let pause = turned.map_or_else(Instant::now, |(_, last)| last + gap);
+        let event = tokio::select! {
+            event = events.recv() => event,
+            _ = tokio::time::sleep_until(pause.into()), if turned.is_some() => break,
+            _ = tokio::signal::ctrl_c() => return Ok(None),
+        };
+
+        match event {
+            Ok(CubeEvent::Move(m)) => {
+                moves.push(m);
+                let now = Instant::now();
+                turned = Some(turned.map_or((now, now), |(first, _)| (first, now)));
+            }
+            Ok(CubeEvent::Disconnected) | Err(RecvError::Closed) => {
+                anyhow::bail!("the cube disconnected")
+            }
+            Ok(_) | Err(RecvError::Lagged(_)) => {}
+        }
+    }
+
+    println!("Recorded {}", Algorithm::from(moves.clone()));
+    let duration = turned.map_or(Duration::ZERO, |(first, last)| last - first);
+    Ok(Some(Demonstration { moves, duration }))
+}
+
+/// Prints `question` and reads the answer from standard input.
+fn prompt(question: &str) -> anyhow::Result<String> {
+    print!("{question}");
+    std::io::stdout().flush()?;
+
+    let mut answer = String::new();
+    std::io::stdin().read_line(&mut answer)?;
+    Ok(answer.trim().to_string())
+}
+
+/// A key by enigo name, alias or character, as in the config.
+fn parse_key(name: &str) -> Option<Key> {
+    let mut chars = name.chars();
+    match (chars.next(), chars.next()) {
+        (Some(c), None) => Some(Key::Unicode(c)),
+        _ => keys::alias(name).or_else(|| ron::from_str(name).ok()),
+    }
+}
+
+/// Asks for the actions of a new bind until the user is done.
+fn choose_actions() -> anyhow::Result<Vec<Action>> {
+    let mut actions = Vec::new();
+
+    loop {
+        println!("Add an action:");
+        println!("  1) key      press a key or shortcut, e.g. `space` or `ctrl+shift+t`");
+        println!("  2) text     type some text");
+        println!("  3) command  run a command");
+        println!("  4) done");
+
+        match prompt("> ")?.as_str() {
+            "1" | "key" => {
+                let shortcut = prompt("Key: ")?;
+                let keys: Option<Vec<_>> =
+                    shortcut.split('+').map(|k| parse_key(k.trim())).collect();
+                let Some((&key, modifiers)) = keys.as_deref().and_then(<[Key]>::split_last) else {
+                    println!("Unknown key in `{shortcut}`");
+                    continue;
+                };
+
+                actions.extend(modifiers.iter().map(|&m| Action::Press(m)));
+                actions.push(Action::Click(key));
+                actions.extend(modifiers.iter().rev().map(|&m| Action::Release(m)));
+            }
+            "2" | "text" => {
+                let text = prompt("Text: ")?;
+                actions.extend(text.chars().map(|c| Action::Click(Key::Unicode(c))));
+            }
+            "3" | "command" => actions.push(Action::Run(prompt("Command: ")?)),
+            "4" | "done" | "" => return Ok(actions),
+            other => println!("Unknown choice `{other}`"),
+        }
+    }
+}
+
+/// Guides the user through adding a bind: performing the gesture, choosing
+/// actions and trying the bind out before saving it.
+async fn add_bind(path: &Path) -> anyhow::Result<()> {
+    let mut config = Config::load(path)?;
+    let gap = Duration::from_millis(config.timeout);
+
+    let name = prompt("Name of the bind (optional): ")?;
+
+    let cancel = CancellationToken::new();
+    let mut events = BluetoothCubeSource::new()
+        .keys(config.cipher)
+        .retry(config.connection)
+        .connect(cancel.clone())
+        .await?;
+
+    println!("Perform the gesture on the cube");
+    let Some(gesture) = perform_gesture(&mut events, gap).await? else {
+        return Ok(());
+    };
+    if gesture.moves.is_empty() {
+        anyhow::bail!("no moves were recorded");
+    }
+
+    if let Some(existing) = config.binds.iter().find(|b| b.trigger == gesture.moves) {
+        println!(
+            "{} is already bound{}",
+            Algorithm::from(gesture.moves.clone()),
+            existing
+                .name
+                .as_ref()
+                .map(|name| format!(" by {name}"))
+                .unwrap_or_default()
+        );
+    }
+
+    let bind = Bind {
+        name: (!name.is_empty()).then_some(name),
+        trigger: gesture.moves,
+        actions: choose_actions()?,
+        ..Bind::default()
+    };
+
+    // A dry run with the rest of the config, so clashes with other binds
+    // show up too.
+    let mut preview = Config::load(path)?;
+    preview.binds.push(bind.clone());
+
+    println!("Try the bind on the cube, its actions are shown instead of played");
+    println!("Press enter when done");
+
+    let (tx, mut actions) = tokio::sync::mpsc::unbounded_channel();
+    let dry_run = cancel.child_token();
+    tokio::spawn(
+        StateMachine::new(CubeEventStream::from(events.resubscribe()), preview)
+            .run(tx, dry_run.clone()),
+    );
+
+    let mut done = tokio::task::spawn_blocking(|| std::io::stdin().read_line(&mut String::new()));
+    loop {
+        tokio::select! {
+            Some(action) = actions.recv() => println!("  {action}"),
+            _ = &mut done => break,
+        }
+    }
+    dry_run.cancel();
+    cancel.cancel();
+
+    if prompt("Save the bind? [Y/n] ")?.eq_ignore_ascii_case("n") {
+        return Ok(());
+    }
+
+    backup(path)?;
+    config.binds.push(bind);
+    config.save(path)?;
+
+    println!("Saved the bind to {}", path.display());
+
+    Ok(())
+}
 
-    let manager = Manager::new().await?;
+async fn test(path: &Path, tests: &Path) -> anyhow::Result<()> {
+    let config = Config::load(path)?;
+    let tests = BindTest::load_all(tests)?;
 
-    let mut adapter_list = manager.adapters().await?;
+    let mut failed = 0;
+    for test in &tests {
+        let fired = test.run(config.clone()).await;
 
-    if adapter_list.is_empty() {
-        error!("Could not find bluetooth adapter");
-        return Ok(ExitCode::FAILURE);
+        if fired == test.fires {
+            println!("ok    {}", test.label());
+        } else {
+            failed += 1;
+            println!(
+                "FAIL  {}: expected [{}], fired [{}]",
+                test.label(),
+                test.fires.join(", "),
+                fired.join(", ")
+            );
+        }
     }
 
-    let adapter = adapter_list.swap_remove(0);
+    println!("{} passed, {failed} failed", tests.len() - failed);
+
+    if failed > 0 {
+        anyhow::bail!("{failed} tests failed");
+    }
 
-    info!("Using adapter: {}", adapter.adapter_info().await?);
+    Ok(())
+}
+
+async fn replay(
+    path: &Path,
+    session: &Path,
+    speed: f64,
+    step: bool,
+    execute: bool,
+) -> anyhow::Result<()> {
+    if !(0.01..=100.0).contains(&speed) {
+        anyhow::bail!("the speed must be between 0.01 and 100");
+    }
 
-    let cube_id = scan_for_cubes(&adapter).await?;
-    let cube = adapter.peripheral(&cube_id).await?;
+    let config = Config::load(path)?;
+    let scenario = Scenario::load(session)?;
+    let timeout = Duration::from_millis(config.timeout);
 
-    info!(
-        "Found cube: {}",
-        cube.properties().await?.unwrap().local_name.unwrap()
+    let playback = Playback::new(speed);
+    playback.set_paused(step);
+
+    let builder = Triplicata::builder().config(config);
+    let builder = if execute {
+        builder.output(EnigoOutput::new()?.release_on_panic())
+    } else {
+        builder
+    };
+    let mut triplicata = builder
+        .source(SimulatedCubeSource::new(scenario).playback(playback.clone()))
+        .build()
+        .await?;
+
+    let mut events = triplicata.events();
+    let mut fired = triplicata.fired_binds();
+    let mut actions = triplicata.actions();
+
+    println!(
+        "Press enter to step while paused, p and enter to pause or resume, q and enter to stop"
     );
 
-    cube.connect().await?;
-    cube.discover_services().await?;
+    let (tx, mut input) = tokio::sync::mpsc::unbounded_channel();
+    tokio::task::spawn_blocking(move || {
+        for line in std::io::stdin().lines() {
+            let Ok(line) = line else {
+                break;
+            };
+            if tx.send(line).is_err() {
+                break;
+            }
+        }
+    });
+
+    // Binds waiting out the timeout can still fire after the last event.
+    let mut end = std::pin::pin!(async {
+        playback.finished().await;
+        tokio::time::sleep(timeout).await;
+    });
+
+    loop {
+        tokio::select! {
+            event = events.recv() => match event {
+                Ok(CubeEvent::Move(m)) => println!("{m}"),
+                Ok(CubeEvent::Battery(level)) => println!("battery {level}%"),
+                Ok(CubeEvent::Connected) => println!("connected"),
+                Ok(CubeEvent::Disconnected) => println!("disconnected"),
+                Ok(_) | Err(RecvError::Lagged(_)) => {}
+                Err(RecvError::Closed) => break,
+            },
+            Ok(bind) = fired.recv() => println!(
+                "  fired {} on {}",
+                bind.bind.label(),
+                Algorithm::from(bind.prefix)
+            ),
+            Ok(action) = actions.recv() => println!("    {action}"),
+            line = input.recv() => match line.as_deref().map(str::trim) {
+                Some("") => playback.step(),
+                Some("p") => {
+                    if playback.toggle() {
+                        println!("Paused");
+                    }
+                }
+                Some("q") | None => break,
+                Some(other) => println!("Unknown command `{other}`"),
+            },
+            _ = &mut end => {
+                println!("End of the session");
+                break;
+            }
+            _ = triplicata.closed() => break,
+        }
+    }
+
+    triplicata.shutdown().await;
 
-    let characteristics = cube.characteristics();
+    Ok(())
+}
+
+async fn robot(command: RobotCommand) -> anyhow::Result<()> {
+    let robot = GanRobot::connect(0).await?;
+
+    let result = match command {
+        RobotCommand::Scramble { length } => robot
+            .scramble(length)
+            .await
+            .map(|scramble| println!("{scramble}")),
+        RobotCommand::Perform { algorithm } => robot.perform(&algorithm).await,
+    };
+
+    robot.disconnect().await?;
+
+    Ok(result?)
+}
+
+/// How long a scrambled cube has to rest before its memo is shown.
+const BLD_REST: Duration = Duration::from_secs(3);
 
-    let mut v1_version = None;
-    let mut v1_hardware = None;
-    let mut v1_cube_state = None;
-    let mut v1_last_moves = None;
-    let mut v1_timing = None;
-    let mut v1_battery = None;
-    let mut v2_write = None;
-    let mut v2_read = None;
+struct BldAttempt {
+    shown: Instant,
+    started: Option<Instant>,
+}
+
+async fn bld(speak: bool, give_up: Duration) -> anyhow::Result<()> {
+    let cancel = CancellationToken::new();
+    let mut events = BluetoothCubeSource::new().connect(cancel.clone()).await?;
+
+    println!("Scramble the cube and put it down to see the memo");
+
+    let mut state = CubeState::SOLVED;
+    let mut attempt: Option<BldAttempt> = None;
+    let mut last_move = Instant::now();
+    let mut rested = true;
 
-    for characteristic in characteristics {
-        match characteristic.uuid.to_string().as_str() {
-            "00002a28-0000-1000-8000-00805f9b34fb" => v1_version = Some(characteristic),
-            "00002a23-0000-1000-8000-00805f9b34fb" => v1_hardware = Some(characteristic),
-            "0000fff2-0000-1000-8000-00805f9b34fb" => v1_cube_state = Some(characteristic),
-            "0000fff5-0000-1000-8000-00805f9b34fb" => v1_last_moves = Some(characteristic),
-            "0000fff6-0000-1000-8000-00805f9b34fb" => v1_timing = Some(characteristic),
-            "0000fff7-0000-1000-8000-00805f9b34fb" => v1_battery = Some(characteristic),
-            "28be4a4a-cd67-11e9-a32f-2a2ae2dbcce4" => v2_write = Some(characteristic),
-            "28be4cb6-cd67-11e9-a32f-2a2ae2dbcce4" => v2_read = Some(characteristic),
-            id => warn!("Unknown characteristic: {id}"),
+    loop {
+        let rest = match attempt {
+            Some(BldAttempt {
+                started: Some(_), ..
+            }) => give_up,
+            _ => BLD_REST,
+        };
+
+        let event = tokio::select! {
+            event = events.recv() => event,
+            _ = tokio::time::sleep_until((last_move + rest).into()), if !rested => {
+                rested = true;
+                match attempt.take() {
+                    None if !state.is_solved() => {
+                        let memo = Memo::new(&state);
+                        println!("{memo}");
+                        if speak {
+                            speak_memo(&memo);
+                        }
+                        attempt = Some(BldAttempt {
+                            shown: Instant::now(),
+                            started: None,
+                        });
+                    }
+                    Some(BldAttempt { started: Some(_), .. }) => {
+                        let (edges, corners) = unsolved_pieces(&state);
+                        println!("DNF, {edges} edges and {corners} corners unsolved");
+                    }
+                    unchanged => attempt = unchanged,
+                }
+                continue;
+            }
+            _ = tokio::signal::ctrl_c() => break,
+        };
+
+        match event {
+            Ok(CubeEvent::StateSync(synced)) => state = synced,
+            Ok(CubeEvent::Move(m)) => {
+                state.apply(m);
+                last_move = Instant::now();
+                rested = false;
+
+                if let Some(attempt) = &mut attempt
+                    && attempt.started.is_none()
+                {
+                    println!("Memo took {:.2}s", attempt.shown.elapsed().as_secs_f64());
+                    attempt.started = Some(last_move);
+                }
+
+                if state.is_solved()
+                    && let Some(BldAttempt {
+                        shown,
+                        started: Some(started),
+                    }) = attempt.take()
+                {
+                    println!(
+                        "Solved, execution took {:.2}s and the whole solve {:.2}s",
+                        started.elapsed().as_secs_f64(),
+                        shown.elapsed().as_secs_f64()
+                    );
+                }
+            }
+            Ok(CubeEvent::Disconnected) | Err(RecvError::Closed) => {
+                anyhow::bail!("the cube disconnected")
+            }
+            Ok(_) | Err(RecvError::Lagged(_)) => {}
         }
     }
 
-    if v1_version.is_some()
-        && v1_hardware.is_some()
-        && v1_cube_state.is_some()
-        && v1_last_moves.is_some()
-        && v1_timing.is_some()
-        && v1_battery.is_some()
+    cancel.cancel();
+
+    Ok(())
+}
+
+fn speak_memo(memo: &Memo) {
+    let spell = |pairs: Vec<String>| {
+        pairs
+            .iter()
+            .map(|pair| pair.chars().map(String::from).collect::<Vec<_>>().join(" "))
+            .collect::<Vec<_>>()
+            .join(", ")
+    };
+
+    let mut text = format!(
+        "Edges: {}. Corners: {}.",
+        spell(memo.edge_pairs()),
+        spell(memo.corner_pairs())
+    );
+    if memo.parity() {
+        text.push_str(" Parity.");
+    }
+
+    if let Err(e) = speech_command(&text).spawn() {
+        warn!("Could not read the memo aloud: {e}");
+    }
+}
+
+#[cfg(target_os = "macos")]
+fn speech_command(text: &str) -> tokio::process::Command {
+    let mut command = tokio::process::Command::new("say");
+    command.arg(text);
+    command
+}
+
+/// The text is passed through the environment so it is never parsed as
+/// PowerShell.
+#[cfg(target_os = "windows")]
+fn speech_command(text: &str) -> tokio::process::Command {
+    let mut command = tokio::process::Command::new("powershell");
+    command
+        .args([
+            "-NoProfile",
+            "-Command",
+            "Add-Type -AssemblyName System.Speech; \
+             (New-Object System.Speech.Synthesis.SpeechSynthesizer).Speak($env:TRIPLICATA_SPEECH)",
+        ])
+        .env("TRIPLICATA_SPEECH", text);
+    command
+}
+
+#[cfg(not(any(target_os = "macos", target_os = "windows")))]
+fn speech_command(text: &str) -> tokio::process::Command {
+    let mut command = tokio::process::Command::new("espeak");
+    command.arg(text);
+    command
+}
+
+/// Beats played before turns start counting, to pick up the tempo.
+const LEAD_IN_BEATS: u32 = 4;
+
+async fn metronome(tps: f64, length: Duration, history: &Path) -> anyhow::Result<()> {
+    if !(tps > 0.0 && tps.is_finite()) {
+        anyhow::bail!("the target TPS must be a positive number");
+    }
+
+    let cancel = CancellationToken::new();
+    let mut events = BluetoothCubeSource::new().connect(cancel.clone()).await?;
+
+    let mut score = RhythmScore::new(tps);
+    let start = Instant::now() + score.interval() * LEAD_IN_BEATS;
+    let end = start + length;
+    let mut beats = tokio::time::interval(score.interval());
+
+    println!(
+        "Turn on the beat at {tps} TPS for {}s, starting after {LEAD_IN_BEATS} beats",
+        length.as_secs()
+    );
+
+    loop {
+        tokio::select! {
+            _ = beats.tick() => {
+                print!("\x07.");
+                std::io::stdout().flush()?;
+            }
+            event = events.recv() => match event {
+                Ok(CubeEvent::Move(_)) => {
+                    let now = Instant::now();
+                    if now >= start {
+                        score.record(now - start);
+                    }
+                }
+                Ok(CubeEvent::Disconnected) | Err(RecvError::Closed) => {
+                    anyhow::bail!("the cube disconnected")
+                }
+                Ok(_) | Err(RecvError::Lagged(_)) => {}
+            },
+            _ = tokio::time::sleep_until(end.into()) => break,
+            _ = tokio::signal::ctrl_c() => {
+                cancel.cancel();
+                return Ok(());
+            }
+        }
+    }
+
+    cancel.cancel();
+    println!();
+
+    let finished = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs();
+    let session = score.session(length, finished);
+    let previous = Session::load_history(history)?;
+
+    println!(
+        "{} turns at {:.2} TPS, {:.0}% on the beat, {:+.0}ms average offset, {:.0}ms spread",
+        session.moves,
+        session.tps,
+        session.accuracy * 100.0,
+        session.mean_offset_ms,
+        session.spread_ms
+    );
+
+    if let Some(best) = previous
+        .iter()
+        .filter(|s| s.target_tps == session.target_tps)
+        .map(|s| s.accuracy)
+        .reduce(f64::max)
     {
-        todo!()
-    } else if v2_write.is_some() && v2_read.is_some() {
-        let moves = move_stream_v2(cube, v2_read.unwrap(), v2_write.unwrap()).await?;
+        println!("Best before at {tps} TPS: {:.0}% on the beat", best * 100.0);
+    }
+
+    session.append_to_history(history)?;
+
+    Ok(())
+}
+
+async fn show(plain: bool) -> anyhow::Result<()> {
+    let cancel = CancellationToken::new();
+    let mut events = BluetoothCubeSource::new().connect(cancel.clone()).await?;
+
+    let mut state = CubeState::SOLVED;
+
+    loop {
+        let event = tokio::select! {
+            event = events.recv() => event,
+            _ = tokio::signal::ctrl_c() => break,
+        };
+
+        match event {
+            Ok(CubeEvent::StateSync(synced)) => state = synced,
+            Ok(CubeEvent::Move(m)) => state.apply(m),
+            Ok(CubeEvent::Disconnected) | Err(RecvError::Closed) => {
+                anyhow::bail!("the cube disconnected")
+            }
+            Ok(_) | Err(RecvError::Lagged(_)) => continue,
+        }
+
+        if plain {
+            println!("{}\n", Net::from(&state).plain());
+        } else {
+            // Clear the screen so the net is redrawn in place.
+            println!("\x1b[2J\x1b[H{}", Net::from(&state));
+        }
+    }
+
+    cancel.cancel();
+
+    Ok(())
+}
+
+fn parse_time(s: &str) -> Result<NaiveTime, chrono::ParseError> {
+    NaiveTime::parse_from_str(s, "%H:%M")
+}
+
+/// The next time the clock shows `at`, today or tomorrow.
+fn next_time(at: NaiveTime, now: DateTime<Local>) -> anyhow::Result<DateTime<Local>> {
+    let mut date = now.date_naive();
+    if at <= now.time() {
+        date = date.succ_opt().unwrap_or(date);
+    }
+
+    date.and_time(at)
+        .and_local_timezone(Local)
+        .earliest()
+        .ok_or_else(|| anyhow::anyhow!("{at} is skipped by a clock change"))
+}
+
+/// The longest sleep before checking the clock again, so time spent suspended
+/// does not delay the alarm.
+const ALARM_CHECK: Duration = Duration::from_secs(30);
+/// How often the bell rings while the alarm sounds.
+const BELL_INTERVAL: Duration = Duration::from_secs(1);
+/// How long to wait before connecting to the cube again.
+const RECONNECT_DELAY: Duration = Duration::from_secs(5);
+
+async fn alarm(at: NaiveTime, sound: Option<PathBuf>) -> anyhow::Result<()> {
+    let when = next_time(at, Local::now())?;
+    println!("The alarm will sound at {}", when.format("%H:%M on %A"));
+
+    while let Ok(left) = (when - Local::now()).to_std() {
+        tokio::time::sleep(left.min(ALARM_CHECK)).await;
+    }
+
+    let mut dismissal = Dismissal::random(&mut rand::rng());
+    println!(
+        "Wake up! Scramble the cube from solved with {}, then solve it",
+        dismissal.scramble()
+    );
+
+    let ringing = CancellationToken::new();
+    tokio::spawn(ring(sound, ringing.clone()));
+    // Stops the bell however the alarm ends.
+    let _ringing = ringing.drop_guard();
 
-        let state_machine = StateMachine::new(moves, config);
+    loop {
+        let cancel = CancellationToken::new();
+        let mut events = match BluetoothCubeSource::new().connect(cancel.clone()).await {
+            Ok(events) => events,
+            Err(e) => {
+                warn!("Could not connect to the cube, trying again: {e}");
+                tokio::time::sleep(RECONNECT_DELAY).await;
+                continue;
+            }
+        };
+
+        loop {
+            let before = dismissal.stage();
+            let stage = match events.recv().await {
+                Ok(CubeEvent::StateSync(state)) => dismissal.sync(state),
+                Ok(CubeEvent::Move(m)) => dismissal.apply(m),
+                Ok(CubeEvent::Disconnected) | Err(RecvError::Closed) => break,
+                Ok(_) | Err(RecvError::Lagged(_)) => continue,
+            };
+            if stage == before {
+                continue;
+            }
+
+            match stage {
+                Stage::Solving => println!("Scrambled, now solve it"),
+                Stage::Dismissed => {
+                    println!("Solved, good morning");
+                    cancel.cancel();
+                    return Ok(());
+                }
+                Stage::Scrambling => {}
+            }
+        }
+
+        warn!("The cube disconnected, connecting again");
+        cancel.cancel();
+    }
+}
+
+/// Plays `sound` or rings the terminal bell until `cancel` is cancelled,
+/// falling back to the bell if the sound cannot be played.
+async fn ring(mut sound: Option<PathBuf>, cancel: CancellationToken) {
+    loop {
+        let once = async {
+            if let Some(path) = &sound {
+                match play_command(path).kill_on_drop(true).status().await {
+                    Ok(status) if status.success() => return,
+                    Ok(status) => warn!("Could not play the alarm sound, {status}"),
+                    Err(e) => warn!("Could not play the alarm sound: {e}"),
+                }
+                sound = None;
+            }
+
+            print!("\x07");
+            let _ = std::io::stdout().flush();
+            tokio::time::sleep(BELL_INTERVAL).await;
+        };
+
+        tokio::select! {
+            _ = once => {}
+            _ = cancel.cancelled() => return,
+        }
+    }
+}
+
+/// Builds the pipeline on a source from `source`, disconnecting the cube while
+/// it is idle if configured.
+async fn build<S: CubeSource + 'static>(
+    builder: TriplicataBuilder<()>,
+    mut source: impl FnMut() -> S + Send + 'static,
+    idle: Option<Idle>,
+    waker: &IdleWaker,
+) -> Result<Triplicata, triplicata::error::Error> {
+    let minutes = |minutes: u64| Duration::from_secs(minutes * 60);
+
+    match idle {
+        Some(idle) => {
+            let source = IdleCubeSource::new(source, minutes(idle.minutes))
+                .rescan(idle.rescan.map(minutes))
+                .waker(waker.clone());
+            builder.source(source).build().await
+        }
+        None => builder.source(source()).build().await,
+    }
+}
+
+/// How long to wait before restarting a pipeline a task of which stopped, so
+/// a task that keeps failing does not spin.
+const RESTART_DELAY: Duration = Duration::from_secs(1);
+
+fn load_config(path: &Path, strict: bool) -> anyhow::Result<Config> {
+    let config = if strict {
+        Config::load_strict(path)?
+    } else {
+        Config::load(path)?
+    };
+    Ok(config)
+}
+
+/// Serves metrics on `address` the first time it is called. The recorder is
+/// global and cannot be replaced, so an address changed by a reload only
+/// applies once triplicata is started again.
+#[cfg(feature = "metrics")]
+fn serve_metrics(address: std::net::SocketAddr) -> anyhow::Result<()> {
+    static SERVING: Mutex<Option<std::net::SocketAddr>> = Mutex::new(None);
+
+    let mut serving = SERVING.lock().unwrap();
+    match *serving {
+        None => {
+            triplicata::metrics::install_prometheus(address)?;
+            info!("Serving metrics on http://{address}/metrics");
+            *serving = Some(address);
+        }
+        Some(serving) if serving != address => {
+            warn!(
+                "Still serving metrics on http://{serving}/metrics until triplicata is restarted"
+            );
+        }
+        Some(_) => {}
+    }
+
+    Ok(())
+}
+
+/// Why [`run`] stopped.
+enum Stop {
+    Quit,
+    /// The config was reloaded, to run again with.
+    Reload(Config),
+    /// A task of the pipeline stopped, to run again as it was.
+    Restart,
+}
 
-        let mut actions = state_machine.run();
+/// Runs the daemon until it is stopped, running again with the new config
+/// when it is reloaded, or with the same one when a task of the pipeline
+/// stops. The cube's status and the move history outlive both, the history
+/// unless its size is changed.
+async fn daemon(path: &Path, strict: bool, simulate: Option<&Path>) -> anyhow::Result<()> {
+    let mut config = load_config(path, strict)?;
+    check_config(&config)?;
+    let status = CubeStatus::new();
+    let mut history = config.history.map(MoveHistory::new);
 
-        let mut enigo = Enigo::new(&Settings::default())?;
+    loop {
+        #[cfg(feature = "metrics")]
+        if let Some(address) = config.metrics {
+            serve_metrics(address)?;
+        }
 
-        while let Some(action) = actions.recv().await {
-            info!("{action:?}");
+        let stop = run(
+            path,
+            strict,
+            simulate,
+            config.clone(),
+            status.clone(),
+            history.clone(),
+        )
+        .await?;
 
-            match action {
-                triplicata::config::Action::Press(key) => enigo.key(key, Direction::Press)?,
-                triplicata::config::Action::Release(key) => enigo.key(key, Direction::Release)?,
-                triplicata::config::Action::Click(key) => enigo.key(key, Direction::Click)?,
-                triplicata::config::Action::Delay(delay) => sleep(Duration::from_millis(delay)),
+        match stop {
+            Stop::Quit => return Ok(()),
+            Stop::Reload(reloaded) => {
+                if reloaded.history != config.history {
+                    history = reloaded.history.map(MoveHistory::new);
+                }
+                config = reloaded;
+            }
+            Stop::Restart => tokio::time::sleep(RESTART_DELAY).await,
+        }
+    }
+}
+
+/// Builds the pipeline for `config` and runs it with everything following
+/// it until it is stopped.
+async fn run(
+    path: &Path,
+    strict: bool,
+    simulate: Option<&Path>,
+    config: Config,
+    status: CubeStatus,
+    history: Option<MoveHistory>,
+) -> anyhow::Result<Stop> {
+    info!("Parsed config with {} binds", config.binds.len());
+
+    let scenario = simulate.map(Scenario::load).transpose()?;
+
+    let backend = config.backend;
+    let keys = config.cipher;
+    let retry = config.connection;
+    let battery_poll = config.battery_poll.map(Duration::from_secs);
+    let calibrator = config.calibration.as_ref().map(|c| c.calibrator());
+    let store = config
+        .cube_state
+        .clone()
+        .map(|path| StateStore::new(path).calibrate(calibrator));
+    let idle = config.idle;
+    let waker = IdleWaker::new();
+    #[cfg(unix)]
+    let control = config.control.clone();
+    #[cfg(unix)]
+    let reloader = Reloader::new();
+    #[cfg(not(unix))]
+    let reloader = ();
+    #[cfg(all(unix, feature = "focus"))]
+    let profiles: Vec<_> = config
+        .focus
+        .iter()
+        .flat_map(|focus| &focus.profiles)
+        .map(|profile| profile.name.clone())
+        .collect();
+    #[cfg(all(unix, not(feature = "focus")))]
+    let profiles = Vec::new();
+    let battery_report = config.battery_report;
+    #[cfg(all(feature = "cstimer", target_os = "linux"))]
+    let cstimer = config.cstimer;
+    #[cfg(feature = "overlay")]
+    let overlay = config.overlay;
+    #[cfg(feature = "api")]
+    let api = config.api;
+    #[cfg(feature = "grpc")]
+    let grpc = config.grpc;
+    #[cfg(any(feature = "api", feature = "grpc"))]
+    let bind_names: Vec<_> = config.binds.iter().map(|bind| bind.name.clone()).collect();
+    #[cfg(feature = "osd")]
+    let osd = config.osd;
+    #[cfg(feature = "udp")]
+    let udp = config.udp;
+    #[cfg(feature = "webhook")]
+    let webhooks = config.webhooks.clone();
+    let smart_timer = config.smart_timer;
+    let inspection = config.inspection;
+    let solves = config.solves.clone();
+    #[cfg(feature = "database")]
+    let database = config.database.clone();
+    let session = config.session.clone();
+    let phases = config.phases.clone();
+    let cases = config.cases;
+    let cues = (!config.cues.is_empty() || config.binds.iter().any(|bind| bind.cue.is_some()))
+        .then(|| config.cues.clone());
+    let type_unicode = config.type_unicode;
+    #[cfg(all(feature = "evdev", target_os = "linux"))]
+    let keyboard = config.evdev.then(VirtualKeyboard::new).transpose()?;
+    let switches = config.switches.clone();
+    let hotkeys = config.hotkeys.clone().unwrap_or_default();
+    #[cfg(feature = "focus")]
+    let profile_count = config
+        .focus
+        .as_ref()
+        .map_or(0, |focus| focus.profiles.len());
+    #[cfg(not(feature = "focus"))]
+    let profile_count = 0;
+    #[cfg(all(feature = "evdev", target_os = "linux"))]
+    let follow_keyboards = config.typing_quiet.is_some()
+        || config.binds.iter().any(|bind| !bind.held.is_empty())
+        || hotkeys.bound().next().is_some();
+    #[cfg(all(feature = "evdev", target_os = "linux"))]
+    let switch_device = switches
+        .as_ref()
+        .is_some_and(|switches| switches.device)
+        .then(VirtualSwitches::new)
+        .transpose()?;
+    #[cfg(feature = "focus")]
+    let focus = config.focus.clone();
+    #[cfg(feature = "twitch")]
+    let twitch = config.twitch.clone();
+    #[cfg(feature = "twitch")]
+    let rewards: Vec<_> = config
+        .redemption_binds
+        .iter()
+        .map(|bind| bind.reward.clone())
+        .collect();
+
+    let output = EnigoOutput::new()?
+        .release_on_panic()
+        .type_unicode(type_unicode)
+        .switches(switches);
+    #[cfg(all(feature = "evdev", target_os = "linux"))]
+    let output = output.evdev(keyboard).switch_device(switch_device);
+    #[cfg(feature = "hue")]
+    let output = output.hue(config.hue.clone());
+    #[cfg(feature = "spotify")]
+    let output = output.spotify(config.spotify.clone().map(SpotifyClient::new));
+    #[cfg(feature = "twitch")]
+    let output = output.twitch(twitch.clone().map(TwitchClient::new));
+
+    let builder = Triplicata::builder().config(config).output(output);
+
+    let mut triplicata = match (scenario, backend) {
+        (Some(scenario), _) => {
+            let source = move || SimulatedCubeSource::new(scenario.clone());
+            build(builder, source, idle, &waker).await?
+        }
+        (None, Backend::Btleplug) => {
+            let source = move || {
+                BluetoothCubeSource::new()
+                    .keys(keys)
+                    .retry(retry)
+                    .state_store(store.clone())
+                    .battery_interval(battery_poll)
+            };
+            build(builder, source, idle, &waker).await?
+        }
+        #[cfg(all(feature = "bluez", target_os = "linux"))]
+        (None, Backend::Bluez) => {
+            let source = move || {
+                triplicata::bluez::BluezCubeSource::new()
+                    .keys(keys)
+                    .retry(retry)
+                    .state_store(store.clone())
+                    .battery_interval(battery_poll)
             };
+            build(builder, source, idle, &waker).await?
+        }
+        #[cfg(not(all(feature = "bluez", target_os = "linux")))]
+        (None, Backend::Bluez) => anyhow::bail!("triplicata was built without the bluez backend"),
+    };
+
+    #[cfg(all(feature = "cstimer", target_os = "linux"))]
+    let bridge = cstimer.then(|| {
+        let cancel = CancellationToken::new();
+        let task = tokio::spawn(
+            triplicata::cstimer::CsTimerBridge::new()
+                .serve(triplicata.event_stream(), cancel.clone()),
+        );
+        (cancel, task)
+    });
+
+    let cancel = CancellationToken::new();
+    let following = {
+        let status = status.clone();
+        let events = triplicata.event_stream();
+        let report = battery_report.map(|minutes| Duration::from_secs(minutes * 60));
+        let cancel = cancel.clone();
+        tokio::spawn(async move { status.follow(events, report, cancel).await })
+    };
+    #[cfg(unix)]
+    let control = control.map(|path| {
+        let control = Control::new()
+            .status(Some(status.clone()))
+            .waker(idle.map(|_| waker.clone()))
+            .history(history.clone())
+            .injector(Some(triplicata.injector()))
+            .reloader(Some(reloader.clone()))
+            .profiles(profiles);
+        tokio::spawn(triplicata::control::serve(path, control, cancel.clone()))
+    });
+    #[cfg(feature = "api")]
+    let api = api.map(|address| {
+        let api = triplicata::api::Api::new(
+            status.clone(),
+            triplicata.injector(),
+            triplicata.pause(),
+            bind_names.clone(),
+        )
+        .history(history.clone());
+        tokio::spawn(triplicata::api::serve(
+            address,
+            api,
+            triplicata.event_stream(),
+            cancel.clone(),
+        ))
+    });
+    #[cfg(feature = "grpc")]
+    let grpc = grpc.map(|address| {
+        let service = triplicata::grpc::GrpcService::new(
+            status.clone(),
+            triplicata.injector(),
+            triplicata.pause(),
+            bind_names.clone(),
+        );
+        tokio::spawn(triplicata::grpc::serve(address, service, cancel.clone()))
+    });
+    let recording = history.map(|history| {
+        let events = triplicata.event_stream();
+        let cancel = cancel.clone();
+        tokio::spawn(async move { history.record(events, cancel).await })
+    });
+    let cues = cues.map(|cues| {
+        tokio::spawn(triplicata::sound::play_cues(
+            triplicata.fired_binds(),
+            cues,
+            cancel.clone(),
+        ))
+    });
+    #[cfg(feature = "osd")]
+    let osd = osd.then(|| {
+        tokio::spawn(triplicata::osd::show_fired(
+            triplicata.fired_binds(),
+            cancel.clone(),
+        ))
+    });
+    #[cfg(feature = "overlay")]
+    let overlay = overlay.map(|address| {
+        tokio::spawn(triplicata::overlay::serve(
+            address,
+            triplicata.event_stream(),
+            cancel.clone(),
+        ))
+    });
+    #[cfg(feature = "udp")]
+    let udp = udp.map(|target| {
+        tokio::spawn(triplicata::udp::send_events(
+            target,
+            triplicata.event_stream(),
+            cancel.clone(),
+        ))
+    });
+    #[cfg(feature = "webhook")]
+    let webhooks = (!webhooks.is_empty()).then(|| {
+        tokio::spawn(triplicata::webhook::send_events(
+            webhooks,
+            triplicata.event_stream(),
+            triplicata.fired_binds(),
+            cancel.clone(),
+        ))
+    });
+    let (hotkey_tx, mut hotkeys_pressed) = tokio::sync::mpsc::unbounded_channel();
+    #[cfg(all(feature = "evdev", target_os = "linux"))]
+    let keyboards = follow_keyboards.then(|| {
+        tokio::spawn(triplicata::modifiers::follow_keyboards(
+            triplicata.modifiers(),
+            triplicata.typing(),
+            HotkeyWatcher::new(&hotkeys),
+            hotkey_tx,
+            cancel.clone(),
+        ))
+    });
+    #[cfg(not(all(feature = "evdev", target_os = "linux")))]
+    drop((hotkey_tx, hotkeys));
+    let pause = triplicata.pause();
+    let kill_switch = triplicata.kill_switch();
+    let injector = triplicata.injector();
+    let mut profile = None;
+    let timer =
+        smart_timer.then(|| tokio::spawn(forward_timer(triplicata.injector(), cancel.clone())));
+    #[cfg(feature = "database")]
+    let database = database.map(|path| {
+        tokio::spawn(triplicata::database::store_solves(
+            path,
+            phases.clone(),
+            triplicata.event_stream(),
+            cancel.clone(),
+        ))
+    });
+    let solves = solves.map(|directory| {
+        tokio::spawn(triplicata::solve::export_solves(
+            directory,
+            phases,
+            triplicata.event_stream(),
+            cancel.clone(),
+        ))
+    });
+    let session = session.map(|path| {
+        tokio::spawn(triplicata::simulator::record_session(
+            path,
+            triplicata.event_stream(),
+            cancel.clone(),
+        ))
+    });
+    let inspection = inspection.then(|| {
+        tokio::spawn(triplicata::inspection::judge(
+            triplicata.event_stream(),
+            cancel.clone(),
+        ))
+    });
+    #[cfg(feature = "twitch")]
+    let redemptions = twitch.filter(|_| !rewards.is_empty()).map(|twitch| {
+        tokio::spawn(triplicata::twitch::follow_redemptions(
+            twitch,
+            rewards,
+            triplicata.injector(),
+            cancel.clone(),
+        ))
+    });
+    #[cfg(feature = "focus")]
+    let focus = focus.map(|focus| {
+        tokio::spawn(triplicata::focus::follow_focus(
+            focus,
+            triplicata.injector(),
+            cancel.clone(),
+        ))
+    });
+    let cases = cases.then(|| {
+        tokio::spawn(triplicata::cfop::log_cases(
+            triplicata.event_stream(),
+            cancel.clone(),
+        ))
+    });
+
+    let result = {
+        let closed = triplicata.closed();
+        tokio::pin!(closed);
+
+        loop {
+            tokio::select! {
+                result = tokio::signal::ctrl_c() => {
+                    break result.map(|()| Stop::Quit).map_err(anyhow::Error::from);
+                }
+                result = &mut closed => match result {
+                    Err(triplicata::error::Error::TaskStopped(task)) => {
+                        warn!("The {task} task stopped, restarting the pipeline");
+                        break Ok(Stop::Restart);
+                    }
+                    result => break result.map(|()| Stop::Quit).map_err(anyhow::Error::from),
+                },
+                Some(hotkey) = hotkeys_pressed.recv() => match hotkey {
+                    HotkeyAction::Pause => {
+                        let paused = pause.toggle();
+                        info!(paused, "Pause toggled from the hotkey");
+                    }
+                    HotkeyAction::NextProfile if profile_count == 0 => {
+                        warn!("There are no profiles to switch to");
+                    }
+                    HotkeyAction::NextProfile => {
+                        let next = profile.map_or(0, |profile| (profile + 1) % profile_count);
+                        profile = Some(next);
+                        injector.inject_event(CubeEvent::Profile(next));
+                    }
+                    HotkeyAction::Resync => {
+                        info!("Resyncing from the hotkey");
+                        injector.inject_event(CubeEvent::Lagged(0));
+                    }
+                    HotkeyAction::Kill => kill_switch.pull(),
+                    HotkeyAction::Quit => {
+                        info!("Quitting from the hotkey");
+                        break Ok(Stop::Quit);
+                    }
+                },
+                _ = reloaded(&reloader) => {
+                    let reloaded = load_config(path, strict)
+                        .and_then(|config| check_config(&config).map(|()| config));
+                    match reloaded {
+                        Ok(config) => {
+                            info!("Reloading the config");
+                            break Ok(Stop::Reload(config));
+                        }
+                        Err(e) => warn!("Not reloading, the config is invalid: {e}"),
+                    }
+                }
+            }
+        }
+    };
+
+    #[cfg(all(feature = "cstimer", target_os = "linux"))]
+    if let Some((cancel, task)) = bridge {
+        cancel.cancel();
+        if let Ok(Err(e)) = task.await {
+            warn!("csTimer emulation failed: {e}");
+        }
+    }
+
+    cancel.cancel();
+    #[cfg(unix)]
+    if let Some(control) = control
+        && let Ok(Err(e)) = control.await
+    {
+        warn!("Control socket failed: {e}");
+    }
+    if let Some(recording) = recording {
+        let _ = recording.await;
+    }
+    let _ = following.await;
+    if let Some(cues) = cues {
+        let _ = cues.await;
+    }
+    #[cfg(feature = "osd")]
+    if let Some(osd) = osd {
+        let _ = osd.await;
+    }
+    #[cfg(feature = "webhook")]
+    if let Some(webhooks) = webhooks {
+        let _ = webhooks.await;
+    }
+    #[cfg(feature = "overlay")]
+    if let Some(overlay) = overlay
+        && let Ok(Err(e)) = overlay.await
+    {
+        warn!("Overlay failed: {e}");
+    }
+    #[cfg(feature = "api")]
+    if let Some(api) = api
+        && let Ok(Err(e)) = api.await
+    {
+        warn!("API failed: {e}");
+    }
+    #[cfg(feature = "grpc")]
+    if let Some(grpc) = grpc
+        && let Ok(Err(e)) = grpc.await
+    {
+        warn!("gRPC failed: {e}");
+    }
+    #[cfg(feature = "udp")]
+    if let Some(udp) = udp
+        && let Ok(Err(e)) = udp.await
+    {
+        warn!("Sending events over UDP failed: {e}");
+    }
+    if let Some(timer) = timer
+        && let Ok(Err(e)) = timer.await
+    {
+        warn!("Smart timer failed: {e}");
+    }
+    if let Some(inspection) = inspection {
+        let _ = inspection.await;
+    }
+    if let Some(solves) = solves
+        && let Ok(Err(e)) = solves.await
+    {
+        warn!("Solve export failed: {e}");
+    }
+    #[cfg(feature = "database")]
+    if let Some(database) = database
+        && let Ok(Err(e)) = database.await
+    {
+        warn!("Could not save solves to the database: {e}");
+    }
+    if let Some(session) = session
+        && let Ok(Err(e)) = session.await
+    {
+        warn!("Session recording failed: {e}");
+    }
+    #[cfg(all(feature = "evdev", target_os = "linux"))]
+    if let Some(keyboards) = keyboards
+        && let Ok(Err(e)) = keyboards.await
+    {
+        warn!("Could not follow the keyboards: {e}");
+    }
+    #[cfg(feature = "twitch")]
+    if let Some(redemptions) = redemptions
+        && let Ok(Err(e)) = redemptions.await
+    {
+        warn!("Following Twitch redemptions failed: {e}");
+    }
+    #[cfg(feature = "focus")]
+    if let Some(focus) = focus
+        && let Ok(Err(e)) = focus.await
+    {
+        warn!("Following the focused window failed: {e}");
+    }
+    if let Some(cases) = cases
+        && let Ok(report) = cases.await
+        && !report.is_empty()
+    {
+        println!("{report}");
+    }
+
+    triplicata.shutdown().await;
+
+    result
+}
+
+#[cfg(unix)]
+async fn reloaded(reloader: &Reloader) {
+    reloader.requested().await;
+}
+
+/// Reloading is only requested through the control socket.
+#[cfg(not(unix))]
+async fn reloaded(_reloader: &()) {
+    std::future::pending().await
+}
+
+/// Settings that parse but cannot work together or in this build.
+fn check_config(config: &Config) -> anyhow::Result<()> {
+    if config.cstimer && !cfg!(all(feature = "cstimer", target_os = "linux")) {
+        anyhow::bail!("triplicata was built without csTimer emulation");
+    }
+
+    if config.backend == Backend::Bluez && !cfg!(all(feature = "bluez", target_os = "linux")) {
+        anyhow::bail!("triplicata was built without the bluez backend");
+    }
+
+    if config.binds.iter().any(|bind| !bind.held.is_empty())
+        && !cfg!(all(feature = "evdev", target_os = "linux"))
+    {
+        anyhow::bail!("binds needing held modifiers are only supported on Linux with evdev");
+    }
+
+    if config
+        .hotkeys
+        .as_ref()
+        .is_some_and(|hotkeys| hotkeys.bound().next().is_some())
+        && !cfg!(all(feature = "evdev", target_os = "linux"))
+    {
+        anyhow::bail!("hotkeys are only supported on Linux with evdev");
+    }
+
+    if config.typing_quiet.is_some() && !cfg!(all(feature = "evdev", target_os = "linux")) {
+        anyhow::bail!("keeping quiet while typing is only supported on Linux with evdev");
+    }
+
+    if config.calibration.is_some() && config.cube_state.is_none() {
+        anyhow::bail!("calibration is saved with each cube's state, so it needs cube_state");
+    }
+
+    if config.inspection && !config.smart_timer {
+        anyhow::bail!("inspection needs the smart timer to be enabled");
+    }
+
+    if config.database.is_some() && !cfg!(feature = "database") {
+        anyhow::bail!("triplicata was built without the solve database");
+    }
+
+    if config.metrics.is_some() && !cfg!(feature = "metrics") {
+        anyhow::bail!("triplicata was built without metrics");
+    }
+
+    if config.overlay.is_some() && !cfg!(feature = "overlay") {
+        anyhow::bail!("triplicata was built without the overlay");
+    }
+
+    if config.osd && !cfg!(feature = "osd") {
+        anyhow::bail!("triplicata was built without the on-screen display");
+    }
+
+    if config.evdev && !cfg!(all(feature = "evdev", target_os = "linux")) {
+        anyhow::bail!("triplicata was built without the evdev keyboard");
+    }
+
+    if config
+        .switches
+        .as_ref()
+        .is_some_and(|switches| switches.device)
+        && !cfg!(all(feature = "evdev", target_os = "linux"))
+    {
+        anyhow::bail!("triplicata was built without the virtual switch device");
+    }
+
+    if config.battery_poll == Some(0) {
+        anyhow::bail!("`battery_poll` must be at least one second");
+    }
+
+    if config.control.is_some() && !cfg!(unix) {
+        anyhow::bail!("the control socket is only available on Unix");
+    }
+
+    if config.api.is_some() && !cfg!(feature = "api") {
+        anyhow::bail!("triplicata was built without the API");
+    }
+
+    if let Some(address) = config.api
+        && !address.ip().is_loopback()
+        && !config.api_remote
+    {
+        anyhow::bail!(
+            "the API can fire binds from other machines on {address}, set `api_remote: true` to allow it"
+        );
+    }
+
+    if config.grpc.is_some() && !cfg!(feature = "grpc") {
+        anyhow::bail!("triplicata was built without gRPC");
+    }
+
+    if config.history.is_some() && config.control.is_none() && config.api.is_none() {
+        anyhow::bail!("the move history is only queried through the control socket and the API");
+    }
+
+    if let Some(idle) = config.idle
+        && idle.rescan.is_none()
+        && config.control.is_none()
+    {
+        anyhow::bail!("disconnecting an idle cube needs `rescan` or a control socket to reconnect");
+    }
+
+    if config.udp.is_some() && !cfg!(feature = "udp") {
+        anyhow::bail!("triplicata was built without the UDP bridge");
+    }
+
+    #[cfg(feature = "twitch")]
+    if !config.redemption_binds.is_empty() && config.twitch.is_none() {
+        anyhow::bail!("redemption binds need a Twitch account to be configured");
+    }
+    #[cfg(not(feature = "twitch"))]
+    if !config.redemption_binds.is_empty() {
+        anyhow::bail!("triplicata was built without Twitch redemptions");
+    }
+
+    Ok(())
+}
+
+#[derive(Default)]
+struct Doctor {
+    failed: usize,
+}
+
+impl Doctor {
+    fn ok(&self, check: &str, detail: impl Display) {
+        println!("ok    {check:<10} {detail}");
+    }
+
+    fn warn(&self, check: &str, detail: impl Display, fix: &str) {
+        println!("warn  {check:<10} {detail}\n      fix: {fix}");
+    }
+
+    fn fail(&mut self, check: &str, detail: impl Display, fix: &str) {
+        self.failed += 1;
+        println!("FAIL  {check:<10} {detail}\n      fix: {fix}");
+    }
+}
+
+#[cfg(feature = "database")]
+const SOLVES_HELP: &str = "\
+Commands:
+  list               list the solves
+  sessions           list the sessions
+  session <id|all>   only list the solves of one session
+  sort <order>       sort by newest, oldest, fastest or slowest
+  limit <n|all>      list at most n solves
+  show <id>          show a solve's scramble and reconstruction
+  +2 <id>            add two seconds to a solve
+  dnf <id>           mark a solve as not finished
+  ok <id>            take a solve's penalty away
+  delete <id>        delete a solve
+  quit";
+
+/// Opens `database`, or the config's if not given.
+#[cfg(feature = "database")]
+fn open_database(
+    path: &Path,
+    database: Option<PathBuf>,
+) -> anyhow::Result<triplicata::database::SolveDatabase> {
+    let database = match database {
+        Some(database) => database,
+        None => Config::load(path)?.database.ok_or_else(|| {
+            anyhow::anyhow!("no database given and none set as `database` in the config")
+        })?,
+    };
+    Ok(triplicata::database::SolveDatabase::open(&database)?)
+}
+
+/// Browses the solve database with commands typed at a prompt.
+#[cfg(feature = "database")]
+fn browse_solves(path: &Path, database: Option<PathBuf>) -> anyhow::Result<()> {
+    use triplicata::database::{Penalty, SolveQuery, Sort};
+
+    let mut database = open_database(path, database)?;
+
+    let mut session = None;
+    let mut sort = Sort::Newest;
+    let mut limit = Some(20);
+
+    println!("{SOLVES_HELP}");
+    loop {
+        print!("> ");
+        std::io::stdout().flush()?;
+        let mut line = String::new();
+        if std::io::stdin().read_line(&mut line)? == 0 {
+            return Ok(());
+        }
+
+        let mut words = line.split_whitespace();
+        let (Some(command), argument) = (words.next(), words.next()) else {
+            continue;
+        };
+        let id = argument.and_then(|id| id.parse::<i64>().ok());
+
+        match (command, argument, id) {
+            ("list", _, _) => {
+                let mut query = SolveQuery::new().sort(sort);
+                if let Some(session) = session {
+                    query = query.session(session);
+                }
+                if let Some(limit) = limit {
+                    query = query.limit(limit);
+                }
+
+                for solve in database.solves(&query)? {
+                    println!(
+                        "{:>6}  {}  {:>10}  {} moves",
+                        solve.id,
+                        date(solve.started),
+                        solve_time(&solve),
+                        solve.moves.len()
+                    );
+                }
+                if let Some(best) = database.best(&query)? {
+                    println!("Best: {} (#{})", solve_time(&best), best.id);
+                }
+            }
+            ("sessions", _, _) => {
+                for (id, started) in database.sessions()? {
+                    let solves = database.solves(&SolveQuery::new().session(id))?;
+                    println!("{id:>4}  {}  {} solves", date(started), solves.len());
+                }
+            }
+            ("session", Some("all"), _) => session = None,
+            ("session", _, Some(id)) => session = Some(id),
+            ("sort", Some(order), _) => match order {
+                "newest" => sort = Sort::Newest,
+                "oldest" => sort = Sort::Oldest,
+                "fastest" => sort = Sort::Fastest,
+                "slowest" => sort = Sort::Slowest,
+                _ => println!("Sort by newest, oldest, fastest or slowest"),
+            },
+            ("limit", Some("all"), _) => limit = None,
+            ("limit", Some(n), _) => match n.parse() {
+                Ok(n) => limit = Some(n),
+                Err(_) => println!("`{n}` is not a number of solves"),
+            },
+            ("show", _, Some(id)) => match database.solve(id)? {
+                Some(solve) => show_solve(&solve),
+                None => println!("No solve #{id}"),
+            },
+            ("+2" | "dnf" | "ok", _, Some(id)) => {
+                let penalty = match command {
+                    "+2" => Some(Penalty::PlusTwo),
+                    "dnf" => Some(Penalty::Dnf),
+                    _ => None,
+                };
+                if !database.penalize(id, penalty)? {
+                    println!("No solve #{id}");
+                }
+            }
+            ("delete", _, Some(id)) => {
+                if prompt(&format!("Delete solve #{id}? [y/N] "))?.eq_ignore_ascii_case("y")
+                    && !database.delete(id)?
+                {
+                    println!("No solve #{id}");
+                }
+            }
+            ("quit" | "q", _, _) => return Ok(()),
+            ("help", _, _) => println!("{SOLVES_HELP}"),
+            _ => println!("Unknown command `{}`, `help` lists them", line.trim()),
+        }
+    }
+}
+
+/// A solve's time counted, with its penalty.
+#[cfg(feature = "database")]
+fn solve_time(solve: &triplicata::database::StoredSolve) -> String {
+    let seconds = solve.duration.as_secs_f64();
+    match solve.penalty {
+        None => format!("{seconds:.2}"),
+        Some(triplicata::database::Penalty::PlusTwo) => format!("{:.2}+", seconds + 2.0),
+        Some(triplicata::database::Penalty::Dnf) => format!("DNF({seconds:.2})"),
+    }
+}
+
+#[cfg(feature = "database")]
+fn date(time: std::time::SystemTime) -> String {
+    DateTime::<Local>::from(time)
+        .format("%Y-%m-%d %H:%M")
+        .to_string()
+}
+
+/// Prints a solve's scramble and its moves split into phases.
+#[cfg(feature = "database")]
+fn show_solve(solve: &triplicata::database::StoredSolve) {
+    println!(
+        "#{} in session {}, {}",
+        solve.id,
+        solve.session,
+        date(solve.started)
+    );
+    println!("Time: {}", solve_time(solve));
+    println!("Scramble: {}", solve.scramble);
+
+    for (i, (name, moves)) in solve.reconstruction().into_iter().enumerate() {
+        let algorithm = Algorithm::from(moves.iter().map(|m| m.m).collect::<Vec<_>>());
+        match solve.splits.get(i) {
+            Some(split) => println!(
+                "  {algorithm}  // {name}, {:.2}s, {} moves",
+                split.duration.as_secs_f64(),
+                split.moves
+            ),
+            None => println!("  {algorithm}"),
         }
+    }
+
+    let seconds = solve.duration.as_secs_f64();
+    if seconds > 0.0 {
+        println!(
+            "{} moves, {:.2} TPS",
+            solve.moves.len(),
+            solve.moves.len() as f64 / seconds
+        );
+    }
+}
+
+async fn doctor(path: &Path) -> anyhow::Result<()> {
+    let mut doctor = Doctor::default();
+
+    let bluetooth_fix = if cfg!(target_os = "linux") {
+        "start BlueZ with `sudo systemctl enable --now bluetooth` and check `rfkill list` \
+         does not block the adapter"
+    } else if cfg!(target_os = "macos") {
+        "allow your terminal in System Settings > Privacy & Security > Bluetooth"
+    } else {
+        "turn bluetooth on in Settings > Bluetooth & devices"
+    };
+    match bluetooth::adapters().await {
+        Ok(adapters) if adapters.is_empty() => {
+            doctor.fail("bluetooth", "no adapters found", bluetooth_fix)
+        }
+        Ok(adapters) => doctor.ok("bluetooth", adapters.join(", ")),
+        Err(e) => doctor.fail("bluetooth", e, bluetooth_fix),
+    }
+
+    let input_fix = if cfg!(target_os = "macos") {
+        "allow your terminal in System Settings > Privacy & Security > Accessibility, then \
+         restart it"
+    } else if cfg!(target_os = "linux") {
+        "run triplicata inside your graphical session, with DISPLAY or WAYLAND_DISPLAY set"
+    } else {
+        "run triplicata as the logged in user, not as a service"
+    };
+    match EnigoOutput::new() {
+        Ok(_) => doctor.ok("input", "can simulate input"),
+        Err(e) => doctor.fail("input", e, input_fix),
+    }
+
+    if cfg!(target_os = "linux") {
+        let desktop = std::env::var("XDG_CURRENT_DESKTOP").unwrap_or_default();
+        if std::env::var_os("WAYLAND_DISPLAY").is_some() && desktop.contains("GNOME") {
+            doctor.warn(
+                "wayland",
+                "GNOME only accepts simulated input through the RemoteDesktop portal, which \
+                 triplicata does not use",
+                "log in to a GNOME on Xorg session",
+            );
+        }
+
+        match fs::OpenOptions::new().write(true).open("/dev/uinput") {
+            Ok(_) => doctor.ok("uinput", "/dev/uinput is writable"),
+            Err(e) => doctor.warn(
+                "uinput",
+                format!("/dev/uinput: {e}"),
+                "add yourself to the input group with `sudo usermod -aG input $USER`, add the \
+                 udev rule `KERNEL==\"uinput\", GROUP=\"input\", MODE=\"0660\"` and log in again",
+            ),
+        }
+    }
+
+    if !path.exists() {
+        doctor.fail(
+            "config",
+            format!("{} does not exist", path.display()),
+            "write a starter config with `triplicata init`",
+        );
     } else {
-        error!("Unknown protocol version");
-        return Ok(ExitCode::FAILURE);
+        match Config::load_strict(path) {
+            Ok(config) => match check_config(&config) {
+                Ok(()) => doctor.ok("config", format!("{} binds", config.binds.len())),
+                Err(e) => doctor.fail("config", e, "change the config or rebuild triplicata"),
+            },
+            Err(e) if Config::load(path).is_ok() => doctor.warn(
+                "config",
+                e,
+                "fix the name, otherwise the setting is ignored",
+            ),
+            Err(e) => doctor.fail(
+                "config",
+                e,
+                "fix the config, or start over with `triplicata init --force`",
+            ),
+        }
     }
 
-    Ok(ExitCode::SUCCESS)
+    if doctor.failed > 0 {
+        anyhow::bail!("{} checks failed", doctor.failed);
+    }
+
+    Ok(())
+}
+
+/// Feeds a smart timer's state changes into the pipeline so timer binds and
+/// the overlay see them.
+async fn forward_timer(injector: MoveInjector, cancel: CancellationToken) -> anyhow::Result<()> {
+    let mut events = GanTimerSource::new().connect(cancel).await?;
+
+    loop {
+        match events.recv().await {
+            Ok(event @ CubeEvent::Timer(_)) => injector.inject_event(event),
+            Ok(CubeEvent::Disconnected) => warn!("Smart timer disconnected"),
+            Ok(_) | Err(RecvError::Lagged(_)) => {}
+            Err(RecvError::Closed) => return Ok(()),
+        }
+    }
 }
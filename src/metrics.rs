@@ -0,0 +1,246 @@
+//! Counters and histograms recorded by the pipeline, and by custom sources
+//! through the functions below. Recording is a no-op unless the `metrics`
+//! feature is enabled and a recorder is installed, except for the channel
+//! counts, which are always kept for the status answers.
+
+use std::{
+    collections::BTreeMap,
+    fmt,
+    sync::atomic::{AtomicU64, Ordering},
+    time::Duration,
+};
+
+#[cfg(feature = "metrics")]
+use std::net::SocketAddr;
+
+use serde::Serialize;
+use tracing::{trace, warn};
+
+#[cfg(feature = "metrics")]
+use crate::error::MetricsError;
+
+pub const MOVES_RECEIVED: &str = "triplicata_moves_received_total";
+pub const DECRYPT_FAILURES: &str = "triplicata_decrypt_failures_total";
+pub const BINDS_FIRED: &str = "triplicata_binds_fired_total";
+pub const CHANNEL_SENDS: &str = "triplicata_channel_sends_total";
+pub const CHANNEL_DROPS: &str = "triplicata_channel_drops_total";
+pub const CHANNEL_LAGS: &str = "triplicata_channel_lags_total";
+pub const ACTION_LATENCY: &str = "triplicata_action_latency_seconds";
+pub const STAGE_LATENCY: &str = "triplicata_stage_latency_seconds";
+pub const BATTERY_LEVEL: &str = "triplicata_battery_percent";
+
+/// A step an event passes through between the cube and the output.
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum Stage {
+    /// From the notification arriving to its events being decoded.
+    Decode,
+    /// From the notification arriving to the move's binds being dispatched,
+    /// including the time it was queued on the way to the state machine.
+    Match,
+    /// From the notification arriving to the backend having executed the
+    /// action the move fired, including the time it was queued for the
+    /// output.
+    Inject,
+}
+
+impl Stage {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Stage::Decode => "decode",
+            Stage::Match => "match",
+            Stage::Inject => "inject",
+        }
+    }
+}
+
+/// One of the internal channels whose traffic is counted.
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum Channel {
+    /// Events from the cube, counted as the pipeline receives them since each
+    /// source sends them on a channel of its own.
+    CubeEvents,
+    /// The cube's events and injected ones, followed by everything else.
+    EventStream,
+    /// Actions from the state machine to the output.
+    Actions,
+    /// Binds as they fire.
+    FiredBinds,
+    /// Actions once the output played them.
+    PlayedActions,
+    /// Events on their way to the overlay's clients.
+    Overlay,
+}
+
+impl Channel {
+    pub const ALL: [Channel; 6] = [
+        Channel::CubeEvents,
+        Channel::EventStream,
+        Channel::Actions,
+        Channel::FiredBinds,
+        Channel::PlayedActions,
+        Channel::Overlay,
+    ];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Channel::CubeEvents => "cube_events_received",
+            Channel::EventStream => "event_stream",
+            Channel::Actions => "actions",
+            Channel::FiredBinds => "fired_binds",
+            Channel::PlayedActions => "played_actions",
+            Channel::Overlay => "overlay",
+        }
+    }
+}
+
+/// What went through one of the internal channels.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct ChannelStats {
+    pub sent: u64,
+    /// Messages a receiver missed for falling behind.
+    pub dropped: u64,
+    /// How many times a receiver fell behind.
+    pub lags: u64,
+}
+
+impl fmt::Display for ChannelStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} sent, {} dropped in {} lag{}",
+            self.sent,
+            self.dropped,
+            self.lags,
+            if self.lags == 1 { "" } else { "s" }
+        )
+    }
+}
+
+/// Counted on every send, so kept in atomics rather than behind a lock.
+struct ChannelCounters {
+    sent: AtomicU64,
+    dropped: AtomicU64,
+    lags: AtomicU64,
+}
+
+impl ChannelCounters {
+    const fn new() -> Self {
+        Self {
+            sent: AtomicU64::new(0),
+            dropped: AtomicU64::new(0),
+            lags: AtomicU64::new(0),
+        }
+    }
+
+    fn stats(&self) -> ChannelStats {
+        ChannelStats {
+            sent: self.sent.load(Ordering::Relaxed),
+            dropped: self.dropped.load(Ordering::Relaxed),
+            lags: self.lags.load(Ordering::Relaxed),
+        }
+    }
+}
+
+static CHANNELS: [ChannelCounters; Channel::ALL.len()] =
+    [const { ChannelCounters::new() }; Channel::ALL.len()];
+
+fn counters(channel: Channel) -> &'static ChannelCounters {
+    &CHANNELS[channel as usize]
+}
+
+/// What went through each channel used since the start, by name.
+pub fn channels() -> BTreeMap<&'static str, ChannelStats> {
+    Channel::ALL
+        .into_iter()
+        .map(|channel| (channel.as_str(), counters(channel).stats()))
+        .filter(|(_, stats)| *stats != ChannelStats::default())
+        .collect()
+}
+
+/// Serves the recorded metrics in the Prometheus text format at
+/// `http://{address}/metrics`. Must be called from within a tokio runtime.
+#[cfg(feature = "metrics")]
+pub fn install_prometheus(address: SocketAddr) -> Result<(), MetricsError> {
+    metrics_exporter_prometheus::PrometheusBuilder::new()
+        .with_http_listener(address)
+        .install()?;
+
+    ::metrics::describe_counter!(MOVES_RECEIVED, "Moves decoded from the cube");
+    ::metrics::describe_counter!(DECRYPT_FAILURES, "Notifications that could not be decoded");
+    ::metrics::describe_counter!(BINDS_FIRED, "Binds whose actions were played");
+    ::metrics::describe_counter!(CHANNEL_SENDS, "Messages sent through internal channels");
+    ::metrics::describe_counter!(CHANNEL_DROPS, "Events dropped by lagging receivers");
+    ::metrics::describe_counter!(CHANNEL_LAGS, "Times a receiver fell behind its channel");
+    ::metrics::describe_gauge!(BATTERY_LEVEL, "The cube's last reported battery level");
+    ::metrics::describe_histogram!(
+        ACTION_LATENCY,
+        ::metrics::Unit::Seconds,
+        "Time from the triggering move to the action being executed"
+    );
+    ::metrics::describe_histogram!(
+        STAGE_LATENCY,
+        ::metrics::Unit::Seconds,
+        "Time from receiving a move to the end of each pipeline stage"
+    );
+
+    Ok(())
+}
+
+pub fn move_received() {
+    #[cfg(feature = "metrics")]
+    ::metrics::counter!(MOVES_RECEIVED).increment(1);
+}
+
+pub fn decrypt_failure() {
+    #[cfg(feature = "metrics")]
+    ::metrics::counter!(DECRYPT_FAILURES).increment(1);
+}
+
+pub fn bind_fired() {
+    #[cfg(feature = "metrics")]
+    ::metrics::counter!(BINDS_FIRED).increment(1);
+}
+
+#[cfg_attr(not(feature = "metrics"), allow(unused_variables))]
+pub fn battery_level(level: u8) {
+    #[cfg(feature = "metrics")]
+    ::metrics::gauge!(BATTERY_LEVEL).set(level as f64);
+}
+
+pub fn channel_sent(channel: Channel) {
+    counters(channel).sent.fetch_add(1, Ordering::Relaxed);
+
+    #[cfg(feature = "metrics")]
+    ::metrics::counter!(CHANNEL_SENDS, "channel" => channel.as_str()).increment(1);
+}
+
+/// Counts a receiver of `channel` falling behind and missing `count`
+/// messages, warning as the moves among them may have been missed by binds.
+pub fn channel_drops(channel: Channel, count: u64) {
+    let counters = counters(channel);
+    counters.dropped.fetch_add(count, Ordering::Relaxed);
+    counters.lags.fetch_add(1, Ordering::Relaxed);
+    warn!(
+        channel = channel.as_str(),
+        "Fell behind and missed {count} messages, gestures turned meanwhile may not fire"
+    );
+
+    #[cfg(feature = "metrics")]
+    {
+        ::metrics::counter!(CHANNEL_DROPS, "channel" => channel.as_str()).increment(count);
+        ::metrics::counter!(CHANNEL_LAGS, "channel" => channel.as_str()).increment(1);
+    }
+}
+
+#[cfg_attr(not(feature = "metrics"), allow(unused_variables))]
+pub fn action_latency(latency: Duration) {
+    #[cfg(feature = "metrics")]
+    ::metrics::histogram!(ACTION_LATENCY).record(latency);
+}
+
+pub fn stage_latency(stage: Stage, latency: Duration) {
+    trace!(stage = stage.as_str(), ?latency, "Stage latency");
+
+    #[cfg(feature = "metrics")]
+    ::metrics::histogram!(STAGE_LATENCY, "stage" => stage.as_str()).record(latency);
+}
@@ -0,0 +1,122 @@
+//! Scoring how closely turns follow a metronome, for practicing an even
+//! turning rhythm at a target TPS.
+
+use std::time::Duration;
+#[cfg(feature = "config")]
+use std::{fs, io, path::Path};
+
+use serde::{Deserialize, Serialize};
+
+#[cfg(feature = "config")]
+use crate::{config::to_ron, error::ConfigError};
+
+/// The offsets of each turn from its nearest beat.
+#[derive(Debug, Clone)]
+pub struct RhythmScore {
+    interval: Duration,
+    /// Seconds from the nearest beat, negative when early.
+    offsets: Vec<f64>,
+}
+
+/// The summary of one practice session, as kept in the history.
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+pub struct Session {
+    /// Seconds since the UNIX epoch when the session ended.
+    pub finished: u64,
+    pub target_tps: f64,
+    pub tps: f64,
+    pub moves: usize,
+    /// How close turns landed to the beat on average, from 0 to 1.
+    pub accuracy: f64,
+    /// The average offset from the beat in milliseconds, negative when
+    /// rushing.
+    pub mean_offset_ms: f64,
+    /// The standard deviation of the offsets in milliseconds, lower being a
+    /// steadier rhythm even if it is off the beat.
+    pub spread_ms: f64,
+}
+
+impl RhythmScore {
+    pub fn new(tps: f64) -> Self {
+        Self {
+            interval: Duration::from_secs_f64(1.0 / tps),
+            offsets: Vec::new(),
+        }
+    }
+
+    pub fn interval(&self) -> Duration {
+        self.interval
+    }
+
+    /// Records a turn `elapsed` after the first beat.
+    pub fn record(&mut self, elapsed: Duration) {
+        let interval = self.interval.as_secs_f64();
+        let offset = elapsed.as_secs_f64() % interval;
+        self.offsets.push(if offset > interval / 2.0 {
+            offset - interval
+        } else {
+            offset
+        });
+    }
+
+    pub fn moves(&self) -> usize {
+        self.offsets.len()
+    }
+
+    /// 1 when every turn is on a beat and 0 when every turn is halfway
+    /// between beats.
+    pub fn accuracy(&self) -> f64 {
+        let half = self.interval.as_secs_f64() / 2.0;
+        1.0 - self.mean(|offset| offset.abs()) / half
+    }
+
+    pub fn mean_offset(&self) -> f64 {
+        self.mean(|offset| offset)
+    }
+
+    pub fn spread(&self) -> f64 {
+        let mean = self.mean_offset();
+        self.mean(|offset| (offset - mean).powi(2)).sqrt()
+    }
+
+    /// Summarises a session of `length`.
+    pub fn session(&self, length: Duration, finished: u64) -> Session {
+        Session {
+            finished,
+            target_tps: 1.0 / self.interval.as_secs_f64(),
+            tps: self.moves() as f64 / length.as_secs_f64(),
+            moves: self.moves(),
+            accuracy: self.accuracy(),
+            mean_offset_ms: self.mean_offset() * 1000.0,
+            spread_ms: self.spread() * 1000.0,
+        }
+    }
+
+    fn mean(&self, f: impl Fn(f64) -> f64) -> f64 {
+        if self.offsets.is_empty() {
+            return 0.0;
+        }
+
+        self.offsets.iter().copied().map(f).sum::<f64>() / self.offsets.len() as f64
+    }
+}
+
+#[cfg(feature = "config")]
+impl Session {
+    /// Reads the sessions kept at `path`, oldest first. A missing file is an
+    /// empty history.
+    pub fn load_history(path: impl AsRef<Path>) -> Result<Vec<Session>, ConfigError> {
+        match fs::read_to_string(path) {
+            Ok(history) => Ok(ron::from_str(&history)?),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(Vec::new()),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    pub fn append_to_history(&self, path: impl AsRef<Path>) -> Result<(), ConfigError> {
+        let path = path.as_ref();
+        let mut history = Self::load_history(path)?;
+        history.push(self.clone());
+        Ok(fs::write(path, to_ron(&history)?)?)
+    }
+}
@@ -0,0 +1,187 @@
+//! Keyboard modifiers held on the physical keyboards, so a bind can be
+//! limited to when, say, Shift is down and one gesture can do different
+//! things with the other hand on the keyboard, and when the keyboards were
+//! last typed on, so binds can keep quiet while a document is being written.
+
+use std::{
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicU8, Ordering},
+    },
+    time::{Duration, Instant},
+};
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Modifier {
+    Shift,
+    Control,
+    Alt,
+    /// The Windows, Command or Super key.
+    Meta,
+}
+
+impl Modifier {
+    pub const ALL: [Modifier; 4] = [
+        Modifier::Shift,
+        Modifier::Control,
+        Modifier::Alt,
+        Modifier::Meta,
+    ];
+
+    fn bit(self) -> u8 {
+        1 << self as u8
+    }
+}
+
+/// Which modifiers are held, shared between its clones.
+#[derive(Debug, Clone, Default)]
+pub struct Modifiers {
+    held: Arc<AtomicU8>,
+}
+
+impl Modifiers {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn is_held(&self, modifier: Modifier) -> bool {
+        self.held.load(Ordering::Relaxed) & modifier.bit() != 0
+    }
+
+    /// Whether every one of `modifiers` is held.
+    pub fn are_held(&self, modifiers: &[Modifier]) -> bool {
+        modifiers.iter().all(|modifier| self.is_held(*modifier))
+    }
+
+    pub fn set(&self, modifier: Modifier, held: bool) {
+        if held {
+            self.held.fetch_or(modifier.bit(), Ordering::Relaxed);
+        } else {
+            self.held.fetch_and(!modifier.bit(), Ordering::Relaxed);
+        }
+    }
+}
+
+/// When a key other than a modifier was last pressed, shared between its
+/// clones.
+#[derive(Debug, Clone, Default)]
+pub struct Typing {
+    last: Arc<Mutex<Option<Instant>>>,
+}
+
+impl Typing {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Notes a key pressed at `at`.
+    pub fn typed(&self, at: Instant) {
+        *self.last.lock().unwrap() = Some(at);
+    }
+
+    /// Whether a key was pressed in the last `quiet`.
+    pub fn typed_within(&self, quiet: Duration) -> bool {
+        self.last
+            .lock()
+            .unwrap()
+            .is_some_and(|last| last.elapsed() < quiet)
+    }
+}
+
+/// Keeps `modifiers` and `typing` up to date with the keyboards and sends
+/// the `hotkeys` pressed to `pressed` until `cancel` is cancelled, reading
+/// the keyboards through evdev, which needs read access to `/dev/input`.
+/// Keyboards plugged in later are not followed.
+#[cfg(all(feature = "evdev", feature = "runtime", target_os = "linux"))]
+pub async fn follow_keyboards(
+    modifiers: Modifiers,
+    typing: Typing,
+    mut hotkeys: crate::hotkeys::HotkeyWatcher,
+    pressed: tokio::sync::mpsc::UnboundedSender<crate::hotkeys::HotkeyAction>,
+    cancel: tokio_util::sync::CancellationToken,
+) -> std::io::Result<()> {
+    use std::{collections::HashSet, io};
+
+    use evdev::KeyCode;
+    use tokio::{select, time::interval};
+    use tracing::{debug, info};
+
+    use crate::uinput::{DEVICE_NAME, key_code};
+
+    /// How often the keyboards are checked, well below how long a key is
+    /// held down when typing.
+    const POLL: Duration = Duration::from_millis(20);
+
+    let keys = |modifier| match modifier {
+        Modifier::Shift => [KeyCode::KEY_LEFTSHIFT, KeyCode::KEY_RIGHTSHIFT],
+        Modifier::Control => [KeyCode::KEY_LEFTCTRL, KeyCode::KEY_RIGHTCTRL],
+        Modifier::Alt => [KeyCode::KEY_LEFTALT, KeyCode::KEY_RIGHTALT],
+        Modifier::Meta => [KeyCode::KEY_LEFTMETA, KeyCode::KEY_RIGHTMETA],
+    };
+
+    // The virtual keyboard is left out, or modifiers that actions press
+    // would count as held.
+    let mut keyboards: Vec<_> = evdev::enumerate()
+        .map(|(_, device)| device)
+        .filter(|device| {
+            device.name() != Some(DEVICE_NAME)
+                && device
+                    .supported_keys()
+                    .is_some_and(|keys| keys.contains(KeyCode::KEY_LEFTSHIFT))
+        })
+        .collect();
+    if keyboards.is_empty() {
+        return Err(io::Error::new(
+            io::ErrorKind::NotFound,
+            "no keyboard could be read, which needs read access to /dev/input",
+        ));
+    }
+
+    info!("Following {} keyboards", keyboards.len());
+
+    let mut poll = interval(POLL);
+    loop {
+        select! {
+            _ = poll.tick() => {}
+            _ = cancel.cancelled() => return Ok(()),
+        }
+
+        let mut held = 0;
+        let mut typed = false;
+        let mut down = HashSet::new();
+        keyboards.retain(|keyboard| match keyboard.get_key_state() {
+            Ok(pressed) => {
+                down.extend(pressed.iter());
+                let mut modifier_keys = 0;
+                for modifier in Modifier::ALL {
+                    for key in keys(modifier) {
+                        if pressed.contains(key) {
+                            held |= modifier.bit();
+                            modifier_keys += 1;
+                        }
+                    }
+                }
+                typed |= pressed.iter().count() > modifier_keys;
+                true
+            }
+            Err(error) => {
+                debug!(%error, name = ?keyboard.name(), "Keyboard went away");
+                false
+            }
+        });
+
+        modifiers.held.store(held, Ordering::Relaxed);
+        if typed {
+            typing.typed(Instant::now());
+        }
+
+        if !hotkeys.is_empty() {
+            let is_down = |key| key_code(key).is_some_and(|(code, _)| down.contains(&code));
+            for hotkey in hotkeys.update(&modifiers, is_down) {
+                let _ = pressed.send(hotkey);
+            }
+        }
+    }
+}
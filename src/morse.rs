@@ -0,0 +1,153 @@
+//! Typing with Morse code, flicking one move for a dot and its inverse for a
+//! dash. A pause ends the letter, and a longer pause types a space.
+
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{config::Key, cube::Move};
+
+const CODES: [(char, &str); 36] = [
+    ('a', ".-"),
+    ('b', "-..."),
+    ('c', "-.-."),
+    ('d', "-.."),
+    ('e', "."),
+    ('f', "..-."),
+    ('g', "--."),
+    ('h', "...."),
+    ('i', ".."),
+    ('j', ".---"),
+    ('k', "-.-"),
+    ('l', ".-.."),
+    ('m', "--"),
+    ('n', "-."),
+    ('o', "---"),
+    ('p', ".--."),
+    ('q', "--.-"),
+    ('r', ".-."),
+    ('s', "..."),
+    ('t', "-"),
+    ('u', "..-"),
+    ('v', "...-"),
+    ('w', ".--"),
+    ('x', "-..-"),
+    ('y', "-.--"),
+    ('z', "--.."),
+    ('0', "-----"),
+    ('1', ".----"),
+    ('2', "..---"),
+    ('3', "...--"),
+    ('4', "....-"),
+    ('5', "....."),
+    ('6', "-...."),
+    ('7', "--..."),
+    ('8', "---.."),
+    ('9', "----."),
+];
+
+/// Morse input, e.g. `(dot: U, letter_gap: 600, word_gap: 1500)`. The inverse
+/// of `dot` is the dash.
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, Eq)]
+#[serde(default)]
+pub struct Morse {
+    /// The group switching Morse input on and off, like a bind group.
+    pub group: String,
+    pub dot: Move,
+    /// Milliseconds without a flick that end a letter.
+    pub letter_gap: u64,
+    /// Milliseconds without a flick that end a word.
+    pub word_gap: u64,
+}
+
+impl Default for Morse {
+    fn default() -> Self {
+        Self {
+            group: "morse".to_string(),
+            dot: Move::U,
+            letter_gap: 600,
+            word_gap: 1500,
+        }
+    }
+}
+
+/// The letter for a code of dots and dashes, such as `.-`.
+pub fn decode(code: &str) -> Option<char> {
+    CODES
+        .iter()
+        .find(|(_, known)| *known == code)
+        .map(|&(c, _)| c)
+}
+
+#[derive(Debug, Clone)]
+pub struct MorseDecoder {
+    config: Morse,
+    code: String,
+    /// Whether a letter was typed since the last space.
+    in_word: bool,
+}
+
+impl MorseDecoder {
+    pub fn new(config: Morse) -> Self {
+        Self {
+            config,
+            code: String::new(),
+            in_word: false,
+        }
+    }
+
+    pub fn group(&self) -> &str {
+        &self.config.group
+    }
+
+    /// Adds `m` to the letter, returning `false` if it is not a flick.
+    pub fn push(&mut self, m: Move) -> bool {
+        if m == self.config.dot {
+            self.code.push('.');
+        } else if m == self.config.dot.inverse() {
+            self.code.push('-');
+        } else {
+            return false;
+        }
+
+        true
+    }
+
+    /// How long after the last flick a pause ends the letter or word.
+    pub fn next_gap(&self) -> Option<Duration> {
+        if !self.code.is_empty() {
+            Some(Duration::from_millis(self.config.letter_gap))
+        } else if self.in_word {
+            Some(Duration::from_millis(self.config.word_gap))
+        } else {
+            None
+        }
+    }
+
+    /// The keys typed by pausing for `pause` after the last flick. Codes that
+    /// are not a letter are dropped.
+    pub fn pause(&mut self, pause: Duration) -> Vec<Key> {
+        let mut keys = Vec::new();
+
+        if !self.code.is_empty() && pause >= Duration::from_millis(self.config.letter_gap) {
+            match decode(&self.code) {
+                Some(c) => {
+                    keys.push(Key::Unicode(c));
+                    self.in_word = true;
+                }
+                None => tracing::debug!(code = self.code, "Unknown Morse code"),
+            }
+            self.code.clear();
+        }
+
+        if self.code.is_empty()
+            && self.in_word
+            && pause >= Duration::from_millis(self.config.word_gap)
+        {
+            keys.push(Key::Space);
+            self.in_word = false;
+        }
+
+        keys
+    }
+}
@@ -0,0 +1,112 @@
+//! Holding movement keys down with the cube, for games moving with WASD.
+//! Turning a face clockwise holds its key down, letting go of the key of any
+//! other face, so the last face turned is the way moved. Turning the face
+//! back or the stop trigger lets go, and the key repeats while held like any
+//! other.
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    config::{Action, Bind, Key, deserialize_trigger, serialize_trigger},
+    cube::{Direction, Face, Move},
+};
+
+/// Faces held as movement keys, e.g.
+/// `(keys: [(face: U, key: w), (face: L, key: a)], stop: "F F'")`.
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, Eq)]
+#[serde(default)]
+pub struct Movement {
+    /// The group of the movement binds.
+    pub group: String,
+    pub keys: Vec<MovementKey>,
+    /// Lets go of every movement key.
+    #[serde(
+        skip_serializing_if = "Vec::is_empty",
+        deserialize_with = "deserialize_trigger",
+        serialize_with = "serialize_trigger"
+    )]
+    pub stop: Vec<Move>,
+}
+
+/// The key held while `face` was the last turned clockwise. Keys can be given
+/// as an alias or a single character, as in actions.
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct MovementKey {
+    pub face: Face,
+    #[serde(deserialize_with = "crate::keys::deserialize_key")]
+    pub key: Key,
+}
+
+impl Default for Movement {
+    /// WASD on U, L, D and R.
+    fn default() -> Self {
+        let keys = [
+            (Face::U, 'w'),
+            (Face::L, 'a'),
+            (Face::D, 's'),
+            (Face::R, 'd'),
+        ]
+        .map(|(face, c)| MovementKey {
+            face,
+            key: Key::Unicode(c),
+        });
+
+        Self {
+            group: "movement".to_string(),
+            keys: keys.to_vec(),
+            stop: Vec::new(),
+        }
+    }
+}
+
+impl Movement {
+    /// Lets go of every movement key but `key`.
+    fn release_others(&self, key: Option<Key>) -> impl Iterator<Item = Action> {
+        self.keys
+            .iter()
+            .filter(move |other| Some(other.key) != key)
+            .map(|other| Action::Release(other.key))
+    }
+
+    /// A bind holding each face's key and one letting go of it, and one for
+    /// the stop trigger if there is one.
+    pub fn binds(&self) -> Vec<Bind> {
+        let bind = |name: String, trigger: Vec<Move>, actions: Vec<Action>| Bind {
+            name: Some(name),
+            trigger,
+            actions,
+            groups: vec![self.group.clone()],
+            ..Bind::default()
+        };
+
+        let mut binds: Vec<_> = self
+            .keys
+            .iter()
+            .flat_map(|&MovementKey { face, key }| {
+                let hold = Move::new(face, Direction::Clockwise);
+                let release = Move::new(face, Direction::CounterClockwise);
+                let mut actions: Vec<_> = self.release_others(Some(key)).collect();
+                actions.push(Action::Press(key));
+
+                [
+                    bind(format!("Hold {key:?}"), vec![hold], actions),
+                    bind(
+                        format!("Let go of {key:?}"),
+                        vec![release],
+                        vec![Action::Release(key)],
+                    ),
+                ]
+            })
+            .collect();
+
+        if !self.stop.is_empty() {
+            binds.push(bind(
+                "Stop moving".to_string(),
+                self.stop.clone(),
+                self.release_others(None).collect(),
+            ));
+        }
+
+        binds
+    }
+}
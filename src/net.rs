@@ -0,0 +1,92 @@
+//! The cube drawn as an unfolded net for terminals, to check at a glance that
+//! the tracked state matches the cube in hand.
+
+use core::fmt;
+
+use crate::{
+    cube::{CubeState, Face},
+    facelet::FaceletCube,
+};
+
+/// Faces on each row of the net, `None` for blank space.
+const LAYOUT: [[Option<Face>; 4]; 3] = [
+    [None, Some(Face::U), None, None],
+    [Some(Face::L), Some(Face::F), Some(Face::R), Some(Face::B)],
+    [None, Some(Face::D), None, None],
+];
+
+/// Displays as U on top, L F R B across the middle and D below, each sticker
+/// two columns wide.
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub struct Net {
+    facelets: [Face; 54],
+    /// Whether to draw stickers with ANSI colors rather than face letters.
+    color: bool,
+}
+
+impl Net {
+    pub fn new(cube: &FaceletCube) -> Self {
+        Self {
+            facelets: cube.0,
+            color: true,
+        }
+    }
+
+    /// Draws face letters instead of colors, for terminals without color.
+    pub fn plain(self) -> Self {
+        Self {
+            color: false,
+            ..self
+        }
+    }
+
+    fn sticker(&self, f: &mut fmt::Formatter<'_>, face: Face) -> fmt::Result {
+        if !self.color {
+            return write!(f, "{face} ");
+        }
+
+        // 256 color palette indices in the usual color scheme.
+        let color = match face {
+            Face::U => 231,
+            Face::R => 196,
+            Face::F => 34,
+            Face::D => 226,
+            Face::L => 208,
+            Face::B => 21,
+        };
+        write!(f, "\x1b[48;5;{color}m  \x1b[0m")
+    }
+}
+
+impl From<&CubeState> for Net {
+    fn from(state: &CubeState) -> Self {
+        Self::new(&FaceletCube::from(state))
+    }
+}
+
+impl fmt::Display for Net {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, faces) in LAYOUT.iter().enumerate() {
+            for row in 0..3 {
+                if i > 0 || row > 0 {
+                    writeln!(f)?;
+                }
+
+                let last = faces.iter().rposition(Option::is_some).unwrap_or(0);
+                for face in &faces[..=last] {
+                    match face {
+                        Some(face) => {
+                            let start = *face as usize * 9 + row * 3;
+                            for &sticker in &self.facelets[start..start + 3] {
+                                self.sticker(f, sticker)?;
+                            }
+                        }
+                        None => write!(f, "      ")?,
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
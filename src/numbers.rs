@@ -0,0 +1,169 @@
+//! Entering numbers such as PINs, volume levels or workspace indices, with a
+//! digit on each quarter turn:
+//!
+//! | Move | `R` | `R'` | `U` | `U'` | `F` | `F'` | `L` | `L'` | `B` | `B'` |
+//! |------|-----|------|-----|------|-----|------|-----|------|-----|------|
+//! | Digit | 1  | 2    | 3   | 4    | 5   | 6    | 7   | 8    | 9   | 0    |
+//!
+//! `D` confirms the number and `D'` erases the last digit, unless set
+//! otherwise. The digits are either typed as they are entered, or collected
+//! into a value for the actions run on confirming.
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    config::{Action, Bind, Key, deserialize_trigger, serialize_trigger},
+    cube::Move,
+};
+
+/// The move entering each digit.
+pub const DIGITS: [(Move, u8); 10] = [
+    (Move::R, 1),
+    (Move::Rp, 2),
+    (Move::U, 3),
+    (Move::Up, 4),
+    (Move::F, 5),
+    (Move::Fp, 6),
+    (Move::L, 7),
+    (Move::Lp, 8),
+    (Move::B, 9),
+    (Move::Bp, 0),
+];
+
+/// Replaced by the entered number in the commands run on confirming.
+pub const VALUE: &str = "{value}";
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct NumberEntry {
+    /// The group of the digit binds.
+    #[serde(default = "default_group")]
+    pub group: String,
+    /// A trigger starting number entry, which ends again on confirming.
+    /// Number entry is always on otherwise.
+    #[serde(
+        default,
+        deserialize_with = "deserialize_toggle",
+        serialize_with = "serialize_toggle",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub toggle: Option<Vec<Move>>,
+    #[serde(
+        default = "default_confirm",
+        deserialize_with = "deserialize_trigger",
+        serialize_with = "serialize_trigger"
+    )]
+    pub confirm: Vec<Move>,
+    #[serde(
+        default = "default_erase",
+        deserialize_with = "deserialize_trigger",
+        serialize_with = "serialize_trigger"
+    )]
+    pub erase: Vec<Move>,
+    /// Actions run with the number on confirming, with `{value}` in `Run`
+    /// commands replaced by it, e.g. `[Run("pactl set-sink-volume 0 {value}%")]`.
+    /// When empty the digits are typed as keys, and confirming presses enter.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub on_confirm: Vec<Action>,
+}
+
+fn default_group() -> String {
+    "numbers".to_string()
+}
+
+fn default_confirm() -> Vec<Move> {
+    vec![Move::D]
+}
+
+fn default_erase() -> Vec<Move> {
+    vec![Move::Dp]
+}
+
+fn deserialize_toggle<'de, D: serde::Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Option<Vec<Move>>, D::Error> {
+    deserialize_trigger(deserializer).map(Some)
+}
+
+fn serialize_toggle<S: serde::Serializer>(
+    toggle: &Option<Vec<Move>>,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    serialize_trigger(toggle.as_deref().unwrap_or_default(), serializer)
+}
+
+impl NumberEntry {
+    /// Whether the digits are collected into a value rather than typed.
+    pub fn collects(&self) -> bool {
+        !self.on_confirm.is_empty()
+    }
+
+    /// A bind for each digit, confirming and erasing, and one for the toggle
+    /// if set.
+    pub fn binds(&self) -> Vec<Bind> {
+        let bind = |name: String, trigger: Vec<Move>, actions: Vec<Action>| Bind {
+            name: Some(name),
+            trigger,
+            actions,
+            groups: vec![self.group.clone()],
+            ..Bind::default()
+        };
+
+        let mut binds: Vec<Bind> = DIGITS
+            .iter()
+            .map(|&(m, digit)| {
+                let action = if self.collects() {
+                    Action::Digit(digit)
+                } else {
+                    Action::Click(Key::Unicode(char::from(b'0' + digit)))
+                };
+                bind(format!("Digit {digit}"), vec![m], vec![action])
+            })
+            .collect();
+
+        let (erase, mut confirm) = if self.collects() {
+            (Action::EraseDigit, vec![Action::ConfirmNumber])
+        } else {
+            (
+                Action::Click(Key::Backspace),
+                vec![Action::Click(Key::Return)],
+            )
+        };
+
+        if self.toggle.is_some() {
+            confirm.push(Action::DisableGroup(self.group.clone()));
+        }
+
+        binds.push(bind(
+            "Erase digit".to_string(),
+            self.erase.clone(),
+            vec![erase],
+        ));
+        binds.push(bind(
+            "Confirm number".to_string(),
+            self.confirm.clone(),
+            confirm,
+        ));
+
+        if let Some(toggle) = &self.toggle {
+            binds.push(Bind {
+                name: Some(format!("Start {}", self.group)),
+                trigger: toggle.clone(),
+                actions: vec![Action::EnableGroup(self.group.clone())],
+                ..Bind::default()
+            });
+        }
+
+        binds
+    }
+
+    /// The actions run on confirming `value`.
+    pub fn confirm_actions(&self, value: &str) -> Vec<Action> {
+        self.on_confirm
+            .iter()
+            .map(|action| match action {
+                Action::Run(command) => Action::Run(command.replace(VALUE, value)),
+                action => action.clone(),
+            })
+            .collect()
+    }
+}
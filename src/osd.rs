@@ -0,0 +1,107 @@
+//! Desktop notifications flashing each bind as it fires along with the moves
+//! that fired it, for feedback while a game has focus.
+
+use std::time::Duration;
+
+use tokio::{process::Command, select, sync::broadcast};
+use tokio_util::sync::CancellationToken;
+use tracing::{debug, warn};
+
+use crate::{
+    algorithm::Algorithm,
+    metrics::{self, Channel},
+    state_machine::FiredBind,
+};
+
+/// How long each notification stays up, where the platform lets it be set.
+pub const SHOW_FOR: Duration = Duration::from_millis(1500);
+
+/// Shows a notification for each bind in `fired` until `cancel` is cancelled.
+pub async fn show_fired(mut fired: broadcast::Receiver<FiredBind>, cancel: CancellationToken) {
+    loop {
+        let fired = select! {
+            fired = fired.recv() => fired,
+            _ = cancel.cancelled() => return,
+        };
+
+        let fired = match fired {
+            Ok(fired) => fired,
+            Err(broadcast::error::RecvError::Lagged(count)) => {
+                metrics::channel_drops(Channel::FiredBinds, count);
+                continue;
+            }
+            Err(broadcast::error::RecvError::Closed) => return,
+        };
+
+        let title = fired.bind.label();
+        let body = Algorithm::from(fired.prefix).to_string();
+
+        // Not awaited, so a slow notification daemon does not hold back the
+        // next bind.
+        match notify_command(&title, &body).spawn() {
+            Ok(mut child) => {
+                tokio::spawn(async move {
+                    if let Err(error) = child.wait().await {
+                        debug!(%error, "Notification failed");
+                    }
+                });
+            }
+            Err(e) => warn!("Could not show a notification: {e}"),
+        }
+    }
+}
+
+/// Replaces the previous notification rather than stacking them.
+#[cfg(not(any(target_os = "macos", target_os = "windows")))]
+fn notify_command(title: &str, body: &str) -> Command {
+    let mut command = Command::new("notify-send");
+    command
+        .args(["--app-name", "triplicata", "--expire-time"])
+        .arg(SHOW_FOR.as_millis().to_string())
+        .args([
+            "--hint",
+            "string:x-canonical-private-synchronous:triplicata",
+        ])
+        .arg(title)
+        .arg(body);
+    command
+}
+
+/// The text is passed through the environment so it is never parsed as
+/// AppleScript.
+#[cfg(target_os = "macos")]
+fn notify_command(title: &str, body: &str) -> Command {
+    let mut command = Command::new("osascript");
+    command
+        .args([
+            "-e",
+            "display notification (system attribute \"TRIPLICATA_BODY\") \
+             with title (system attribute \"TRIPLICATA_TITLE\")",
+        ])
+        .env("TRIPLICATA_TITLE", title)
+        .env("TRIPLICATA_BODY", body);
+    command
+}
+
+/// The text is passed through the environment so it is never parsed as
+/// PowerShell.
+#[cfg(target_os = "windows")]
+fn notify_command(title: &str, body: &str) -> Command {
+    let script = format!(
+        "Add-Type -AssemblyName System.Windows.Forms; \
+         $n = New-Object System.Windows.Forms.NotifyIcon; \
+         $n.Icon = [System.Drawing.SystemIcons]::Information; \
+         $n.Visible = $true; \
+         $n.ShowBalloonTip({ms}, $env:TRIPLICATA_TITLE, $env:TRIPLICATA_BODY, 'None'); \
+         Start-Sleep -Milliseconds {ms}; \
+         $n.Dispose()",
+        ms = SHOW_FOR.as_millis()
+    );
+
+    let mut command = Command::new("powershell");
+    command
+        .args(["-NoProfile", "-WindowStyle", "Hidden", "-Command", &script])
+        .env("TRIPLICATA_TITLE", title)
+        .env("TRIPLICATA_BODY", body);
+    command
+}
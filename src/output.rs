@@ -0,0 +1,323 @@
+use std::{
+    collections::HashSet,
+    panic,
+    process::Command,
+    sync::{Arc, Mutex, MutexGuard, Once, PoisonError, Weak},
+    thread::sleep,
+    time::Duration,
+};
+
+use enigo::{Axis, Direction, Enigo, Key, Keyboard, Mouse, Settings};
+
+use crate::{config::Action, error::OutputError, switches::Switches};
+#[cfg(feature = "hue")]
+use crate::{error::HueError, hue::HueBridge};
+#[cfg(feature = "spotify")]
+use crate::{error::SpotifyError, spotify::SpotifyClient};
+#[cfg(feature = "twitch")]
+use crate::{error::TwitchError, twitch::TwitchClient};
+#[cfg(all(feature = "evdev", target_os = "linux"))]
+use crate::{switches::VirtualSwitches, uinput::VirtualKeyboard};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum Held {
+    Key(Key),
+    Scancode(u16),
+}
+
+#[derive(Debug, Default)]
+struct HeldKeys {
+    keys: HashSet<Held>,
+    /// Whether they are held on the virtual keyboard, which the panic hook
+    /// cannot reach. Destroying the device releases them instead.
+    on_device: bool,
+}
+
+/// The keys held by the output last set to release them on panic. The hook is
+/// installed once, and outputs rebuilt on reload take the place of the last.
+static PANIC_HELD: Mutex<Weak<Mutex<HeldKeys>>> = Mutex::new(Weak::new());
+static PANIC_HOOK: Once = Once::new();
+
+pub trait OutputBackend {
+    fn execute(&mut self, action: Action) -> Result<(), OutputError>;
+}
+
+pub struct EnigoOutput {
+    enigo: Enigo,
+    /// Keys pressed by a `Press` or `PressScancode` and not released yet.
+    held: Arc<Mutex<HeldKeys>>,
+    /// Whether `Unicode` clicks are typed as text.
+    type_unicode: bool,
+    /// Types keys through this instead of enigo when set.
+    #[cfg(all(feature = "evdev", target_os = "linux"))]
+    keyboard: Option<VirtualKeyboard>,
+    switches: Option<Switches>,
+    /// Presses switches through this instead of keys when set.
+    #[cfg(all(feature = "evdev", target_os = "linux"))]
+    switch_device: Option<VirtualSwitches>,
+    #[cfg(feature = "hue")]
+    hue: Option<HueBridge>,
+    #[cfg(feature = "spotify")]
+    spotify: Option<SpotifyClient>,
+    #[cfg(feature = "twitch")]
+    twitch: Option<TwitchClient>,
+}
+
+impl EnigoOutput {
+    pub fn new() -> Result<Self, OutputError> {
+        Ok(Self {
+            enigo: Enigo::new(&Settings::default())?,
+            held: Arc::default(),
+            type_unicode: false,
+            #[cfg(all(feature = "evdev", target_os = "linux"))]
+            keyboard: None,
+            switches: None,
+            #[cfg(all(feature = "evdev", target_os = "linux"))]
+            switch_device: None,
+            #[cfg(feature = "hue")]
+            hue: None,
+            #[cfg(feature = "spotify")]
+            spotify: None,
+            #[cfg(feature = "twitch")]
+            twitch: None,
+        })
+    }
+
+    /// Releases held keys if any thread panics, so a crash halfway through a
+    /// bind never leaves a key stuck down. The previous panic hook still
+    /// runs first. Keys held on the virtual keyboard are released by the
+    /// device being destroyed instead, as enigo cannot release them.
+    pub fn release_on_panic(self) -> Self {
+        *PANIC_HELD.lock().unwrap_or_else(PoisonError::into_inner) = Arc::downgrade(&self.held);
+
+        PANIC_HOOK.call_once(|| {
+            let previous = panic::take_hook();
+
+            panic::set_hook(Box::new(move |info| {
+                previous(info);
+
+                // The panicking thread may be the one holding either lock.
+                let Some(held) = PANIC_HELD.try_lock().ok().and_then(|held| held.upgrade()) else {
+                    return;
+                };
+                if let Ok(held) = held.try_lock()
+                    && !held.on_device
+                    && !held.keys.is_empty()
+                    && let Ok(mut enigo) = Enigo::new(&Settings::default())
+                {
+                    release(&mut enigo, &held.keys);
+                }
+            }));
+        });
+
+        self
+    }
+
+    /// Types `Unicode` clicks as text, which goes through the active keyboard
+    /// layout and so gives the character asked for on any layout.
+    pub fn type_unicode(mut self, type_unicode: bool) -> Self {
+        self.type_unicode = type_unicode;
+        self
+    }
+
+    /// Types keys through a virtual evdev keyboard, which remappers and games
+    /// reading evdev see as a real one. `Unicode` clicks are then never typed
+    /// as text.
+    #[cfg(all(feature = "evdev", target_os = "linux"))]
+    pub fn evdev(mut self, keyboard: Option<VirtualKeyboard>) -> Self {
+        self.held().on_device = keyboard.is_some();
+        self.keyboard = keyboard;
+        self
+    }
+
+    pub fn switches(mut self, switches: Option<Switches>) -> Self {
+        self.switches = switches;
+        self
+    }
+
+    /// Presses switches as buttons of a virtual joystick rather than keys.
+    #[cfg(all(feature = "evdev", target_os = "linux"))]
+    pub fn switch_device(mut self, device: Option<VirtualSwitches>) -> Self {
+        self.switch_device = device;
+        self
+    }
+
+    fn switch(&mut self, switch: u8) -> Result<(), OutputError> {
+        let switches = self
+            .switches
+            .as_ref()
+            .ok_or(OutputError::NoSwitch(switch))?;
+        let hold = switches.hold();
+
+        #[cfg(all(feature = "evdev", target_os = "linux"))]
+        if let Some(device) = &mut self.switch_device {
+            return device.press(switch, hold);
+        }
+
+        let key = switches.key(switch)?;
+        self.key(key, Direction::Press)?;
+        sleep(hold);
+        self.key(key, Direction::Release)
+    }
+
+    fn scancode(&mut self, code: u16, direction: Direction) -> Result<(), OutputError> {
+        #[cfg(all(feature = "evdev", target_os = "linux"))]
+        if let Some(keyboard) = &mut self.keyboard {
+            return keyboard.scancode(code, direction);
+        }
+
+        self.enigo.raw(code, direction)?;
+        Ok(())
+    }
+
+    fn key(&mut self, key: Key, direction: Direction) -> Result<(), OutputError> {
+        #[cfg(all(feature = "evdev", target_os = "linux"))]
+        if let Some(keyboard) = &mut self.keyboard {
+            return keyboard.key(key, direction);
+        }
+
+        self.enigo.key(key, direction)?;
+        Ok(())
+    }
+
+    /// The held keys, still kept track of after a panic while pressing one.
+    fn held(&self) -> MutexGuard<'_, HeldKeys> {
+        self.held.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Releases every held key, carrying on past keys that fail to release.
+    fn release_all(&mut self) {
+        let held = std::mem::take(&mut self.held().keys);
+        for held in held {
+            let _ = match held {
+                Held::Key(key) => self.key(key, Direction::Release),
+                Held::Scancode(code) => self.scancode(code, Direction::Release),
+            };
+        }
+    }
+
+    fn types_text(&self) -> bool {
+        #[cfg(all(feature = "evdev", target_os = "linux"))]
+        if self.keyboard.is_some() {
+            return false;
+        }
+
+        self.type_unicode
+    }
+
+    #[cfg(feature = "hue")]
+    pub fn hue(mut self, hue: Option<HueBridge>) -> Self {
+        self.hue = hue;
+        self
+    }
+
+    #[cfg(feature = "spotify")]
+    pub fn spotify(mut self, spotify: Option<SpotifyClient>) -> Self {
+        self.spotify = spotify;
+        self
+    }
+
+    #[cfg(feature = "twitch")]
+    pub fn twitch(mut self, twitch: Option<TwitchClient>) -> Self {
+        self.twitch = twitch;
+        self
+    }
+}
+
+impl OutputBackend for EnigoOutput {
+    fn execute(&mut self, action: Action) -> Result<(), OutputError> {
+        match action {
+            Action::Press(key) => {
+                self.key(key, Direction::Press)?;
+                self.held().keys.insert(Held::Key(key));
+            }
+            Action::Release(key) => {
+                self.key(key, Direction::Release)?;
+                self.held().keys.remove(&Held::Key(key));
+            }
+            Action::Click(Key::Unicode(c)) if self.types_text() => {
+                self.enigo.text(c.encode_utf8(&mut [0; 4]))?
+            }
+            Action::Click(key) => self.key(key, Direction::Click)?,
+            Action::PressScancode(code) => {
+                self.scancode(code, Direction::Press)?;
+                self.held().keys.insert(Held::Scancode(code));
+            }
+            Action::ReleaseScancode(code) => {
+                self.scancode(code, Direction::Release)?;
+                self.held().keys.remove(&Held::Scancode(code));
+            }
+            Action::ClickScancode(code) => self.scancode(code, Direction::Click)?,
+            Action::ReleaseAll => self.release_all(),
+            Action::Delay(delay) => sleep(Duration::from_millis(delay)),
+            Action::Scroll(lines) => self.enigo.scroll(lines, Axis::Vertical)?,
+            Action::Switch(switch) => self.switch(switch)?,
+            Action::Run(command) => {
+                let mut child = shell(&command).spawn()?;
+                // Reaped in the background so the bind does not wait for it.
+                std::thread::spawn(move || child.wait());
+            }
+            // Groups and numbers are handled by the state machine.
+            Action::EnableGroup(_)
+            | Action::DisableGroup(_)
+            | Action::ToggleGroup(_)
+            | Action::Digit(_)
+            | Action::EraseDigit
+            | Action::ConfirmNumber => {}
+            action @ Action::Platform { .. } => {
+                for action in action.resolve() {
+                    self.execute(action)?;
+                }
+            }
+            #[cfg(feature = "hue")]
+            Action::Hue(command) => self
+                .hue
+                .as_ref()
+                .ok_or(HueError::NotConfigured)?
+                .send(&command)?,
+            #[cfg(feature = "spotify")]
+            Action::Spotify(command) => self
+                .spotify
+                .as_mut()
+                .ok_or(SpotifyError::NotConfigured)?
+                .send(&command)?,
+            #[cfg(feature = "twitch")]
+            Action::Twitch(command) => self
+                .twitch
+                .as_mut()
+                .ok_or(TwitchError::NotConfigured)?
+                .send(&command)?,
+        };
+
+        Ok(())
+    }
+}
+
+/// Keys are released when the output stops, whether the pipeline shut down
+/// or a task panicked and dropped it.
+impl Drop for EnigoOutput {
+    fn drop(&mut self) {
+        self.release_all();
+    }
+}
+
+fn release(enigo: &mut Enigo, held: &HashSet<Held>) {
+    for &held in held {
+        let _ = match held {
+            Held::Key(key) => enigo.key(key, Direction::Release),
+            Held::Scancode(code) => enigo.raw(code, Direction::Release),
+        };
+    }
+}
+
+fn shell(command: &str) -> Command {
+    let (shell, flag) = if cfg!(windows) {
+        ("cmd", "/C")
+    } else {
+        ("sh", "-c")
+    };
+
+    let mut shell = Command::new(shell);
+    shell.arg(flag).arg(command);
+    shell
+}
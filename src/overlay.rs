@@ -0,0 +1,248 @@
+//! A page showing the live cube, recent moves and a timer, meant to be added
+//! to OBS as a browser source. The page follows a WebSocket feed at `/events`,
+//! and the same events are served as [`CubingEvent`]s at `/cubing`.
+
+use std::{
+    net::SocketAddr,
+    sync::{Arc, Mutex},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use axum::{
+    Router,
+    extract::{
+        State,
+        ws::{Message, WebSocket, WebSocketUpgrade},
+    },
+    response::{Html, Response},
+    routing::get,
+};
+use futures::{Stream, StreamExt};
+use serde::Serialize;
+use tokio::{net::TcpListener, select, sync::broadcast};
+use tokio_util::sync::CancellationToken;
+use tracing::{info, warn};
+
+use crate::{
+    cube::{CubeEvent, CubeState},
+    cubing::CubingEvent,
+    error::OverlayError,
+    metrics::{self, Channel},
+    protocol::timer::TimerState,
+};
+
+const PAGE: &str = include_str!("overlay.html");
+
+#[derive(Serialize, Debug)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum OverlayMessage {
+    State {
+        facelets: String,
+        solved: bool,
+    },
+    Move {
+        notation: String,
+        facelets: String,
+        solved: bool,
+    },
+    Battery {
+        level: u8,
+    },
+    Timer {
+        state: TimerState,
+        time_ms: Option<u128>,
+    },
+    Connected,
+    Disconnected,
+}
+
+struct Overlay {
+    state: Mutex<CubeState>,
+    messages: broadcast::Sender<String>,
+    cubing: broadcast::Sender<String>,
+    cancel: CancellationToken,
+}
+
+#[derive(Clone, Copy)]
+enum Feed {
+    Overlay,
+    Cubing,
+}
+
+impl OverlayMessage {
+    fn state(state: &CubeState) -> Self {
+        OverlayMessage::State {
+            facelets: facelets(state),
+            solved: state.is_solved(),
+        }
+    }
+}
+
+impl Feed {
+    fn channel(self, overlay: &Overlay) -> &broadcast::Sender<String> {
+        match self {
+            Feed::Overlay => &overlay.messages,
+            Feed::Cubing => &overlay.cubing,
+        }
+    }
+
+    fn snapshot(self, overlay: &Overlay) -> String {
+        let state = *overlay.state.lock().unwrap();
+        match self {
+            Feed::Overlay => to_json(&OverlayMessage::state(&state)),
+            Feed::Cubing => to_json(&CubingEvent::State {
+                state: (&state).into(),
+                time_stamp: time_stamp(),
+            }),
+        }
+    }
+}
+
+fn to_json(value: &impl Serialize) -> String {
+    serde_json::to_string(value).expect("feed messages always serialize")
+}
+
+/// Milliseconds since the UNIX epoch, as cubing.js time stamps are.
+fn time_stamp() -> f64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs_f64()
+        * 1000.0
+}
+
+fn facelets(state: &CubeState) -> String {
+    state
+        .facelets()
+        .iter()
+        .map(|face| face.to_string())
+        .collect()
+}
+
+/// Serves the overlay at `http://{address}/` until `cancel` is cancelled.
+pub async fn serve(
+    address: SocketAddr,
+    events: impl Stream<Item = CubeEvent> + Send + Unpin + 'static,
+    cancel: CancellationToken,
+) -> Result<(), OverlayError> {
+    let listener = TcpListener::bind(address).await?;
+
+    let (messages, _) = broadcast::channel(64);
+    let (cubing, _) = broadcast::channel(64);
+    let overlay = Arc::new(Overlay {
+        state: Mutex::new(CubeState::SOLVED),
+        messages,
+        cubing,
+        cancel: cancel.clone(),
+    });
+
+    tokio::spawn(relay(events, overlay.clone()));
+
+    let app = Router::new()
+        .route("/", get(|| async { Html(PAGE) }))
+        .route(
+            "/events",
+            get(|ws, state| upgrade(ws, state, Feed::Overlay)),
+        )
+        .route("/cubing", get(|ws, state| upgrade(ws, state, Feed::Cubing)))
+        .with_state(overlay);
+
+    info!("Serving overlay on http://{address}/");
+
+    axum::serve(listener, app)
+        .with_graceful_shutdown(cancel.cancelled_owned())
+        .await?;
+
+    Ok(())
+}
+
+async fn relay(mut events: impl Stream<Item = CubeEvent> + Unpin, overlay: Arc<Overlay>) {
+    loop {
+        let event = select! {
+            event = events.next() => event,
+            _ = overlay.cancel.cancelled() => break,
+        };
+
+        let Some(event) = event else {
+            break;
+        };
+
+        let (message, cubing) = {
+            let mut state = overlay.state.lock().unwrap();
+            let cubing = |state: &CubeState| CubingEvent::new(&event, state, time_stamp());
+            match event {
+                CubeEvent::Move(m) => {
+                    state.apply(m);
+                    let message = OverlayMessage::Move {
+                        notation: m.to_notation(),
+                        facelets: facelets(&state),
+                        solved: state.is_solved(),
+                    };
+                    (Some(message), cubing(&state))
+                }
+                CubeEvent::StateSync(synced) => {
+                    *state = synced;
+                    (Some(OverlayMessage::state(&state)), cubing(&state))
+                }
+                CubeEvent::Battery(level) => (Some(OverlayMessage::Battery { level }), None),
+                CubeEvent::Orientation(_) => (None, cubing(&state)),
+                CubeEvent::Connected => (Some(OverlayMessage::Connected), None),
+                CubeEvent::Disconnected => (Some(OverlayMessage::Disconnected), None),
+                CubeEvent::Timer(timer) => {
+                    let message = OverlayMessage::Timer {
+                        state: timer.state,
+                        time_ms: timer.time.map(|time| time.as_millis()),
+                    };
+                    (Some(message), None)
+                }
+                CubeEvent::Lagged(_)
+                | CubeEvent::Redemption(_)
+                | CubeEvent::Profile(_)
+                | CubeEvent::Fire(_)
+                | CubeEvent::Received(_) => continue,
+            }
+        };
+
+        if let Some(message) = message {
+            let _ = overlay.messages.send(to_json(&message));
+        }
+        if let Some(cubing) = cubing {
+            let _ = overlay.cubing.send(to_json(&cubing));
+        }
+    }
+}
+
+async fn upgrade(
+    ws: WebSocketUpgrade,
+    State(overlay): State<Arc<Overlay>>,
+    feed: Feed,
+) -> Response {
+    ws.on_upgrade(move |socket| follow(socket, overlay, feed))
+}
+
+async fn follow(mut socket: WebSocket, overlay: Arc<Overlay>, feed: Feed) {
+    let mut messages = feed.channel(&overlay).subscribe();
+    let mut pending = Some(feed.snapshot(&overlay));
+
+    loop {
+        let message = match pending.take() {
+            Some(message) => message,
+            None => select! {
+                message = messages.recv() => match message {
+                    Ok(message) => message,
+                    Err(broadcast::error::RecvError::Lagged(count)) => {
+                        metrics::channel_drops(Channel::Overlay, count);
+                        warn!("Overlay client missed {count} events, resending state");
+                        feed.snapshot(&overlay)
+                    }
+                    Err(broadcast::error::RecvError::Closed) => break,
+                },
+                _ = overlay.cancel.cancelled() => break,
+            },
+        };
+
+        if socket.send(Message::Text(message.into())).await.is_err() {
+            break;
+        }
+    }
+}
@@ -0,0 +1,100 @@
+//! Bind packs, a self-contained file of binds with a little metadata, so
+//! gesture sets can be shared without merging config files by hand.
+
+use std::{fs, path::Path, str::FromStr};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    config::{Bind, Config, to_ron},
+    error::ConfigError,
+};
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct BindPack {
+    /// The pack format version, bumped on incompatible changes.
+    pub version: u32,
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub author: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    pub binds: Vec<Bind>,
+}
+
+/// What [`BindPack::merge_into`] did with the pack's binds.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct MergeSummary {
+    pub added: usize,
+    pub replaced: usize,
+    /// Binds left out because the config already binds their trigger.
+    pub skipped: usize,
+}
+
+impl BindPack {
+    pub const VERSION: u32 = 1;
+
+    pub fn new(name: impl Into<String>, binds: Vec<Bind>) -> Self {
+        Self {
+            version: Self::VERSION,
+            name: name.into(),
+            author: None,
+            description: None,
+            binds,
+        }
+    }
+
+    pub fn author(mut self, author: impl Into<String>) -> Self {
+        self.author = Some(author.into());
+        self
+    }
+
+    pub fn description(mut self, description: impl Into<String>) -> Self {
+        self.description = Some(description.into());
+        self
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, ConfigError> {
+        fs::read_to_string(path)?.parse()
+    }
+
+    pub fn to_ron(&self) -> Result<String, ConfigError> {
+        to_ron(self)
+    }
+
+    /// Adds the pack's binds to `config`. Binds whose trigger is already
+    /// bound are skipped, or replace the existing bind if `replace` is set.
+    pub fn merge_into(self, config: &mut Config, replace: bool) -> MergeSummary {
+        let mut summary = MergeSummary::default();
+
+        for bind in self.binds {
+            match config.binds.iter_mut().find(|b| b.trigger == bind.trigger) {
+                Some(existing) if replace => {
+                    *existing = bind;
+                    summary.replaced += 1;
+                }
+                Some(_) => summary.skipped += 1,
+                None => {
+                    config.binds.push(bind);
+                    summary.added += 1;
+                }
+            }
+        }
+
+        summary
+    }
+}
+
+impl FromStr for BindPack {
+    type Err = ConfigError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let pack: BindPack = ron::from_str(s)?;
+
+        if pack.version > Self::VERSION {
+            return Err(ConfigError::UnsupportedPackVersion(pack.version));
+        }
+
+        Ok(pack)
+    }
+}
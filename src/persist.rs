@@ -0,0 +1,230 @@
+//! The state of each cube saved across sessions, so a scrambled cube whose
+//! firmware forgets its state while asleep does not have to be solved before
+//! its state is known again, along with its calibrated orientation.
+
+use std::{
+    collections::HashMap,
+    fs,
+    path::PathBuf,
+    time::{Duration, Instant},
+};
+
+use serde::{Deserialize, Serialize};
+use tracing::{info, warn};
+
+use crate::{
+    calibration::Calibrator,
+    cube::{CubeEvent, CubeState, Quaternion},
+    error::PersistError,
+};
+
+/// The least time between two saves while the cube is connected.
+const SAVE_INTERVAL: Duration = Duration::from_secs(5);
+
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq)]
+pub struct SavedCube {
+    pub state: CubeState,
+    /// The cube's move counter when the state was saved.
+    pub move_count: u8,
+    /// The cube's neutral orientation, if it was calibrated.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reference: Option<Quaternion>,
+}
+
+/// A JSON file holding the saved state of each cube by its device id.
+#[derive(Debug, Clone)]
+pub struct StateStore {
+    path: PathBuf,
+    /// Calibrates each cube's orientation, starting from its saved one.
+    calibrator: Option<Calibrator>,
+}
+
+impl StateStore {
+    pub fn new(path: PathBuf) -> Self {
+        Self {
+            path,
+            calibrator: None,
+        }
+    }
+
+    /// Calibrates each cube's orientation with `calibrator`, saving it with
+    /// the cube's state.
+    pub fn calibrate(mut self, calibrator: Option<Calibrator>) -> Self {
+        self.calibrator = calibrator;
+        self
+    }
+
+    fn read(&self) -> Result<HashMap<String, SavedCube>, PersistError> {
+        match fs::read(&self.path) {
+            Ok(bytes) => Ok(serde_json::from_slice(&bytes)?),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(HashMap::new()),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    pub fn load(&self, id: &str) -> Result<Option<SavedCube>, PersistError> {
+        Ok(self.read()?.remove(id))
+    }
+
+    pub fn save(&self, id: &str, saved: SavedCube) -> Result<(), PersistError> {
+        let mut cubes = self.read()?;
+        cubes.insert(id.to_string(), saved);
+        fs::write(&self.path, serde_json::to_vec_pretty(&cubes)?)?;
+        Ok(())
+    }
+}
+
+/// Tracks one connection to a cube, restoring its saved state if the state it
+/// reports on connecting is stale, and saving it again when asked.
+#[derive(Debug)]
+pub struct StateKeeper {
+    store: StateStore,
+    id: String,
+    saved: Option<SavedCube>,
+    tracked: Option<CubeState>,
+    calibrator: Option<Calibrator>,
+    reference: Option<Quaternion>,
+    /// What calibration times are counted from.
+    started: Instant,
+    dirty: bool,
+    /// When the state was last saved, and the move counter then.
+    last_saved: Option<(Instant, u8)>,
+}
+
+/// A save of a cube's state taken from a [`StateKeeper`], to be written off
+/// the async tasks.
+#[derive(Debug)]
+pub struct PendingSave {
+    store: StateStore,
+    id: String,
+    saved: SavedCube,
+}
+
+impl PendingSave {
+    pub fn write(self) {
+        if let Err(e) = self.store.save(&self.id, self.saved) {
+            warn!("Could not save the cube state: {e}");
+        }
+    }
+}
+
+impl StateKeeper {
+    pub fn new(store: StateStore, id: String) -> Self {
+        let saved = store.load(&id).unwrap_or_else(|e| {
+            warn!("Could not load the saved cube state: {e}");
+            None
+        });
+
+        let reference = saved.and_then(|saved| saved.reference);
+        let calibrator = store.calibrator.clone().map(|mut calibrator| {
+            calibrator.set_reference(reference);
+            calibrator
+        });
+
+        Self {
+            store,
+            id,
+            saved,
+            tracked: None,
+            calibrator,
+            reference,
+            started: Instant::now(),
+            dirty: false,
+            last_saved: None,
+        }
+    }
+
+    /// Follows `event`, given the cube's move counter as of it. The cube's
+    /// first state report is replaced by the saved state when the counter
+    /// shows no moves were made since it was saved, as the cube then only
+    /// disagrees because it forgot. Orientations are made relative to the
+    /// calibrated one.
+    pub fn event(&mut self, event: CubeEvent, move_count: Option<u8>) -> CubeEvent {
+        let event = match &mut self.calibrator {
+            Some(calibrator) => {
+                let event = calibrator.push(event, self.started.elapsed());
+                if calibrator.reference() != self.reference {
+                    self.reference = calibrator.reference();
+                    self.dirty = true;
+                }
+                event
+            }
+            None => event,
+        };
+
+        match event {
+            CubeEvent::StateSync(reported) => {
+                let state = match self.saved.take() {
+                    Some(saved) if Some(saved.move_count) == move_count => {
+                        if saved.state != reported {
+                            info!("Restoring the saved state of the cube");
+                        }
+                        saved.state
+                    }
+                    _ => reported,
+                };
+                self.tracked = Some(state);
+                self.dirty = true;
+                CubeEvent::StateSync(state)
+            }
+            CubeEvent::Move(m) => {
+                if let Some(state) = &mut self.tracked {
+                    state.apply(m);
+                    self.dirty = true;
+                }
+                event
+            }
+            _ => event,
+        }
+    }
+
+    /// The tracked state, if it changed since it was last saved.
+    fn unsaved(&self, move_count: Option<u8>) -> Option<SavedCube> {
+        let (Some(state), Some(move_count)) = (self.tracked, move_count) else {
+            return None;
+        };
+
+        self.dirty.then_some(SavedCube {
+            state,
+            move_count,
+            reference: self.reference,
+        })
+    }
+
+    /// Saves the tracked state if it changed since it was last saved.
+    pub fn save(&mut self, move_count: Option<u8>) {
+        let Some(saved) = self.unsaved(move_count) else {
+            return;
+        };
+
+        match self.store.save(&self.id, saved) {
+            Ok(()) => {
+                self.dirty = false;
+                self.last_saved = Some((Instant::now(), saved.move_count));
+            }
+            Err(e) => warn!("Could not save the cube state: {e}"),
+        }
+    }
+
+    /// The tracked state to save while the cube is still connected, if it
+    /// was moved since it was last saved, at most once every few seconds.
+    /// It counts as saved once taken, so a failed write is only retried
+    /// once the cube changes again.
+    pub fn pending(&mut self, move_count: Option<u8>) -> Option<PendingSave> {
+        let saved = self.unsaved(move_count)?;
+
+        if let Some((at, count)) = self.last_saved
+            && (count == saved.move_count || at.elapsed() < SAVE_INTERVAL)
+        {
+            return None;
+        }
+
+        self.dirty = false;
+        self.last_saved = Some((Instant::now(), saved.move_count));
+        Some(PendingSave {
+            store: self.store.clone(),
+            id: self.id.clone(),
+            saved,
+        })
+    }
+}
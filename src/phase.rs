@@ -0,0 +1,58 @@
+//! Solve phases for multi-phase splits, ended by a move count, a time into
+//! the solve or a point reached on the cube.
+
+use alloc::string::String;
+use core::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{cube::Face, facelet::FaceletCube};
+
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct Phase {
+    pub name: String,
+    pub end: PhaseEnd,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PhaseEnd {
+    /// After this many moves into the phase.
+    Moves(usize),
+    /// With the first move made this many milliseconds into the solve.
+    Time(u64),
+    Milestone(Milestone),
+}
+
+/// Points in a layer by layer solve, reached on any face.
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Milestone {
+    Cross,
+    F2l,
+    Oll,
+    Solved,
+}
+
+impl PhaseEnd {
+    /// Whether the phase is over with `cube`, after `moves` moves into the
+    /// phase and `elapsed` into the solve.
+    pub fn reached(&self, cube: &FaceletCube, moves: usize, elapsed: Duration) -> bool {
+        match *self {
+            PhaseEnd::Moves(count) => moves >= count,
+            PhaseEnd::Time(millis) => elapsed >= Duration::from_millis(millis),
+            PhaseEnd::Milestone(milestone) => milestone.reached(cube),
+        }
+    }
+}
+
+impl Milestone {
+    pub fn reached(&self, cube: &FaceletCube) -> bool {
+        let mut faces = Face::ALL.into_iter();
+
+        match self {
+            Milestone::Cross => faces.any(|face| cube.cross_solved(face)),
+            Milestone::F2l => faces.any(|face| cube.solved_below(face)),
+            Milestone::Oll => faces.any(|face| cube.solved_below(face) && cube.face_solved(face)),
+            Milestone::Solved => faces.all(|face| cube.face_solved(face)),
+        }
+    }
+}
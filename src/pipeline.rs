@@ -0,0 +1,373 @@
+use std::{
+    sync::{Arc, Mutex},
+    time::Instant,
+};
+
+use futures::StreamExt;
+use tokio::{
+    select,
+    sync::{broadcast, mpsc},
+    task::JoinHandle,
+};
+use tokio_util::sync::CancellationToken;
+use tracing::{debug, error, info, info_span, warn};
+
+use crate::{
+    algorithm::Algorithm,
+    config::{Action, Config},
+    cube::{CubeEvent, Move},
+    error::{ConfigError, CubeError, Error, NotationError},
+    metrics::{self, Channel, Stage},
+    modifiers::{Modifiers, Typing},
+    output::OutputBackend,
+    smoothing::OrientationFilter,
+    source::{CubeEventStream, CubeSource},
+    state_machine::{FiredBind, KillSwitch, OutputQueue, Pause, StateMachine},
+};
+
+/// When the latest move reached the state machine, and when it was received
+/// from the cube, which is the same for injected moves.
+#[derive(Debug, Clone, Copy)]
+struct MoveTimes {
+    reached: Instant,
+    received: Instant,
+}
+
+impl MoveTimes {
+    fn now() -> Self {
+        let now = Instant::now();
+        Self {
+            reached: now,
+            received: now,
+        }
+    }
+}
+
+pub struct Triplicata {
+    events: broadcast::Sender<CubeEvent>,
+    actions: broadcast::Sender<Action>,
+    fired: broadcast::Sender<FiredBind>,
+    pause: Pause,
+    kill_switch: KillSwitch,
+    modifiers: Modifiers,
+    typing: Typing,
+    cancel: CancellationToken,
+    source: Option<JoinHandle<Result<(), CubeError>>>,
+    state_machine: Option<JoinHandle<()>>,
+    output: Option<JoinHandle<()>>,
+}
+
+/// Feeds events into a running pipeline as if they came from the cube.
+#[derive(Clone, Debug)]
+pub struct MoveInjector {
+    events: broadcast::Sender<CubeEvent>,
+}
+
+pub struct TriplicataBuilder<S> {
+    config: Option<Config>,
+    source: S,
+    output: Option<Box<dyn OutputBackend + Send>>,
+}
+
+impl Triplicata {
+    pub fn builder() -> TriplicataBuilder<()> {
+        TriplicataBuilder {
+            config: None,
+            source: (),
+            output: None,
+        }
+    }
+
+    pub fn events(&self) -> broadcast::Receiver<CubeEvent> {
+        self.events.subscribe()
+    }
+
+    pub fn event_stream(&self) -> CubeEventStream {
+        self.events.subscribe().into()
+    }
+
+    pub fn injector(&self) -> MoveInjector {
+        MoveInjector {
+            events: self.events.clone(),
+        }
+    }
+
+    pub fn actions(&self) -> broadcast::Receiver<Action> {
+        self.actions.subscribe()
+    }
+
+    pub fn fired_binds(&self) -> broadcast::Receiver<FiredBind> {
+        self.fired.subscribe()
+    }
+
+    /// Pauses and resumes the binds.
+    pub fn pause(&self) -> Pause {
+        self.pause.clone()
+    }
+
+    /// Releases held keys and pauses the binds when pulled.
+    pub fn kill_switch(&self) -> KillSwitch {
+        self.kill_switch.clone()
+    }
+
+    /// The keyboard modifiers binds check, to be kept up to date.
+    pub fn modifiers(&self) -> Modifiers {
+        self.modifiers.clone()
+    }
+
+    /// When the keyboards were last typed on, to be kept up to date.
+    pub fn typing(&self) -> Typing {
+        self.typing.clone()
+    }
+
+    /// Resolves once the cube connection ends or a task of the pipeline stops
+    /// without [`Triplicata::shutdown`] being called, with the reason. A
+    /// stopped task is [`Error::TaskStopped`], after which the pipeline is
+    /// only half alive and is best shut down and built again. Only resolves
+    /// once.
+    pub async fn closed(&mut self) -> Result<(), Error> {
+        let (Some(source), Some(state_machine), Some(output)) =
+            (&mut self.source, &mut self.state_machine, &mut self.output)
+        else {
+            return std::future::pending().await;
+        };
+
+        // The state machine and output can end right after the source when
+        // the cube disconnects, which is to be reported as the disconnect.
+        let stopped = select! {
+            biased;
+            result = source => {
+                self.source = None;
+                match result {
+                    Ok(result) => return Ok(result?),
+                    Err(e) => {
+                        error!("Cube task failed: {e}");
+                        "cube"
+                    }
+                }
+            }
+            result = state_machine => {
+                self.state_machine = None;
+                if let Err(e) = result {
+                    error!("State machine task failed: {e}");
+                }
+                "state machine"
+            }
+            result = output => {
+                self.output = None;
+                if let Err(e) = result {
+                    error!("Output task failed: {e}");
+                }
+                "output"
+            }
+        };
+
+        Err(Error::TaskStopped(stopped))
+    }
+
+    /// Disconnects the cube and waits for every task to finish, after which a
+    /// new pipeline can be built.
+    pub async fn shutdown(self) {
+        self.cancel.cancel();
+        if let Some(source) = self.source {
+            let _ = source.await;
+        }
+        for task in [self.state_machine, self.output].into_iter().flatten() {
+            let _ = task.await;
+        }
+
+        info!("Shut down");
+    }
+}
+
+impl MoveInjector {
+    pub fn inject(&self, m: Move) {
+        self.inject_event(CubeEvent::Move(m));
+    }
+
+    pub fn inject_event(&self, event: CubeEvent) {
+        metrics::channel_sent(Channel::EventStream);
+        let _ = self.events.send(event);
+    }
+
+    /// Follows the events of the pipeline, both the cube's and injected ones.
+    pub fn subscribe(&self) -> broadcast::Receiver<CubeEvent> {
+        self.events.subscribe()
+    }
+
+    pub fn inject_algorithm(&self, algorithm: &Algorithm) -> Result<(), NotationError> {
+        for m in algorithm.to_moves()? {
+            self.inject(m);
+        }
+
+        Ok(())
+    }
+}
+
+impl<S> TriplicataBuilder<S> {
+    pub fn config(mut self, config: Config) -> Self {
+        self.config = Some(config);
+        self
+    }
+
+    pub fn source<T: CubeSource>(self, source: T) -> TriplicataBuilder<T> {
+        TriplicataBuilder {
+            config: self.config,
+            source,
+            output: self.output,
+        }
+    }
+
+    pub fn output(mut self, output: impl OutputBackend + Send + 'static) -> Self {
+        self.output = Some(Box::new(output));
+        self
+    }
+}
+
+impl<S: CubeSource> TriplicataBuilder<S> {
+    pub async fn build(self) -> Result<Triplicata, Error> {
+        let config = self.config.ok_or(ConfigError::Missing)?;
+
+        let cancel = CancellationToken::new();
+
+        let mut cube_events = self.source.connect(cancel.child_token()).await?;
+
+        // Cube events are forwarded into a channel owned by the pipeline so
+        // that injected moves share it with the cube.
+        let (events, _) = broadcast::channel(16);
+        let event_sender = events.clone();
+        let source_cancel = cancel.clone();
+        let mut smoothing = config.smoothing.map(OrientationFilter::new);
+        let started = Instant::now();
+        let source = tokio::spawn(async move {
+            loop {
+                let event = select! {
+                    event = cube_events.recv() => event,
+                    _ = source_cancel.cancelled() => return Ok(()),
+                };
+
+                match event {
+                    Ok(mut event) => {
+                        metrics::channel_sent(Channel::CubeEvents);
+                        if let Some(filter) = &mut smoothing {
+                            match &mut event {
+                                CubeEvent::Orientation(q) => {
+                                    *q = filter.push(*q, started.elapsed())
+                                }
+                                CubeEvent::Disconnected => filter.reset(),
+                                _ => {}
+                            }
+                        }
+                        metrics::channel_sent(Channel::EventStream);
+                        let _ = event_sender.send(event);
+                    }
+                    Err(broadcast::error::RecvError::Lagged(count)) => {
+                        metrics::channel_drops(Channel::CubeEvents, count);
+                        metrics::channel_sent(Channel::EventStream);
+                        let _ = event_sender.send(CubeEvent::Lagged(count));
+                    }
+                    Err(broadcast::error::RecvError::Closed) => {
+                        warn!("Cube events closed");
+                        return Err(CubeError::Disconnected);
+                    }
+                }
+            }
+        });
+
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        let (actions, _) = broadcast::channel(16);
+
+        // Binds fire on the move that completes them, so the latest move is
+        // the one every action is measured from.
+        let last_move = Arc::new(Mutex::new(MoveTimes::now()));
+        let move_time = last_move.clone();
+        let mut received = None;
+        let stream = CubeEventStream::from(events.subscribe()).inspect(move |event| match event {
+            CubeEvent::Received(at) => received = Some(*at),
+            CubeEvent::Move(_) => {
+                let mut times = MoveTimes::now();
+                times.received = received.take().unwrap_or(times.reached);
+                *move_time.lock().unwrap() = times;
+            }
+            _ => {}
+        });
+        let (fired_tx, mut fired_rx) = mpsc::unbounded_channel();
+        let (fired, _) = broadcast::channel(16);
+        let fired_sender = fired.clone();
+        tokio::spawn(async move {
+            while let Some(bind) = fired_rx.recv().await {
+                metrics::channel_sent(Channel::FiredBinds);
+                let _ = fired_sender.send(bind);
+            }
+        });
+        let pause = Pause::new();
+        let kill_switch = KillSwitch::new();
+        let queue = OutputQueue::new();
+        let modifiers = Modifiers::new();
+        let typing = Typing::new();
+        let state_machine = tokio::spawn(
+            StateMachine::new(stream, config)
+                .report_fired(fired_tx)
+                .pause(pause.clone())
+                .kill_switch(kill_switch.clone())
+                .output_queue(queue.clone())
+                .modifiers(modifiers.clone())
+                .typing(typing.clone())
+                .run(tx, cancel.clone()),
+        );
+
+        let mut backend = self.output;
+        let action_sender = actions.clone();
+        let dropping = kill_switch.clone();
+        let output = tokio::task::spawn_blocking(move || {
+            let _span = info_span!("output").entered();
+
+            while let Some(action) = rx.blocking_recv() {
+                let skipped = if dropping.is_dropping() {
+                    let release = matches!(action, Action::ReleaseAll);
+                    if release {
+                        dropping.stop_dropping();
+                    }
+                    (!release).then_some("it was queued before the kill switch")
+                } else {
+                    queue.skips_next().then_some("a later bind replaced it")
+                };
+                if let Some(reason) = skipped {
+                    debug!(%action, "Skipping the action as {reason}");
+                    queue.played();
+                    continue;
+                }
+
+                info!(%action, "Executing");
+
+                if let Some(backend) = backend.as_mut()
+                    && let Err(error) = backend.execute(action.clone())
+                {
+                    error!(%action, %error, "Could not execute action");
+                }
+
+                let times = *last_move.lock().unwrap();
+                metrics::stage_latency(Stage::Inject, times.received.elapsed());
+                metrics::action_latency(times.reached.elapsed());
+
+                queue.played();
+                metrics::channel_sent(Channel::PlayedActions);
+                let _ = action_sender.send(action);
+            }
+        });
+
+        Ok(Triplicata {
+            events,
+            actions,
+            fired,
+            pause,
+            kill_switch,
+            modifiers,
+            typing,
+            cancel,
+            source: Some(source),
+            state_machine: Some(state_machine),
+            output: Some(output),
+        })
+    }
+}
@@ -0,0 +1,50 @@
+//! Ready-made configs bundled into the binary, written out by `triplicata init
+//! --preset` and `triplicata preset apply`.
+
+use crate::{config::Config, error::ConfigError};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Preset {
+    pub name: &'static str,
+    pub description: &'static str,
+    /// The config file contents.
+    pub source: &'static str,
+}
+
+pub const PRESETS: &[Preset] = &[
+    Preset {
+        name: "starter",
+        description: "A few single-turn binds to build on",
+        source: include_str!("presets/starter.ron"),
+    },
+    Preset {
+        name: "media",
+        description: "Play, pause, skip and volume on double turns",
+        source: include_str!("presets/media.ron"),
+    },
+    Preset {
+        name: "obs",
+        description: "F13 to F19 for OBS scene, stream and recording hotkeys",
+        source: include_str!("presets/obs.ron"),
+    },
+    Preset {
+        name: "vim",
+        description: "Cursor movement, insert, escape, undo and redo",
+        source: include_str!("presets/vim.ron"),
+    },
+    Preset {
+        name: "osu",
+        description: "Z and X on every turn for rhythm games",
+        source: include_str!("presets/osu.ron"),
+    },
+];
+
+impl Preset {
+    pub fn find(name: &str) -> Option<&'static Preset> {
+        PRESETS.iter().find(|preset| preset.name == name)
+    }
+
+    pub fn config(&self) -> Result<Config, ConfigError> {
+        self.source.parse()
+    }
+}
@@ -0,0 +1,183 @@
+//! The characteristics each GAN protocol generation is known to expose, used
+//! to tell the generation of a cube from what it advertises.
+
+use alloc::vec::Vec;
+
+use uuid::Uuid;
+
+use crate::{
+    GAN_GEN1_BATTERY_CHARACTERISTIC, GAN_GEN1_CUBE_STATE_CHARACTERISTIC,
+    GAN_GEN1_HARDWARE_CHARACTERISTIC, GAN_GEN1_LAST_MOVES_CHARACTERISTIC,
+    GAN_GEN1_TIMING_CHARACTERISTIC, GAN_GEN1_VERSION_CHARACTERISTIC,
+    GAN_GEN2_COMMAND_CHARACTERISTIC, GAN_GEN2_STATE_CHARACTERISTIC, error::ProtocolError,
+};
+
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum Generation {
+    V1,
+    V2,
+}
+
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum Role {
+    V1Version,
+    V1Hardware,
+    V1CubeState,
+    V1LastMoves,
+    V1Timing,
+    V1Battery,
+    V2Command,
+    V2State,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct KnownCharacteristic {
+    pub role: Role,
+    pub uuid: Uuid,
+    /// Whether the generation cannot be talked to without it.
+    pub required: bool,
+    /// What it provides, for telling the user what a cube lacks.
+    pub purpose: &'static str,
+}
+
+/// Generations in the order they are tried, newest first.
+pub const GENERATIONS: [Generation; 2] = [Generation::V2, Generation::V1];
+
+const V1_CHARACTERISTICS: [KnownCharacteristic; 6] = [
+    KnownCharacteristic {
+        role: Role::V1Version,
+        uuid: GAN_GEN1_VERSION_CHARACTERISTIC,
+        required: false,
+        purpose: "firmware version",
+    },
+    KnownCharacteristic {
+        role: Role::V1Hardware,
+        uuid: GAN_GEN1_HARDWARE_CHARACTERISTIC,
+        required: false,
+        purpose: "hardware version",
+    },
+    KnownCharacteristic {
+        role: Role::V1CubeState,
+        uuid: GAN_GEN1_CUBE_STATE_CHARACTERISTIC,
+        required: false,
+        purpose: "cube state",
+    },
+    KnownCharacteristic {
+        role: Role::V1LastMoves,
+        uuid: GAN_GEN1_LAST_MOVES_CHARACTERISTIC,
+        required: true,
+        purpose: "last moves",
+    },
+    KnownCharacteristic {
+        role: Role::V1Timing,
+        uuid: GAN_GEN1_TIMING_CHARACTERISTIC,
+        required: false,
+        purpose: "move timing",
+    },
+    KnownCharacteristic {
+        role: Role::V1Battery,
+        uuid: GAN_GEN1_BATTERY_CHARACTERISTIC,
+        required: false,
+        purpose: "battery level",
+    },
+];
+
+const V2_CHARACTERISTICS: [KnownCharacteristic; 2] = [
+    KnownCharacteristic {
+        role: Role::V2Command,
+        uuid: GAN_GEN2_COMMAND_CHARACTERISTIC,
+        required: true,
+        purpose: "command",
+    },
+    KnownCharacteristic {
+        role: Role::V2State,
+        uuid: GAN_GEN2_STATE_CHARACTERISTIC,
+        required: true,
+        purpose: "state",
+    },
+];
+
+impl Generation {
+    pub fn characteristics(self) -> &'static [KnownCharacteristic] {
+        match self {
+            Generation::V1 => &V1_CHARACTERISTICS,
+            Generation::V2 => &V2_CHARACTERISTICS,
+        }
+    }
+}
+
+/// The characteristics of a device sorted by the role they play in its
+/// protocol generation.
+#[derive(Debug)]
+pub struct Identified<T> {
+    pub generation: Generation,
+    found: Vec<(Role, T)>,
+    /// Characteristics no generation knows about.
+    pub unknown: Vec<Uuid>,
+}
+
+/// Picks the newest generation whose required characteristics are all
+/// present. Optional characteristics may be missing. Fails listing everything
+/// found when no generation matches.
+pub fn identify<T>(
+    characteristics: impl IntoIterator<Item = (Uuid, T)>,
+) -> Result<Identified<T>, ProtocolError> {
+    let characteristics: Vec<(Uuid, T)> = characteristics.into_iter().collect();
+    let has = |uuid: Uuid| characteristics.iter().any(|(u, _)| *u == uuid);
+
+    let generation = GENERATIONS
+        .into_iter()
+        .find(|generation| {
+            generation
+                .characteristics()
+                .iter()
+                .filter(|known| known.required)
+                .all(|known| has(known.uuid))
+        })
+        .ok_or_else(|| {
+            ProtocolError::UnrecognizedCharacteristics(
+                characteristics.iter().map(|(uuid, _)| *uuid).collect(),
+            )
+        })?;
+
+    let mut found = Vec::new();
+    let mut unknown = Vec::new();
+
+    for (uuid, characteristic) in characteristics {
+        match generation
+            .characteristics()
+            .iter()
+            .find(|known| known.uuid == uuid)
+        {
+            Some(known) => found.push((known.role, characteristic)),
+            None => unknown.push(uuid),
+        }
+    }
+
+    Ok(Identified {
+        generation,
+        found,
+        unknown,
+    })
+}
+
+impl<T> Identified<T> {
+    /// Removes the characteristic playing `role`.
+    pub fn take(&mut self, role: Role) -> Result<T, ProtocolError> {
+        let index = self
+            .found
+            .iter()
+            .position(|(r, _)| *r == role)
+            .ok_or(ProtocolError::UnknownVersion)?;
+
+        Ok(self.found.swap_remove(index).1)
+    }
+
+    /// The optional characteristics of the generation that were not found.
+    pub fn missing(&self) -> impl Iterator<Item = &'static KnownCharacteristic> + '_ {
+        self.generation
+            .characteristics()
+            .iter()
+            .filter(|known| !self.found.iter().any(|(role, _)| *role == known.role))
+    }
+}
@@ -0,0 +1,293 @@
+use aes::{
+    Aes128, Block,
+    cipher::{BlockDecrypt, BlockEncrypt, KeyInit, generic_array::GenericArray},
+};
+
+use alloc::vec::Vec;
+use core::fmt;
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer, de};
+
+use crate::error::ProtocolError;
+
+pub const GAN_V2_KEY: [u8; 16] = [
+    0x01, 0x02, 0x42, 0x28, 0x31, 0x91, 0x16, 0x07, 0x20, 0x05, 0x18, 0x54, 0x42, 0x11, 0x12, 0x53,
+];
+pub const GAN_V2_IV: [u8; 16] = [
+    0x11, 0x03, 0x32, 0x28, 0x21, 0x01, 0x76, 0x27, 0x20, 0x95, 0x78, 0x14, 0x32, 0x12, 0x02, 0x43,
+];
+
+/// The base keys of first generation cubes, picked by the firmware's minor
+/// version.
+pub const GAN_V1_KEYS: [[u8; 16]; 2] = [
+    [
+        0xc6, 0xca, 0x15, 0xdf, 0x4f, 0x6e, 0x13, 0xb6, 0x77, 0x0d, 0xe6, 0x59, 0x3a, 0xaf, 0xba,
+        0xa2,
+    ],
+    [
+        0x43, 0xe2, 0x5b, 0xd6, 0x7d, 0xdc, 0x78, 0xd8, 0x07, 0x60, 0xa3, 0xda, 0x82, 0x3c, 0x01,
+        0xf1,
+    ],
+];
+
+/// The base key and IV that device keys are derived from, written in configs
+/// as 32 hex digits each. Defaults to [`GAN_V2_KEY`] and [`GAN_V2_IV`], other
+/// keys are only needed for unusual firmware.
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CipherKeys {
+    #[serde(with = "hex")]
+    pub key: [u8; 16],
+    #[serde(with = "hex")]
+    pub iv: [u8; 16],
+}
+
+impl Default for CipherKeys {
+    fn default() -> Self {
+        Self {
+            key: GAN_V2_KEY,
+            iv: GAN_V2_IV,
+        }
+    }
+}
+
+mod hex {
+    use super::*;
+
+    struct Hex<'a>(&'a [u8; 16]);
+
+    impl fmt::Display for Hex<'_> {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            self.0.iter().try_for_each(|byte| write!(f, "{byte:02x}"))
+        }
+    }
+
+    pub fn serialize<S: Serializer>(bytes: &[u8; 16], serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(&Hex(bytes))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<[u8; 16], D::Error> {
+        struct HexVisitor;
+
+        impl de::Visitor<'_> for HexVisitor {
+            type Value = [u8; 16];
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("16 bytes as 32 hex digits")
+            }
+
+            fn visit_str<E: de::Error>(self, v: &str) -> Result<Self::Value, E> {
+                let digits = v.as_bytes();
+                if digits.len() != 32 {
+                    return Err(E::invalid_length(digits.len(), &self));
+                }
+
+                let digit = |c: u8| (c as char).to_digit(16);
+                let mut bytes = [0; 16];
+                for (byte, pair) in bytes.iter_mut().zip(digits.chunks(2)) {
+                    let (Some(high), Some(low)) = (digit(pair[0]), digit(pair[1])) else {
+                        return Err(E::invalid_value(de::Unexpected::Str(v), &self));
+                    };
+                    *byte = (high << 4 | low) as u8;
+                }
+                Ok(bytes)
+            }
+        }
+
+        deserializer.deserialize_str(HexVisitor)
+    }
+}
+
+/// Manufacturer data company identifier under which GAN cubes advertise their
+/// device identifier.
+pub const GAN_MANUFACTURER_ID: u16 = 36097;
+
+/// Cipher used by GAN v2 cubes for both notifications and commands.
+///
+/// Packets are longer than a single AES block (notifications and commands are
+/// 20 bytes), so each packet is covered by two overlapping AES-128 blocks, each
+/// XORed with the IV CBC style: the first 16 bytes and the last 16 bytes. On
+/// encryption the first block is processed first and the last block second,
+/// so the overlapping bytes are encrypted twice. Decryption undoes this in the
+/// reverse order.
+///
+/// The key and IV are the base [`GAN_V2_KEY`] and [`GAN_V2_IV`] with the first
+/// six bytes offset by the device salt, see [`GANCubeVersion2Cipher::from_salt`].
+/// Other base keys can be given with [`GANCubeVersion2Cipher::from_salt_with`].
+#[derive(Clone)]
+pub struct GANCubeVersion2Cipher {
+    aes: Aes128,
+    device_iv: [u8; 16],
+}
+
+impl GANCubeVersion2Cipher {
+    pub fn new(device_key: [u8; 16], device_iv: [u8; 16]) -> Self {
+        Self {
+            aes: Aes128::new(GenericArray::from_slice(&device_key)),
+            device_iv,
+        }
+    }
+
+    pub fn from_salt(salt: [u8; 6]) -> Self {
+        Self::from_salt_with(CipherKeys::default(), salt)
+    }
+
+    pub fn from_salt_with(keys: CipherKeys, salt: [u8; 6]) -> Self {
+        let CipherKeys { mut key, mut iv } = keys;
+        for (idx, byte) in salt.iter().enumerate() {
+            key[idx] = ((key[idx] as u16 + *byte as u16) % 255) as u8;
+            iv[idx] = ((iv[idx] as u16 + *byte as u16) % 255) as u8;
+        }
+
+        Self::new(key, iv)
+    }
+
+    pub fn salt_from_manufacturer_data(data: &[u8]) -> Result<[u8; 6], ProtocolError> {
+        if data.len() < 9 {
+            return Err(ProtocolError::InvalidDeviceIdentifier);
+        }
+
+        let mut salt = [0; 6];
+        salt.copy_from_slice(&data[3..9]);
+        Ok(salt)
+    }
+
+    fn decrypt_block(&self, block: &mut [u8]) {
+        let block = Block::from_mut_slice(block);
+        self.aes.decrypt_block(block);
+        for (byte, iv) in block.iter_mut().zip(self.device_iv) {
+            *byte ^= iv;
+        }
+    }
+
+    fn encrypt_block(&self, block: &mut [u8]) {
+        for (byte, iv) in block.iter_mut().zip(self.device_iv) {
+            *byte ^= iv;
+        }
+        self.aes.encrypt_block(Block::from_mut_slice(block));
+    }
+
+    pub fn decrypt_in_place(&self, value: &mut [u8]) -> Result<(), ProtocolError> {
+        if value.len() <= 16 {
+            return Err(ProtocolError::PacketTooShort(value.len()));
+        }
+
+        // Packets are larger than block size. First decrypt the last 16 bytes,
+        // then the first 16 bytes, which overlap with the block before.
+        let offset = value.len() - 16;
+        self.decrypt_block(&mut value[offset..]);
+        self.decrypt_block(&mut value[..16]);
+
+        Ok(())
+    }
+
+    pub fn encrypt_in_place(&self, value: &mut [u8]) -> Result<(), ProtocolError> {
+        if value.len() <= 16 {
+            return Err(ProtocolError::PacketTooShort(value.len()));
+        }
+
+        // The reverse of decryption, the first 16 bytes then the last 16.
+        let offset = value.len() - 16;
+        self.encrypt_block(&mut value[..16]);
+        self.encrypt_block(&mut value[offset..]);
+
+        Ok(())
+    }
+
+    pub fn decrypt(&self, value: &[u8]) -> Result<Vec<u8>, ProtocolError> {
+        let mut value = value.to_vec();
+        self.decrypt_in_place(&mut value)?;
+        Ok(value)
+    }
+
+    pub fn encrypt(&self, value: &[u8]) -> Result<Vec<u8>, ProtocolError> {
+        let mut value = value.to_vec();
+        self.encrypt_in_place(&mut value)?;
+        Ok(value)
+    }
+}
+
+/// The cipher of first generation cubes, AES-128 without an IV over the same
+/// two overlapping blocks as [`GANCubeVersion2Cipher`], or the one block of a
+/// 16 byte value.
+///
+/// The key is one of [`GAN_V1_KEYS`] with the first six bytes offset by the
+/// cube's hardware id, see [`GANCubeVersion1Cipher::for_firmware`].
+#[derive(Clone)]
+pub struct GANCubeVersion1Cipher {
+    aes: Aes128,
+}
+
+impl GANCubeVersion1Cipher {
+    pub fn new(device_key: [u8; 16]) -> Self {
+        Self {
+            aes: Aes128::new(GenericArray::from_slice(&device_key)),
+        }
+    }
+
+    /// The cipher of a cube reporting the firmware `version` and the
+    /// `hardware` id, as read from their characteristics, or `None` for the
+    /// early firmware that sends its values in the clear.
+    pub fn for_firmware(version: &[u8], hardware: &[u8]) -> Result<Option<Self>, ProtocolError> {
+        let [major, minor, patch, ..] = *version else {
+            return Err(ProtocolError::InvalidDeviceIdentifier);
+        };
+        let version = u32::from_be_bytes([0, major, minor, patch]);
+        if version <= 0x010007 || version & 0xfffe00 != 0x010000 {
+            return Ok(None);
+        }
+
+        let hardware = hardware
+            .get(..6)
+            .ok_or(ProtocolError::InvalidDeviceIdentifier)?;
+        let mut key = GAN_V1_KEYS[minor as usize];
+        for (idx, byte) in hardware.iter().rev().enumerate() {
+            key[idx] = key[idx].wrapping_add(*byte);
+        }
+
+        Ok(Some(Self::new(key)))
+    }
+
+    pub fn decrypt_in_place(&self, value: &mut [u8]) -> Result<(), ProtocolError> {
+        if value.len() < 16 {
+            return Err(ProtocolError::PacketTooShort(value.len()));
+        }
+
+        let offset = value.len() - 16;
+        if offset > 0 {
+            self.aes
+                .decrypt_block(Block::from_mut_slice(&mut value[offset..]));
+        }
+        self.aes
+            .decrypt_block(Block::from_mut_slice(&mut value[..16]));
+
+        Ok(())
+    }
+
+    pub fn encrypt_in_place(&self, value: &mut [u8]) -> Result<(), ProtocolError> {
+        if value.len() < 16 {
+            return Err(ProtocolError::PacketTooShort(value.len()));
+        }
+
+        let offset = value.len() - 16;
+        self.aes
+            .encrypt_block(Block::from_mut_slice(&mut value[..16]));
+        if offset > 0 {
+            self.aes
+                .encrypt_block(Block::from_mut_slice(&mut value[offset..]));
+        }
+
+        Ok(())
+    }
+
+    pub fn decrypt(&self, value: &[u8]) -> Result<Vec<u8>, ProtocolError> {
+        let mut value = value.to_vec();
+        self.decrypt_in_place(&mut value)?;
+        Ok(value)
+    }
+
+    pub fn encrypt(&self, value: &[u8]) -> Result<Vec<u8>, ProtocolError> {
+        let mut value = value.to_vec();
+        self.encrypt_in_place(&mut value)?;
+        Ok(value)
+    }
+}
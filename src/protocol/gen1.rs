@@ -0,0 +1,92 @@
+//! The first generation protocol of the GAN 356i and cubes of its time. These
+//! cubes do not notify, their characteristics are read over and over instead,
+//! and only the last moves characteristic is needed to follow the cube.
+
+use alloc::vec::Vec;
+
+use crate::{
+    cube::{CubeEvent, Direction, Face, Move},
+    error::ProtocolError,
+    protocol::cipher::GANCubeVersion1Cipher,
+};
+
+/// The last moves value holds the orientation, then the move counter, then
+/// the last six moves with the newest last.
+pub const MOVE_COUNT_OFFSET: usize = 12;
+pub const LAST_MOVES: usize = 6;
+pub const LAST_MOVES_LENGTH: usize = MOVE_COUNT_OFFSET + 1 + LAST_MOVES;
+
+pub const BATTERY_OFFSET: usize = 7;
+
+#[derive(Clone)]
+pub struct Decoder {
+    /// Early firmware sends its values in the clear.
+    cipher: Option<GANCubeVersion1Cipher>,
+    last_move_count: Option<u8>,
+}
+
+impl Decoder {
+    pub fn new(cipher: Option<GANCubeVersion1Cipher>) -> Self {
+        Self {
+            cipher,
+            last_move_count: None,
+        }
+    }
+
+    /// The cube's move counter as of the last read of its last moves.
+    pub fn move_count(&self) -> Option<u8> {
+        self.last_move_count
+    }
+
+    fn decrypt(&self, value: &[u8]) -> Result<Vec<u8>, ProtocolError> {
+        match &self.cipher {
+            Some(cipher) => cipher.decrypt(value),
+            None => Ok(value.to_vec()),
+        }
+    }
+
+    /// The moves made since the last moves were last read, oldest first. The
+    /// first read only learns the counter. A half turn is reported as two
+    /// quarter turns.
+    pub fn decode_moves(&mut self, value: &[u8]) -> Result<Vec<CubeEvent>, ProtocolError> {
+        let value = self.decrypt(value)?;
+        if value.len() < LAST_MOVES_LENGTH {
+            return Err(ProtocolError::PacketTooShort(value.len()));
+        }
+
+        let current_move_count = value[MOVE_COUNT_OFFSET];
+        let mut events = Vec::new();
+
+        let Some(last) = self.last_move_count.replace(current_move_count) else {
+            return Ok(events);
+        };
+
+        // The counter wraps around and only the last six moves are kept, any
+        // made before them between two reads are lost.
+        let move_count = current_move_count.wrapping_sub(last).min(LAST_MOVES as u8) as usize;
+
+        for &m in &value[LAST_MOVES_LENGTH - move_count..LAST_MOVES_LENGTH] {
+            let face = *Face::ALL
+                .get(m as usize / 3)
+                .ok_or(ProtocolError::InvalidChecksum)?;
+            let (m, turns) = match m % 3 {
+                0 => (Move::new(face, Direction::Clockwise), 1),
+                1 => (Move::new(face, Direction::Clockwise), 2),
+                _ => (Move::new(face, Direction::CounterClockwise), 1),
+            };
+            events.extend(core::iter::repeat_n(CubeEvent::Move(m), turns));
+        }
+
+        Ok(events)
+    }
+
+    /// The battery level in percent.
+    pub fn decode_battery(&self, value: &[u8]) -> Result<u8, ProtocolError> {
+        let value = self.decrypt(value)?;
+        let level = *value
+            .get(BATTERY_OFFSET)
+            .ok_or(ProtocolError::PacketTooShort(value.len()))?;
+
+        Ok(level.min(100))
+    }
+}
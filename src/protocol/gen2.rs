@@ -0,0 +1,293 @@
+use core::{ops::Deref, time::Duration};
+
+use crate::{
+    cube::{CubeEvent, CubeState, Move, Quaternion},
+    error::ProtocolError,
+    protocol::{cipher::GANCubeVersion2Cipher, extract_bits, insert_bits},
+};
+
+pub const CUBE_GYRO_MESSAGE: u8 = 1;
+pub const CUBE_MOVE_MESSAGE: u8 = 2;
+pub const CUBE_STATE_MESSAGE: u8 = 4;
+pub const CUBE_BATTERY_STATE_MESSAGE: u8 = 9;
+
+pub const PACKET_LENGTH: usize = 20;
+
+/// The most events a single packet can decode into, a move packet holds at
+/// most the last seven moves.
+pub const MAX_EVENTS: usize = 7;
+
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum Command {
+    RequestState,
+    RequestBattery,
+}
+
+impl Command {
+    pub fn packet(self) -> [u8; PACKET_LENGTH] {
+        let mut packet = [0; PACKET_LENGTH];
+        packet[0] = match self {
+            Command::RequestState => CUBE_STATE_MESSAGE,
+            Command::RequestBattery => CUBE_BATTERY_STATE_MESSAGE,
+        };
+        packet
+    }
+
+    pub fn from_packet(packet: &[u8]) -> Option<Self> {
+        match *packet.first()? {
+            CUBE_STATE_MESSAGE => Some(Command::RequestState),
+            CUBE_BATTERY_STATE_MESSAGE => Some(Command::RequestBattery),
+            _ => None,
+        }
+    }
+}
+
+/// The events decoded from one packet, stored inline so decoding does not
+/// allocate.
+#[derive(Clone, Copy, Debug)]
+pub struct DecodedEvents {
+    events: [CubeEvent; MAX_EVENTS],
+    len: usize,
+}
+
+impl DecodedEvents {
+    fn new() -> Self {
+        Self {
+            events: [CubeEvent::Connected; MAX_EVENTS],
+            len: 0,
+        }
+    }
+
+    fn push(&mut self, event: CubeEvent) {
+        if let Some(slot) = self.events.get_mut(self.len) {
+            *slot = event;
+            self.len += 1;
+        }
+    }
+}
+
+impl Deref for DecodedEvents {
+    type Target = [CubeEvent];
+
+    fn deref(&self) -> &Self::Target {
+        &self.events[..self.len]
+    }
+}
+
+impl IntoIterator for DecodedEvents {
+    type Item = CubeEvent;
+    type IntoIter = core::iter::Take<core::array::IntoIter<CubeEvent, MAX_EVENTS>>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.events.into_iter().take(self.len)
+    }
+}
+
+#[derive(Clone)]
+pub struct Decoder {
+    cipher: GANCubeVersion2Cipher,
+    last_move_count: Option<u8>,
+}
+
+impl Decoder {
+    pub fn new(cipher: GANCubeVersion2Cipher) -> Self {
+        Self {
+            cipher,
+            last_move_count: None,
+        }
+    }
+
+    /// The cube's move counter as of the last move or state message.
+    pub fn move_count(&self) -> Option<u8> {
+        self.last_move_count
+    }
+
+    pub fn encode(&self, command: Command) -> Result<[u8; PACKET_LENGTH], ProtocolError> {
+        let mut packet = command.packet();
+        self.cipher.encrypt_in_place(&mut packet)?;
+        Ok(packet)
+    }
+
+    pub fn decode(&mut self, packet: &[u8]) -> Result<DecodedEvents, ProtocolError> {
+        let mut buffer = [0; PACKET_LENGTH];
+        let value = buffer
+            .get_mut(..packet.len())
+            .ok_or(ProtocolError::PacketTooLong(packet.len()))?;
+        value.copy_from_slice(packet);
+        self.cipher.decrypt_in_place(value)?;
+
+        let value = &*value;
+        let message_type = extract_bits(value, 0, 4) as u8;
+
+        let mut events = DecodedEvents::new();
+
+        match message_type {
+            CUBE_GYRO_MESSAGE => {
+                let component = |start| {
+                    let raw = extract_bits(value, start, 16);
+                    let sign = if raw >> 15 == 0 { 1.0 } else { -1.0 };
+                    sign * (raw & 0x7fff) as f32 / 0x7fff as f32
+                };
+
+                events.push(CubeEvent::Orientation(Quaternion {
+                    w: component(4),
+                    x: component(20),
+                    y: component(36),
+                    z: component(52),
+                }));
+            }
+            CUBE_MOVE_MESSAGE => {
+                let current_move_count = extract_bits(value, 4, 8) as u8;
+
+                let Some(last) = self.last_move_count.replace(current_move_count) else {
+                    return Ok(events);
+                };
+
+                // The counter wraps around and a packet holds at most the last
+                // seven moves.
+                let move_count = current_move_count.wrapping_sub(last).min(7) as usize;
+
+                for i in (0..move_count).rev() {
+                    if let Some(m) = Move::ALL.get(extract_bits(value, 12 + i * 5, 5) as usize) {
+                        events.push(CubeEvent::Move(*m));
+                    }
+                }
+            }
+            CUBE_STATE_MESSAGE => {
+                self.last_move_count = Some(extract_bits(value, 4, 8) as u8);
+
+                let mut corner_permutation = [0; 8];
+                let mut corner_orientation = [0; 8];
+                let mut edge_permutation = [0; 12];
+                let mut edge_orientation = [0; 12];
+
+                // The last corner and edge are implied by the others.
+                for i in 0..7 {
+                    corner_permutation[i] = extract_bits(value, 12 + i * 3, 3) as u8;
+                    corner_orientation[i] = extract_bits(value, 33 + i * 2, 2) as u8;
+                }
+                corner_permutation[7] =
+                    28u8.wrapping_sub(corner_permutation[..7].iter().sum::<u8>());
+                corner_orientation[7] = (3 - corner_orientation[..7].iter().sum::<u8>() % 3) % 3;
+
+                for i in 0..11 {
+                    edge_permutation[i] = extract_bits(value, 47 + i * 4, 4) as u8;
+                    edge_orientation[i] = extract_bits(value, 91 + i, 1) as u8;
+                }
+                edge_permutation[11] = 66u8.wrapping_sub(edge_permutation[..11].iter().sum::<u8>());
+                edge_orientation[11] = (2 - edge_orientation[..11].iter().sum::<u8>() % 2) % 2;
+
+                events.push(CubeEvent::StateSync(CubeState {
+                    corner_permutation,
+                    corner_orientation,
+                    edge_permutation,
+                    edge_orientation,
+                }));
+            }
+            CUBE_BATTERY_STATE_MESSAGE => {
+                events.push(CubeEvent::Battery(extract_bits(value, 8, 8).min(100) as u8));
+            }
+            _ => {}
+        }
+
+        Ok(events)
+    }
+}
+
+/// Produces the notifications a GAN v2 cube would send, so triplicata can
+/// present itself as a cube to software that only speaks the cube protocol.
+#[derive(Clone)]
+pub struct Emulator {
+    cipher: GANCubeVersion2Cipher,
+    move_count: u8,
+    recent: [(Move, u16); MAX_EVENTS],
+}
+
+impl Emulator {
+    pub fn new(cipher: GANCubeVersion2Cipher) -> Self {
+        Self {
+            cipher,
+            move_count: 0,
+            recent: [(Move::U, 0); MAX_EVENTS],
+        }
+    }
+
+    fn encrypt(
+        &self,
+        mut packet: [u8; PACKET_LENGTH],
+    ) -> Result<[u8; PACKET_LENGTH], ProtocolError> {
+        self.cipher.encrypt_in_place(&mut packet)?;
+        Ok(packet)
+    }
+
+    /// Decrypts a command written by the client.
+    pub fn command(&self, packet: &[u8]) -> Result<Option<Command>, ProtocolError> {
+        let mut buffer = [0; PACKET_LENGTH];
+        let value = buffer
+            .get_mut(..packet.len())
+            .ok_or(ProtocolError::PacketTooLong(packet.len()))?;
+        value.copy_from_slice(packet);
+        self.cipher.decrypt_in_place(value)?;
+
+        Ok(Command::from_packet(value))
+    }
+
+    /// Encodes `m` along with the moves before it, `since_last` is the time
+    /// since the previous move.
+    pub fn move_packet(
+        &mut self,
+        m: Move,
+        since_last: Duration,
+    ) -> Result<[u8; PACKET_LENGTH], ProtocolError> {
+        self.move_count = self.move_count.wrapping_add(1);
+        self.recent.rotate_right(1);
+        self.recent[0] = (m, since_last.as_millis().min(u16::MAX as u128) as u16);
+
+        let mut packet = [0; PACKET_LENGTH];
+        insert_bits(&mut packet, 0, 4, CUBE_MOVE_MESSAGE as u32);
+        insert_bits(&mut packet, 4, 8, self.move_count as u32);
+
+        for (i, (m, elapsed)) in self.recent.iter().enumerate() {
+            insert_bits(&mut packet, 12 + i * 5, 5, *m as u32);
+            insert_bits(&mut packet, 47 + i * 16, 16, *elapsed as u32);
+        }
+
+        self.encrypt(packet)
+    }
+
+    pub fn state_packet(&self, state: &CubeState) -> Result<[u8; PACKET_LENGTH], ProtocolError> {
+        let mut packet = [0; PACKET_LENGTH];
+        insert_bits(&mut packet, 0, 4, CUBE_STATE_MESSAGE as u32);
+        insert_bits(&mut packet, 4, 8, self.move_count as u32);
+
+        for i in 0..7 {
+            insert_bits(
+                &mut packet,
+                12 + i * 3,
+                3,
+                state.corner_permutation[i] as u32,
+            );
+            insert_bits(
+                &mut packet,
+                33 + i * 2,
+                2,
+                state.corner_orientation[i] as u32,
+            );
+        }
+
+        for i in 0..11 {
+            insert_bits(&mut packet, 47 + i * 4, 4, state.edge_permutation[i] as u32);
+            insert_bits(&mut packet, 91 + i, 1, state.edge_orientation[i] as u32);
+        }
+
+        self.encrypt(packet)
+    }
+
+    pub fn battery_packet(&self, level: u8) -> Result<[u8; PACKET_LENGTH], ProtocolError> {
+        let mut packet = [0; PACKET_LENGTH];
+        insert_bits(&mut packet, 0, 4, CUBE_BATTERY_STATE_MESSAGE as u32);
+        insert_bits(&mut packet, 8, 8, level.min(100) as u32);
+
+        self.encrypt(packet)
+    }
+}
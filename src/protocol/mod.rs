@@ -0,0 +1,44 @@
+//! Protocol decoding shared by every cube source. This module only depends on
+//! `core` and `alloc` so it can be used without the `std` feature, for example
+//! on an embedded bridge relaying decoded events to a PC.
+
+pub mod characteristics;
+pub mod cipher;
+pub mod gen1;
+pub mod gen2;
+pub mod robot;
+pub mod timer;
+
+/// Reads `count` bits, at most 32, starting at bit `start` with the most
+/// significant bit of each byte first, as GAN packets are laid out.
+pub fn extract_bits(data: &[u8], start: usize, count: usize) -> u32 {
+    debug_assert!(count <= 32, "cannot extract {count} bits into a u32");
+    if count == 0 {
+        return 0;
+    }
+
+    // The bits span at most five bytes, which are read whole into a word and
+    // shifted down to the field.
+    let first = start / 8;
+    let last = (start + count - 1) / 8;
+    let word = data[first..=last]
+        .iter()
+        .fold(0u64, |word, byte| word << 8 | *byte as u64);
+    let trailing = (last + 1) * 8 - (start + count);
+
+    ((word >> trailing) & ((1 << count) - 1)) as u32
+}
+
+/// Writes the low `count` bits of `value` at bit `start`, the inverse of
+/// [`extract_bits`].
+pub fn insert_bits(data: &mut [u8], start: usize, count: usize, value: u32) {
+    for i in 0..count {
+        let bit = start + i;
+        let mask = 1 << (7 - (bit % 8));
+        if value & (1 << (count - 1 - i)) != 0 {
+            data[bit / 8] |= mask;
+        } else {
+            data[bit / 8] &= !mask;
+        }
+    }
+}
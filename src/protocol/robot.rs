@@ -0,0 +1,61 @@
+//! GAN Robot move commands. The robot grips the cube by the U face, so it can
+//! only turn the other five faces. Moves are queued one byte each, with up to
+//! [`MOVES_PER_PACKET`] moves per write and unused bytes set to `0xFF`.
+
+use alloc::{string::ToString, vec::Vec};
+
+use crate::{
+    algorithm::{Algorithm, Layer, Turn},
+    cube::Face,
+    error::NotationError,
+};
+
+pub const MOVES_PER_PACKET: usize = 18;
+
+const PADDING: u8 = 0xFF;
+
+/// The faces the robot can turn, in the order of their move codes.
+pub const FACES: [Face; 5] = [Face::R, Face::F, Face::D, Face::L, Face::B];
+
+/// Each face has four codes, a quarter turn, two half turns that differ only
+/// in direction, and a counter clockwise quarter turn.
+pub fn move_code(turn: Turn) -> Result<u8, NotationError> {
+    let unsupported = || NotationError::UnsupportedTurn(turn.to_string());
+
+    let Layer::Face(face) = turn.layer else {
+        return Err(unsupported());
+    };
+
+    let face = FACES
+        .iter()
+        .position(|&f| f == face)
+        .ok_or_else(unsupported)? as u8;
+
+    let offset = match turn.amount {
+        1 | -3 => 0,
+        2 => 1,
+        -2 => 2,
+        -1 | 3 => 3,
+        _ => return Err(unsupported()),
+    };
+
+    Ok(face * 4 + offset)
+}
+
+/// Splits `algorithm` into packets ready to write to the robot.
+pub fn encode(algorithm: &Algorithm) -> Result<Vec<[u8; MOVES_PER_PACKET]>, NotationError> {
+    let codes = algorithm
+        .0
+        .iter()
+        .map(|&turn| move_code(turn))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    Ok(codes
+        .chunks(MOVES_PER_PACKET)
+        .map(|chunk| {
+            let mut packet = [PADDING; MOVES_PER_PACKET];
+            packet[..chunk.len()].copy_from_slice(chunk);
+            packet
+        })
+        .collect())
+}
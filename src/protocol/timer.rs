@@ -0,0 +1,116 @@
+//! GAN Smart Timer and Halo timer notifications. Packets are unencrypted,
+//! start with `0xFE` and end with a little endian CRC-16/CCITT of everything
+//! after the first two bytes.
+
+use core::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+use crate::error::ProtocolError;
+
+const PACKET_HEADER: u8 = 0xFE;
+
+/// Header, length, an unused byte, the state and the checksum.
+const MIN_PACKET_LENGTH: usize = 6;
+
+#[derive(PartialEq, Eq, Clone, Copy, Debug, Deserialize, Serialize)]
+pub enum TimerState {
+    Disconnect,
+    /// Hands have been on the pads long enough to start.
+    GetSet,
+    /// Hands were lifted before the timer was ready.
+    HandsOff,
+    Running,
+    Stopped,
+    Idle,
+    HandsOn,
+    Finished,
+}
+
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub struct TimerEvent {
+    pub state: TimerState,
+    /// The recorded time, only sent when the timer stops.
+    pub time: Option<Duration>,
+}
+
+impl TryFrom<u8> for TimerState {
+    type Error = ProtocolError;
+
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        Ok(match value {
+            0 => TimerState::Disconnect,
+            1 => TimerState::GetSet,
+            2 => TimerState::HandsOff,
+            3 => TimerState::Running,
+            4 => TimerState::Stopped,
+            5 => TimerState::Idle,
+            6 => TimerState::HandsOn,
+            7 => TimerState::Finished,
+            _ => return Err(ProtocolError::UnknownTimerState(value)),
+        })
+    }
+}
+
+pub fn decode(packet: &[u8]) -> Result<TimerEvent, ProtocolError> {
+    if packet.len() < MIN_PACKET_LENGTH {
+        return Err(ProtocolError::PacketTooShort(packet.len()));
+    }
+
+    if packet[0] != PACKET_HEADER {
+        return Err(ProtocolError::InvalidChecksum);
+    }
+
+    let (body, checksum) = packet.split_at(packet.len() - 2);
+    if crc16_ccitt(&body[2..]) != u16::from_le_bytes([checksum[0], checksum[1]]) {
+        return Err(ProtocolError::InvalidChecksum);
+    }
+
+    let state = TimerState::try_from(packet[3])?;
+    let time = match state {
+        TimerState::Stopped => {
+            let time = body
+                .get(4..8)
+                .ok_or(ProtocolError::PacketTooShort(packet.len()))?;
+            Some(decode_time(time))
+        }
+        _ => None,
+    };
+
+    Ok(TimerEvent { state, time })
+}
+
+/// Decodes the times read from the time characteristic, the time on the
+/// display followed by the three before it.
+pub fn decode_recorded_times(data: &[u8]) -> Result<[Duration; 4], ProtocolError> {
+    if data.len() < 16 {
+        return Err(ProtocolError::PacketTooShort(data.len()));
+    }
+
+    Ok(core::array::from_fn(|i| {
+        decode_time(&data[i * 4..i * 4 + 4])
+    }))
+}
+
+/// Minutes, seconds and little endian milliseconds.
+fn decode_time(data: &[u8]) -> Duration {
+    let millis = u16::from_le_bytes([data[2], data[3]]) as u64;
+    Duration::from_secs(data[0] as u64 * 60 + data[1] as u64) + Duration::from_millis(millis)
+}
+
+fn crc16_ccitt(data: &[u8]) -> u16 {
+    let mut crc: u16 = 0xFFFF;
+
+    for &byte in data {
+        crc ^= (byte as u16) << 8;
+        for _ in 0..8 {
+            crc = if crc & 0x8000 != 0 {
+                (crc << 1) ^ 0x1021
+            } else {
+                crc << 1
+            };
+        }
+    }
+
+    crc
+}
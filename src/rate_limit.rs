@@ -0,0 +1,81 @@
+//! Rate limits on binds, so a runaway loop or a burst of lagged moves
+//! matching many binds at once cannot flood the output.
+
+use std::{collections::VecDeque, time::Duration};
+
+use serde::{Deserialize, Serialize};
+
+/// At most `max` firings every `per`, e.g. `(max: 5, per: Second)`, with the
+/// firings over it dropped unless the `policy` is `Queue`.
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RateLimit {
+    pub max: u32,
+    pub per: Period,
+    #[serde(default)]
+    pub policy: RatePolicy,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Period {
+    Second,
+    Minute,
+}
+
+#[derive(Deserialize, Serialize, Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum RatePolicy {
+    #[default]
+    Drop,
+    /// Plays the firings over the limit as soon as it allows, in order.
+    Queue,
+}
+
+impl Period {
+    pub fn duration(self) -> Duration {
+        match self {
+            Period::Second => Duration::from_secs(1),
+            Period::Minute => Duration::from_secs(60),
+        }
+    }
+}
+
+/// The firings a rate limit counts, as times since a start that is the same
+/// for every call.
+#[derive(Debug, Default, Clone)]
+pub struct RateLimiter {
+    fired: VecDeque<Duration>,
+}
+
+impl RateLimiter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// When a firing at `now` can be played under `limit`, which is `now`
+    /// while under it, or `None` if the firing is dropped.
+    pub fn next_free(&mut self, limit: &RateLimit, now: Duration) -> Option<Duration> {
+        let window = limit.per.duration();
+        while self
+            .fired
+            .front()
+            .is_some_and(|&fired| fired + window <= now)
+        {
+            self.fired.pop_front();
+        }
+
+        let max = limit.max as usize;
+        if self.fired.len() < max {
+            return Some(now);
+        }
+
+        match limit.policy {
+            RatePolicy::Queue if max > 0 => Some(self.fired[self.fired.len() - max] + window),
+            _ => None,
+        }
+    }
+
+    /// Counts a firing played at `at`.
+    pub fn record(&mut self, at: Duration) {
+        let i = self.fired.partition_point(|&fired| fired <= at);
+        self.fired.insert(i, at);
+    }
+}
@@ -0,0 +1,82 @@
+//! Timeouts and retries for the steps of connecting to a cube, so a hung
+//! bluetooth stack fails with an error instead of waiting forever.
+
+use core::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+/// How long each step of connecting may take and how often it is tried, e.g.
+/// `(timeout: 10000, attempts: 3, backoff: 1000)`.
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(default)]
+pub struct RetryPolicy {
+    /// Milliseconds each attempt may take.
+    pub timeout: u64,
+    /// Attempts at each step before giving up.
+    pub attempts: u32,
+    /// Milliseconds to wait between attempts.
+    pub backoff: u64,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            timeout: 10_000,
+            attempts: 3,
+            backoff: 1_000,
+        }
+    }
+}
+
+impl RetryPolicy {
+    pub fn timeout(&self) -> Duration {
+        Duration::from_millis(self.timeout)
+    }
+
+    pub fn backoff(&self) -> Duration {
+        Duration::from_millis(self.backoff)
+    }
+
+    /// Runs `attempt` until it succeeds, timing out each try, returning the
+    /// last failure once every attempt has been used. `step` completes "could
+    /// not ..." in the logs and errors.
+    #[cfg(any(feature = "bluetooth", all(feature = "bluez", target_os = "linux")))]
+    pub(crate) async fn run<T, E, F>(
+        &self,
+        step: &'static str,
+        mut attempt: impl FnMut() -> F,
+    ) -> Result<T, crate::error::CubeError>
+    where
+        F: Future<Output = Result<T, E>>,
+        crate::error::CubeError: From<E>,
+    {
+        use crate::error::CubeError;
+
+        let attempts = self.attempts.max(1);
+        let mut tried = 0;
+
+        loop {
+            tried += 1;
+
+            let error = match tokio::time::timeout(self.timeout(), attempt()).await {
+                Ok(Ok(value)) => return Ok(value),
+                Ok(Err(e)) => CubeError::from(e),
+                Err(_) => CubeError::TimedOut {
+                    step,
+                    timeout: self.timeout(),
+                },
+            };
+
+            if tried >= attempts {
+                return Err(CubeError::GaveUp {
+                    step,
+                    attempts,
+                    last: Box::new(error),
+                });
+            }
+
+            tracing::warn!("Could not {step}, attempt {tried} of {attempts}: {error}");
+            tokio::time::sleep(self.backoff()).await;
+        }
+    }
+}
@@ -0,0 +1,107 @@
+//! Driving a GAN Robot, so scrambles can be applied to the cube without
+//! touching it.
+
+use std::time::Duration;
+
+use btleplug::{
+    api::{Characteristic, Peripheral as _, WriteType},
+    platform::Peripheral,
+};
+use tokio::time::{Instant, sleep};
+use tracing::debug;
+
+use crate::{
+    GAN_ROBOT_MOVE_CHARACTERISTIC, GAN_ROBOT_STATUS_CHARACTERISTIC,
+    algorithm::Algorithm,
+    bluetooth::{scan_for_accessory, select_adapter},
+    error::{ProtocolError, RobotError},
+    protocol::robot::{self, FACES},
+    scramble,
+};
+
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// How long a packet of moves may take before the robot is assumed stuck.
+const PACKET_TIMEOUT: Duration = Duration::from_secs(30);
+
+pub struct GanRobot {
+    device: Peripheral,
+    moves: Characteristic,
+    status: Characteristic,
+}
+
+impl GanRobot {
+    pub async fn connect(adapter: usize) -> Result<Self, RobotError> {
+        let adapter = select_adapter(adapter).await?;
+        let device = scan_for_accessory(&adapter, "robot", |peripheral| {
+            peripheral
+                .characteristics()
+                .iter()
+                .any(|c| c.uuid == GAN_ROBOT_MOVE_CHARACTERISTIC)
+        })
+        .await?;
+
+        let find = |uuid| {
+            device
+                .characteristics()
+                .into_iter()
+                .find(|c| c.uuid == uuid)
+                .ok_or(ProtocolError::UnknownVersion)
+        };
+
+        let moves = find(GAN_ROBOT_MOVE_CHARACTERISTIC)?;
+        let status = find(GAN_ROBOT_STATUS_CHARACTERISTIC)?;
+
+        Ok(Self {
+            device,
+            moves,
+            status,
+        })
+    }
+
+    /// Performs `algorithm`, returning once the robot has finished. Fails
+    /// before moving if it has turns the robot cannot make.
+    pub async fn perform(&self, algorithm: &Algorithm) -> Result<(), RobotError> {
+        let packets = robot::encode(algorithm)?;
+
+        for packet in packets {
+            self.device
+                .write(&self.moves, &packet, WriteType::WithResponse)
+                .await?;
+            self.wait_until_idle().await?;
+        }
+
+        Ok(())
+    }
+
+    /// Generates a scramble the robot can perform, performs it and returns it.
+    pub async fn scramble(&self, length: usize) -> Result<Algorithm, RobotError> {
+        let scramble = scramble::random_moves(length, &FACES, &mut rand::rng());
+        self.perform(&scramble).await?;
+        Ok(scramble)
+    }
+
+    pub async fn disconnect(self) -> Result<(), RobotError> {
+        Ok(self.device.disconnect().await?)
+    }
+
+    /// The first byte of the status is the number of moves still queued.
+    async fn wait_until_idle(&self) -> Result<(), RobotError> {
+        let deadline = Instant::now() + PACKET_TIMEOUT;
+
+        loop {
+            sleep(POLL_INTERVAL).await;
+
+            let status = self.device.read(&self.status).await?;
+            match status.first() {
+                Some(0) => return Ok(()),
+                Some(queued) => debug!("Robot has {queued} moves queued"),
+                None => return Err(ProtocolError::PacketTooShort(0).into()),
+            }
+
+            if Instant::now() >= deadline {
+                return Err(RobotError::Timeout);
+            }
+        }
+    }
+}
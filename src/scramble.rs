@@ -0,0 +1,55 @@
+//! Random move scrambles.
+
+use rand::{Rng, seq::IndexedRandom};
+
+use crate::{
+    algorithm::{Algorithm, Layer, Turn},
+    cube::Face,
+};
+
+const AMOUNTS: [i8; 3] = [1, 2, -1];
+
+/// A scramble of `length` turns on `faces`. The same face is never turned
+/// twice in a row and opposite faces at most twice, so no turns cancel.
+pub fn random_moves(length: usize, faces: &[Face], rng: &mut impl Rng) -> Algorithm {
+    let mut turns: Vec<Turn> = Vec::with_capacity(length);
+
+    while turns.len() < length {
+        let Some(&face) = faces.choose(rng) else {
+            break;
+        };
+
+        let redundant = match turns[..] {
+            [
+                ..,
+                Turn {
+                    layer: Layer::Face(before),
+                    ..
+                },
+                Turn {
+                    layer: Layer::Face(last),
+                    ..
+                },
+            ] => last == face || (last == face.opposite() && before == face),
+            [
+                ..,
+                Turn {
+                    layer: Layer::Face(last),
+                    ..
+                },
+            ] => last == face,
+            _ => false,
+        };
+
+        if redundant {
+            continue;
+        }
+
+        turns.push(Turn {
+            layer: Layer::Face(face),
+            amount: *AMOUNTS.choose(rng).expect("amounts are not empty"),
+        });
+    }
+
+    Algorithm(turns)
+}
@@ -0,0 +1,438 @@
+//! A virtual cube that plays a scripted scenario, for trying binds and
+//! testing consumers without hardware. A scenario has one step per line:
+//!
+//! ```text
+//! # Moves are turned a pace apart, 100ms unless changed.
+//! pace 150ms
+//! R U R' U'
+//! wait 2s
+//! battery 15
+//! # Drops the connection for a second, then reconnects.
+//! disconnect 1s
+//! F R U R' U' F'
+//! # Drops the connection for good.
+//! disconnect
+//! ```
+//!
+//! The cube stays connected after the last step until it is cancelled.
+//!
+//! Sessions recorded with [`record_session`] are scenarios too, so a past
+//! session can be played back through the binds to see why one fired or
+//! did not, at another speed or one event at a time with a [`Playback`].
+
+use std::{
+    fs::{self, File},
+    io::Write,
+    path::{Path, PathBuf},
+    str::FromStr,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use futures::{Stream, StreamExt};
+use tokio::{
+    select,
+    sync::{
+        Notify,
+        broadcast::{self, Receiver, Sender},
+    },
+    time::{Instant, sleep},
+};
+use tokio_util::sync::CancellationToken;
+use tracing::{Instrument, debug, info, info_span};
+
+use crate::{
+    algorithm::Algorithm,
+    cube::{CubeEvent, Move},
+    error::{CubeError, ScenarioError},
+    source::CubeSource,
+};
+
+/// The time between moves until a `pace` step changes it.
+pub const DEFAULT_PACE: Duration = Duration::from_millis(100);
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Step {
+    Moves(Vec<Move>),
+    Wait(Duration),
+    /// Sets the time before each following move.
+    Pace(Duration),
+    Battery(u8),
+    /// Disconnects, reconnecting after the duration if there is one.
+    Disconnect(Option<Duration>),
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Scenario {
+    pub steps: Vec<Step>,
+}
+
+impl Scenario {
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, ScenarioError> {
+        fs::read_to_string(path)?.parse()
+    }
+
+    /// Sends the scenario's events as `playback` allows, returning whether
+    /// the cube is still connected at the end.
+    async fn play(&self, tx: &Sender<CubeEvent>, playback: &Playback) -> bool {
+        let mut pace = DEFAULT_PACE;
+        for step in &self.steps {
+            debug!(?step, "Playing");
+
+            match step {
+                Step::Moves(moves) => {
+                    for m in moves {
+                        playback.sleep(pace).await;
+                        playback.proceed().await;
+                        let _ = tx.send(CubeEvent::Move(*m));
+                    }
+                }
+                Step::Wait(duration) => playback.sleep(*duration).await,
+                Step::Pace(duration) => pace = *duration,
+                Step::Battery(level) => {
+                    playback.proceed().await;
+                    let _ = tx.send(CubeEvent::Battery(*level));
+                }
+                Step::Disconnect(None) => {
+                    playback.proceed().await;
+                    let _ = tx.send(CubeEvent::Disconnected);
+                    return false;
+                }
+                Step::Disconnect(Some(duration)) => {
+                    playback.proceed().await;
+                    let _ = tx.send(CubeEvent::Disconnected);
+                    playback.sleep(*duration).await;
+                    let _ = tx.send(CubeEvent::Connected);
+                }
+            }
+        }
+
+        true
+    }
+}
+
+impl FromStr for Scenario {
+    type Err = ScenarioError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut steps = Vec::new();
+
+        for (index, line) in s.lines().enumerate() {
+            let line = line.split('#').next().unwrap_or_default().trim();
+            if line.is_empty() {
+                continue;
+            }
+
+            steps.push(parse_step(line).map_err(|reason| ScenarioError::Invalid {
+                line: index + 1,
+                reason,
+            })?);
+        }
+
+        Ok(Self { steps })
+    }
+}
+
+fn parse_step(line: &str) -> Result<Step, String> {
+    let (keyword, argument) = line
+        .split_once(char::is_whitespace)
+        .map_or((line, ""), |(keyword, argument)| (keyword, argument.trim()));
+
+    match keyword {
+        "wait" => Ok(Step::Wait(parse_duration(argument)?)),
+        "pace" => Ok(Step::Pace(parse_duration(argument)?)),
+        "battery" => argument
+            .trim_end_matches('%')
+            .parse()
+            .ok()
+            .filter(|level| *level <= 100)
+            .map(Step::Battery)
+            .ok_or_else(|| format!("invalid battery level `{argument}`")),
+        "disconnect" if argument.is_empty() => Ok(Step::Disconnect(None)),
+        "disconnect" => Ok(Step::Disconnect(Some(parse_duration(argument)?))),
+        _ => line
+            .parse::<Algorithm>()
+            .and_then(|algorithm| algorithm.to_moves())
+            .map(Step::Moves)
+            .map_err(|e| e.to_string()),
+    }
+}
+
+/// Parses durations such as `500ms` or `1.5s`.
+fn parse_duration(s: &str) -> Result<Duration, String> {
+    let invalid = || format!("invalid duration `{s}`, e.g. `500ms` or `2s`");
+
+    let (number, unit) = if let Some(number) = s.strip_suffix("ms") {
+        (number, 0.001)
+    } else if let Some(number) = s.strip_suffix('s') {
+        (number, 1.0)
+    } else {
+        return Err(invalid());
+    };
+
+    number
+        .trim()
+        .parse::<f64>()
+        .ok()
+        .map(|number| number * unit)
+        .and_then(|seconds| Duration::try_from_secs_f64(seconds).ok())
+        .ok_or_else(invalid)
+}
+
+/// The speed a scenario plays at and whether it is paused, shared between
+/// its clones. While paused, each [`Playback::step`] lets one more event
+/// through and the waits before it are skipped.
+#[derive(Debug, Clone)]
+pub struct Playback {
+    state: Arc<Mutex<PlaybackState>>,
+    changed: Arc<Notify>,
+    speed: f64,
+}
+
+#[derive(Debug, Default)]
+struct PlaybackState {
+    paused: bool,
+    steps: usize,
+    finished: bool,
+}
+
+impl Default for Playback {
+    fn default() -> Self {
+        Self::new(1.0)
+    }
+}
+
+impl Playback {
+    /// Plays at `speed` times the recorded speed, e.g. `0.5` for half speed.
+    pub fn new(speed: f64) -> Self {
+        Self {
+            state: Arc::default(),
+            changed: Arc::new(Notify::new()),
+            speed,
+        }
+    }
+
+    pub fn speed(&self) -> f64 {
+        self.speed
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.state.lock().unwrap().paused
+    }
+
+    pub fn set_paused(&self, paused: bool) {
+        let mut state = self.state.lock().unwrap();
+        state.paused = paused;
+        state.steps = 0;
+        self.changed.notify_waiters();
+    }
+
+    /// Pauses if playing and plays if paused, returning whether it is now
+    /// paused.
+    pub fn toggle(&self) -> bool {
+        let paused = !self.is_paused();
+        self.set_paused(paused);
+        paused
+    }
+
+    /// Lets the next event through while paused.
+    pub fn step(&self) {
+        self.state.lock().unwrap().steps += 1;
+        self.changed.notify_waiters();
+    }
+
+    /// Resolves once the whole scenario has been played.
+    pub async fn finished(&self) {
+        loop {
+            let changed = self.changed.notified();
+            if self.state.lock().unwrap().finished {
+                return;
+            }
+            changed.await;
+        }
+    }
+
+    fn finish(&self) {
+        self.state.lock().unwrap().finished = true;
+        self.changed.notify_waiters();
+    }
+
+    /// Waits until the next event may be sent.
+    async fn proceed(&self) {
+        loop {
+            // Created before checking so a change in between is not missed.
+            let changed = self.changed.notified();
+            {
+                let mut state = self.state.lock().unwrap();
+                if !state.paused {
+                    return;
+                }
+                if state.steps > 0 {
+                    state.steps -= 1;
+                    return;
+                }
+            }
+            changed.await;
+        }
+    }
+
+    async fn sleep(&self, duration: Duration) {
+        if self.is_paused() {
+            return;
+        }
+
+        if self.speed == 1.0 {
+            sleep(duration).await;
+        } else {
+            // Slow enough speeds wait longer than a duration can hold.
+            let scaled = Duration::try_from_secs_f64(duration.as_secs_f64() / self.speed)
+                .unwrap_or(Duration::MAX);
+            sleep(scaled).await;
+        }
+    }
+}
+
+/// A cube that plays a [`Scenario`] instead of connecting to hardware.
+#[derive(Debug, Clone, Default)]
+pub struct SimulatedCubeSource {
+    scenario: Scenario,
+    playback: Playback,
+}
+
+impl SimulatedCubeSource {
+    pub fn new(scenario: Scenario) -> Self {
+        Self {
+            scenario,
+            playback: Playback::default(),
+        }
+    }
+
+    /// Plays the scenario as `playback` says rather than as written.
+    pub fn playback(mut self, playback: Playback) -> Self {
+        self.playback = playback;
+        self
+    }
+}
+
+impl CubeSource for SimulatedCubeSource {
+    async fn connect(self, cancel: CancellationToken) -> Result<Receiver<CubeEvent>, CubeError> {
+        let (tx, rx) = broadcast::channel::<CubeEvent>(16);
+        let _ = tx.send(CubeEvent::Connected);
+
+        let simulate = async move {
+            let connected = select! {
+                connected = self.scenario.play(&tx, &self.playback) => connected,
+                _ = cancel.cancelled() => true,
+            };
+            self.playback.finish();
+
+            if connected {
+                cancel.cancelled().await;
+                let _ = tx.send(CubeEvent::Disconnected);
+            }
+        };
+        tokio::spawn(simulate.instrument(info_span!("simulator")));
+
+        Ok(rx)
+    }
+}
+
+/// Turns cube events into the lines of a scenario that plays them back with
+/// the same timing.
+#[derive(Debug)]
+pub struct SessionRecorder {
+    last: Instant,
+    /// When the cube disconnected, if it has not connected again since.
+    disconnected: Option<Instant>,
+}
+
+impl SessionRecorder {
+    /// Starts recording at `at`, each move being turned exactly after the
+    /// wait before it.
+    pub fn new(at: Instant) -> Self {
+        Self {
+            last: at,
+            disconnected: None,
+        }
+    }
+
+    /// The first lines of a recording.
+    pub fn header(&self) -> String {
+        "# Recorded by triplicata\npace 0ms\n".to_string()
+    }
+
+    /// The lines playing `event`, received at `at`.
+    pub fn push(&mut self, event: &CubeEvent, at: Instant) -> Option<String> {
+        let line = match event {
+            CubeEvent::Move(m) => m.to_string(),
+            CubeEvent::Battery(level) => format!("battery {level}"),
+            CubeEvent::Disconnected => {
+                let wait = self.wait(at);
+                self.disconnected = Some(at);
+                return wait;
+            }
+            CubeEvent::Connected => {
+                let disconnected = self.disconnected.take()?;
+                self.last = at;
+                return Some(format!(
+                    "disconnect {}ms\n",
+                    at.duration_since(disconnected).as_millis()
+                ));
+            }
+            _ => return None,
+        };
+
+        // Lines cannot be played while disconnected, so they wait for the
+        // reconnection.
+        if self.disconnected.is_some() {
+            return None;
+        }
+
+        Some(format!("{}{line}\n", self.wait(at).unwrap_or_default()))
+    }
+
+    /// The last line, if the cube is still disconnected.
+    pub fn finish(&self) -> Option<String> {
+        self.disconnected.map(|_| "disconnect\n".to_string())
+    }
+
+    fn wait(&mut self, at: Instant) -> Option<String> {
+        let wait = at.duration_since(std::mem::replace(&mut self.last, at));
+        (!wait.is_zero()).then(|| format!("wait {}ms\n", wait.as_millis()))
+    }
+}
+
+/// Writes `events` to `path` as a scenario until they end or `cancel` is
+/// cancelled, replacing what was there.
+pub async fn record_session(
+    path: PathBuf,
+    mut events: impl Stream<Item = CubeEvent> + Unpin,
+    cancel: CancellationToken,
+) -> Result<(), ScenarioError> {
+    let mut file = File::create(&path)?;
+    let mut recorder = SessionRecorder::new(Instant::now());
+    file.write_all(recorder.header().as_bytes())?;
+
+    info!("Recording the session to {}", path.display());
+
+    loop {
+        let event = select! {
+            event = events.next() => event,
+            _ = cancel.cancelled() => break,
+        };
+
+        let Some(event) = event else {
+            break;
+        };
+
+        if let Some(lines) = recorder.push(&event, Instant::now()) {
+            file.write_all(lines.as_bytes())?;
+        }
+    }
+
+    if let Some(line) = recorder.finish() {
+        file.write_all(line.as_bytes())?;
+    }
+
+    Ok(())
+}
@@ -0,0 +1,121 @@
+//! Smoothing of the cube's orientation, whose raw readings jitter too much
+//! to steer a pointer or a joystick with.
+
+use std::{f32::consts::TAU, time::Duration};
+
+use serde::{Deserialize, Serialize};
+
+use crate::cube::Quaternion;
+
+/// How orientations are smoothed, e.g. `Ema(alpha: 0.3)` or
+/// `OneEuro(min_cutoff: 1.0, beta: 0.01)`.
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq)]
+pub enum Smoothing {
+    /// Moves `alpha` of the way to each new orientation, from 0 for frozen to
+    /// 1 for unsmoothed.
+    Ema { alpha: f32 },
+    /// The one euro filter, smoothing hard while the cube is held still and
+    /// less the faster it turns, so slow aiming is steady and quick turns
+    /// do not lag.
+    OneEuro {
+        /// Hertz of jitter let through while still, lower for steadier.
+        #[serde(default = "default_cutoff")]
+        min_cutoff: f32,
+        /// How quickly the cutoff rises with speed, higher for less lag.
+        #[serde(default)]
+        beta: f32,
+        /// Hertz the speed itself is smoothed at.
+        #[serde(default = "default_cutoff")]
+        d_cutoff: f32,
+    },
+}
+
+fn default_cutoff() -> f32 {
+    1.0
+}
+
+/// Smooths a stream of orientations.
+#[derive(Debug, Clone)]
+pub struct OrientationFilter {
+    smoothing: Smoothing,
+    /// The last smoothed orientation and when it was read.
+    last: Option<(Duration, [f32; 4])>,
+    /// The smoothed rate of change of each component, per second.
+    speed: [f32; 4],
+}
+
+impl OrientationFilter {
+    pub fn new(smoothing: Smoothing) -> Self {
+        Self {
+            smoothing,
+            last: None,
+            speed: [0.0; 4],
+        }
+    }
+
+    /// Starts over, as after the cube reconnects.
+    pub fn reset(&mut self) {
+        self.last = None;
+        self.speed = [0.0; 4];
+    }
+
+    /// Smooths `q`, read at `at` since a start that is the same for every
+    /// call.
+    pub fn push(&mut self, q: Quaternion, at: Duration) -> Quaternion {
+        let mut raw = [q.x, q.y, q.z, q.w];
+        let Some((last_at, last)) = self.last else {
+            self.last = Some((at, raw));
+            return q;
+        };
+
+        // q and -q are the same orientation, so the one nearer the last is
+        // smoothed towards rather than swinging the long way round.
+        if dot(raw, last) < 0.0 {
+            raw = raw.map(|c| -c);
+        }
+
+        let smoothed = match self.smoothing {
+            Smoothing::Ema { alpha } => lerp(last, raw, alpha.clamp(0.0, 1.0)),
+            Smoothing::OneEuro {
+                min_cutoff,
+                beta,
+                d_cutoff,
+            } => {
+                let dt = at.saturating_sub(last_at).as_secs_f32().max(1e-3);
+                let speed = std::array::from_fn(|i| (raw[i] - last[i]) / dt);
+                self.speed = lerp(self.speed, speed, smoothing_factor(d_cutoff, dt));
+
+                let cutoff = min_cutoff + beta * dot(self.speed, self.speed).sqrt();
+                lerp(last, raw, smoothing_factor(cutoff, dt))
+            }
+        };
+
+        let smoothed = normalize(smoothed);
+        self.last = Some((at, smoothed));
+        let [x, y, z, w] = smoothed;
+        Quaternion { x, y, z, w }
+    }
+}
+
+/// How far to move towards a new reading `dt` seconds after the last to let
+/// through changes below `cutoff` hertz.
+fn smoothing_factor(cutoff: f32, dt: f32) -> f32 {
+    let tau = 1.0 / (TAU * cutoff.max(f32::EPSILON));
+    1.0 / (1.0 + tau / dt)
+}
+
+fn lerp(from: [f32; 4], to: [f32; 4], t: f32) -> [f32; 4] {
+    std::array::from_fn(|i| from[i] + (to[i] - from[i]) * t)
+}
+
+fn dot(a: [f32; 4], b: [f32; 4]) -> f32 {
+    a.iter().zip(b).map(|(a, b)| a * b).sum()
+}
+
+fn normalize(q: [f32; 4]) -> [f32; 4] {
+    let length = dot(q, q).sqrt();
+    if length == 0.0 {
+        return q;
+    }
+    q.map(|c| c / length)
+}
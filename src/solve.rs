@@ -0,0 +1,297 @@
+//! Solves picked out of the move stream, exported as timestamped move lists
+//! so reconstructions and videos can be lined up with the moves.
+
+use std::{
+    fmt::Write,
+    fs,
+    path::PathBuf,
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+use futures::{Stream, StreamExt};
+use serde::Serialize;
+use tokio::select;
+use tokio_util::sync::CancellationToken;
+use tracing::info;
+
+use crate::{
+    cube::{CubeEvent, CubeState, Move},
+    error::SolveError,
+    facelet::FaceletCube,
+    phase::Phase,
+};
+
+/// How long a scrambled cube has to rest before the next move starts a solve.
+pub const INSPECTION: Duration = Duration::from_millis(1500);
+
+/// How long the last move stays on screen in subtitles.
+const LAST_SUBTITLE: Duration = Duration::from_secs(1);
+
+#[derive(Debug, Clone)]
+pub struct Solve {
+    /// When the first move was made.
+    pub started: SystemTime,
+    /// The cube before the first move.
+    pub scramble: CubeState,
+    pub moves: Vec<TimedMove>,
+    pub duration: Duration,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TimedMove {
+    pub m: Move,
+    /// Time since the first move of the solve.
+    pub offset: Duration,
+}
+
+/// The time and moves spent in one phase of a solve.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Split {
+    pub name: String,
+    pub duration: Duration,
+    pub moves: usize,
+}
+
+/// Follows cube events and returns each solve once the cube is solved.
+#[derive(Debug, Default)]
+pub struct SolveRecorder {
+    state: CubeState,
+    last_move: Option<Instant>,
+    /// The solve in progress and when its first move was received.
+    current: Option<(Instant, Solve)>,
+}
+
+#[derive(Serialize)]
+struct ExportedSolve {
+    started_ms: u64,
+    duration_ms: u64,
+    moves: Vec<ExportedMove>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    splits: Vec<ExportedSplit>,
+}
+
+#[derive(Serialize)]
+struct ExportedMove {
+    #[serde(rename = "move")]
+    notation: String,
+    offset_ms: u64,
+}
+
+#[derive(Serialize)]
+struct ExportedSplit {
+    name: String,
+    duration_ms: u64,
+    moves: usize,
+}
+
+impl SolveRecorder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Updates the tracked cube with `event`, received at `at`.
+    pub fn push(&mut self, event: &CubeEvent, at: Instant) -> Option<Solve> {
+        match *event {
+            CubeEvent::Move(m) => self.push_move(m, at),
+            CubeEvent::StateSync(state) => {
+                self.state = state;
+                self.current = None;
+                None
+            }
+            CubeEvent::Disconnected | CubeEvent::Lagged(_) => {
+                self.current = None;
+                None
+            }
+            _ => None,
+        }
+    }
+
+    fn push_move(&mut self, m: Move, at: Instant) -> Option<Solve> {
+        let rested = self
+            .last_move
+            .is_none_or(|last| at.duration_since(last) >= INSPECTION);
+        self.last_move = Some(at);
+
+        if self.current.is_none() && rested && !self.state.is_solved() {
+            let solve = Solve {
+                started: SystemTime::now() - Instant::now().saturating_duration_since(at),
+                scramble: self.state,
+                moves: Vec::new(),
+                duration: Duration::ZERO,
+            };
+            self.current = Some((at, solve));
+        }
+
+        self.state.apply(m);
+
+        let (start, solve) = self.current.as_mut()?;
+        let offset = at.duration_since(*start);
+        solve.moves.push(TimedMove { m, offset });
+
+        if !self.state.is_solved() {
+            return None;
+        }
+
+        let (_, mut solve) = self.current.take()?;
+        solve.duration = offset;
+        Some(solve)
+    }
+}
+
+impl Solve {
+    /// Splits the solve into `phases`, ended in order. A move can end several
+    /// phases, leaving the later ones empty, and the last phase always ends
+    /// with the solve.
+    pub fn splits(&self, phases: &[Phase]) -> Vec<Split> {
+        let mut splits = Vec::with_capacity(phases.len());
+        let (mut start, mut started) = (0, Duration::ZERO);
+        let mut state = self.scramble;
+
+        for (i, m) in self.moves.iter().enumerate() {
+            state.apply(m.m);
+            let cube = FaceletCube::from(&state);
+
+            while splits.len() + 1 < phases.len()
+                && phases[splits.len()]
+                    .end
+                    .reached(&cube, i + 1 - start, m.offset)
+            {
+                splits.push(Split {
+                    name: phases[splits.len()].name.clone(),
+                    duration: m.offset - started,
+                    moves: i + 1 - start,
+                });
+                (start, started) = (i + 1, m.offset);
+            }
+        }
+
+        for phase in &phases[splits.len()..] {
+            splits.push(Split {
+                name: phase.name.clone(),
+                duration: self.duration.saturating_sub(started),
+                moves: self.moves.len() - start,
+            });
+            (start, started) = (self.moves.len(), self.duration);
+        }
+
+        splits
+    }
+
+    pub fn to_json(&self, phases: &[Phase]) -> Result<String, SolveError> {
+        let solve = ExportedSolve {
+            started_ms: self
+                .started
+                .duration_since(UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_millis() as u64,
+            duration_ms: self.duration.as_millis() as u64,
+            moves: self
+                .moves
+                .iter()
+                .map(|m| ExportedMove {
+                    notation: m.m.to_string(),
+                    offset_ms: m.offset.as_millis() as u64,
+                })
+                .collect(),
+            splits: self
+                .splits(phases)
+                .into_iter()
+                .map(|split| ExportedSplit {
+                    name: split.name,
+                    duration_ms: split.duration.as_millis() as u64,
+                    moves: split.moves,
+                })
+                .collect(),
+        };
+
+        Ok(serde_json::to_string_pretty(&solve)?)
+    }
+
+    /// Each move as a subtitle shown until the next move.
+    pub fn to_srt(&self) -> String {
+        let mut srt = String::new();
+
+        for (i, m) in self.moves.iter().enumerate() {
+            let end = self
+                .moves
+                .get(i + 1)
+                .map_or(m.offset + LAST_SUBTITLE, |next| next.offset);
+
+            let _ = write!(
+                srt,
+                "{}\n{} --> {}\n{}\n\n",
+                i + 1,
+                srt_time(m.offset),
+                srt_time(end),
+                m.m
+            );
+        }
+
+        srt
+    }
+}
+
+/// `hours:minutes:seconds,milliseconds`
+fn srt_time(time: Duration) -> String {
+    let millis = time.as_millis();
+    format!(
+        "{:02}:{:02}:{:02},{:03}",
+        millis / 3_600_000,
+        millis / 60_000 % 60,
+        millis / 1000 % 60,
+        millis % 1000
+    )
+}
+
+/// Writes every solve in `events` to `directory` as JSON and SRT files named
+/// after the time the solve started, with splits for `phases`, until `cancel`
+/// is cancelled.
+pub async fn export_solves(
+    directory: PathBuf,
+    phases: Vec<Phase>,
+    mut events: impl Stream<Item = CubeEvent> + Unpin,
+    cancel: CancellationToken,
+) -> Result<(), SolveError> {
+    fs::create_dir_all(&directory)?;
+
+    let mut recorder = SolveRecorder::new();
+
+    loop {
+        let event = select! {
+            event = events.next() => event,
+            _ = cancel.cancelled() => return Ok(()),
+        };
+
+        let Some(event) = event else {
+            return Ok(());
+        };
+
+        let Some(solve) = recorder.push(&event, Instant::now()) else {
+            continue;
+        };
+
+        let name = solve
+            .started
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_millis();
+        let path = directory.join(format!("solve-{name}"));
+
+        fs::write(path.with_extension("json"), solve.to_json(&phases)?)?;
+        fs::write(path.with_extension("srt"), solve.to_srt())?;
+
+        info!(
+            "Exported a {:.2}s solve to {}",
+            solve.duration.as_secs_f64(),
+            path.display()
+        );
+        for split in solve.splits(&phases) {
+            info!(
+                "{}: {:.2}s, {} moves",
+                split.name,
+                split.duration.as_secs_f64(),
+                split.moves
+            );
+        }
+    }
+}
@@ -0,0 +1,148 @@
+//! Playing sound files and generated tones through the platform's player, for
+//! alarms and the cues played as binds fire.
+
+use std::{collections::HashMap, f32::consts::TAU, fs, path::Path, time::Duration};
+
+use tokio::{process::Command, select, sync::broadcast};
+use tokio_util::sync::CancellationToken;
+use tracing::{debug, warn};
+
+use crate::{
+    config::Cue,
+    metrics::{self, Channel},
+    state_machine::FiredBind,
+};
+
+/// How long a [`Cue::Tone`] lasts.
+pub const TONE_LENGTH: Duration = Duration::from_millis(150);
+
+const SAMPLE_RATE: u32 = 22_050;
+
+/// Samples faded in and out at each end of a tone, so it does not click.
+const FADE: u32 = 220;
+
+#[cfg(target_os = "macos")]
+pub fn play_command(path: &Path) -> Command {
+    let mut command = Command::new("afplay");
+    command.arg(path);
+    command
+}
+
+/// The path is passed through the environment so it is never parsed as
+/// PowerShell. Only WAV files can be played.
+#[cfg(target_os = "windows")]
+pub fn play_command(path: &Path) -> Command {
+    let mut command = Command::new("powershell");
+    command
+        .args([
+            "-NoProfile",
+            "-Command",
+            "(New-Object Media.SoundPlayer $env:TRIPLICATA_SOUND).PlaySync()",
+        ])
+        .env("TRIPLICATA_SOUND", path);
+    command
+}
+
+#[cfg(not(any(target_os = "macos", target_os = "windows")))]
+pub fn play_command(path: &Path) -> Command {
+    let mut command = Command::new("paplay");
+    command.arg(path);
+    command
+}
+
+/// A mono 16-bit WAV file of a sine wave at `hertz`.
+pub fn tone_wav(hertz: u32, length: Duration) -> Vec<u8> {
+    let samples = (SAMPLE_RATE as u128 * length.as_millis() / 1000) as u32;
+    let data_size = samples * 2;
+
+    let mut wav = Vec::with_capacity(44 + data_size as usize);
+    wav.extend_from_slice(b"RIFF");
+    wav.extend_from_slice(&(36 + data_size).to_le_bytes());
+    wav.extend_from_slice(b"WAVEfmt ");
+    wav.extend_from_slice(&16u32.to_le_bytes());
+    // PCM, one channel.
+    wav.extend_from_slice(&1u16.to_le_bytes());
+    wav.extend_from_slice(&1u16.to_le_bytes());
+    wav.extend_from_slice(&SAMPLE_RATE.to_le_bytes());
+    wav.extend_from_slice(&(SAMPLE_RATE * 2).to_le_bytes());
+    wav.extend_from_slice(&2u16.to_le_bytes());
+    wav.extend_from_slice(&16u16.to_le_bytes());
+    wav.extend_from_slice(b"data");
+    wav.extend_from_slice(&data_size.to_le_bytes());
+
+    for i in 0..samples {
+        let t = i as f32 / SAMPLE_RATE as f32;
+        let fade = (i.min(samples - i) as f32 / FADE as f32).min(1.0);
+        let sample = (TAU * hertz as f32 * t).sin() * fade * 0.5;
+        wav.extend_from_slice(&((sample * i16::MAX as f32) as i16).to_le_bytes());
+    }
+
+    wav
+}
+
+/// Starts playing `cue` without waiting for it to finish.
+pub fn play(cue: &Cue) {
+    let path = match cue {
+        Cue::Sound(path) => path.clone(),
+        Cue::Tone(hertz) => {
+            let path = std::env::temp_dir().join(format!("triplicata-tone-{hertz}.wav"));
+            if !path.exists()
+                && let Err(e) = fs::write(&path, tone_wav(*hertz, TONE_LENGTH))
+            {
+                warn!("Could not write the tone to {}: {e}", path.display());
+                return;
+            }
+            path
+        }
+    };
+
+    match play_command(&path).spawn() {
+        Ok(mut child) => {
+            tokio::spawn(async move {
+                if let Err(error) = child.wait().await {
+                    debug!(%error, "Cue failed");
+                }
+            });
+        }
+        Err(e) => warn!("Could not play {}: {e}", path.display()),
+    }
+}
+
+/// The cue for `fired`, its own or else that of the first of its groups
+/// with one in `group_cues`.
+pub fn cue_for<'a>(fired: &'a FiredBind, group_cues: &'a HashMap<String, Cue>) -> Option<&'a Cue> {
+    fired.bind.cue.as_ref().or_else(|| {
+        fired
+            .bind
+            .groups
+            .iter()
+            .find_map(|group| group_cues.get(group))
+    })
+}
+
+/// Plays the cue of each bind in `fired` until `cancel` is cancelled.
+pub async fn play_cues(
+    mut fired: broadcast::Receiver<FiredBind>,
+    group_cues: HashMap<String, Cue>,
+    cancel: CancellationToken,
+) {
+    loop {
+        let fired = select! {
+            fired = fired.recv() => fired,
+            _ = cancel.cancelled() => return,
+        };
+
+        match fired {
+            Ok(fired) => {
+                if let Some(cue) = cue_for(&fired, &group_cues) {
+                    play(cue);
+                }
+            }
+            Err(broadcast::error::RecvError::Lagged(count)) => {
+                metrics::channel_drops(Channel::FiredBinds, count);
+                continue;
+            }
+            Err(broadcast::error::RecvError::Closed) => return,
+        }
+    }
+}
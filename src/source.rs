@@ -0,0 +1,84 @@
+use std::{
+    future::Future,
+    pin::Pin,
+    task::{Context, Poll},
+    time::Duration,
+};
+
+use futures::Stream;
+use tokio::sync::broadcast::Receiver;
+use tokio_stream::wrappers::{BroadcastStream, errors::BroadcastStreamRecvError};
+use tokio_util::sync::CancellationToken;
+
+use crate::{
+    cube::CubeEvent,
+    error::CubeError,
+    metrics::{self, Channel},
+};
+
+/// How often native sources check whether the cube went silent, and ask it
+/// for its battery level unless told otherwise, so a connection that has
+/// silently died is noticed.
+pub const HEARTBEAT_INTERVAL: Duration = Duration::from_millis(500);
+
+/// How long a native source waits without hearing from the cube before it
+/// gives up on the connection and sends [`CubeEvent::Disconnected`]. Some
+/// bluetooth stacks otherwise keep a dead connection open indefinitely.
+pub const SILENCE_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// How long to wait without hearing from a cube asked for its battery level
+/// every `battery_interval`. An idle cube only talks to answer, so asking
+/// less often than every heartbeat stretches the wait to match.
+pub fn silence_timeout(battery_interval: Duration) -> Duration {
+    SILENCE_TIMEOUT.max(battery_interval * 4)
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub trait CubeSource {
+    /// Connects to the cube, which stays connected until `cancel` is
+    /// cancelled.
+    fn connect(
+        self,
+        cancel: CancellationToken,
+    ) -> impl Future<Output = Result<Receiver<CubeEvent>, CubeError>> + Send;
+}
+
+/// Browser futures are not `Send`, so sources on the web are only required to
+/// be polled from the thread they were created on.
+#[cfg(target_arch = "wasm32")]
+pub trait CubeSource {
+    fn connect(
+        self,
+        cancel: CancellationToken,
+    ) -> impl Future<Output = Result<Receiver<CubeEvent>, CubeError>>;
+}
+
+/// A [`Stream`] of cube events. Events missed by a slow consumer are reported
+/// as a single [`CubeEvent::Lagged`].
+pub struct CubeEventStream {
+    inner: BroadcastStream<CubeEvent>,
+}
+
+impl From<Receiver<CubeEvent>> for CubeEventStream {
+    fn from(value: Receiver<CubeEvent>) -> Self {
+        Self {
+            inner: BroadcastStream::new(value),
+        }
+    }
+}
+
+impl Stream for CubeEventStream {
+    type Item = CubeEvent;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        Pin::new(&mut self.inner).poll_next(cx).map(|event| {
+            event.map(|event| match event {
+                Ok(event) => event,
+                Err(BroadcastStreamRecvError::Lagged(count)) => {
+                    metrics::channel_drops(Channel::EventStream, count);
+                    CubeEvent::Lagged(count)
+                }
+            })
+        })
+    }
+}
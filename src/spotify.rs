@@ -0,0 +1,172 @@
+//! Spotify playback control through the Web API, for what media keys cannot
+//! do.
+
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
+
+use crate::error::SpotifyError;
+
+const API: &str = "https://api.spotify.com/v1";
+const TOKEN: &str = "https://accounts.spotify.com/api/token";
+
+/// An application's client id and a refresh token from the PKCE flow with
+/// the `user-modify-playback-state`, `user-read-playback-state`,
+/// `user-read-currently-playing` and `user-library-modify` scopes, e.g.
+/// `(client_id: "...", refresh_token: "...")`.
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct Spotify {
+    pub client_id: String,
+    pub refresh_token: String,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, Eq)]
+pub enum SpotifyCommand {
+    /// Plays a playlist, album or artist by URI, e.g.
+    /// `Play("spotify:playlist:37i9dQZF1DX8NTLI2TtZa6")`.
+    Play(String),
+    /// Saves the current track to the library.
+    Like,
+    /// Moves playback to the device with this name and keeps playing.
+    Transfer(String),
+}
+
+impl fmt::Display for SpotifyCommand {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SpotifyCommand::Play(uri) => write!(f, "play {uri}"),
+            SpotifyCommand::Like => write!(f, "like"),
+            SpotifyCommand::Transfer(device) => write!(f, "transfer to {device}"),
+        }
+    }
+}
+
+pub struct SpotifyClient {
+    spotify: Spotify,
+    agent: ureq::Agent,
+    /// Access tokens last an hour, a new one is fetched when it runs out.
+    access_token: Option<String>,
+}
+
+impl SpotifyClient {
+    pub fn new(spotify: Spotify) -> Self {
+        Self {
+            spotify,
+            agent: ureq::Agent::new(),
+            access_token: None,
+        }
+    }
+
+    pub fn send(&mut self, command: &SpotifyCommand) -> Result<(), SpotifyError> {
+        match command {
+            SpotifyCommand::Play(uri) => {
+                self.request("PUT", "me/player/play", Some(json!({ "context_uri": uri })))?;
+            }
+            SpotifyCommand::Like => {
+                let playing = self.request("GET", "me/player/currently-playing", None)?;
+                let id = playing["item"]["id"]
+                    .as_str()
+                    .ok_or(SpotifyError::NothingPlaying)?;
+                self.request("PUT", "me/tracks", Some(json!({ "ids": [id] })))?;
+            }
+            SpotifyCommand::Transfer(name) => {
+                let devices = self.request("GET", "me/player/devices", None)?;
+                let id = devices["devices"]
+                    .as_array()
+                    .into_iter()
+                    .flatten()
+                    .find(|device| {
+                        device["name"]
+                            .as_str()
+                            .is_some_and(|device| device.eq_ignore_ascii_case(name))
+                    })
+                    .and_then(|device| device["id"].as_str())
+                    .ok_or_else(|| SpotifyError::NoDevice(name.clone()))?;
+                self.request(
+                    "PUT",
+                    "me/player",
+                    Some(json!({ "device_ids": [id], "play": true })),
+                )?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Makes a request, refreshing the access token once if it ran out.
+    fn request(
+        &mut self,
+        method: &str,
+        path: &str,
+        body: Option<Value>,
+    ) -> Result<Value, SpotifyError> {
+        let mut refreshed = false;
+
+        loop {
+            let token = match &self.access_token {
+                Some(token) => token.clone(),
+                None => {
+                    refreshed = true;
+                    self.refresh()?
+                }
+            };
+
+            let request = self
+                .agent
+                .request(method, &format!("{API}/{path}"))
+                .set("Authorization", &format!("Bearer {token}"));
+            let response = match &body {
+                Some(body) => request.send_json(body),
+                None => request.call(),
+            };
+
+            return match response {
+                // Playback commands answer 204 without a body.
+                Ok(response) => Ok(response.into_json().unwrap_or(Value::Null)),
+                Err(ureq::Error::Status(401, _)) if !refreshed => {
+                    self.access_token = None;
+                    continue;
+                }
+                Err(ureq::Error::Status(status, response)) => Err(SpotifyError::Refused(format!(
+                    "{status} {}",
+                    response.into_string().unwrap_or_default()
+                ))),
+                Err(e) => Err(Box::new(e).into()),
+            };
+        }
+    }
+
+    fn refresh(&mut self) -> Result<String, SpotifyError> {
+        let response: Value = self
+            .agent
+            .post(TOKEN)
+            .send_form(&[
+                ("grant_type", "refresh_token"),
+                ("refresh_token", &self.spotify.refresh_token),
+                ("client_id", &self.spotify.client_id),
+            ])
+            .map_err(|e| match e {
+                ureq::Error::Status(status, response) => SpotifyError::Refused(format!(
+                    "{status} {}",
+                    response.into_string().unwrap_or_default()
+                )),
+                e => Box::new(e).into(),
+            })?
+            .into_json()?;
+
+        // Spotify may rotate the refresh token, the new one is used for the
+        // rest of the session.
+        if let Some(refresh_token) = response["refresh_token"].as_str() {
+            self.spotify.refresh_token = refresh_token.to_string();
+        }
+
+        let token = response["access_token"]
+            .as_str()
+            .ok_or_else(|| SpotifyError::Refused(response.to_string()))?
+            .to_string();
+        self.access_token = Some(token.clone());
+
+        Ok(token)
+    }
+}
@@ -1,31 +1,303 @@
-use std::time::Duration;
+use std::{
+    cmp::Reverse,
+    collections::{HashMap, HashSet},
+    sync::{
+        Arc,
+        atomic::{AtomicBool, AtomicU64, Ordering},
+    },
+    time::Duration,
+};
 
-use tokio::{select, time::Instant};
-use tracing::debug;
+use futures::{Stream, StreamExt};
+#[cfg(not(target_arch = "wasm32"))]
+use tokio::time::{Instant, sleep_until};
+use tokio::{
+    select,
+    sync::{
+        Notify,
+        mpsc::{UnboundedSender, error::SendError},
+    },
+};
+use tokio_util::sync::CancellationToken;
+use tracing::{debug, error, info, info_span, warn};
+#[cfg(target_arch = "wasm32")]
+use web_time::Instant;
 
+#[cfg(not(target_arch = "wasm32"))]
+use crate::morse::MorseDecoder;
 use crate::{
-    config::{Action, Config},
-    cube::Move,
+    config::{Action, Bind, Config, Overlap},
+    cube::{CubeEvent, Move},
+    metrics::{self, Channel, Stage},
+    modifiers::{Modifiers, Typing},
+    rate_limit::RateLimiter,
 };
 
+/// A bind that fired and the moves that fired it.
+#[derive(Debug, Clone)]
+pub struct FiredBind {
+    pub bind: Bind,
+    pub prefix: Vec<Move>,
+}
+
+/// Whether binds are paused, shared between its clones. Moves turned while
+/// paused are ignored.
+#[derive(Debug, Clone, Default)]
+pub struct Pause {
+    paused: Arc<AtomicBool>,
+}
+
+impl Pause {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused.load(Ordering::Relaxed)
+    }
+
+    pub fn set(&self, paused: bool) {
+        self.paused.store(paused, Ordering::Relaxed);
+    }
+
+    /// Pauses if running and resumes if paused, returning whether it is now
+    /// paused.
+    pub fn toggle(&self) -> bool {
+        !self.paused.fetch_xor(true, Ordering::Relaxed)
+    }
+}
+
+/// Stops everything when pulled, shared between its clones: held keys are
+/// released and the binds paused, whatever they were doing.
+#[derive(Debug, Clone, Default)]
+pub struct KillSwitch {
+    pulled: Arc<Notify>,
+    /// Whether the actions queued before the release are being dropped.
+    dropping: Arc<AtomicBool>,
+}
+
+impl KillSwitch {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn pull(&self) {
+        self.pulled.notify_one();
+    }
+
+    async fn pulled(&self) {
+        self.pulled.notified().await;
+    }
+
+    /// Whether actions should be dropped rather than played, as they were
+    /// queued before the kill switch and its `ReleaseAll`.
+    pub fn is_dropping(&self) -> bool {
+        self.dropping.load(Ordering::Relaxed)
+    }
+
+    /// Plays actions again, once the `ReleaseAll` is reached.
+    pub fn stop_dropping(&self) {
+        self.dropping.store(false, Ordering::Relaxed);
+    }
+}
+
+/// How far the output is through the actions sent to it, shared between its
+/// clones, so binds can merge into or cut short what is still playing.
+#[derive(Debug, Clone, Default)]
+pub struct OutputQueue {
+    sent: Arc<AtomicU64>,
+    played: Arc<AtomicU64>,
+    /// Actions sent before this many are skipped rather than played.
+    skip_before: Arc<AtomicU64>,
+}
+
+impl OutputQueue {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Whether the output should skip its next action, as a bind replaced
+    /// it. Asked before each action, which is then marked `played`.
+    pub fn skips_next(&self) -> bool {
+        self.played.load(Ordering::Relaxed) < self.skip_before.load(Ordering::Relaxed)
+    }
+
+    /// Marks the output's current action as played or skipped.
+    pub fn played(&self) {
+        self.played.fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn send(&self, tx: &UnboundedSender<Action>, action: Action) -> Result<(), SendError<Action>> {
+        tx.send(action)?;
+        self.sent.fetch_add(1, Ordering::Relaxed);
+        Ok(())
+    }
+
+    fn sent(&self) -> u64 {
+        self.sent.load(Ordering::Relaxed)
+    }
+
+    /// Whether the first `end` actions sent are not all played yet.
+    fn is_playing(&self, end: u64) -> bool {
+        self.played.load(Ordering::Relaxed) < end
+    }
+
+    /// Skips every action sent so far that the output has not reached.
+    fn skip_sent(&self) {
+        self.skip_before.store(self.sent(), Ordering::Relaxed);
+    }
+}
+
+/// Sends `action` to the output, counting it in `queue` if there is one.
+fn send(
+    tx: &UnboundedSender<Action>,
+    queue: Option<&OutputQueue>,
+    action: Action,
+) -> Result<(), SendError<Action>> {
+    match queue {
+        Some(queue) => queue.send(tx, action)?,
+        None => tx.send(action)?,
+    }
+    metrics::channel_sent(Channel::Actions);
+    Ok(())
+}
+
 #[derive(Debug)]
-pub struct StateMachine {
-    reciever: tokio::sync::broadcast::Receiver<Move>,
+pub struct StateMachine<S> {
+    events: S,
     current_prefix: Vec<Move>,
     tentative_bind: Option<usize>,
+    /// When the first move of the current prefix was turned.
+    prefix_started: Option<Instant>,
+    /// When the next move was received from the cube, if it came from one.
+    received: Option<crate::cube::Instant>,
+    /// Groups whose binds are turned off.
+    disabled_groups: HashSet<String>,
+    /// The digits of the number being entered.
+    number: String,
+    /// Where to report each bind that fires.
+    fired: Option<UnboundedSender<FiredBind>>,
+    pause: Pause,
+    kill_switch: KillSwitch,
+    /// The last moves turned, matched against the kill gesture whether
+    /// paused or not.
+    recent: Vec<Move>,
+    /// When the last of `recent` was turned.
+    recent_turned: Instant,
+    /// What rate limits count from, as they keep times relative to a start.
+    started: Instant,
+    /// The firings counted by the config's rate limit.
+    rate_limiter: RateLimiter,
+    /// The firings counted by each bind's own rate limit.
+    bind_limiters: HashMap<usize, RateLimiter>,
+    /// Firings held back by rate limits, each with when it is due and the
+    /// moves that fired it.
+    queued: Vec<(Duration, usize, Vec<Move>)>,
+    /// How far the output is, for binds that merge into or replace what is
+    /// still playing.
+    output_queue: Option<OutputQueue>,
+    /// How many actions had been sent once each bind's last firing was.
+    playing: HashMap<usize, u64>,
+    /// The keyboard modifiers held, for binds that need some.
+    modifiers: Modifiers,
+    typing: Typing,
+    #[cfg(not(target_arch = "wasm32"))]
+    morse: Option<MorseDecoder>,
+    /// When the last Morse flick was turned.
+    #[cfg(not(target_arch = "wasm32"))]
+    last_flick: Instant,
     config: Config,
 }
 
-impl StateMachine {
-    pub fn new(reciever: tokio::sync::broadcast::Receiver<Move>, config: Config) -> Self {
+impl<S> StateMachine<S> {
+    pub fn new(events: S, mut config: Config) -> Self {
+        config.resolve_platform_actions();
+        config.add_mirrored_binds();
+        #[cfg(not(target_arch = "wasm32"))]
+        config.add_chord_binds();
+        #[cfg(not(target_arch = "wasm32"))]
+        config.add_number_binds();
+        #[cfg(not(target_arch = "wasm32"))]
+        config.add_dial_binds();
+        #[cfg(not(target_arch = "wasm32"))]
+        config.add_movement_binds();
+
         Self {
-            reciever,
-            config,
+            events,
             tentative_bind: None,
             current_prefix: Vec::new(),
+            prefix_started: None,
+            received: None,
+            disabled_groups: config.disabled_groups.iter().cloned().collect(),
+            number: String::new(),
+            fired: None,
+            pause: Pause::new(),
+            kill_switch: KillSwitch::new(),
+            recent: Vec::new(),
+            recent_turned: Instant::now(),
+            started: Instant::now(),
+            rate_limiter: RateLimiter::new(),
+            bind_limiters: HashMap::new(),
+            queued: Vec::new(),
+            output_queue: None,
+            playing: HashMap::new(),
+            modifiers: Modifiers::new(),
+            typing: Typing::new(),
+            #[cfg(not(target_arch = "wasm32"))]
+            morse: config.morse.clone().map(MorseDecoder::new),
+            #[cfg(not(target_arch = "wasm32"))]
+            last_flick: Instant::now(),
+            config,
         }
     }
 
+    /// Sends every bind to `fired` as it fires.
+    pub fn report_fired(mut self, fired: UnboundedSender<FiredBind>) -> Self {
+        self.fired = Some(fired);
+        self
+    }
+
+    /// Ignores moves while `pause` is paused.
+    pub fn pause(mut self, pause: Pause) -> Self {
+        self.pause = pause;
+        self
+    }
+
+    /// Stops everything, pausing through the machine's pause, when
+    /// `kill_switch` is pulled.
+    pub fn kill_switch(mut self, kill_switch: KillSwitch) -> Self {
+        self.kill_switch = kill_switch;
+        self
+    }
+
+    /// Follows how far the output is through `queue`, which the output has
+    /// to keep up to date. Without it, binds that merge into or replace what
+    /// is still playing always play as if the output were idle.
+    pub fn output_queue(mut self, queue: OutputQueue) -> Self {
+        self.output_queue = Some(queue);
+        self
+    }
+
+    /// Follows the keyboard modifiers held through `modifiers`.
+    pub fn modifiers(mut self, modifiers: Modifiers) -> Self {
+        self.modifiers = modifiers;
+        self
+    }
+
+    /// Ignores moves while `typing` was typed on within the config's
+    /// `typing_quiet`.
+    pub fn typing(mut self, typing: Typing) -> Self {
+        self.typing = typing;
+        self
+    }
+
+    /// Whether moves are ignored as the keyboards are being typed on.
+    fn typing_now(&self) -> bool {
+        self.config
+            .typing_quiet
+            .is_some_and(|quiet| self.typing.typed_within(Duration::from_millis(quiet)))
+    }
+
     fn reset(&mut self, tx: &mut tokio::sync::mpsc::UnboundedSender<Action>) {
         if let Some(bind) = self.tentative_bind {
             self.play_bind(bind, tx);
@@ -35,23 +307,318 @@ impl StateMachine {
         self.tentative_bind = None;
     }
 
+    /// Drops the current prefix without playing its bind, used when moves
+    /// were missed and the prefix no longer matches what was turned.
+    fn resync(&mut self) {
+        self.current_prefix.clear();
+        self.tentative_bind = None;
+    }
+
+    /// Notes `m` towards the kill gesture, returning whether it completes it.
+    fn kill_gesture(&mut self, m: Move) -> bool {
+        let gesture = &self.config.kill_gesture;
+        if gesture.is_empty() {
+            return false;
+        }
+
+        let now = Instant::now();
+        if now - self.recent_turned > Duration::from_millis(self.config.timeout) {
+            self.recent.clear();
+        }
+        self.recent_turned = now;
+        self.recent.push(m);
+        if self.recent.len() > gesture.len() {
+            self.recent.remove(0);
+        }
+
+        *gesture == self.recent
+    }
+
+    /// Pauses the binds, drops the current prefix without playing its bind
+    /// and releases every held key.
+    fn kill(&mut self, tx: &mut tokio::sync::mpsc::UnboundedSender<Action>) {
+        warn!("Kill switch pulled, releasing held keys and pausing the binds");
+
+        self.pause.set(true);
+        self.resync();
+        self.recent.clear();
+        self.queued.clear();
+        self.kill_switch.dropping.store(true, Ordering::Relaxed);
+        let _ = send(tx, self.output_queue.as_ref(), Action::ReleaseAll);
+    }
+
+    /// The bind the current prefix completes, preferring the one matched
+    /// with the fewest mistakes, then the one needing the most modifiers.
     fn get_tentative_bind(&self) -> Option<usize> {
-        for (i, bind) in self.config.binds.iter().enumerate() {
-            if bind.trigger == self.current_prefix {
-                return Some(i);
+        self.enabled_binds()
+            .filter_map(|(i, bind)| {
+                Some((
+                    bind.matches(&self.current_prefix)?,
+                    Reverse(bind.held.len()),
+                    i,
+                ))
+            })
+            .min()
+            .map(|(_, _, i)| i)
+    }
+
+    /// Binds that are not disabled, whose time limit has not run out and
+    /// whose modifiers are held.
+    fn enabled_binds(&self) -> impl Iterator<Item = (usize, &Bind)> {
+        let elapsed = self
+            .prefix_started
+            .map_or(Duration::ZERO, |started| started.elapsed());
+
+        self.config
+            .binds
+            .iter()
+            .enumerate()
+            .filter(move |(_, bind)| {
+                !bind
+                    .groups
+                    .iter()
+                    .any(|group| self.disabled_groups.contains(group))
+                    && bind
+                        .within
+                        .is_none_or(|within| elapsed <= Duration::from_millis(within))
+                    && self.modifiers.are_held(&bind.held)
+            })
+    }
+
+    /// When `bind` can fire under the rate limits, counting it as fired
+    /// then, or `None` if it is dropped.
+    fn admit(&mut self, bind: usize) -> Option<Duration> {
+        let now = self.started.elapsed();
+        let global = self.config.rate_limit;
+        let own = self.config.binds[bind].rate_limit;
+
+        let mut at = now;
+        if let Some(limit) = &global {
+            at = at.max(self.rate_limiter.next_free(limit, now)?);
+        }
+        if let Some(limit) = &own {
+            let limiter = self.bind_limiters.entry(bind).or_default();
+            at = at.max(limiter.next_free(limit, now)?);
+        }
+
+        if global.is_some() {
+            self.rate_limiter.record(at);
+        }
+        if own.is_some() {
+            self.bind_limiters.entry(bind).or_default().record(at);
+        }
+        Some(at)
+    }
+
+    /// Plays `bind` now, later or not at all, as the rate limits allow.
+    fn play_bind(&mut self, bind: usize, tx: &mut tokio::sync::mpsc::UnboundedSender<Action>) {
+        let prefix = self.current_prefix.clone();
+        match self.admit(bind) {
+            Some(at) if at <= self.started.elapsed() => self.fire_bind(bind, prefix, tx),
+            Some(at) => {
+                debug!(bind = %self.config.binds[bind].label(), "Rate limited, queueing the bind");
+                self.queued.push((at, bind, prefix));
             }
+            None => {
+                warn!(bind = %self.config.binds[bind].label(), "Rate limited, dropping the bind");
+            }
+        }
+    }
+
+    /// When the first queued firing is due.
+    fn queued_deadline(&self) -> Option<Instant> {
+        let due = self.queued.iter().map(|(at, _, _)| *at).min()?;
+        Some(self.started + due)
+    }
+
+    /// Plays the queued firings that are due, in the order they are due.
+    fn play_queued(&mut self, tx: &mut tokio::sync::mpsc::UnboundedSender<Action>) {
+        let now = self.started.elapsed();
+        let (mut due, queued) = std::mem::take(&mut self.queued)
+            .into_iter()
+            .partition::<Vec<_>, _>(|(at, _, _)| *at <= now);
+        self.queued = queued;
+
+        due.sort_by_key(|(at, _, _)| *at);
+        for (_, bind, prefix) in due {
+            self.fire_bind(bind, prefix, tx);
         }
+    }
 
+    fn fire_bind(
+        &mut self,
+        bind: usize,
+        prefix: Vec<Move>,
+        tx: &mut tokio::sync::mpsc::UnboundedSender<Action>,
+    ) {
+        let index = bind;
+        let bind = &self.config.binds[bind];
+        let _span = info_span!("bind", bind = %bind.label()).entered();
+
+        if let Some(queue) = &self.output_queue {
+            match bind.overlap {
+                Overlap::Queue => {}
+                Overlap::Coalesce => {
+                    if self
+                        .playing
+                        .get(&index)
+                        .is_some_and(|&end| queue.is_playing(end))
+                    {
+                        debug!("Still playing, merging the firing into it");
+                        return;
+                    }
+                }
+                Overlap::Replace => queue.skip_sent(),
+            }
+        }
+
+        info!(?prefix, "Bind fired");
+
+        if let Some(fired) = &self.fired {
+            let _ = fired.send(FiredBind {
+                bind: bind.clone(),
+                prefix,
+            });
+        }
+
+        Self::play_actions(
+            &bind.actions,
+            &mut self.disabled_groups,
+            &mut self.number,
+            &self.config,
+            self.output_queue.as_ref(),
+            tx,
+        );
+
+        if let Some(queue) = &self.output_queue {
+            self.playing.insert(index, queue.sent());
+        }
+    }
+
+    fn play_actions(
+        actions: &[Action],
+        disabled_groups: &mut HashSet<String>,
+        number: &mut String,
+        config: &Config,
+        queue: Option<&OutputQueue>,
+        tx: &mut tokio::sync::mpsc::UnboundedSender<Action>,
+    ) {
+        metrics::bind_fired();
+        Self::run_actions(actions, disabled_groups, number, config, queue, tx);
+    }
+
+    /// Sends `actions` to the output, except for group changes and number
+    /// entry which take effect here.
+    #[cfg_attr(target_arch = "wasm32", allow(unused_variables))]
+    fn run_actions(
+        actions: &[Action],
+        disabled_groups: &mut HashSet<String>,
+        number: &mut String,
+        config: &Config,
+        queue: Option<&OutputQueue>,
+        tx: &mut tokio::sync::mpsc::UnboundedSender<Action>,
+    ) {
+        for action in actions {
+            match action {
+                Action::EnableGroup(group) => {
+                    disabled_groups.remove(group);
+                    continue;
+                }
+                Action::DisableGroup(group) => {
+                    disabled_groups.insert(group.clone());
+                    continue;
+                }
+                Action::ToggleGroup(group) => {
+                    if !disabled_groups.remove(group) {
+                        disabled_groups.insert(group.clone());
+                    }
+                    continue;
+                }
+                Action::Digit(digit) => {
+                    number.push(char::from(b'0' + digit % 10));
+                    continue;
+                }
+                Action::EraseDigit => {
+                    number.pop();
+                    continue;
+                }
+                Action::ConfirmNumber => {
+                    let value = std::mem::take(number);
+                    if value.is_empty() {
+                        continue;
+                    }
+
+                    info!(value, "Number entered");
+                    #[cfg(not(target_arch = "wasm32"))]
+                    if let Some(entry) = &config.number_entry {
+                        let actions = entry.confirm_actions(&value);
+                        Self::run_actions(&actions, disabled_groups, number, config, queue, tx);
+                    }
+                    continue;
+                }
+                _ => {}
+            }
+
+            if send(tx, queue, action.clone()).is_err() {
+                warn!("Output stopped, dropping {action:?}");
+                return;
+            }
+        }
+    }
+
+    /// Adds `m` to the Morse letter if it is a flick while Morse is on.
+    #[cfg(not(target_arch = "wasm32"))]
+    fn flick(&mut self, m: Move) -> bool {
+        let Some(morse) = &mut self.morse else {
+            return false;
+        };
+
+        if self.disabled_groups.contains(morse.group()) || !morse.push(m) {
+            return false;
+        }
+
+        self.last_flick = Instant::now();
+        true
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    fn flick(&mut self, _m: Move) -> bool {
+        false
+    }
+
+    /// When a pause ends the Morse letter or word.
+    #[cfg(not(target_arch = "wasm32"))]
+    fn morse_deadline(&self) -> Option<Instant> {
+        Some(self.last_flick + self.morse.as_ref()?.next_gap()?)
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    fn morse_deadline(&self) -> Option<Instant> {
         None
     }
 
-    fn play_bind(&self, bind: usize, tx: &mut tokio::sync::mpsc::UnboundedSender<Action>) {
-        for action in &self.config.binds[bind].actions {
-            tx.send(*action).expect("could not send action");
+    #[cfg(not(target_arch = "wasm32"))]
+    fn morse_pause(&mut self, tx: &mut tokio::sync::mpsc::UnboundedSender<Action>) {
+        let Some(morse) = &mut self.morse else {
+            return;
+        };
+
+        for key in morse.pause(self.last_flick.elapsed()) {
+            debug!(?key, "Morse typed");
+            if send(tx, self.output_queue.as_ref(), Action::Click(key)).is_err() {
+                warn!("Output stopped, dropping {key:?}");
+                return;
+            }
         }
     }
 
+    #[cfg(target_arch = "wasm32")]
+    fn morse_pause(&mut self, _tx: &mut tokio::sync::mpsc::UnboundedSender<Action>) {}
+
     fn push_move(&mut self, m: Move, tx: &mut tokio::sync::mpsc::UnboundedSender<Action>) {
+        if self.current_prefix.is_empty() {
+            self.prefix_started = Some(Instant::now());
+        }
         self.current_prefix.push(m);
 
         match self.get_tentative_bind() {
@@ -62,56 +629,184 @@ impl StateMachine {
                 }
 
                 self.current_prefix.drain(..(self.current_prefix.len() - 1));
+                self.prefix_started = Some(Instant::now());
             }
         }
 
         let mut next_tentative = None;
         let mut only_one = false;
 
-        for (i, bind) in self.config.binds.iter().enumerate() {
-            if bind.trigger.starts_with(&self.current_prefix) {
-                if next_tentative.is_none() {
-                    next_tentative = Some(i);
-                    only_one = true;
-                } else {
-                    only_one = false;
+        for (i, bind) in self.enabled_binds() {
+            if bind.starts_with(&self.current_prefix) {
+                match next_tentative {
+                    None => {
+                        next_tentative = Some(i);
+                        only_one = true;
+                    }
+                    // A bind needing modifiers wins over one without while
+                    // they are held.
+                    Some(next) => {
+                        if bind.held.len() > self.config.binds[next].held.len() {
+                            next_tentative = Some(i);
+                        }
+                        only_one = false;
+                    }
                 }
             }
         }
 
         self.tentative_bind = next_tentative;
 
-        match (next_tentative, only_one) {
-            (Some(_), true) => {
-                self.reset(tx);
-            }
-            _ => {}
+        if next_tentative.is_some() && only_one {
+            self.reset(tx);
         }
     }
+}
 
-    pub fn run(mut self) -> tokio::sync::mpsc::UnboundedReceiver<Action> {
-        let (mut tx, rx) = tokio::sync::mpsc::unbounded_channel();
-
+impl<S, E> StateMachine<S>
+where
+    S: Stream<Item = E> + Unpin,
+    E: Into<CubeEvent>,
+{
+    pub async fn run(
+        mut self,
+        mut tx: tokio::sync::mpsc::UnboundedSender<Action>,
+        cancel: CancellationToken,
+    ) {
         let timeout = Duration::from_millis(self.config.timeout);
 
         let mut last_move = Instant::now();
 
-        tokio::spawn(async move {
-            loop {
-                select! {
-                    Ok(m) = self.reciever.recv() => {
-                        self.push_move(m, &mut tx);
-                    }
-                    _ = tokio::time::sleep_until(last_move + timeout) => {
-                        self.reset(&mut tx);
+        loop {
+            let morse_deadline = self.morse_deadline();
+            let queued_deadline = self.queued_deadline();
+
+            select! {
+                event = self.events.next() => {
+                    let Some(event) = event else {
+                        break;
+                    };
+                    let event = event.into();
+                    let received = match event {
+                        CubeEvent::Move(_) => self.received.take(),
+                        _ => None,
+                    };
+
+                    match event {
+                        CubeEvent::Received(at) => {
+                            self.received = Some(at);
+                            continue;
+                        }
+                        CubeEvent::Move(m) if self.kill_gesture(m) => {
+                            self.kill(&mut tx);
+                        }
+                        CubeEvent::Move(_) if self.pause.is_paused() => continue,
+                        CubeEvent::Move(m) if self.typing_now() => {
+                            debug!(%m, "Ignoring a move while typing");
+                            continue;
+                        }
+                        CubeEvent::Move(m) if self.flick(m) => continue,
+                        CubeEvent::Move(m) => {
+                            // Injected moves were never received, so they
+                            // are measured from here.
+                            let matching = Instant::now();
+                            self.push_move(m, &mut tx);
+                            let latency = match received {
+                                Some(at) => at.elapsed(),
+                                None => matching.elapsed(),
+                            };
+                            metrics::stage_latency(Stage::Match, latency);
+                        }
+                        CubeEvent::Lagged(count) => {
+                            warn!("Missed {count} events, discarding {:?}", self.current_prefix);
+                            self.resync();
+                        }
+                        CubeEvent::Timer(event) => {
+                            for bind in &self.config.timer_binds {
+                                if bind.state == event.state {
+                                    Self::play_actions(
+                                        &bind.actions,
+                                        &mut self.disabled_groups,
+                                        &mut self.number,
+                                        &self.config,
+                                        self.output_queue.as_ref(),
+                                        &mut tx,
+                                    );
+                                }
+                            }
+                            continue;
+                        }
+                        CubeEvent::Redemption(bind) => {
+                            if let Some(bind) = self.config.redemption_binds.get(bind) {
+                                Self::play_actions(
+                                    &bind.actions,
+                                    &mut self.disabled_groups,
+                                    &mut self.number,
+                                    &self.config,
+                                    self.output_queue.as_ref(),
+                                    &mut tx,
+                                );
+                            }
+                            continue;
+                        }
+                        CubeEvent::Fire(bind) => {
+                            if let Some(bind) = self.config.binds.get(bind) {
+                                info!(bind = %bind.label(), "Bind fired without its trigger");
+                                Self::play_actions(
+                                    &bind.actions,
+                                    &mut self.disabled_groups,
+                                    &mut self.number,
+                                    &self.config,
+                                    self.output_queue.as_ref(),
+                                    &mut tx,
+                                );
+                            }
+                            continue;
+                        }
+                        #[cfg(all(feature = "focus", not(target_arch = "wasm32")))]
+                        CubeEvent::Profile(profile) => {
+                            if let Some(focus) = &self.config.focus {
+                                focus.switch(profile, &mut self.disabled_groups);
+                            }
+                            continue;
+                        }
+                        _ => continue,
                     }
                 }
+                _ = sleep_until(queued_deadline.unwrap_or(last_move)), if queued_deadline.is_some() => {
+                    self.play_queued(&mut tx);
+                    continue;
+                }
+                _ = self.kill_switch.pulled() => {
+                    self.kill(&mut tx);
+                    continue;
+                }
+                _ = sleep_until(last_move + timeout) => {
+                    self.reset(&mut tx);
+                }
+                _ = sleep_until(morse_deadline.unwrap_or(last_move)), if morse_deadline.is_some() => {
+                    self.morse_pause(&mut tx);
+                    continue;
+                }
+                _ = cancel.cancelled() => {
+                    break;
+                }
+            }
 
-                debug!("{:?} ({:?})", self.current_prefix, self.tentative_bind);
-                last_move = Instant::now();
+            if tx.is_closed() {
+                error!("Output stopped, stopping state machine");
+                break;
             }
-        });
 
-        rx
+            debug!(prefix = ?self.current_prefix, tentative = ?self.tentative_bind);
+            last_move = Instant::now();
+        }
+
+        self.reset(&mut tx);
     }
 }
+
+#[cfg(target_arch = "wasm32")]
+async fn sleep_until(deadline: Instant) {
+    gloo_timers::future::sleep(deadline.saturating_duration_since(Instant::now())).await;
+}
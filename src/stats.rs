@@ -0,0 +1,173 @@
+//! Statistics of stored solves as CSV, one row per solve, to follow progress
+//! in a spreadsheet or notebook.
+
+use std::{
+    fmt::{self, Display},
+    str::FromStr,
+    time::Duration,
+};
+
+use chrono::{DateTime, SecondsFormat, Utc};
+
+use crate::{database::StoredSolve, error::DatabaseError};
+
+/// A column of the CSV, by its name in `--columns`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Column {
+    Id,
+    Session,
+    /// When the solve started, in RFC 3339.
+    Started,
+    /// The time counted in seconds, with penalties, empty for a DNF.
+    Time,
+    /// The time taken in seconds, without penalties.
+    Duration,
+    Penalty,
+    Moves,
+    /// Turns per second.
+    Tps,
+    /// The scrambled cube as a facelet string.
+    Scramble,
+    /// The time and moves of each phase, two columns per phase.
+    Splits,
+}
+
+impl Column {
+    /// Every column but the scramble, in the order they are written.
+    pub const DEFAULT: [Column; 9] = [
+        Column::Id,
+        Column::Session,
+        Column::Started,
+        Column::Time,
+        Column::Duration,
+        Column::Penalty,
+        Column::Moves,
+        Column::Tps,
+        Column::Splits,
+    ];
+
+    fn name(self) -> &'static str {
+        match self {
+            Column::Id => "id",
+            Column::Session => "session",
+            Column::Started => "started",
+            Column::Time => "time",
+            Column::Duration => "duration",
+            Column::Penalty => "penalty",
+            Column::Moves => "moves",
+            Column::Tps => "tps",
+            Column::Scramble => "scramble",
+            Column::Splits => "splits",
+        }
+    }
+}
+
+impl Display for Column {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for Column {
+    type Err = DatabaseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        [
+            Column::Id,
+            Column::Session,
+            Column::Started,
+            Column::Time,
+            Column::Duration,
+            Column::Penalty,
+            Column::Moves,
+            Column::Tps,
+            Column::Scramble,
+            Column::Splits,
+        ]
+        .into_iter()
+        .find(|column| column.name() == s)
+        .ok_or_else(|| DatabaseError::UnknownColumn(s.to_string()))
+    }
+}
+
+/// `solves` as CSV with a header, with the `columns` in order. The splits
+/// have a time and a moves column for every phase any of the solves has, in
+/// the order first seen.
+pub fn to_csv(solves: &[StoredSolve], columns: &[Column]) -> String {
+    let mut phases: Vec<&str> = Vec::new();
+    for split in solves.iter().flat_map(|solve| &solve.splits) {
+        if !phases.contains(&split.name.as_str()) {
+            phases.push(&split.name);
+        }
+    }
+
+    let header = columns.iter().flat_map(|&column| match column {
+        Column::Splits => phases
+            .iter()
+            .flat_map(|phase| [format!("{phase} time"), format!("{phase} moves")])
+            .collect(),
+        column => vec![column.to_string()],
+    });
+    let mut csv = row(header);
+
+    for solve in solves {
+        let cells = columns.iter().flat_map(|&column| match column {
+            Column::Id => vec![solve.id.to_string()],
+            Column::Session => vec![solve.session.to_string()],
+            Column::Started => vec![
+                DateTime::<Utc>::from(solve.started).to_rfc3339_opts(SecondsFormat::Millis, true),
+            ],
+            Column::Time => vec![solve.time().map(seconds).unwrap_or_default()],
+            Column::Duration => vec![seconds(solve.duration)],
+            Column::Penalty => vec![
+                solve
+                    .penalty
+                    .map(|penalty| penalty.to_string())
+                    .unwrap_or_default(),
+            ],
+            Column::Moves => vec![solve.moves.len().to_string()],
+            Column::Tps => vec![if solve.duration.is_zero() {
+                String::new()
+            } else {
+                format!(
+                    "{:.2}",
+                    solve.moves.len() as f64 / solve.duration.as_secs_f64()
+                )
+            }],
+            Column::Scramble => vec![solve.scramble.clone()],
+            Column::Splits => phases
+                .iter()
+                .flat_map(
+                    |&phase| match solve.splits.iter().find(|split| split.name == phase) {
+                        Some(split) => [seconds(split.duration), split.moves.to_string()],
+                        None => [String::new(), String::new()],
+                    },
+                )
+                .collect(),
+        });
+        csv.push_str(&row(cells));
+    }
+
+    csv
+}
+
+fn seconds(duration: Duration) -> String {
+    format!("{:.3}", duration.as_secs_f64())
+}
+
+/// A line of CSV, quoting the cells that need it.
+fn row(cells: impl IntoIterator<Item = String>) -> String {
+    let mut line = cells
+        .into_iter()
+        .map(|cell| {
+            if cell.contains([',', '"', '\n', '\r']) {
+                format!("\"{}\"", cell.replace('"', "\"\""))
+            } else {
+                cell
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(",");
+    line.push('\n');
+    line
+}
@@ -0,0 +1,104 @@
+//! Whether the cube is connected and how charged it is, followed from its
+//! events for the `status` command and the logs.
+
+use std::{
+    fmt,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use futures::{Stream, StreamExt};
+use serde::Serialize;
+use tokio::{select, time::interval};
+use tokio_util::sync::CancellationToken;
+use tracing::{info, warn};
+
+use crate::cube::CubeEvent;
+
+/// Battery percentage below which reports are logged as warnings.
+pub const LOW_BATTERY: u8 = 20;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct Status {
+    pub connected: bool,
+    /// The last battery percentage the cube reported.
+    pub battery: Option<u8>,
+}
+
+impl fmt::Display for Status {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (self.connected, self.battery) {
+            (false, _) => write!(f, "disconnected"),
+            (true, Some(level)) => write!(f, "connected, battery {level}%"),
+            (true, None) => write!(f, "connected, battery unknown"),
+        }
+    }
+}
+
+/// The status of the cube, shared between its clones.
+#[derive(Debug, Clone, Default)]
+pub struct CubeStatus {
+    status: Arc<Mutex<Status>>,
+}
+
+impl CubeStatus {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn get(&self) -> Status {
+        *self.status.lock().expect("status lock poisoned")
+    }
+
+    pub fn push(&self, event: &CubeEvent) {
+        let mut status = self.status.lock().expect("status lock poisoned");
+        match *event {
+            CubeEvent::Connected => status.connected = true,
+            CubeEvent::Disconnected => *status = Status::default(),
+            CubeEvent::Battery(level) => status.battery = Some(level),
+            _ => {}
+        }
+    }
+
+    /// Follows `events` until they end or `cancel` is cancelled, logging the
+    /// battery level every `report`, as a warning once it runs low.
+    pub async fn follow(
+        &self,
+        mut events: impl Stream<Item = CubeEvent> + Unpin,
+        report: Option<Duration>,
+        cancel: CancellationToken,
+    ) {
+        let mut ticks = report.map(interval);
+
+        loop {
+            let tick = async {
+                match &mut ticks {
+                    Some(ticks) => ticks.tick().await,
+                    None => std::future::pending().await,
+                }
+            };
+
+            let event = select! {
+                event = events.next() => event,
+                _ = tick => {
+                    match self.get() {
+                        Status { connected: true, battery: Some(level) } if level < LOW_BATTERY => {
+                            warn!("Cube battery low at {level}%");
+                        }
+                        Status { connected: true, battery: Some(level) } => {
+                            info!("Cube battery at {level}%");
+                        }
+                        _ => {}
+                    }
+                    continue;
+                }
+                _ = cancel.cancelled() => return,
+            };
+
+            match event {
+                Some(event) => self.push(&event),
+                None => return,
+            }
+        }
+    }
+}
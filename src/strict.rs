@@ -0,0 +1,131 @@
+//! Strict config checking. Unknown fields are normally ignored, so a typo
+//! silently drops a setting; here they are rejected along with the nearest
+//! valid name.
+
+use ron::Value;
+use serde::{
+    Deserialize, Deserializer,
+    de::{self, Visitor},
+    forward_to_deserialize_any,
+};
+
+use crate::{
+    config::{Bind, Config, RedemptionBind, TimerBind},
+    error::ConfigError,
+    phase::Phase,
+};
+
+/// Checks every struct in the config `s` for fields that do not exist.
+pub(crate) fn check_fields(s: &str) -> Result<(), ConfigError> {
+    let config: Value = ron::from_str(s)?;
+    check_struct(&config, struct_fields::<Config>(), "the config")?;
+
+    let Value::Map(config) = config else {
+        return Ok(());
+    };
+
+    let lists = [
+        ("binds", struct_fields::<Bind>(), "a bind"),
+        ("timer_binds", struct_fields::<TimerBind>(), "a timer bind"),
+        (
+            "redemption_binds",
+            struct_fields::<RedemptionBind>(),
+            "a redemption bind",
+        ),
+        ("phases", struct_fields::<Phase>(), "a phase"),
+    ];
+    for (field, fields, location) in lists {
+        let items = config
+            .iter()
+            .find(|(key, _)| **key == Value::String(field.to_string()));
+        if let Some((_, Value::Seq(items))) = items {
+            for item in items {
+                check_struct(item, fields, location)?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Adds a suggestion to errors for unknown variants.
+pub(crate) fn suggest_variant(error: ron::error::SpannedError) -> ConfigError {
+    let suggestion = match &error.code {
+        ron::Error::NoSuchEnumVariant {
+            expected, found, ..
+        } => nearest(found, expected),
+        _ => None,
+    };
+
+    match suggestion {
+        Some(suggestion) => ConfigError::UnknownVariant { error, suggestion },
+        None => error.into(),
+    }
+}
+
+fn check_struct(
+    value: &Value,
+    fields: &'static [&'static str],
+    location: &'static str,
+) -> Result<(), ConfigError> {
+    let Value::Map(map) = value else {
+        return Ok(());
+    };
+
+    for (key, _) in map.iter() {
+        if let Value::String(field) = key
+            && !fields.contains(&field.as_str())
+        {
+            return Err(ConfigError::UnknownField {
+                field: field.clone(),
+                location,
+                suggestion: nearest(field, fields),
+            });
+        }
+    }
+
+    Ok(())
+}
+
+fn nearest(name: &str, candidates: &[&'static str]) -> Option<&'static str> {
+    candidates
+        .iter()
+        .map(|&candidate| (strsim::damerau_levenshtein(name, candidate), candidate))
+        // Allow about one typo in every three letters.
+        .filter(|&(distance, candidate)| distance <= (candidate.len() / 3).max(1))
+        .min_by_key(|&(distance, _)| distance)
+        .map(|(_, candidate)| candidate)
+}
+
+/// The field names of `T`, as its `Deserialize` impl asks for them.
+fn struct_fields<'de, T: Deserialize<'de>>() -> &'static [&'static str] {
+    struct Fields<'a>(&'a mut &'static [&'static str]);
+
+    impl<'de> Deserializer<'de> for Fields<'_> {
+        type Error = de::value::Error;
+
+        fn deserialize_any<V: Visitor<'de>>(self, _: V) -> Result<V::Value, Self::Error> {
+            Err(de::Error::custom("not a struct"))
+        }
+
+        fn deserialize_struct<V: Visitor<'de>>(
+            self,
+            _: &'static str,
+            fields: &'static [&'static str],
+            _: V,
+        ) -> Result<V::Value, Self::Error> {
+            *self.0 = fields;
+            Err(de::Error::custom("only reading fields"))
+        }
+
+        forward_to_deserialize_any! {
+            bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+            bytes byte_buf option unit unit_struct newtype_struct seq tuple
+            tuple_struct map enum identifier ignored_any
+        }
+    }
+
+    let mut fields: &'static [&'static str] = &[];
+    let _ = T::deserialize(Fields(&mut fields));
+    fields
+}
@@ -0,0 +1,114 @@
+//! Assistive switches for switch access scanning software, so binds can
+//! act as the switches a motor-impaired user would press. Each switch is a
+//! key, as most switch interfaces send, or on Linux a button of a virtual
+//! joystick for software listening for switch boxes.
+
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{config::Key, error::OutputError};
+
+/// Switches, e.g. `(keys: [space, enter, F1, F2], hold: 100)`.
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, Eq)]
+#[serde(default)]
+pub struct Switches {
+    /// The key of each switch, from switch 1.
+    #[serde(deserialize_with = "deserialize_keys")]
+    pub keys: Vec<Key>,
+    /// Milliseconds each switch is held down, as scanning software may miss
+    /// shorter presses.
+    pub hold: u64,
+    /// Presses the buttons of a virtual joystick named `triplicata switches`
+    /// instead of keys, Linux only.
+    pub device: bool,
+}
+
+impl Default for Switches {
+    fn default() -> Self {
+        Self {
+            keys: vec![Key::Space, Key::Return],
+            hold: 100,
+            device: false,
+        }
+    }
+}
+
+fn deserialize_keys<'de, D: serde::Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Vec<Key>, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(transparent)]
+    struct Wrapped(#[serde(deserialize_with = "crate::keys::deserialize_key")] Key);
+
+    let keys: Vec<Wrapped> = Vec::deserialize(deserializer)?;
+    Ok(keys.into_iter().map(|Wrapped(key)| key).collect())
+}
+
+impl Switches {
+    pub fn hold(&self) -> Duration {
+        Duration::from_millis(self.hold)
+    }
+
+    /// The key of `switch`, counting from 1.
+    pub fn key(&self, switch: u8) -> Result<Key, OutputError> {
+        usize::from(switch)
+            .checked_sub(1)
+            .and_then(|i| self.keys.get(i))
+            .copied()
+            .ok_or(OutputError::NoSwitch(switch))
+    }
+}
+
+/// A joystick made through uinput whose buttons are the switches.
+#[cfg(all(feature = "evdev", target_os = "linux"))]
+pub struct VirtualSwitches {
+    device: evdev::uinput::VirtualDevice,
+}
+
+#[cfg(all(feature = "evdev", target_os = "linux"))]
+impl VirtualSwitches {
+    pub const DEVICE_NAME: &str = "triplicata switches";
+
+    /// The most switches the device has buttons for.
+    pub const COUNT: u8 = 40;
+
+    /// Creates the device, which needs write access to `/dev/uinput`.
+    pub fn new() -> Result<Self, OutputError> {
+        use evdev::{AttributeSet, KeyCode, uinput::VirtualDevice};
+
+        let mut buttons = AttributeSet::<KeyCode>::new();
+        for switch in 1..=Self::COUNT {
+            buttons.insert(Self::button(switch));
+        }
+
+        let device = VirtualDevice::builder()
+            .and_then(|builder| builder.name(Self::DEVICE_NAME).with_keys(&buttons))
+            .and_then(|builder| builder.build())
+            .map_err(OutputError::Evdev)?;
+
+        Ok(Self { device })
+    }
+
+    fn button(switch: u8) -> evdev::KeyCode {
+        evdev::KeyCode(evdev::KeyCode::BTN_TRIGGER_HAPPY1.0 + u16::from(switch) - 1)
+    }
+
+    /// Presses `switch`, counting from 1, for `hold`.
+    pub fn press(&mut self, switch: u8, hold: Duration) -> Result<(), OutputError> {
+        use evdev::KeyEvent;
+
+        if !(1..=Self::COUNT).contains(&switch) {
+            return Err(OutputError::NoSwitch(switch));
+        }
+
+        let button = Self::button(switch);
+        self.device
+            .emit(&[*KeyEvent::new(button, 1)])
+            .map_err(OutputError::Evdev)?;
+        std::thread::sleep(hold);
+        self.device
+            .emit(&[*KeyEvent::new(button, 0)])
+            .map_err(OutputError::Evdev)
+    }
+}
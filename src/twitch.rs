@@ -0,0 +1,247 @@
+//! Twitch chat messages and stream markers as actions, and channel point
+//! redemptions as events through EventSub.
+
+use std::fmt;
+
+use futures::StreamExt;
+use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
+use tokio::select;
+use tokio_tungstenite::{connect_async, tungstenite::Message};
+use tokio_util::sync::CancellationToken;
+use tracing::{debug, info};
+
+use crate::{
+    MoveInjector,
+    cube::{CubeEvent, Face},
+    error::TwitchError,
+    scramble,
+};
+
+const HELIX: &str = "https://api.twitch.tv/helix";
+const EVENTSUB: &str = "wss://eventsub.wss.twitch.tv/ws";
+
+/// The length of scramble challenges.
+const SCRAMBLE_LENGTH: usize = 20;
+
+/// An application's client id and a user access token for the channel, e.g.
+/// `(client_id: "...", token: "...")`. Chat messages need the
+/// `user:write:chat` scope, markers `channel:manage:broadcast` and
+/// redemptions `channel:read:redemptions`.
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct Twitch {
+    pub client_id: String,
+    pub token: String,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, Eq)]
+pub enum TwitchCommand {
+    /// Sends a message to chat.
+    Chat(String),
+    /// Marks the current point of the stream to find it again in the VOD.
+    Marker(Option<String>),
+    /// Sends a random scramble to chat as a challenge.
+    Scramble,
+}
+
+impl fmt::Display for TwitchCommand {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TwitchCommand::Chat(message) => write!(f, "chat {message:?}"),
+            TwitchCommand::Marker(Some(description)) => write!(f, "marker {description:?}"),
+            TwitchCommand::Marker(None) => write!(f, "marker"),
+            TwitchCommand::Scramble => write!(f, "scramble challenge"),
+        }
+    }
+}
+
+/// Runs commands as the token's user, in their own channel.
+pub struct TwitchClient {
+    twitch: Twitch,
+    agent: ureq::Agent,
+    /// The token's user, looked up on the first request that needs it.
+    user_id: Option<String>,
+}
+
+impl TwitchClient {
+    pub fn new(twitch: Twitch) -> Self {
+        Self {
+            twitch,
+            agent: ureq::Agent::new(),
+            user_id: None,
+        }
+    }
+
+    pub fn send(&mut self, command: &TwitchCommand) -> Result<(), TwitchError> {
+        match command {
+            TwitchCommand::Chat(message) => self.chat(message),
+            TwitchCommand::Marker(description) => {
+                let user_id = self.user_id()?;
+                self.helix(
+                    self.agent.post(&format!("{HELIX}/streams/markers")),
+                    Some(json!({
+                        "user_id": user_id,
+                        "description": description.as_deref().unwrap_or_default(),
+                    })),
+                )?;
+                Ok(())
+            }
+            TwitchCommand::Scramble => {
+                let scramble =
+                    scramble::random_moves(SCRAMBLE_LENGTH, &Face::ALL, &mut rand::rng());
+                self.chat(&format!("Scramble challenge: {scramble}"))
+            }
+        }
+    }
+
+    fn chat(&mut self, message: &str) -> Result<(), TwitchError> {
+        let user_id = self.user_id()?;
+        let response = self.helix(
+            self.agent.post(&format!("{HELIX}/chat/messages")),
+            Some(json!({
+                "broadcaster_id": user_id,
+                "sender_id": user_id,
+                "message": message,
+            })),
+        )?;
+
+        // Messages caught by AutoMod or chat settings are dropped with a 200.
+        let sent = &response["data"][0];
+        if sent["is_sent"] == false {
+            return Err(TwitchError::Refused(
+                sent["drop_reason"]["message"]
+                    .as_str()
+                    .unwrap_or("the message was dropped")
+                    .to_string(),
+            ));
+        }
+
+        Ok(())
+    }
+
+    /// Subscribes the EventSub WebSocket session to channel point
+    /// redemptions.
+    fn subscribe(&mut self, session_id: &str) -> Result<(), TwitchError> {
+        let user_id = self.user_id()?;
+        self.helix(
+            self.agent.post(&format!("{HELIX}/eventsub/subscriptions")),
+            Some(json!({
+                "type": "channel.channel_points_custom_reward_redemption.add",
+                "version": "1",
+                "condition": { "broadcaster_user_id": user_id },
+                "transport": { "method": "websocket", "session_id": session_id },
+            })),
+        )?;
+        Ok(())
+    }
+
+    fn user_id(&mut self) -> Result<String, TwitchError> {
+        if let Some(user_id) = &self.user_id {
+            return Ok(user_id.clone());
+        }
+
+        let users = self.helix(self.agent.get(&format!("{HELIX}/users")), None)?;
+        let user_id = users["data"][0]["id"]
+            .as_str()
+            .ok_or_else(|| TwitchError::Unexpected(users.to_string()))?
+            .to_string();
+
+        self.user_id = Some(user_id.clone());
+        Ok(user_id)
+    }
+
+    fn helix(&self, request: ureq::Request, body: Option<Value>) -> Result<Value, TwitchError> {
+        let request = request
+            .set("Authorization", &format!("Bearer {}", self.twitch.token))
+            .set("Client-Id", &self.twitch.client_id);
+
+        let response = match body {
+            Some(body) => request.send_json(body),
+            None => request.call(),
+        };
+
+        match response {
+            // Markers and subscriptions answer with JSON, but not every
+            // endpoint does.
+            Ok(response) => Ok(response.into_json().unwrap_or(Value::Null)),
+            Err(ureq::Error::Status(status, response)) => Err(TwitchError::Refused(format!(
+                "{status} {}",
+                response.into_string().unwrap_or_default()
+            ))),
+            Err(e) => Err(Box::new(e).into()),
+        }
+    }
+}
+
+/// Follows channel point redemptions, injecting [`CubeEvent::Redemption`]
+/// for redemptions of the rewards titled `rewards`.
+pub async fn follow_redemptions(
+    twitch: Twitch,
+    rewards: Vec<String>,
+    injector: MoveInjector,
+    cancel: CancellationToken,
+) -> Result<(), TwitchError> {
+    let mut client = Some(TwitchClient::new(twitch));
+    let mut url = EVENTSUB.to_string();
+
+    loop {
+        let (mut socket, _) = connect_async(&url).await.map_err(Box::new)?;
+
+        loop {
+            let message = select! {
+                message = socket.next() => message,
+                _ = cancel.cancelled() => return Ok(()),
+            };
+
+            let text = match message {
+                Some(Ok(Message::Text(text))) => text,
+                Some(Ok(Message::Close(_))) | None => return Err(TwitchError::Closed),
+                Some(Ok(_)) => continue,
+                Some(Err(e)) => return Err(Box::new(e).into()),
+            };
+
+            let message: Value = serde_json::from_str(&text)?;
+            let payload = &message["payload"];
+
+            match message["metadata"]["message_type"].as_str() {
+                Some("session_welcome") => {
+                    // Subscriptions carry over to reconnected sessions.
+                    let Some(mut subscriber) = client.take() else {
+                        continue;
+                    };
+                    let session_id = payload["session"]["id"]
+                        .as_str()
+                        .ok_or_else(|| TwitchError::Unexpected(text.to_string()))?
+                        .to_string();
+
+                    tokio::task::spawn_blocking(move || subscriber.subscribe(&session_id))
+                        .await
+                        .expect("subscribing does not panic")?;
+                    info!("Following channel point redemptions");
+                }
+                Some("session_reconnect") => {
+                    url = payload["session"]["reconnect_url"]
+                        .as_str()
+                        .ok_or_else(|| TwitchError::Unexpected(text.to_string()))?
+                        .to_string();
+                    break;
+                }
+                Some("notification") => {
+                    let event = &payload["event"];
+                    let reward = event["reward"]["title"].as_str().unwrap_or_default();
+
+                    if let Some(bind) = rewards.iter().position(|r| r == reward) {
+                        info!("{} redeemed {reward}", event["user_name"]);
+                        injector.inject_event(CubeEvent::Redemption(bind));
+                    }
+                }
+                Some("revocation") => {
+                    return Err(TwitchError::Refused(
+                        payload["subscription"]["status"].to_string(),
+                    ));
+                }
+                _ => debug!("EventSub {}", message["metadata"]["message_type"]),
+            }
+        }
+    }
+}
@@ -0,0 +1,126 @@
+//! Cube events sent over UDP as OSC messages, so games can use the cube as a
+//! controller without any bluetooth code. Each event is one datagram holding
+//! one OSC 1.0 message, sent as soon as it arrives:
+//!
+//! - `/cube/connected` and `/cube/disconnected`, without arguments.
+//! - `/cube/move` with the move's notation (`s`), its face (`i`, U R F D L B
+//!   as 0 to 5) and its direction (`i`, 1 clockwise and -1 counterclockwise).
+//! - `/cube/orientation` with the orientation quaternion as `f` w, x, y and z.
+//! - `/cube/battery` with the battery percentage (`i`).
+//!
+//! Any OSC library can read these, and `receivers/` has small reference
+//! receivers for Godot and Unity that need none.
+
+use std::net::{Ipv4Addr, Ipv6Addr, SocketAddr};
+
+use futures::{Stream, StreamExt};
+use tokio::{net::UdpSocket, select};
+use tokio_util::sync::CancellationToken;
+use tracing::{debug, info};
+
+use crate::{
+    cube::{CubeEvent, Direction},
+    error::UdpError,
+};
+
+enum Argument<'a> {
+    Int(i32),
+    Float(f32),
+    String(&'a str),
+}
+
+/// Appends an OSC string, NUL terminated and padded to four bytes.
+fn push_string(packet: &mut Vec<u8>, s: &str) {
+    packet.extend(s.as_bytes());
+    packet.extend(std::iter::repeat_n(0, 4 - s.len() % 4));
+}
+
+fn message(address: &str, arguments: &[Argument]) -> Vec<u8> {
+    let mut packet = Vec::new();
+    push_string(&mut packet, address);
+
+    let tags: String = std::iter::once(',')
+        .chain(arguments.iter().map(|argument| match argument {
+            Argument::Int(_) => 'i',
+            Argument::Float(_) => 'f',
+            Argument::String(_) => 's',
+        }))
+        .collect();
+    push_string(&mut packet, &tags);
+
+    for argument in arguments {
+        match argument {
+            Argument::Int(value) => packet.extend(value.to_be_bytes()),
+            Argument::Float(value) => packet.extend(value.to_be_bytes()),
+            Argument::String(value) => push_string(&mut packet, value),
+        }
+    }
+
+    packet
+}
+
+/// The OSC message for `event`, if it is one that is sent.
+pub fn encode(event: &CubeEvent) -> Option<Vec<u8>> {
+    let packet = match event {
+        CubeEvent::Connected => message("/cube/connected", &[]),
+        CubeEvent::Disconnected => message("/cube/disconnected", &[]),
+        CubeEvent::Move(m) => message(
+            "/cube/move",
+            &[
+                Argument::String(&m.to_string()),
+                Argument::Int(m.face() as i32),
+                Argument::Int(match m.direction() {
+                    Direction::Clockwise => 1,
+                    Direction::CounterClockwise => -1,
+                }),
+            ],
+        ),
+        CubeEvent::Orientation(q) => message(
+            "/cube/orientation",
+            &[
+                Argument::Float(q.w),
+                Argument::Float(q.x),
+                Argument::Float(q.y),
+                Argument::Float(q.z),
+            ],
+        ),
+        CubeEvent::Battery(level) => message("/cube/battery", &[Argument::Int(*level as i32)]),
+        _ => return None,
+    };
+
+    Some(packet)
+}
+
+/// Sends events to `target` until `cancel` is cancelled or the events end.
+pub async fn send_events(
+    target: SocketAddr,
+    mut events: impl Stream<Item = CubeEvent> + Unpin,
+    cancel: CancellationToken,
+) -> Result<(), UdpError> {
+    let local: SocketAddr = match target {
+        SocketAddr::V4(_) => (Ipv4Addr::UNSPECIFIED, 0).into(),
+        SocketAddr::V6(_) => (Ipv6Addr::UNSPECIFIED, 0).into(),
+    };
+    let socket = UdpSocket::bind(local).await?;
+
+    info!("Sending cube events to udp://{target}");
+
+    loop {
+        let event = select! {
+            event = events.next() => event,
+            _ = cancel.cancelled() => return Ok(()),
+        };
+
+        let Some(event) = event else {
+            return Ok(());
+        };
+
+        // Nothing may be listening until the game starts, which is not worth
+        // stopping for.
+        if let Some(packet) = encode(&event)
+            && let Err(error) = socket.send_to(&packet, target).await
+        {
+            debug!(%error, "Could not send event");
+        }
+    }
+}
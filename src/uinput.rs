@@ -0,0 +1,226 @@
+//! A virtual keyboard made through uinput, Linux only. Remappers such as keyd
+//! and input-remapper, and games reading evdev directly, see it as a real
+//! keyboard named `triplicata`, which input injected through X11 or a Wayland
+//! portal is not.
+
+use std::io;
+
+use enigo::{Direction, Key};
+use evdev::{AttributeSet, InputEvent, KeyCode, KeyEvent, uinput::VirtualDevice};
+
+use crate::error::OutputError;
+
+pub const DEVICE_NAME: &str = "triplicata";
+
+pub struct VirtualKeyboard {
+    device: VirtualDevice,
+}
+
+impl VirtualKeyboard {
+    /// Creates the device, which needs write access to `/dev/uinput`.
+    pub fn new() -> Result<Self, OutputError> {
+        Self::create().map_err(OutputError::Evdev)
+    }
+
+    fn create() -> io::Result<Self> {
+        // Every key of a full keyboard, so remappers treat it as one.
+        let mut keys = AttributeSet::<KeyCode>::new();
+        for code in (KeyCode::KEY_ESC.0..=KeyCode::KEY_MICMUTE.0).map(KeyCode) {
+            keys.insert(code);
+        }
+
+        let device = VirtualDevice::builder()?
+            .name(DEVICE_NAME)
+            .with_keys(&keys)?
+            .build()?;
+
+        Ok(Self { device })
+    }
+
+    pub fn key(&mut self, key: Key, direction: Direction) -> Result<(), OutputError> {
+        let (code, shift) = key_code(key).ok_or(OutputError::Unmapped(key))?;
+
+        let mut events = Vec::new();
+        if shift && direction != Direction::Release {
+            events.push(event(KeyCode::KEY_LEFTSHIFT, 1));
+        }
+        if direction != Direction::Release {
+            events.push(event(code, 1));
+        }
+        if direction != Direction::Press {
+            events.push(event(code, 0));
+        }
+        if shift && direction != Direction::Press {
+            events.push(event(KeyCode::KEY_LEFTSHIFT, 0));
+        }
+
+        self.device.emit(&events).map_err(OutputError::Evdev)
+    }
+
+    /// Sends the evdev key code `code` as is.
+    pub fn scancode(&mut self, code: u16, direction: Direction) -> Result<(), OutputError> {
+        let code = KeyCode(code);
+
+        let mut events = Vec::new();
+        if direction != Direction::Release {
+            events.push(event(code, 1));
+        }
+        if direction != Direction::Press {
+            events.push(event(code, 0));
+        }
+
+        self.device.emit(&events).map_err(OutputError::Evdev)
+    }
+}
+
+fn event(code: KeyCode, value: i32) -> InputEvent {
+    *KeyEvent::new(code, value)
+}
+
+/// The key code for `key`, and whether shift is held for it. Characters are
+/// placed as on a US layout, which the desktop's layout then translates.
+pub(crate) fn key_code(key: Key) -> Option<(KeyCode, bool)> {
+    let code = match key {
+        Key::Unicode(c) => return char_code(c),
+        Key::Alt => KeyCode::KEY_LEFTALT,
+        Key::Backspace => KeyCode::KEY_BACKSPACE,
+        Key::CapsLock => KeyCode::KEY_CAPSLOCK,
+        Key::Control => KeyCode::KEY_LEFTCTRL,
+        Key::Delete => KeyCode::KEY_DELETE,
+        Key::DownArrow => KeyCode::KEY_DOWN,
+        Key::End => KeyCode::KEY_END,
+        Key::Escape => KeyCode::KEY_ESC,
+        Key::F1 => KeyCode::KEY_F1,
+        Key::F2 => KeyCode::KEY_F2,
+        Key::F3 => KeyCode::KEY_F3,
+        Key::F4 => KeyCode::KEY_F4,
+        Key::F5 => KeyCode::KEY_F5,
+        Key::F6 => KeyCode::KEY_F6,
+        Key::F7 => KeyCode::KEY_F7,
+        Key::F8 => KeyCode::KEY_F8,
+        Key::F9 => KeyCode::KEY_F9,
+        Key::F10 => KeyCode::KEY_F10,
+        Key::F11 => KeyCode::KEY_F11,
+        Key::F12 => KeyCode::KEY_F12,
+        Key::F13 => KeyCode::KEY_F13,
+        Key::F14 => KeyCode::KEY_F14,
+        Key::F15 => KeyCode::KEY_F15,
+        Key::F16 => KeyCode::KEY_F16,
+        Key::F17 => KeyCode::KEY_F17,
+        Key::F18 => KeyCode::KEY_F18,
+        Key::F19 => KeyCode::KEY_F19,
+        Key::F20 => KeyCode::KEY_F20,
+        Key::Home => KeyCode::KEY_HOME,
+        Key::Insert => KeyCode::KEY_INSERT,
+        Key::LeftArrow => KeyCode::KEY_LEFT,
+        Key::Meta => KeyCode::KEY_LEFTMETA,
+        Key::Numlock => KeyCode::KEY_NUMLOCK,
+        Key::PageDown => KeyCode::KEY_PAGEDOWN,
+        Key::PageUp => KeyCode::KEY_PAGEUP,
+        Key::Pause => KeyCode::KEY_PAUSE,
+        Key::Return => KeyCode::KEY_ENTER,
+        Key::RightArrow => KeyCode::KEY_RIGHT,
+        Key::ScrollLock => KeyCode::KEY_SCROLLLOCK,
+        Key::Shift => KeyCode::KEY_LEFTSHIFT,
+        Key::Space => KeyCode::KEY_SPACE,
+        Key::Tab => KeyCode::KEY_TAB,
+        Key::UpArrow => KeyCode::KEY_UP,
+        Key::VolumeDown => KeyCode::KEY_VOLUMEDOWN,
+        Key::VolumeMute => KeyCode::KEY_MUTE,
+        Key::VolumeUp => KeyCode::KEY_VOLUMEUP,
+        Key::MediaNextTrack => KeyCode::KEY_NEXTSONG,
+        Key::MediaPlayPause => KeyCode::KEY_PLAYPAUSE,
+        Key::MediaPrevTrack => KeyCode::KEY_PREVIOUSSONG,
+        Key::MediaStop => KeyCode::KEY_STOPCD,
+        _ => return None,
+    };
+
+    Some((code, false))
+}
+
+fn char_code(c: char) -> Option<(KeyCode, bool)> {
+    const LETTERS: [KeyCode; 26] = [
+        KeyCode::KEY_A,
+        KeyCode::KEY_B,
+        KeyCode::KEY_C,
+        KeyCode::KEY_D,
+        KeyCode::KEY_E,
+        KeyCode::KEY_F,
+        KeyCode::KEY_G,
+        KeyCode::KEY_H,
+        KeyCode::KEY_I,
+        KeyCode::KEY_J,
+        KeyCode::KEY_K,
+        KeyCode::KEY_L,
+        KeyCode::KEY_M,
+        KeyCode::KEY_N,
+        KeyCode::KEY_O,
+        KeyCode::KEY_P,
+        KeyCode::KEY_Q,
+        KeyCode::KEY_R,
+        KeyCode::KEY_S,
+        KeyCode::KEY_T,
+        KeyCode::KEY_U,
+        KeyCode::KEY_V,
+        KeyCode::KEY_W,
+        KeyCode::KEY_X,
+        KeyCode::KEY_Y,
+        KeyCode::KEY_Z,
+    ];
+    const DIGITS: [KeyCode; 10] = [
+        KeyCode::KEY_0,
+        KeyCode::KEY_1,
+        KeyCode::KEY_2,
+        KeyCode::KEY_3,
+        KeyCode::KEY_4,
+        KeyCode::KEY_5,
+        KeyCode::KEY_6,
+        KeyCode::KEY_7,
+        KeyCode::KEY_8,
+        KeyCode::KEY_9,
+    ];
+    // The shifted symbols of the digit row, `)` being on 0.
+    const SHIFTED_DIGITS: &str = ")!@#$%^&*(";
+
+    if c.is_ascii_lowercase() {
+        return Some((LETTERS[(c as u8 - b'a') as usize], false));
+    }
+    if c.is_ascii_uppercase() {
+        return Some((LETTERS[(c as u8 - b'A') as usize], true));
+    }
+    if let Some(digit) = c.to_digit(10) {
+        return Some((DIGITS[digit as usize], false));
+    }
+    if let Some(digit) = SHIFTED_DIGITS.find(c) {
+        return Some((DIGITS[digit], true));
+    }
+
+    Some(match c {
+        ' ' => (KeyCode::KEY_SPACE, false),
+        '\n' => (KeyCode::KEY_ENTER, false),
+        '\t' => (KeyCode::KEY_TAB, false),
+        '-' => (KeyCode::KEY_MINUS, false),
+        '_' => (KeyCode::KEY_MINUS, true),
+        '=' => (KeyCode::KEY_EQUAL, false),
+        '+' => (KeyCode::KEY_EQUAL, true),
+        '[' => (KeyCode::KEY_LEFTBRACE, false),
+        '{' => (KeyCode::KEY_LEFTBRACE, true),
+        ']' => (KeyCode::KEY_RIGHTBRACE, false),
+        '}' => (KeyCode::KEY_RIGHTBRACE, true),
+        '\\' => (KeyCode::KEY_BACKSLASH, false),
+        '|' => (KeyCode::KEY_BACKSLASH, true),
+        ';' => (KeyCode::KEY_SEMICOLON, false),
+        ':' => (KeyCode::KEY_SEMICOLON, true),
+        '\'' => (KeyCode::KEY_APOSTROPHE, false),
+        '"' => (KeyCode::KEY_APOSTROPHE, true),
+        '`' => (KeyCode::KEY_GRAVE, false),
+        '~' => (KeyCode::KEY_GRAVE, true),
+        ',' => (KeyCode::KEY_COMMA, false),
+        '<' => (KeyCode::KEY_COMMA, true),
+        '.' => (KeyCode::KEY_DOT, false),
+        '>' => (KeyCode::KEY_DOT, true),
+        '/' => (KeyCode::KEY_SLASH, false),
+        '?' => (KeyCode::KEY_SLASH, true),
+        _ => return None,
+    })
+}
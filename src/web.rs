@@ -0,0 +1,200 @@
+use js_sys::{Array, DataView, Reflect, Uint8Array};
+use tokio::sync::{
+    broadcast::{self, Receiver},
+    oneshot,
+};
+use tokio_util::sync::CancellationToken;
+use wasm_bindgen::{JsCast, JsValue, closure::Closure};
+use wasm_bindgen_futures::JsFuture;
+use web_sys::{
+    BluetoothAdvertisingEvent, BluetoothDevice, BluetoothLeScanFilterInit,
+    BluetoothRemoteGattCharacteristic, BluetoothRemoteGattService, Event, RequestDeviceOptions,
+};
+use web_time::Instant;
+
+use crate::{
+    GAN_GEN2_COMMAND_CHARACTERISTIC, GAN_GEN2_SERVICE, GAN_GEN2_STATE_CHARACTERISTIC,
+    cube::CubeEvent,
+    error::{CubeError, ProtocolError},
+    metrics::{self, Stage},
+    protocol::{
+        cipher::{CipherKeys, GAN_MANUFACTURER_ID, GANCubeVersion2Cipher},
+        gen2::{Command, Decoder},
+    },
+    source::CubeSource,
+};
+
+impl From<JsValue> for CubeError {
+    fn from(value: JsValue) -> Self {
+        CubeError::Web(format!("{value:?}"))
+    }
+}
+
+/// A cube connected through the browser's Web Bluetooth API.
+///
+/// `connect` must be called from a user gesture, as browsers will refuse to
+/// show the device picker otherwise.
+#[derive(Debug, Default)]
+pub struct WebBluetoothCubeSource {
+    salt: Option<[u8; 6]>,
+    keys: CipherKeys,
+}
+
+impl WebBluetoothCubeSource {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Uses a known salt instead of waiting for an advertisement, for browsers
+    /// that do not support `watchAdvertisements`.
+    pub fn salt(mut self, salt: [u8; 6]) -> Self {
+        self.salt = Some(salt);
+        self
+    }
+
+    /// Derives the cube's cipher from other base keys than GAN's.
+    pub fn keys(mut self, keys: CipherKeys) -> Self {
+        self.keys = keys;
+        self
+    }
+}
+
+fn data_view_bytes(view: &DataView) -> Vec<u8> {
+    Uint8Array::new_with_byte_offset_and_length(
+        &view.buffer(),
+        view.byte_offset() as u32,
+        view.byte_length() as u32,
+    )
+    .to_vec()
+}
+
+async fn request_cube() -> Result<BluetoothDevice, CubeError> {
+    let bluetooth = web_sys::window()
+        .and_then(|window| window.navigator().bluetooth())
+        .ok_or(CubeError::NoAdapter)?;
+
+    let filter = BluetoothLeScanFilterInit::new();
+    filter.set_name_prefix("GAN");
+
+    let options = RequestDeviceOptions::new();
+    options.set_filters(&[filter]);
+    options.set_optional_services(&[GAN_GEN2_SERVICE.to_string().into()]);
+    Reflect::set(
+        &options,
+        &"optionalManufacturerData".into(),
+        &Array::of1(&GAN_MANUFACTURER_ID.into()),
+    )?;
+
+    Ok(JsFuture::from(bluetooth.request_device(&options))
+        .await?
+        .unchecked_into())
+}
+
+async fn advertised_salt(device: &BluetoothDevice) -> Result<[u8; 6], CubeError> {
+    let (tx, rx) = oneshot::channel();
+    let mut tx = Some(tx);
+
+    let on_advertisement = Closure::<dyn FnMut(Event)>::new(move |event: Event| {
+        let event: BluetoothAdvertisingEvent = event.unchecked_into();
+
+        if let Some(data) = event.manufacturer_data().get(GAN_MANUFACTURER_ID)
+            && let Some(tx) = tx.take()
+        {
+            let _ = tx.send(data_view_bytes(&data));
+        }
+    });
+
+    device.set_onadvertisementreceived(Some(on_advertisement.as_ref().unchecked_ref()));
+    JsFuture::from(device.watch_advertisements()).await?;
+
+    let data = rx.await.map_err(|_| ProtocolError::MissingDeviceIdentifier);
+    device.set_onadvertisementreceived(None);
+
+    Ok(GANCubeVersion2Cipher::salt_from_manufacturer_data(&data?)?)
+}
+
+impl CubeSource for WebBluetoothCubeSource {
+    async fn connect(self, cancel: CancellationToken) -> Result<Receiver<CubeEvent>, CubeError> {
+        let device = request_cube().await?;
+
+        let salt = match self.salt {
+            Some(salt) => salt,
+            None => advertised_salt(&device).await?,
+        };
+
+        let mut decoder = Decoder::new(GANCubeVersion2Cipher::from_salt_with(self.keys, salt));
+        let encoder = decoder.clone();
+
+        let gatt = device.gatt().ok_or(CubeError::MissingProperties)?;
+        JsFuture::from(gatt.connect()).await?;
+
+        let service: BluetoothRemoteGattService =
+            JsFuture::from(gatt.get_primary_service_with_str(&GAN_GEN2_SERVICE.to_string()))
+                .await?
+                .unchecked_into();
+        let read: BluetoothRemoteGattCharacteristic = JsFuture::from(
+            service.get_characteristic_with_str(&GAN_GEN2_STATE_CHARACTERISTIC.to_string()),
+        )
+        .await?
+        .unchecked_into();
+        let write: BluetoothRemoteGattCharacteristic = JsFuture::from(
+            service.get_characteristic_with_str(&GAN_GEN2_COMMAND_CHARACTERISTIC.to_string()),
+        )
+        .await?
+        .unchecked_into();
+
+        let (tx, rx) = broadcast::channel::<CubeEvent>(10);
+
+        let event_sender = tx.clone();
+        let on_value = Closure::<dyn FnMut(Event)>::new(move |event: Event| {
+            let Some(value) = event
+                .target()
+                .and_then(|target| target.dyn_into::<BluetoothRemoteGattCharacteristic>().ok())
+                .and_then(|characteristic| characteristic.value())
+            else {
+                return;
+            };
+
+            let received = Instant::now();
+            let Ok(events) = decoder.decode(&data_view_bytes(&value)) else {
+                metrics::decrypt_failure();
+                return;
+            };
+            metrics::stage_latency(Stage::Decode, received.elapsed());
+
+            for event in events {
+                if let CubeEvent::Move(_) = event {
+                    metrics::move_received();
+                    let _ = event_sender.send(CubeEvent::Received(received));
+                }
+
+                let _ = event_sender.send(event);
+            }
+        });
+        read.set_oncharacteristicvaluechanged(Some(on_value.as_ref().unchecked_ref()));
+        on_value.forget();
+
+        let disconnect_sender = tx.clone();
+        let on_disconnect = Closure::<dyn FnMut(Event)>::new(move |_: Event| {
+            let _ = disconnect_sender.send(CubeEvent::Disconnected);
+        });
+        device.set_ongattserverdisconnected(Some(on_disconnect.as_ref().unchecked_ref()));
+        on_disconnect.forget();
+
+        JsFuture::from(read.start_notifications()).await?;
+
+        wasm_bindgen_futures::spawn_local(async move {
+            cancel.cancelled().await;
+            gatt.disconnect();
+        });
+
+        let _ = tx.send(CubeEvent::Connected);
+
+        for command in [Command::RequestState, Command::RequestBattery] {
+            let packet = encoder.encode(command)?;
+            JsFuture::from(write.write_value_with_response_with_u8_slice(&packet)?).await?;
+        }
+
+        Ok(rx)
+    }
+}
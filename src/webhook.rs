@@ -0,0 +1,256 @@
+//! Webhooks, JSON POSTed to URLs as chosen events happen so automations
+//! elsewhere can react without polling. Each body is an object tagged by its
+//! `event`:
+//!
+//! - `{"event": "solved"}` when a move solves the cube.
+//! - `{"event": "fired", "bind": "Save"}` when a bind fires, by its label.
+//! - `{"event": "connected"}` and `{"event": "disconnected"}`.
+//! - `{"event": "battery_low", "level": 15}` when the battery drops below
+//!   [`LOW_BATTERY`], once until it is charged again.
+//!
+//! Failed deliveries are tried again as the webhook's `retry` says, waiting
+//! twice as long after each failure. Deliveries to one URL are made in order,
+//! so a URL that is down does not hold back the others.
+
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use futures::{Stream, StreamExt};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use tokio::{
+    select,
+    sync::{broadcast, mpsc},
+};
+use tokio_util::sync::CancellationToken;
+use tracing::{debug, info, warn};
+
+use crate::{
+    cube::{CubeEvent, CubeState},
+    error::WebhookError,
+    metrics::{self, Channel},
+    retry::RetryPolicy,
+    state_machine::FiredBind,
+    status::LOW_BATTERY,
+};
+
+/// A URL and the events sent to it, e.g.
+/// `(url: "https://example.com/hook", events: [Solved, BatteryLow])`.
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct Webhook {
+    pub url: String,
+    /// The events to send, every one when empty.
+    #[serde(default)]
+    pub events: Vec<WebhookEvent>,
+    /// How long each delivery may take and how often it is tried.
+    #[serde(default)]
+    pub retry: RetryPolicy,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WebhookEvent {
+    Solved,
+    Fired,
+    Connected,
+    Disconnected,
+    BatteryLow,
+}
+
+/// What is sent for an event.
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum Payload {
+    Solved,
+    Fired { bind: String },
+    Connected,
+    Disconnected,
+    BatteryLow { level: u8 },
+}
+
+impl Webhook {
+    pub fn wants(&self, event: WebhookEvent) -> bool {
+        self.events.is_empty() || self.events.contains(&event)
+    }
+}
+
+impl Payload {
+    pub fn event(&self) -> WebhookEvent {
+        match self {
+            Payload::Solved => WebhookEvent::Solved,
+            Payload::Fired { .. } => WebhookEvent::Fired,
+            Payload::Connected => WebhookEvent::Connected,
+            Payload::Disconnected => WebhookEvent::Disconnected,
+            Payload::BatteryLow { .. } => WebhookEvent::BatteryLow,
+        }
+    }
+
+    /// The body POSTed for the payload, stamped with when it happened.
+    pub fn to_json(&self, at: SystemTime) -> String {
+        let mut body = serde_json::to_value(self).unwrap_or_default();
+        body["time_ms"] = json!(
+            at.duration_since(UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_millis() as u64
+        );
+        body.to_string()
+    }
+}
+
+impl From<&FiredBind> for Payload {
+    fn from(fired: &FiredBind) -> Self {
+        Payload::Fired {
+            bind: fired.bind.label(),
+        }
+    }
+}
+
+/// Picks the payloads out of cube events.
+#[derive(Debug, Default)]
+pub struct PayloadTracker {
+    state: CubeState,
+    battery_low: bool,
+}
+
+impl PayloadTracker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Updates the tracked cube with `event`, returning what to send for it.
+    pub fn push(&mut self, event: &CubeEvent) -> Option<Payload> {
+        match *event {
+            CubeEvent::Move(m) => {
+                let was_solved = self.state.is_solved();
+                self.state.apply(m);
+                (!was_solved && self.state.is_solved()).then_some(Payload::Solved)
+            }
+            CubeEvent::StateSync(state) => {
+                self.state = state;
+                None
+            }
+            CubeEvent::Connected => Some(Payload::Connected),
+            CubeEvent::Disconnected => Some(Payload::Disconnected),
+            CubeEvent::Battery(level) => {
+                let was_low = std::mem::replace(&mut self.battery_low, level < LOW_BATTERY);
+                (self.battery_low && !was_low).then_some(Payload::BatteryLow { level })
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Sends the payloads of `events` and `fired` to `webhooks` until `cancel` is
+/// cancelled or the events end.
+pub async fn send_events(
+    webhooks: Vec<Webhook>,
+    mut events: impl Stream<Item = CubeEvent> + Unpin,
+    mut fired: broadcast::Receiver<FiredBind>,
+    cancel: CancellationToken,
+) {
+    let queues: Vec<_> = webhooks
+        .into_iter()
+        .map(|webhook| {
+            info!("Sending events to {}", webhook.url);
+
+            let (tx, rx) = mpsc::unbounded_channel();
+            tokio::spawn(deliver(webhook.clone(), rx, cancel.clone()));
+            (webhook, tx)
+        })
+        .collect();
+    let mut tracker = PayloadTracker::new();
+
+    loop {
+        let payload = select! {
+            event = events.next() => match event {
+                Some(event) => tracker.push(&event),
+                None => return,
+            },
+            bind = fired.recv() => match bind {
+                Ok(bind) => Some(Payload::from(&bind)),
+                Err(broadcast::error::RecvError::Lagged(count)) => {
+                    metrics::channel_drops(Channel::FiredBinds, count);
+                    None
+                }
+                Err(broadcast::error::RecvError::Closed) => return,
+            },
+            _ = cancel.cancelled() => return,
+        };
+
+        let Some(payload) = payload else {
+            continue;
+        };
+
+        let body = payload.to_json(SystemTime::now());
+        for (webhook, queue) in &queues {
+            if webhook.wants(payload.event()) {
+                let _ = queue.send(body.clone());
+            }
+        }
+    }
+}
+
+/// Delivers the bodies queued for `webhook` in order, each on a blocking
+/// thread as `ureq` is synchronous.
+async fn deliver(
+    webhook: Webhook,
+    mut bodies: mpsc::UnboundedReceiver<String>,
+    cancel: CancellationToken,
+) {
+    let agent = ureq::AgentBuilder::new()
+        .timeout(webhook.retry.timeout())
+        .build();
+    let attempts = webhook.retry.attempts.max(1);
+
+    loop {
+        let body = select! {
+            body = bodies.recv() => body,
+            _ = cancel.cancelled() => return,
+        };
+
+        let Some(body) = body else {
+            return;
+        };
+
+        let mut backoff = webhook.retry.backoff();
+        for tried in 1..=attempts {
+            let (agent, url, body) = (agent.clone(), webhook.url.clone(), body.clone());
+            let error = match tokio::task::spawn_blocking(move || post(&agent, &url, &body)).await {
+                Ok(Ok(())) => {
+                    debug!(url = %webhook.url, "Delivered webhook");
+                    break;
+                }
+                Ok(Err(e)) => e.to_string(),
+                Err(e) => e.to_string(),
+            };
+
+            if tried == attempts {
+                warn!(
+                    "Gave up on the webhook to {} after {attempts} attempts: {error}",
+                    webhook.url
+                );
+                break;
+            }
+
+            debug!(url = %webhook.url, %error, "Webhook failed, attempt {tried} of {attempts}");
+            select! {
+                _ = tokio::time::sleep(backoff) => {}
+                _ = cancel.cancelled() => return,
+            }
+            backoff *= 2;
+        }
+    }
+}
+
+fn post(agent: &ureq::Agent, url: &str, body: &str) -> Result<(), WebhookError> {
+    match agent
+        .post(url)
+        .set("Content-Type", "application/json")
+        .send_string(body)
+    {
+        Ok(_) => Ok(()),
+        Err(ureq::Error::Status(status, response)) => Err(WebhookError::Refused(format!(
+            "{status} {}",
+            response.into_string().unwrap_or_default()
+        ))),
+        Err(e) => Err(Box::new(e).into()),
+    }
+}
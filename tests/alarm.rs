@@ -0,0 +1,61 @@
+#![cfg(feature = "alarm")]
+
+use triplicata::{
+    alarm::{Dismissal, Stage},
+    algorithm::Algorithm,
+    cube::{CubeState, Move},
+};
+
+fn moves(algorithm: &str) -> Vec<Move> {
+    algorithm.parse::<Algorithm>().unwrap().to_moves().unwrap()
+}
+
+fn play(dismissal: &mut Dismissal, algorithm: &str) -> Stage {
+    for m in moves(algorithm) {
+        dismissal.apply(m);
+    }
+    dismissal.stage()
+}
+
+#[test]
+fn scramble_then_solve_dismisses() {
+    let mut dismissal = Dismissal::new("R U2 F'".parse().unwrap()).unwrap();
+
+    assert_eq!(play(&mut dismissal, "R U"), Stage::Scrambling);
+    assert_eq!(play(&mut dismissal, "U F'"), Stage::Solving);
+    assert_eq!(play(&mut dismissal, "F U2 R'"), Stage::Dismissed);
+}
+
+#[test]
+fn solving_before_scrambling_does_not_dismiss() {
+    let mut dismissal = Dismissal::new("R U".parse().unwrap()).unwrap();
+
+    assert_eq!(play(&mut dismissal, "R R'"), Stage::Scrambling);
+}
+
+#[test]
+fn mistakes_while_scrambling_can_be_undone() {
+    let mut dismissal = Dismissal::new("R U".parse().unwrap()).unwrap();
+
+    assert_eq!(play(&mut dismissal, "R F F'"), Stage::Scrambling);
+    assert_eq!(play(&mut dismissal, "U"), Stage::Solving);
+}
+
+#[test]
+fn synced_state_counts() {
+    let mut dismissal = Dismissal::new("R".parse().unwrap()).unwrap();
+
+    let mut scrambled = CubeState::SOLVED;
+    scrambled.apply(moves("R")[0]);
+
+    assert_eq!(dismissal.sync(scrambled), Stage::Solving);
+    assert_eq!(dismissal.sync(CubeState::SOLVED), Stage::Dismissed);
+}
+
+#[test]
+fn random_dismissals_start_scrambling() {
+    let dismissal = Dismissal::random(&mut rand::rng());
+
+    assert_eq!(dismissal.stage(), Stage::Scrambling);
+    assert!(!dismissal.scramble().0.is_empty());
+}
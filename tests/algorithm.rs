@@ -0,0 +1,82 @@
+use triplicata::{
+    algorithm::{Algorithm, Axis, Layer, Slice, Turn},
+    cube::{Face, Move},
+    error::NotationError,
+};
+
+#[test]
+fn round_trips_standard_and_sign_notation() {
+    let sign: Algorithm = "RUR'r2 3f'".parse().unwrap();
+    let standard: Algorithm = "R U R' Rw2 3Fw'".parse().unwrap();
+
+    assert_eq!(sign, standard);
+    assert_eq!(standard.to_string(), "R U R' Rw2 3Fw'");
+    assert_eq!(standard.to_sign(), "R U R' r2 3f'");
+    assert_eq!(
+        standard.0[4],
+        Turn {
+            layer: Layer::Wide(Face::F, 3),
+            amount: -1,
+        }
+    );
+}
+
+#[test]
+fn parses_amounts_and_apostrophes() {
+    let algorithm: Algorithm = "R2' U’ M2 y".parse().unwrap();
+
+    assert_eq!(
+        algorithm.0,
+        [
+            Turn {
+                layer: Layer::Face(Face::R),
+                amount: -2,
+            },
+            Turn {
+                layer: Layer::Face(Face::U),
+                amount: -1,
+            },
+            Turn {
+                layer: Layer::Slice(Slice::M),
+                amount: 2,
+            },
+            Turn {
+                layer: Layer::Rotation(Axis::Y),
+                amount: 1,
+            },
+        ]
+    );
+    assert_eq!(algorithm.to_string(), "R2' U' M2 y");
+}
+
+#[test]
+fn rejects_invalid_turns() {
+    assert!(matches!(
+        "R200".parse::<Algorithm>(),
+        Err(NotationError::InvalidMove(_))
+    ));
+    assert!(matches!(
+        "3R".parse::<Algorithm>(),
+        Err(NotationError::UnsupportedTurn(turn)) if turn == "3R"
+    ));
+    for notation in ["0Rw", "0r", "R0", "2", "Q"] {
+        assert!(
+            notation.parse::<Algorithm>().is_err(),
+            "{notation} should not parse"
+        );
+    }
+}
+
+#[test]
+fn expands_face_turns_into_moves() {
+    let algorithm: Algorithm = "R2 U'".parse().unwrap();
+    assert_eq!(algorithm.to_moves().unwrap(), [Move::R, Move::R, Move::Up]);
+
+    for notation in ["R M", "x R", "Rw"] {
+        let algorithm: Algorithm = notation.parse().unwrap();
+        assert!(matches!(
+            algorithm.to_moves(),
+            Err(NotationError::UnsupportedTurn(_))
+        ));
+    }
+}
@@ -0,0 +1,60 @@
+use triplicata::protocol::{extract_bits, insert_bits};
+
+/// The original bit by bit implementation, kept as the reference.
+fn extract_bits_reference(data: &[u8], start: usize, count: usize) -> u32 {
+    let mut result = 0;
+    for i in 0..count {
+        let bit = start + i;
+        result <<= 1;
+        if data[bit / 8] & (1 << (7 - (bit % 8))) != 0 {
+            result |= 1;
+        }
+    }
+    result
+}
+
+/// A packet's worth of bytes that are not all zero or all one.
+fn packet(seed: u32) -> [u8; 20] {
+    let mut state = seed;
+    std::array::from_fn(|_| {
+        state = state.wrapping_mul(1_103_515_245).wrapping_add(12345);
+        (state >> 16) as u8
+    })
+}
+
+#[test]
+fn matches_reference_for_every_field() {
+    for seed in 0..16 {
+        let data = packet(seed);
+        for start in 0..data.len() * 8 {
+            for count in 0..=32.min(data.len() * 8 - start) {
+                assert_eq!(
+                    extract_bits(&data, start, count),
+                    extract_bits_reference(&data, start, count),
+                    "{count} bits at {start} of {data:02x?}"
+                );
+            }
+        }
+    }
+}
+
+#[test]
+fn reads_fields_across_bytes() {
+    let data = [0b1010_1100, 0b0011_0101, 0xff, 0x00, 0x80, 0x01];
+
+    assert_eq!(extract_bits(&data, 0, 4), 0b1010);
+    assert_eq!(extract_bits(&data, 4, 8), 0b1100_0011);
+    assert_eq!(extract_bits(&data, 12, 5), 0b01011);
+    assert_eq!(extract_bits(&data, 8, 32), 0x35ff_0080);
+    assert_eq!(extract_bits(&data, 15, 32), 0xff80_4000);
+    assert_eq!(extract_bits(&data, 47, 1), 1);
+}
+
+#[test]
+fn inverts_insert_bits() {
+    let mut data = packet(7);
+    for (start, count, value) in [(0, 4, 0x9), (4, 8, 0xa5), (12, 5, 0x13), (47, 16, 0xbeef)] {
+        insert_bits(&mut data, start, count, value);
+        assert_eq!(extract_bits(&data, start, count), value);
+    }
+}
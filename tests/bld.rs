@@ -0,0 +1,43 @@
+use triplicata::{
+    bld::{Memo, unsolved_pieces},
+    cube::{CubeState, Move},
+};
+
+fn scrambled(moves: &[Move]) -> CubeState {
+    let mut state = CubeState::SOLVED;
+    for &m in moves {
+        state.apply(m);
+    }
+    state
+}
+
+#[test]
+fn has_nothing_to_memo_when_solved() {
+    let memo = Memo::new(&CubeState::SOLVED);
+
+    assert!(memo.is_empty());
+    assert!(!memo.parity());
+    assert_eq!(unsolved_pieces(&CubeState::SOLVED), (0, 0));
+}
+
+#[test]
+fn breaks_into_a_new_cycle_once_the_buffer_is_home() {
+    let memo = Memo::new(&scrambled(&[Move::U, Move::U]));
+
+    assert_eq!(memo.edges, ['A', 'B', 'D', 'B']);
+    assert_eq!(memo.corners, ['A', 'D', 'B', 'D']);
+    assert!(!memo.parity());
+    assert_eq!(memo.to_string(), "edges: AB DB\ncorners: AD BD");
+}
+
+#[test]
+fn flags_parity_for_an_odd_number_of_edge_targets() {
+    let state = scrambled(&[Move::U]);
+    let memo = Memo::new(&state);
+
+    assert_eq!(memo.edges, ['B', 'A', 'D']);
+    assert_eq!(memo.corners, ['B', 'A', 'D']);
+    assert!(memo.parity());
+    assert_eq!(memo.to_string(), "edges: BA D\ncorners: BA D\nparity");
+    assert_eq!(unsolved_pieces(&state), (4, 4));
+}
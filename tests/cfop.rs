@@ -0,0 +1,76 @@
+#![cfg(feature = "solves")]
+
+use triplicata::{
+    algorithm::Algorithm,
+    cfop::{Step, recognize},
+    cube::{CubeState, Face, Move},
+    facelet::FaceletCube,
+};
+
+/// A solved cube set up into the case `algorithm` solves, with `before` and
+/// `after` turns of U around it.
+fn case(algorithm: &str, before: usize, after: usize) -> FaceletCube {
+    let setup = algorithm.parse::<Algorithm>().unwrap().inverse();
+
+    let mut state = CubeState::SOLVED;
+    for _ in 0..after {
+        state.apply(Move::U);
+    }
+    for m in setup.to_moves().unwrap() {
+        state.apply(m);
+    }
+    for _ in 0..before {
+        state.apply(Move::U);
+    }
+
+    FaceletCube::from(&state)
+}
+
+#[test]
+fn recognizes_oll_cases() {
+    for (name, algorithm) in [
+        ("27", "R U R' U R U2 R'"),
+        ("26", "R U2 R' U' R U' R'"),
+        ("45", "F R U R' U' F'"),
+        ("33", "R U R' U' R' F R F'"),
+    ] {
+        let case = recognize(&case(algorithm, 0, 0), Face::U, Step::Oll);
+        assert_eq!((case.step, case.name), (Step::Oll, name));
+    }
+}
+
+#[test]
+fn recognizes_pll_cases() {
+    for (name, algorithm) in [
+        ("T", "R U R' U' R' F R2 U' R' U' R U R' F'"),
+        ("Jb", "R U R' F' R U R' U' R' F R2 U' R'"),
+        ("Ra", "R U' R' U' R U R D R' U' R D' R' U2 R'"),
+    ] {
+        let case = recognize(&case(algorithm, 0, 0), Face::U, Step::Pll);
+        assert_eq!((case.step, case.name), (Step::Pll, name));
+    }
+}
+
+#[test]
+fn recognizes_cases_after_any_turn_of_u() {
+    for before in 0..4 {
+        for after in 0..4 {
+            let t = case("R U R' U' R' F R2 U' R' U' R U R' F'", before, after);
+            assert_eq!(recognize(&t, Face::U, Step::Pll).name, "T");
+
+            let sune = case("R U R' U R U2 R'", before, after);
+            assert_eq!(recognize(&sune, Face::U, Step::Oll).name, "27");
+        }
+    }
+}
+
+#[test]
+fn recognizes_skips() {
+    let solved = FaceletCube::from(&CubeState::SOLVED);
+    assert_eq!(recognize(&solved, Face::U, Step::Oll).name, "skip");
+    assert_eq!(recognize(&solved, Face::U, Step::Pll).name, "skip");
+
+    // Oriented but not permuted.
+    let t = case("R U R' U' R' F R2 U' R' U' R U R' F'", 1, 0);
+    assert_eq!(recognize(&t, Face::U, Step::Oll).name, "skip");
+}
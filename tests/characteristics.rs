@@ -0,0 +1,66 @@
+use triplicata::{
+    GAN_GEN1_BATTERY_CHARACTERISTIC, GAN_GEN1_CUBE_STATE_CHARACTERISTIC,
+    GAN_GEN1_LAST_MOVES_CHARACTERISTIC, GAN_GEN1_TIMING_CHARACTERISTIC,
+    GAN_GEN1_VERSION_CHARACTERISTIC, GAN_GEN2_COMMAND_CHARACTERISTIC,
+    GAN_GEN2_STATE_CHARACTERISTIC,
+    error::ProtocolError,
+    protocol::characteristics::{Generation, Role, identify},
+};
+use uuid::Uuid;
+
+fn named(uuids: &[Uuid]) -> Vec<(Uuid, Uuid)> {
+    uuids.iter().map(|uuid| (*uuid, *uuid)).collect()
+}
+
+#[test]
+fn identifies_v2() {
+    let identified = identify(named(&[
+        GAN_GEN2_COMMAND_CHARACTERISTIC,
+        GAN_GEN2_STATE_CHARACTERISTIC,
+    ]))
+    .unwrap();
+
+    assert_eq!(identified.generation, Generation::V2);
+    assert_eq!(identified.missing().count(), 0);
+}
+
+#[test]
+fn identifies_v1_from_a_subset() {
+    let mut identified = identify(named(&[
+        GAN_GEN1_VERSION_CHARACTERISTIC,
+        GAN_GEN1_LAST_MOVES_CHARACTERISTIC,
+        GAN_GEN1_TIMING_CHARACTERISTIC,
+    ]))
+    .unwrap();
+
+    assert_eq!(identified.generation, Generation::V1);
+
+    let missing: Vec<_> = identified.missing().map(|known| known.role).collect();
+    assert_eq!(
+        missing,
+        [Role::V1Hardware, Role::V1CubeState, Role::V1Battery]
+    );
+    assert_eq!(
+        identified.take(Role::V1LastMoves).unwrap(),
+        GAN_GEN1_LAST_MOVES_CHARACTERISTIC
+    );
+}
+
+#[test]
+fn v1_needs_its_last_moves() {
+    let identified = identify(named(&[
+        GAN_GEN1_CUBE_STATE_CHARACTERISTIC,
+        GAN_GEN1_BATTERY_CHARACTERISTIC,
+    ]));
+
+    let Err(ProtocolError::UnrecognizedCharacteristics(found)) = identified else {
+        panic!("identified a generation without its last moves");
+    };
+    assert_eq!(
+        found,
+        [
+            GAN_GEN1_CUBE_STATE_CHARACTERISTIC,
+            GAN_GEN1_BATTERY_CHARACTERISTIC
+        ]
+    );
+}
@@ -0,0 +1,75 @@
+#![cfg(all(feature = "runtime", feature = "config"))]
+
+use triplicata::{chords::builtin_layout, config::Config, harness::BindTest};
+
+async fn fires(config: &str, moves: &str) -> Vec<String> {
+    let config: Config = config.parse().unwrap();
+    let test = BindTest {
+        name: None,
+        moves: moves
+            .parse::<triplicata::algorithm::Algorithm>()
+            .unwrap()
+            .to_moves()
+            .unwrap(),
+        fires: Vec::new(),
+    };
+    test.run(config).await
+}
+
+#[test]
+fn builtin_layout_has_unique_chords() {
+    let layout = builtin_layout();
+    assert_eq!(layout.len(), 32);
+
+    for (i, chord) in layout.iter().enumerate() {
+        assert!(
+            layout[i + 1..]
+                .iter()
+                .all(|other| other.trigger != chord.trigger)
+        );
+        assert!(layout[i + 1..].iter().all(|other| other.key != chord.key));
+    }
+}
+
+#[tokio::test]
+async fn types_with_chords() {
+    let fired = fires(
+        "(timeout: 500, binds: [], text_entry: Some(()))",
+        "R U R' D B U'",
+    )
+    .await;
+
+    assert_eq!(
+        fired,
+        [
+            "Type Unicode('a')",
+            "Type Unicode('g')",
+            "Type Unicode('z')"
+        ]
+    );
+}
+
+#[tokio::test]
+async fn toggles_typing() {
+    let config = r#"(timeout: 500, binds: [], text_entry: Some((toggle: "D")))"#;
+
+    assert!(fires(config, "R U").await.is_empty());
+    assert_eq!(
+        fires(config, "D R U D R U").await,
+        ["Toggle text", "Type Unicode('a')", "Toggle text"]
+    );
+}
+
+#[tokio::test]
+async fn uses_a_custom_layout() {
+    let config = r#"(
+        timeout: 500,
+        binds: [],
+        text_entry: Some((layout: [(trigger: "R R", key: x), (trigger: "R U", key: enter)])),
+    )"#;
+
+    assert_eq!(
+        fires(config, "R U R R").await,
+        ["Type Return", "Type Unicode('x')"]
+    );
+}
@@ -0,0 +1,79 @@
+use triplicata::{error::ProtocolError, protocol::cipher::GANCubeVersion2Cipher};
+
+const SALT: [u8; 6] = [0xab, 0x12, 0xcd, 0x34, 0xef, 0x56];
+
+const KEY: [u8; 16] = [
+    0xac, 0x14, 0x10, 0x5c, 0x21, 0xe7, 0x16, 0x07, 0x20, 0x05, 0x18, 0x54, 0x42, 0x11, 0x12, 0x53,
+];
+const IV: [u8; 16] = [
+    0xbc, 0x15, 0x00, 0x5c, 0x11, 0x57, 0x76, 0x27, 0x20, 0x95, 0x78, 0x14, 0x32, 0x12, 0x02, 0x43,
+];
+
+const PLAIN: [u8; 20] = [
+    0x00, 0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x07, 0x08, 0x09, 0x0a, 0x0b, 0x0c, 0x0d, 0x0e, 0x0f,
+    0x10, 0x11, 0x12, 0x13,
+];
+const CIPHER: [u8; 20] = [
+    0x17, 0x65, 0x74, 0x0a, 0x24, 0xce, 0xe1, 0x8b, 0xbf, 0x55, 0x09, 0x02, 0x73, 0x9e, 0x77, 0x47,
+    0x96, 0x84, 0x56, 0x5b,
+];
+
+#[test]
+fn encrypts_known_packet() {
+    let cipher = GANCubeVersion2Cipher::new(KEY, IV);
+
+    assert_eq!(cipher.encrypt(&PLAIN).unwrap(), CIPHER);
+}
+
+#[test]
+fn decrypts_known_packet() {
+    let cipher = GANCubeVersion2Cipher::new(KEY, IV);
+
+    assert_eq!(cipher.decrypt(&CIPHER).unwrap(), PLAIN);
+}
+
+#[test]
+fn derives_key_from_salt() {
+    let cipher = GANCubeVersion2Cipher::from_salt(SALT);
+
+    assert_eq!(cipher.encrypt(&PLAIN).unwrap(), CIPHER);
+}
+
+#[test]
+fn round_trips_longer_packets() {
+    let cipher = GANCubeVersion2Cipher::from_salt(SALT);
+    let packet: Vec<u8> = (0..31).collect();
+
+    let encrypted = cipher.encrypt(&packet).unwrap();
+
+    assert_ne!(encrypted, packet);
+    assert_eq!(cipher.decrypt(&encrypted).unwrap(), packet);
+}
+
+#[test]
+fn rejects_short_packets() {
+    let cipher = GANCubeVersion2Cipher::from_salt(SALT);
+
+    assert!(matches!(
+        cipher.encrypt(&[0; 16]),
+        Err(ProtocolError::PacketTooShort(16))
+    ));
+    assert!(matches!(
+        cipher.decrypt(&[0; 4]),
+        Err(ProtocolError::PacketTooShort(4))
+    ));
+}
+
+#[test]
+fn extracts_salt_from_manufacturer_data() {
+    let data = [0x00, 0x01, 0x02, 0xab, 0x12, 0xcd, 0x34, 0xef, 0x56];
+
+    assert_eq!(
+        GANCubeVersion2Cipher::salt_from_manufacturer_data(&data).unwrap(),
+        SALT
+    );
+    assert!(matches!(
+        GANCubeVersion2Cipher::salt_from_manufacturer_data(&data[..8]),
+        Err(ProtocolError::InvalidDeviceIdentifier)
+    ));
+}
@@ -0,0 +1,32 @@
+//! The state machine harness shared by the tests of what binds play.
+
+use futures::Stream;
+use tokio::sync::mpsc;
+use tokio_util::sync::CancellationToken;
+use triplicata::{config::Config, cube::CubeEvent, state_machine::StateMachine};
+
+/// The actions the state machine sends for `events` with `config`, as
+/// displayed, once the events end. `build` sets up the machine beyond its
+/// config.
+pub async fn played<S, E>(
+    config: &str,
+    events: S,
+    build: impl FnOnce(StateMachine<S>) -> StateMachine<S>,
+) -> Vec<String>
+where
+    S: Stream<Item = E> + Unpin,
+    E: Into<CubeEvent>,
+{
+    let config: Config = config.parse().unwrap();
+    let (tx, mut rx) = mpsc::unbounded_channel();
+
+    build(StateMachine::new(events, config))
+        .run(tx, CancellationToken::new())
+        .await;
+
+    let mut actions = Vec::new();
+    while let Ok(action) = rx.try_recv() {
+        actions.push(action.to_string());
+    }
+    actions
+}
@@ -0,0 +1,198 @@
+#![cfg(feature = "config")]
+
+use std::{fs, path::PathBuf};
+
+use triplicata::{
+    config::{Bind, Config},
+    cube::Move,
+    error::ConfigError,
+};
+
+/// The only bind of a config triggered by `R U R' U'` with `tolerance`.
+fn sexy_move(tolerance: usize) -> Bind {
+    let config: Config = format!(
+        r#"(timeout: 500, binds: [(trigger: "R U R' U'", actions: [], tolerance: {tolerance})])"#
+    )
+    .parse()
+    .unwrap();
+    config.binds[0].clone()
+}
+
+#[test]
+fn matches_the_exact_trigger() {
+    let bind = sexy_move(0);
+
+    assert_eq!(
+        bind.matches(&[Move::R, Move::U, Move::Rp, Move::Up]),
+        Some(0)
+    );
+    assert_eq!(bind.matches(&[Move::R, Move::U, Move::Rp, Move::U]), None);
+    assert!(bind.starts_with(&[Move::R, Move::U]));
+    assert!(!bind.starts_with(&[Move::U]));
+}
+
+#[test]
+fn matches_within_the_tolerance() {
+    let bind = sexy_move(1);
+
+    // A turn the wrong way.
+    assert_eq!(
+        bind.matches(&[Move::R, Move::U, Move::R, Move::Up]),
+        Some(1)
+    );
+    // A stray move between two moves of the trigger.
+    assert_eq!(
+        bind.matches(&[Move::R, Move::U, Move::F, Move::Rp, Move::Up]),
+        Some(1)
+    );
+    assert_eq!(
+        bind.matches(&[Move::R, Move::U, Move::Rp, Move::Up]),
+        Some(0)
+    );
+}
+
+#[test]
+fn misses_just_beyond_the_tolerance() {
+    let bind = sexy_move(1);
+
+    assert_eq!(bind.matches(&[Move::R, Move::Up, Move::R, Move::Up]), None);
+    assert_eq!(
+        bind.matches(&[Move::R, Move::U, Move::F, Move::Rp, Move::U]),
+        None
+    );
+    // Stray moves are only allowed within the trigger.
+    assert_eq!(
+        bind.matches(&[Move::F, Move::R, Move::U, Move::Rp, Move::Up]),
+        None
+    );
+}
+
+/// A config file unique to the test holding `contents`.
+fn config_file(name: &str, contents: &str) -> PathBuf {
+    let path = std::env::temp_dir().join(format!("triplicata-{}-{name}.ron", std::process::id()));
+    fs::write(&path, contents).unwrap();
+    path
+}
+
+#[test]
+fn rejects_unknown_fields_when_strict() {
+    let path = config_file(
+        "strict",
+        r#"(timeout: 500, binds: [(trigger: "R U", actions: [], tolerence: 1)])"#,
+    );
+
+    let error = Config::load_strict(&path).unwrap_err();
+    assert!(matches!(
+        error,
+        ConfigError::UnknownField {
+            ref field,
+            suggestion: Some("tolerance"),
+            ..
+        } if field == "tolerence"
+    ));
+    assert!(error.to_string().contains("did you mean `tolerance`?"));
+
+    let config = Config::load(&path).unwrap();
+    assert_eq!(config.binds[0].tolerance, 0);
+
+    fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn accepts_known_fields_when_strict() {
+    let path = config_file(
+        "lenient",
+        r#"(timeout: 500, binds: [(trigger: "R U", actions: [], tolerance: 1)])"#,
+    );
+
+    let config = Config::load_strict(&path).unwrap();
+    assert_eq!(config.binds[0].tolerance, 1);
+
+    fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn mirrors_triggers_left_to_right() {
+    let mut config: Config = r#"(timeout: 500, binds: [
+        (trigger: "R U R'", actions: [Run("right")], mirror: true),
+        (trigger: "F U F'", actions: [Run("front")], mirror: true),
+        (trigger: "F' U' F", actions: [Run("taken")]),
+    ])"#
+    .parse()
+    .unwrap();
+
+    let mirrored = config.binds[0].mirrored();
+    assert_eq!(mirrored.trigger, [Move::Lp, Move::Up, Move::L]);
+    assert!(!mirrored.mirror);
+
+    config.add_mirrored_binds();
+    let triggers: Vec<_> = config.binds.iter().map(|bind| bind.label()).collect();
+    // The mirror of `F U F'` is already bound, so it is not added again.
+    assert_eq!(triggers, ["R U R'", "F U F'", "F' U' F", "L' U' L"]);
+}
+
+/// The actions of a config's only bind, as displayed.
+fn actions(actions: &str) -> Vec<String> {
+    let config: Config =
+        format!(r#"(timeout: 500, binds: [(trigger: "R", actions: [{actions}])])"#)
+            .parse()
+            .unwrap();
+    config.binds[0]
+        .actions
+        .iter()
+        .map(|action| action.to_string())
+        .collect()
+}
+
+#[test]
+fn reads_keys_by_alias_character_or_name() {
+    assert_eq!(
+        actions("Click(esc), Click(ESC), Click(a), Click(Unicode('x')), Press(F5)"),
+        [
+            "Escape",
+            "Escape",
+            "Unicode('a')",
+            "Unicode('x')",
+            "press F5"
+        ]
+    );
+}
+
+#[test]
+#[cfg(not(windows))]
+fn reads_a_capital_letter_as_the_letter_key() {
+    assert_eq!(actions("Click(A)"), ["Unicode('a')"]);
+}
+
+#[test]
+fn rejects_unknown_and_quoted_keys() {
+    for actions in ["Click(escap)", r#"Click("a")"#] {
+        let config = format!(r#"(timeout: 500, binds: [(trigger: "R", actions: [{actions}])])"#);
+        assert!(config.parse::<Config>().is_err(), "{actions} parsed");
+    }
+}
+
+#[test]
+fn lists_binds_as_a_table() {
+    let config: Config = r#"(timeout: 500, binds: [
+        (
+            name: Some("Save"),
+            description: Some("Saves the file"),
+            trigger: "R U",
+            actions: [Run("save")],
+            groups: ["editing"],
+        ),
+        (trigger: "F U F'", actions: [Click(esc), Delay(50)], mirror: true),
+    ])"#
+    .parse()
+    .unwrap();
+
+    assert_eq!(
+        config.bind_table().lines().collect::<Vec<_>>(),
+        [
+            "name  trigger           actions            groups   description",
+            r#"Save  R U               run "save"         editing  Saves the file"#,
+            "      F U F' | F' U' F  Escape, wait 50ms",
+        ]
+    );
+}
@@ -0,0 +1,122 @@
+#![cfg(all(feature = "control", unix))]
+
+use std::time::Instant;
+
+use serde_json::{Value, json};
+use triplicata::{
+    control::{Control, Reloader, event_notification},
+    cube::{CubeEvent, Move},
+    metrics::{self, Channel},
+    status::CubeStatus,
+};
+
+fn call(control: &Control, request: Value) -> Value {
+    let (reply, _) = control.call(&request.to_string());
+    serde_json::from_str(&reply.unwrap()).unwrap()
+}
+
+#[test]
+fn answers_version_and_status() {
+    let status = CubeStatus::new();
+    status.push(&CubeEvent::Connected);
+    status.push(&CubeEvent::Battery(80));
+    let control = Control::new().status(Some(status));
+
+    let reply = call(
+        &control,
+        json!({"jsonrpc": "2.0", "id": 1, "method": "version"}),
+    );
+    assert_eq!(reply["id"], 1);
+    assert_eq!(reply["result"]["api"], 1);
+
+    let reply = call(
+        &control,
+        json!({"jsonrpc": "2.0", "id": "a", "method": "status"}),
+    );
+    assert_eq!(
+        reply,
+        json!({"jsonrpc": "2.0", "id": "a", "result": {"connected": true, "battery": 80}})
+    );
+}
+
+#[test]
+fn reports_errors_with_codes() {
+    let control = Control::new();
+
+    let reply: Value = serde_json::from_str(&control.call("{").0.unwrap()).unwrap();
+    assert_eq!(reply["error"]["code"], -32700);
+    assert_eq!(reply["id"], Value::Null);
+
+    let reply = call(
+        &control,
+        json!({"jsonrpc": "2.0", "id": 1, "method": "dance"}),
+    );
+    assert_eq!(reply["error"]["code"], -32601);
+
+    let reply = call(
+        &control,
+        json!({"jsonrpc": "2.0", "id": 2, "method": "status"}),
+    );
+    assert_eq!(reply["error"]["code"], -32000);
+
+    let reply = call(
+        &control,
+        json!({"jsonrpc": "1.0", "id": 3, "method": "version"}),
+    );
+    assert_eq!(reply["error"]["code"], -32600);
+}
+
+#[tokio::test]
+async fn reload_is_kept_until_waited_on() {
+    let reloader = Reloader::new();
+    let control = Control::new().reloader(Some(reloader.clone()));
+
+    // Notifications are not answered.
+    let (reply, _) = control.call(r#"{"jsonrpc": "2.0", "method": "reload"}"#);
+    assert_eq!(reply, None);
+
+    reloader.requested().await;
+}
+
+#[test]
+fn events_are_tagged_by_type() {
+    let notification: Value =
+        serde_json::from_str(&event_notification(&CubeEvent::Move(Move::Rp)).unwrap()).unwrap();
+    assert_eq!(
+        notification,
+        json!({"jsonrpc": "2.0", "method": "event", "params": {"type": "move", "move": "R'"}})
+    );
+
+    let notification: Value =
+        serde_json::from_str(&event_notification(&CubeEvent::Battery(50)).unwrap()).unwrap();
+    assert_eq!(
+        notification["params"],
+        json!({"type": "battery", "level": 50})
+    );
+    // Only the pipeline follows when moves were received.
+    assert!(event_notification(&CubeEvent::Received(Instant::now())).is_none());
+}
+
+#[test]
+fn answers_channel_counts() {
+    for _ in 0..3 {
+        metrics::channel_sent(Channel::PlayedActions);
+    }
+    metrics::channel_drops(Channel::PlayedActions, 2);
+    let control = Control::new();
+
+    let reply = call(
+        &control,
+        json!({"jsonrpc": "2.0", "id": 1, "method": "channels"}),
+    );
+    assert_eq!(
+        reply["result"]["played_actions"],
+        json!({"sent": 3, "dropped": 2, "lags": 1})
+    );
+    assert!(
+        control
+            .handle("channels")
+            .unwrap()
+            .contains("played_actions: 3 sent, 2 dropped in 1 lag")
+    );
+}
@@ -0,0 +1,49 @@
+use triplicata::cube::{Face, Move};
+
+#[test]
+fn builds_every_move_from_its_face_and_direction() {
+    for m in Move::ALL {
+        assert_eq!(Move::new(m.face(), m.direction()), m);
+    }
+}
+
+#[test]
+fn inverts_every_move() {
+    for m in Move::ALL {
+        assert_ne!(m.inverse(), m);
+        assert_eq!(m.inverse().face(), m.face());
+        assert_eq!(m.inverse().inverse(), m);
+    }
+}
+
+#[test]
+fn round_trips_notation() {
+    for m in Move::ALL {
+        assert_eq!(m.to_string().parse::<Move>().unwrap(), m);
+    }
+    assert_eq!("R’".parse::<Move>().unwrap(), Move::Rp);
+    assert!("R2".parse::<Move>().is_err());
+}
+
+#[test]
+fn mirrors_left_to_right() {
+    assert_eq!(Move::R.mirror(), Move::Lp);
+    assert_eq!(Move::Lp.mirror(), Move::R);
+    assert_eq!(Move::U.mirror(), Move::Up);
+    assert_eq!(Move::Fp.mirror(), Move::F);
+
+    for m in Move::ALL {
+        assert_eq!(m.mirror().mirror(), m);
+    }
+}
+
+#[test]
+fn pairs_opposite_faces() {
+    for face in Face::ALL {
+        assert_ne!(face.opposite(), face);
+        assert_eq!(face.opposite().opposite(), face);
+    }
+    assert_eq!(Face::U.opposite(), Face::D);
+    assert_eq!(Face::R.opposite(), Face::L);
+    assert_eq!(Face::F.opposite(), Face::B);
+}
@@ -0,0 +1,179 @@
+#![cfg(feature = "database")]
+
+use std::time::{Duration, UNIX_EPOCH};
+
+use triplicata::{
+    cube::{CubeState, Move},
+    database::{Penalty, SolveDatabase, SolveQuery, Sort},
+    phase::{Phase, PhaseEnd},
+    solve::{Solve, TimedMove},
+};
+
+/// A solve undoing `R U`, started `started` seconds after the epoch and
+/// taking `millis` milliseconds.
+fn solve(started: u64, millis: u64) -> Solve {
+    let mut scramble = CubeState::SOLVED;
+    scramble.apply(Move::R);
+    scramble.apply(Move::U);
+
+    Solve {
+        started: UNIX_EPOCH + Duration::from_secs(started),
+        scramble,
+        moves: vec![
+            TimedMove {
+                m: Move::Up,
+                offset: Duration::ZERO,
+            },
+            TimedMove {
+                m: Move::Rp,
+                offset: Duration::from_millis(millis),
+            },
+        ],
+        duration: Duration::from_millis(millis),
+    }
+}
+
+#[test]
+fn reads_back_a_solve_with_its_moves_and_splits() {
+    let mut database = SolveDatabase::in_memory().unwrap();
+    let session = database.start_session(UNIX_EPOCH).unwrap();
+    let phases = [
+        Phase {
+            name: "first".to_string(),
+            end: PhaseEnd::Moves(1),
+        },
+        Phase {
+            name: "last".to_string(),
+            end: PhaseEnd::Moves(1),
+        },
+    ];
+    let solve = solve(10, 1500);
+    let id = database.insert(session, &solve, &phases).unwrap();
+
+    let stored = database.solves(&SolveQuery::new()).unwrap();
+    assert_eq!(stored.len(), 1);
+    assert_eq!(stored[0].id, id);
+    assert_eq!(stored[0].session, session);
+    assert_eq!(stored[0].started, solve.started);
+    assert_eq!(stored[0].duration, solve.duration);
+    assert_eq!(stored[0].moves, solve.moves);
+    assert_eq!(stored[0].splits, solve.splits(&phases));
+    assert_eq!(stored[0].scramble.len(), 54);
+    assert_ne!(&stored[0].scramble[..9], "UUUUUUUUU");
+}
+
+#[test]
+fn queries_by_session_time_and_count() {
+    let mut database = SolveDatabase::in_memory().unwrap();
+    let first = database.start_session(UNIX_EPOCH).unwrap();
+    let second = database
+        .start_session(UNIX_EPOCH + Duration::from_secs(100))
+        .unwrap();
+    for (session, started, millis) in [
+        (first, 10, 3000),
+        (first, 20, 2000),
+        (second, 110, 2500),
+        (second, 120, 4000),
+    ] {
+        database
+            .insert(session, &solve(started, millis), &[])
+            .unwrap();
+    }
+
+    assert_eq!(database.sessions().unwrap().len(), 2);
+
+    let durations = |query: SolveQuery| -> Vec<u128> {
+        database
+            .solves(&query)
+            .unwrap()
+            .iter()
+            .map(|solve| solve.duration.as_millis())
+            .collect()
+    };
+    assert_eq!(durations(SolveQuery::new()), [4000, 2500, 2000, 3000]);
+    assert_eq!(durations(SolveQuery::new().session(first)), [2000, 3000]);
+    assert_eq!(
+        durations(SolveQuery::new().since(UNIX_EPOCH + Duration::from_secs(20))),
+        [4000, 2500, 2000]
+    );
+    assert_eq!(durations(SolveQuery::new().limit(1)), [4000]);
+
+    let best = database.best(&SolveQuery::new().session(second)).unwrap();
+    assert_eq!(best.map(|solve| solve.duration.as_millis()), Some(2500));
+}
+
+#[test]
+fn penalties_count_in_sorting_and_the_best() {
+    let mut database = SolveDatabase::in_memory().unwrap();
+    let session = database.start_session(UNIX_EPOCH).unwrap();
+    let fast = database.insert(session, &solve(10, 1000), &[]).unwrap();
+    let slow = database.insert(session, &solve(20, 2500), &[]).unwrap();
+    let plus_two = database.insert(session, &solve(30, 1500), &[]).unwrap();
+
+    assert!(database.penalize(fast, Some(Penalty::Dnf)).unwrap());
+    assert!(database.penalize(plus_two, Some(Penalty::PlusTwo)).unwrap());
+    assert!(!database.penalize(100, Some(Penalty::Dnf)).unwrap());
+
+    let ids = |sort| -> Vec<i64> {
+        database
+            .solves(&SolveQuery::new().sort(sort))
+            .unwrap()
+            .iter()
+            .map(|solve| solve.id)
+            .collect()
+    };
+    assert_eq!(ids(Sort::Fastest), [slow, plus_two, fast]);
+    assert_eq!(ids(Sort::Slowest), [fast, plus_two, slow]);
+
+    let best = database.best(&SolveQuery::new()).unwrap().unwrap();
+    assert_eq!(best.id, slow);
+
+    database.penalize(fast, None).unwrap();
+    let best = database.best(&SolveQuery::new()).unwrap().unwrap();
+    assert_eq!(best.id, fast);
+    assert_eq!(best.time(), Some(Duration::from_millis(1000)));
+    assert_eq!(
+        database.solve(plus_two).unwrap().unwrap().time(),
+        Some(Duration::from_millis(3500))
+    );
+}
+
+#[test]
+fn deletes_a_solve() {
+    let mut database = SolveDatabase::in_memory().unwrap();
+    let session = database.start_session(UNIX_EPOCH).unwrap();
+    let id = database.insert(session, &solve(10, 1000), &[]).unwrap();
+
+    assert!(database.delete(id).unwrap());
+    assert!(!database.delete(id).unwrap());
+    assert_eq!(database.solve(id).unwrap(), None);
+    assert!(database.solves(&SolveQuery::new()).unwrap().is_empty());
+}
+
+#[test]
+fn reconstructs_each_phase() {
+    let mut database = SolveDatabase::in_memory().unwrap();
+    let session = database.start_session(UNIX_EPOCH).unwrap();
+    let phases = [
+        Phase {
+            name: "first".to_string(),
+            end: PhaseEnd::Moves(1),
+        },
+        Phase {
+            name: "last".to_string(),
+            end: PhaseEnd::Moves(1),
+        },
+    ];
+    let id = database.insert(session, &solve(10, 1000), &phases).unwrap();
+    let stored = database.solve(id).unwrap().unwrap();
+
+    let reconstruction: Vec<_> = stored
+        .reconstruction()
+        .into_iter()
+        .map(|(name, moves)| (name, moves.iter().map(|m| m.m).collect::<Vec<_>>()))
+        .collect();
+    assert_eq!(
+        reconstruction,
+        [("first", vec![Move::Up]), ("last", vec![Move::Rp])]
+    );
+}
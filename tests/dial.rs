@@ -0,0 +1,54 @@
+#![cfg(feature = "config")]
+
+use triplicata::{
+    cube::{Direction, Face},
+    dial::{Dial, DialTarget},
+};
+
+fn step(dial: &Dial, direction: Direction) -> Vec<String> {
+    dial.step(direction)
+        .iter()
+        .map(ToString::to_string)
+        .collect()
+}
+
+#[test]
+fn scrolls_by_the_sensitivity() {
+    let dial = Dial {
+        sensitivity: 3,
+        ..Dial::default()
+    };
+
+    assert_eq!(step(&dial, Direction::Clockwise), ["scroll 3"]);
+    assert_eq!(step(&dial, Direction::CounterClockwise), ["scroll -3"]);
+}
+
+#[test]
+fn steps_the_volume() {
+    let dial = Dial {
+        target: DialTarget::Volume,
+        sensitivity: 2,
+        ..Dial::default()
+    };
+
+    assert_eq!(step(&dial, Direction::Clockwise), ["VolumeUp", "VolumeUp"]);
+    assert_eq!(
+        step(&dial, Direction::CounterClockwise),
+        ["VolumeDown", "VolumeDown"]
+    );
+}
+
+#[test]
+fn binds_both_directions_of_the_face() {
+    let dial = Dial {
+        face: Face::R,
+        target: DialTarget::Zoom,
+        ..Dial::default()
+    };
+
+    let binds = dial.binds();
+    assert_eq!(binds.len(), 2);
+    assert!(binds.iter().all(|bind| bind.groups == ["dial"]));
+    assert_eq!(binds[0].label(), "Dial R");
+    assert_eq!(binds[1].label(), "Dial R'");
+}
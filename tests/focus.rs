@@ -0,0 +1,112 @@
+#![cfg(feature = "focus")]
+
+use std::{collections::HashSet, time::Duration};
+
+use tokio::time::Instant;
+use triplicata::focus::{Focus, Profile, ProfileSwitcher, Window};
+
+const SETTLE: Duration = Duration::from_millis(750);
+
+fn focus() -> Focus {
+    ron::from_str(
+        r#"(
+            profiles: [
+                (name: "Code", windows: ["code", "kitty"], groups: ["editor"]),
+                (name: "Game", windows: ["Minecraft"], groups: ["game", "media"]),
+                (name: "Desktop", windows: [""], groups: ["media"]),
+            ],
+        )"#,
+    )
+    .unwrap()
+}
+
+fn window(class: &str, title: &str) -> Window {
+    Window {
+        title: title.to_string(),
+        class: class.to_string(),
+    }
+}
+
+#[test]
+fn matches_title_or_class_ignoring_case() {
+    let focus = focus();
+
+    assert_eq!(focus.profile_of(&window("Code", "main.rs")), Some(0));
+    assert_eq!(focus.profile_of(&window("kitty", "~")), Some(0));
+    assert_eq!(focus.profile_of(&window("java", "minecraft 1.21")), Some(1));
+    assert_eq!(focus.profile_of(&window("firefox", "News")), Some(2));
+    assert_eq!(focus.settle, 750);
+}
+
+#[test]
+fn matches_nothing_without_patterns() {
+    let profile = Profile {
+        name: "Nothing".to_string(),
+        windows: Vec::new(),
+        groups: Vec::new(),
+    };
+
+    assert!(!profile.matches(&window("firefox", "News")));
+}
+
+#[test]
+fn switching_disables_other_profiles() {
+    let focus = focus();
+    let mut disabled = HashSet::from(["unrelated".to_string()]);
+
+    focus.switch(1, &mut disabled);
+    assert_eq!(
+        disabled,
+        HashSet::from(["unrelated".to_string(), "editor".to_string()])
+    );
+
+    focus.switch(0, &mut disabled);
+    assert_eq!(
+        disabled,
+        HashSet::from([
+            "unrelated".to_string(),
+            "game".to_string(),
+            "media".to_string()
+        ])
+    );
+}
+
+#[test]
+fn first_profile_switches_straight_away() {
+    let mut switcher = ProfileSwitcher::new(SETTLE);
+
+    assert_eq!(switcher.observe(None, Instant::now()), None);
+    assert_eq!(switcher.observe(Some(1), Instant::now()), Some(1));
+    assert_eq!(switcher.current(), Some(1));
+}
+
+#[test]
+fn switches_once_settled() {
+    let start = Instant::now();
+    let ms = |ms| start + Duration::from_millis(ms);
+    let mut switcher = ProfileSwitcher::new(SETTLE);
+
+    assert_eq!(switcher.observe(Some(0), ms(0)), Some(0));
+    assert_eq!(switcher.observe(Some(1), ms(250)), None);
+    assert_eq!(switcher.observe(Some(1), ms(750)), None);
+    assert_eq!(switcher.observe(Some(1), ms(1000)), Some(1));
+    assert_eq!(switcher.observe(Some(1), ms(1250)), None);
+    assert_eq!(switcher.current(), Some(1));
+}
+
+#[test]
+fn alt_tabbing_does_not_switch() {
+    let start = Instant::now();
+    let ms = |ms| start + Duration::from_millis(ms);
+    let mut switcher = ProfileSwitcher::new(SETTLE);
+
+    assert_eq!(switcher.observe(Some(0), ms(0)), Some(0));
+    assert_eq!(switcher.observe(Some(1), ms(250)), None);
+    assert_eq!(switcher.observe(Some(2), ms(500)), None);
+    assert_eq!(switcher.observe(Some(1), ms(750)), None);
+    // Back to the current profile before anything settled.
+    assert_eq!(switcher.observe(Some(0), ms(1000)), None);
+    assert_eq!(switcher.observe(Some(1), ms(1250)), None);
+    assert_eq!(switcher.observe(Some(1), ms(1750)), None);
+    assert_eq!(switcher.current(), Some(0));
+}
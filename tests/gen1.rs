@@ -0,0 +1,124 @@
+use triplicata::{
+    cube::{CubeEvent, Move},
+    error::ProtocolError,
+    protocol::{cipher::GANCubeVersion1Cipher, gen1::Decoder},
+};
+
+const HARDWARE: [u8; 6] = [0x11, 0x22, 0x33, 0x44, 0x55, 0x66];
+
+/// A last moves value with the counter at `count` and `moves` the last ones
+/// made, newest last.
+fn last_moves(count: u8, moves: [u8; 6]) -> Vec<u8> {
+    let mut value = vec![0; 12];
+    value.push(count);
+    value.extend(moves);
+    value
+}
+
+#[test]
+fn decodes_moves_since_the_last_read() {
+    let mut decoder = Decoder::new(None);
+
+    // The first read only learns the counter.
+    assert!(
+        decoder
+            .decode_moves(&last_moves(7, [0; 6]))
+            .unwrap()
+            .is_empty()
+    );
+
+    // R, then U', then F2.
+    let events = decoder
+        .decode_moves(&last_moves(10, [0, 0, 0, 3, 2, 7]))
+        .unwrap();
+    assert_eq!(
+        events,
+        [
+            CubeEvent::Move(Move::R),
+            CubeEvent::Move(Move::Up),
+            CubeEvent::Move(Move::F),
+            CubeEvent::Move(Move::F),
+        ]
+    );
+    assert_eq!(decoder.move_count(), Some(10));
+}
+
+#[test]
+fn keeps_the_last_six_moves_across_the_counter_wrapping() {
+    let mut decoder = Decoder::new(None);
+    decoder.decode_moves(&last_moves(250, [0; 6])).unwrap();
+
+    let events = decoder
+        .decode_moves(&last_moves(4, [3, 8, 9, 11, 14, 17]))
+        .unwrap();
+    assert_eq!(
+        events,
+        [
+            CubeEvent::Move(Move::R),
+            CubeEvent::Move(Move::Fp),
+            CubeEvent::Move(Move::D),
+            CubeEvent::Move(Move::Dp),
+            CubeEvent::Move(Move::Lp),
+            CubeEvent::Move(Move::Bp),
+        ]
+    );
+}
+
+#[test]
+fn rejects_short_values() {
+    let mut decoder = Decoder::new(None);
+
+    assert!(matches!(
+        decoder.decode_moves(&[0; 13]),
+        Err(ProtocolError::PacketTooShort(13))
+    ));
+    assert!(matches!(
+        decoder.decode_battery(&[0; 4]),
+        Err(ProtocolError::PacketTooShort(4))
+    ));
+}
+
+#[test]
+fn only_later_firmware_encrypts() {
+    for version in [[1, 0, 7], [2, 0, 8], [1, 2, 0]] {
+        assert!(
+            GANCubeVersion1Cipher::for_firmware(&version, &HARDWARE)
+                .unwrap()
+                .is_none()
+        );
+    }
+
+    assert!(
+        GANCubeVersion1Cipher::for_firmware(&[1, 0, 8], &HARDWARE)
+            .unwrap()
+            .is_some()
+    );
+    assert!(GANCubeVersion1Cipher::for_firmware(&[1, 1, 0], &HARDWARE[..3]).is_err());
+}
+
+#[test]
+fn decrypts_moves_and_battery() {
+    let cipher = GANCubeVersion1Cipher::for_firmware(&[1, 1, 0], &HARDWARE)
+        .unwrap()
+        .unwrap();
+    let mut decoder = Decoder::new(Some(cipher.clone()));
+
+    let first = cipher.encrypt(&last_moves(1, [0; 6])).unwrap();
+    let second = cipher.encrypt(&last_moves(2, [0, 0, 0, 0, 0, 15])).unwrap();
+    assert_ne!(second, last_moves(2, [0, 0, 0, 0, 0, 15]));
+
+    decoder.decode_moves(&first).unwrap();
+    assert_eq!(
+        decoder.decode_moves(&second).unwrap(),
+        [CubeEvent::Move(Move::B)]
+    );
+
+    let mut battery = vec![0; 16];
+    battery[7] = 64;
+    assert_eq!(
+        decoder
+            .decode_battery(&cipher.encrypt(&battery).unwrap())
+            .unwrap(),
+        64
+    );
+}
@@ -0,0 +1,83 @@
+#![cfg(feature = "config")]
+
+use std::time::Duration;
+
+use triplicata::{
+    config::Config,
+    cube::Move,
+    gesture::{Demonstration, MIN_DEMONSTRATIONS, already_bound, learn},
+};
+
+fn demonstration(moves: &[Move], millis: u64) -> Demonstration {
+    Demonstration {
+        moves: moves.to_vec(),
+        duration: Duration::from_millis(millis),
+    }
+}
+
+#[test]
+fn learns_what_the_demonstrations_have_in_common() {
+    let bind = learn(&[
+        demonstration(&[Move::R, Move::U, Move::Rp, Move::Up], 400),
+        demonstration(&[Move::R, Move::U, Move::F, Move::Rp, Move::Up], 600),
+        demonstration(&[Move::R, Move::U, Move::Rp, Move::Up], 500),
+    ])
+    .unwrap();
+
+    assert_eq!(bind.trigger, [Move::R, Move::U, Move::Rp, Move::Up]);
+    // The stray F is the only mistake made.
+    assert_eq!(bind.tolerance, 1);
+    assert_eq!(bind.within, Some(900));
+    assert!(bind.actions.is_empty());
+}
+
+#[test]
+fn needs_the_minimum_number_of_demonstrations() {
+    let sexy_move = demonstration(&[Move::R, Move::U, Move::Rp, Move::Up], 400);
+
+    assert!(learn(&[]).is_none());
+    assert!(learn(&vec![sexy_move.clone(); MIN_DEMONSTRATIONS - 1]).is_none());
+
+    let bind = learn(&vec![sexy_move; MIN_DEMONSTRATIONS]).unwrap();
+    assert_eq!(bind.trigger, [Move::R, Move::U, Move::Rp, Move::Up]);
+    assert_eq!(bind.tolerance, 0);
+}
+
+#[test]
+fn learns_nothing_without_moves_in_common() {
+    assert!(
+        learn(&[
+            demonstration(&[Move::R, Move::U], 200),
+            demonstration(&[Move::F, Move::D], 200),
+        ])
+        .is_none()
+    );
+}
+
+#[test]
+fn does_not_bind_a_gesture_twice() {
+    let config: Config = r#"(timeout: 500, binds: [
+        (trigger: "R U R' U'", actions: [], name: Some("sexy"), tolerance: 1),
+        (trigger: "F R U", actions: []),
+    ])"#
+    .parse()
+    .unwrap();
+
+    let learned = |moves: &[Move]| learn(&[demonstration(moves, 300), demonstration(moves, 300)]);
+
+    let same = learned(&[Move::R, Move::U, Move::Rp, Move::Up]).unwrap();
+    assert_eq!(
+        already_bound(&config.binds, &same).map(|bind| bind.label()),
+        Some("sexy".to_string())
+    );
+
+    // Within the existing bind's tolerance, so it would fire first.
+    let close = learned(&[Move::R, Move::U, Move::R, Move::Up]).unwrap();
+    assert_eq!(
+        already_bound(&config.binds, &close).map(|bind| bind.label()),
+        Some("sexy".to_string())
+    );
+
+    let new = learned(&[Move::L, Move::U, Move::Lp, Move::Up]).unwrap();
+    assert!(already_bound(&config.binds, &new).is_none());
+}
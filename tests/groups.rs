@@ -0,0 +1,50 @@
+#![cfg(all(feature = "runtime", feature = "config"))]
+
+mod common;
+
+use futures::stream;
+use triplicata::cube::{CubeEvent, Move};
+
+const CONFIG: &str = r#"(
+    timeout: 500,
+    binds: [
+        (trigger: "R U", actions: [Run("save")], groups: ["editing"]),
+        (trigger: "F", actions: [DisableGroup("editing")]),
+        (trigger: "B", actions: [EnableGroup("editing")]),
+        (trigger: "D", actions: [ToggleGroup("editing")]),
+    ],
+)"#;
+
+async fn played(moves: &[Move]) -> Vec<String> {
+    let events = stream::iter(moves.iter().map(|m| CubeEvent::Move(*m)));
+    common::played(CONFIG, events, |machine| machine).await
+}
+
+const SAVE: &[Move] = &[Move::R, Move::U];
+const DISABLE: &[Move] = &[Move::F];
+const ENABLE: &[Move] = &[Move::B];
+const TOGGLE: &[Move] = &[Move::D];
+
+#[tokio::test]
+async fn disabled_groups_turn_their_binds_off_until_enabled() {
+    assert_eq!(played(SAVE).await, [r#"run "save""#]);
+    assert!(played(&[DISABLE, SAVE].concat()).await.is_empty());
+    assert_eq!(
+        played(&[DISABLE, SAVE, ENABLE, SAVE].concat()).await,
+        [r#"run "save""#]
+    );
+}
+
+#[tokio::test]
+async fn toggles_groups() {
+    assert!(played(&[TOGGLE, SAVE].concat()).await.is_empty());
+    assert_eq!(
+        played(&[TOGGLE, SAVE, TOGGLE, SAVE].concat()).await,
+        [r#"run "save""#]
+    );
+    // Toggling a disabled group enables it.
+    assert_eq!(
+        played(&[DISABLE, TOGGLE, SAVE].concat()).await,
+        [r#"run "save""#]
+    );
+}
@@ -0,0 +1,93 @@
+#![cfg(feature = "history")]
+
+use std::time::UNIX_EPOCH;
+
+use futures::stream;
+use tokio_util::sync::CancellationToken;
+use triplicata::{
+    cube::{CubeEvent, Move},
+    history::MoveHistory,
+};
+
+fn moves(history: &MoveHistory) -> Vec<Move> {
+    history.moves().iter().map(|m| m.m).collect()
+}
+
+#[test]
+fn forgets_the_oldest_moves() {
+    let history = MoveHistory::new(3);
+    for m in [Move::R, Move::U, Move::Rp, Move::Up] {
+        history.push(m, UNIX_EPOCH);
+    }
+
+    assert_eq!(moves(&history), [Move::U, Move::Rp, Move::Up]);
+    assert_eq!(
+        history.recent(2).iter().map(|m| m.m).collect::<Vec<_>>(),
+        [Move::Rp, Move::Up]
+    );
+}
+
+#[test]
+fn keeps_nothing_without_capacity() {
+    let history = MoveHistory::new(0);
+    history.push(Move::R, UNIX_EPOCH);
+
+    assert!(history.moves().is_empty());
+}
+
+#[tokio::test]
+async fn records_only_moves() {
+    let history = MoveHistory::new(10);
+    let events = stream::iter([
+        CubeEvent::Connected,
+        CubeEvent::Move(Move::F),
+        CubeEvent::Battery(80),
+        CubeEvent::Move(Move::B),
+        CubeEvent::Disconnected,
+    ]);
+
+    history.record(events, CancellationToken::new()).await;
+
+    assert_eq!(moves(&history), [Move::F, Move::B]);
+}
+
+#[cfg(all(feature = "control", unix))]
+#[test]
+fn answers_history_queries() {
+    use std::time::Duration;
+
+    use triplicata::control::Control;
+
+    let history = MoveHistory::new(10);
+    history.push(Move::R, UNIX_EPOCH + Duration::from_millis(1500));
+    history.push(Move::Up, UNIX_EPOCH + Duration::from_millis(2250));
+    let control = Control::new().history(Some(history));
+
+    assert_eq!(control.handle("history").unwrap(), "1500:R 2250:U'");
+    assert_eq!(control.handle("history 1").unwrap(), "2250:U'");
+    assert!(control.handle("history many").is_err());
+    assert!(Control::new().handle("history").is_err());
+    assert_eq!(control.handle("since 2000").unwrap(), "2250:U'");
+    assert!(control.handle("since").is_err());
+}
+
+#[test]
+fn finds_moves_since_a_time() {
+    let at = |ms| UNIX_EPOCH + std::time::Duration::from_millis(ms);
+    let history = MoveHistory::new(3);
+    history.push(Move::R, at(1000));
+    history.push(Move::U, at(2000));
+    history.push(Move::F, at(3000));
+
+    let since = |ms| {
+        history
+            .since(at(ms))
+            .map(|moves| moves.iter().map(|m| m.m).collect::<Vec<_>>())
+    };
+    assert_eq!(since(1500), Some(vec![Move::U, Move::F]));
+    assert_eq!(since(3000), Some(vec![]));
+
+    history.push(Move::B, at(4000));
+    assert_eq!(since(500), None);
+    assert_eq!(since(1000), Some(vec![Move::U, Move::F, Move::B]));
+}
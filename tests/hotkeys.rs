@@ -0,0 +1,46 @@
+#![cfg(all(feature = "input", not(target_arch = "wasm32")))]
+
+use triplicata::{
+    config::Key,
+    hotkeys::{HotkeyAction, HotkeyWatcher, Hotkeys},
+    modifiers::{Modifier, Modifiers},
+};
+
+fn hotkeys() -> Hotkeys {
+    ron::from_str(
+        "(
+            pause: Some((held: [Control, Alt], key: p)),
+            quit: Some((key: F12)),
+        )",
+    )
+    .unwrap()
+}
+
+#[test]
+fn lists_the_hotkeys_set() {
+    let actions: Vec<_> = hotkeys().bound().map(|(action, _)| action).collect();
+    assert_eq!(actions, [HotkeyAction::Pause, HotkeyAction::Quit]);
+}
+
+#[test]
+fn presses_each_hotkey_once_until_let_go() {
+    let mut watcher = HotkeyWatcher::new(&hotkeys());
+    let modifiers = Modifiers::new();
+    let p = |key| key == Key::Unicode('p');
+
+    // The key alone is not the hotkey.
+    assert!(watcher.update(&modifiers, p).is_empty());
+
+    modifiers.set(Modifier::Control, true);
+    modifiers.set(Modifier::Alt, true);
+    assert_eq!(watcher.update(&modifiers, p), [HotkeyAction::Pause]);
+    assert!(watcher.update(&modifiers, p).is_empty());
+
+    assert!(watcher.update(&modifiers, |_| false).is_empty());
+    assert_eq!(watcher.update(&modifiers, p), [HotkeyAction::Pause]);
+
+    assert_eq!(
+        watcher.update(&modifiers, |key| key == Key::F12),
+        [HotkeyAction::Quit]
+    );
+}
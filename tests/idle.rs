@@ -0,0 +1,111 @@
+#![cfg(all(feature = "idle", feature = "simulator"))]
+
+use std::time::Duration;
+
+use tokio::{sync::broadcast::Receiver, time::Instant};
+use tokio_util::sync::CancellationToken;
+use triplicata::{
+    cube::{CubeEvent, Move},
+    idle::{IdleCubeSource, IdleWaker},
+    simulator::{Scenario, SimulatedCubeSource},
+    source::CubeSource,
+};
+
+const IDLE: Duration = Duration::from_secs(60);
+
+fn source(scenario: &str) -> IdleCubeSource<impl FnMut() -> SimulatedCubeSource + Send + 'static> {
+    let scenario: Scenario = scenario.parse().unwrap();
+    IdleCubeSource::new(move || SimulatedCubeSource::new(scenario.clone()), IDLE)
+}
+
+/// The next `count` events, with the time each arrived.
+async fn next(events: &mut Receiver<CubeEvent>, count: usize) -> Vec<(CubeEvent, Duration)> {
+    let start = Instant::now();
+    let mut received = Vec::new();
+    while received.len() < count {
+        received.push((events.recv().await.unwrap(), start.elapsed()));
+    }
+    received
+}
+
+#[tokio::test(start_paused = true)]
+async fn disconnects_when_idle_and_rescans() {
+    let cancel = CancellationToken::new();
+    let mut events = source("R")
+        .rescan(Some(Duration::from_secs(300)))
+        .connect(cancel.clone())
+        .await
+        .unwrap();
+
+    let ms = Duration::from_millis;
+    assert_eq!(
+        next(&mut events, 6).await,
+        [
+            (CubeEvent::Connected, ms(0)),
+            (CubeEvent::Move(Move::R), ms(100)),
+            (CubeEvent::Disconnected, ms(60_100)),
+            (CubeEvent::Connected, ms(360_100)),
+            (CubeEvent::Move(Move::R), ms(360_200)),
+            (CubeEvent::Disconnected, ms(420_200)),
+        ]
+    );
+
+    cancel.cancel();
+}
+
+#[tokio::test(start_paused = true)]
+async fn moves_keep_the_cube_connected() {
+    let cancel = CancellationToken::new();
+    let mut events = source("R\nwait 50s\nU\nwait 50s\nR'")
+        .connect(cancel.clone())
+        .await
+        .unwrap();
+
+    let received = next(&mut events, 5).await;
+    assert_eq!(received[4].0, CubeEvent::Disconnected);
+    assert_eq!(received[4].1, Duration::from_millis(100_300) + IDLE);
+
+    cancel.cancel();
+}
+
+#[tokio::test(start_paused = true)]
+async fn wakes_on_command() {
+    let cancel = CancellationToken::new();
+    let waker = IdleWaker::new();
+    let mut events = source("")
+        .waker(waker.clone())
+        .connect(cancel.clone())
+        .await
+        .unwrap();
+
+    assert_eq!(events.recv().await.unwrap(), CubeEvent::Connected);
+    assert_eq!(events.recv().await.unwrap(), CubeEvent::Disconnected);
+
+    waker.wake();
+    assert_eq!(events.recv().await.unwrap(), CubeEvent::Connected);
+
+    cancel.cancel();
+}
+
+#[tokio::test(start_paused = true)]
+async fn ends_when_the_cube_disconnects_by_itself() {
+    let mut events = source("R\ndisconnect")
+        .connect(CancellationToken::new())
+        .await
+        .unwrap();
+
+    let received: Vec<_> = next(&mut events, 3)
+        .await
+        .into_iter()
+        .map(|(e, _)| e)
+        .collect();
+    assert_eq!(
+        received,
+        [
+            CubeEvent::Connected,
+            CubeEvent::Move(Move::R),
+            CubeEvent::Disconnected
+        ]
+    );
+    assert!(events.recv().await.is_err());
+}
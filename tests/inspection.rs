@@ -0,0 +1,86 @@
+#![cfg(feature = "inspection")]
+
+use std::time::{Duration, Instant};
+
+use triplicata::{
+    cube::{CubeEvent, Move},
+    inspection::{Attempt, Judge, Penalty},
+    protocol::timer::{TimerEvent, TimerState},
+};
+
+const TIME: Duration = Duration::from_millis(9870);
+
+fn timer(state: TimerState, time: Option<Duration>) -> CubeEvent {
+    CubeEvent::Timer(TimerEvent { state, time })
+}
+
+/// The attempt judged when the solve starts `inspection` after the pads are
+/// first tapped, turning the cube during inspection if `turned`.
+fn attempt(inspection: Duration, turned: bool) -> Attempt {
+    let mut judge = Judge::new();
+    let start = Instant::now();
+
+    judge.push(&CubeEvent::Move(Move::R), start);
+    judge.push(&timer(TimerState::HandsOff, None), start);
+    if turned {
+        judge.push(&CubeEvent::Move(Move::U), start + inspection / 2);
+    }
+    judge.push(&timer(TimerState::Running, None), start + inspection);
+
+    judge
+        .push(
+            &timer(TimerState::Stopped, Some(TIME)),
+            start + inspection + TIME,
+        )
+        .unwrap()
+}
+
+#[test]
+fn penalizes_late_starts() {
+    for (inspection, penalty) in [
+        (Duration::from_secs(15), None),
+        (Duration::from_millis(15_010), Some(Penalty::PlusTwo)),
+        (Duration::from_secs(17), Some(Penalty::PlusTwo)),
+        (Duration::from_millis(17_010), Some(Penalty::Dnf)),
+    ] {
+        let attempt = attempt(inspection, false);
+        assert_eq!(attempt.inspection, Some(inspection));
+        assert_eq!(attempt.penalty, penalty, "inspected for {inspection:?}");
+    }
+}
+
+#[test]
+fn counts_two_seconds_for_a_plus_two() {
+    let attempt = attempt(Duration::from_secs(16), false);
+
+    assert_eq!(attempt.result(), Some(TIME + Duration::from_secs(2)));
+    assert_eq!(attempt.to_string(), "11.87+");
+}
+
+#[test]
+fn disqualifies_turning_during_inspection() {
+    let attempt = attempt(Duration::from_secs(5), true);
+
+    assert_eq!(attempt.penalty, Some(Penalty::Dnf));
+    assert_eq!(attempt.result(), None);
+    assert_eq!(attempt.to_string(), "DNF(9.87)");
+}
+
+#[test]
+fn does_not_inspect_a_solved_cube() {
+    let mut judge = Judge::new();
+    let start = Instant::now();
+
+    judge.push(&timer(TimerState::HandsOff, None), start);
+    assert_eq!(judge.inspecting_since(), None);
+    judge.push(
+        &timer(TimerState::Running, None),
+        start + Duration::from_secs(30),
+    );
+
+    let attempt = judge
+        .push(&timer(TimerState::Stopped, Some(TIME)), start)
+        .unwrap();
+    assert_eq!(attempt.inspection, None);
+    assert_eq!(attempt.penalty, None);
+}
@@ -0,0 +1,67 @@
+#![cfg(feature = "std")]
+
+use std::{
+    fs,
+    path::PathBuf,
+    time::{Duration, UNIX_EPOCH},
+};
+
+use triplicata::log_file::{RotatingFile, Rotation};
+
+fn log_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("triplicata-{}-{name}", std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    dir
+}
+
+#[test]
+fn rotates_once_too_large() {
+    let dir = log_dir("size");
+    let path = dir.join("triplicata.log");
+    let mut file = RotatingFile::open(&path)
+        .unwrap()
+        .rotation(Rotation::Never)
+        .max_size(Some(10))
+        .keep(2);
+
+    for line in ["first\n", "second\n", "third\n", "fourth\n"] {
+        file.write_at(line.as_bytes(), UNIX_EPOCH).unwrap();
+    }
+
+    assert_eq!(fs::read_to_string(&path).unwrap(), "fourth\n");
+    assert_eq!(
+        fs::read_to_string(dir.join("triplicata.log.1")).unwrap(),
+        "third\n"
+    );
+    assert_eq!(
+        fs::read_to_string(dir.join("triplicata.log.2")).unwrap(),
+        "second\n"
+    );
+    assert!(!dir.join("triplicata.log.3").exists());
+
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn rotates_each_hour() {
+    let dir = log_dir("hourly");
+    let path = dir.join("triplicata.log");
+    let mut file = RotatingFile::open(&path)
+        .unwrap()
+        .rotation(Rotation::Hourly);
+
+    let start = UNIX_EPOCH + Duration::from_secs(1_000 * 3600);
+    file.write_at(b"one\n", start).unwrap();
+    file.write_at(b"two\n", start + Duration::from_secs(60))
+        .unwrap();
+    file.write_at(b"three\n", start + Duration::from_secs(3600))
+        .unwrap();
+
+    assert_eq!(fs::read_to_string(&path).unwrap(), "three\n");
+    assert_eq!(
+        fs::read_to_string(dir.join("triplicata.log.1")).unwrap(),
+        "one\ntwo\n"
+    );
+
+    fs::remove_dir_all(dir).unwrap();
+}
@@ -0,0 +1,85 @@
+#![cfg(all(feature = "runtime", feature = "config"))]
+
+mod common;
+
+use std::time::{Duration, Instant};
+
+use futures::stream;
+use triplicata::{
+    cube::{CubeEvent, Move},
+    modifiers::{Modifier, Modifiers, Typing},
+};
+
+const CONFIG: &str = r#"(
+    timeout: 500,
+    binds: [
+        (trigger: "R U", actions: [Run("plain")]),
+        (trigger: "R U", actions: [Run("shifted")], held: [Shift]),
+        (trigger: "L D", actions: [Run("chorded")], held: [Control, Alt]),
+    ],
+)"#;
+
+async fn played(moves: &[Move], modifiers: Modifiers) -> Vec<String> {
+    let events = stream::iter(moves.iter().map(|m| CubeEvent::Move(*m)));
+    common::played(CONFIG, events, |machine| machine.modifiers(modifiers)).await
+}
+
+#[test]
+fn tracks_each_modifier() {
+    let modifiers = Modifiers::new();
+    modifiers.set(Modifier::Shift, true);
+    modifiers.set(Modifier::Alt, true);
+    modifiers.set(Modifier::Alt, false);
+
+    assert!(modifiers.clone().is_held(Modifier::Shift));
+    assert!(!modifiers.is_held(Modifier::Alt));
+    assert!(modifiers.are_held(&[]));
+    assert!(!modifiers.are_held(&[Modifier::Shift, Modifier::Control]));
+}
+
+#[tokio::test]
+async fn fires_binds_only_while_their_modifiers_are_held() {
+    assert_eq!(
+        played(&[Move::R, Move::U], Modifiers::new()).await,
+        [r#"run "plain""#]
+    );
+
+    let shifted = Modifiers::new();
+    shifted.set(Modifier::Shift, true);
+    assert_eq!(
+        played(&[Move::R, Move::U], shifted.clone()).await,
+        [r#"run "shifted""#]
+    );
+    assert!(played(&[Move::L, Move::D], shifted).await.is_empty());
+
+    let chorded = Modifiers::new();
+    chorded.set(Modifier::Control, true);
+    chorded.set(Modifier::Alt, true);
+    assert_eq!(
+        played(&[Move::L, Move::D], chorded).await,
+        [r#"run "chorded""#]
+    );
+}
+
+#[tokio::test]
+async fn ignores_moves_while_typing() {
+    let config = r#"(
+        timeout: 500,
+        typing_quiet: Some(2000),
+        binds: [(trigger: "R U", actions: [Run("plain")])],
+    )"#;
+    let played = |typing: Typing| {
+        let moves = stream::iter([CubeEvent::Move(Move::R), CubeEvent::Move(Move::U)]);
+        common::played(config, moves, |machine| machine.typing(typing))
+    };
+
+    let typing = Typing::new();
+    assert_eq!(played(typing.clone()).await, [r#"run "plain""#]);
+
+    typing.typed(Instant::now());
+    assert!(typing.typed_within(Duration::from_secs(2)));
+    assert!(played(typing.clone()).await.is_empty());
+
+    typing.typed(Instant::now() - Duration::from_secs(3));
+    assert_eq!(played(typing).await, [r#"run "plain""#]);
+}
@@ -0,0 +1,63 @@
+#![cfg(feature = "config")]
+
+use std::time::Duration;
+
+use triplicata::{
+    config::Key,
+    cube::Move,
+    morse::{Morse, MorseDecoder, decode},
+};
+
+fn flick(decoder: &mut MorseDecoder, code: &str) {
+    for symbol in code.chars() {
+        let m = if symbol == '.' { Move::U } else { Move::Up };
+        assert!(decoder.push(m));
+    }
+}
+
+#[test]
+fn decodes_letters_and_digits() {
+    assert_eq!(decode(".-"), Some('a'));
+    assert_eq!(decode("..."), Some('s'));
+    assert_eq!(decode("-----"), Some('0'));
+    assert_eq!(decode("......."), None);
+}
+
+#[test]
+fn pauses_end_letters_then_words() {
+    let mut decoder = MorseDecoder::new(Morse::default());
+    assert_eq!(decoder.next_gap(), None);
+
+    flick(&mut decoder, "...");
+    assert_eq!(decoder.next_gap(), Some(Duration::from_millis(600)));
+    assert!(decoder.pause(Duration::from_millis(300)).is_empty());
+    assert_eq!(
+        decoder.pause(Duration::from_millis(600)),
+        vec![Key::Unicode('s')]
+    );
+
+    assert_eq!(decoder.next_gap(), Some(Duration::from_millis(1500)));
+    assert_eq!(decoder.pause(Duration::from_millis(1500)), vec![Key::Space]);
+    assert_eq!(decoder.next_gap(), None);
+}
+
+#[test]
+fn long_pause_ends_letter_and_word() {
+    let mut decoder = MorseDecoder::new(Morse::default());
+
+    flick(&mut decoder, "-");
+    assert_eq!(
+        decoder.pause(Duration::from_secs(5)),
+        vec![Key::Unicode('t'), Key::Space]
+    );
+}
+
+#[test]
+fn unknown_codes_and_other_moves_type_nothing() {
+    let mut decoder = MorseDecoder::new(Morse::default());
+
+    assert!(!decoder.push(Move::R));
+    flick(&mut decoder, ".......");
+    assert!(decoder.pause(Duration::from_secs(5)).is_empty());
+    assert_eq!(decoder.next_gap(), None);
+}
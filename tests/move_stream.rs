@@ -0,0 +1,372 @@
+#![cfg(feature = "bluetooth")]
+
+//! Drives `move_stream_v2` with a mock peripheral that replays encrypted
+//! notifications, so decryption and move parsing are covered without a cube.
+
+use std::{
+    collections::{BTreeSet, HashMap},
+    pin::Pin,
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicUsize, Ordering},
+    },
+    time::Duration,
+};
+
+use async_trait::async_trait;
+use btleplug::{
+    Error, Result,
+    api::{
+        BDAddr, CharPropFlags, Characteristic, Descriptor, Peripheral, PeripheralProperties,
+        Service, ValueNotification, WriteType,
+    },
+    platform::PeripheralId,
+};
+use futures::{Stream, StreamExt, channel::oneshot, stream};
+use tokio::sync::broadcast::Receiver;
+use tokio_util::sync::CancellationToken;
+use triplicata::{
+    GAN_GEN2_COMMAND_CHARACTERISTIC, GAN_GEN2_SERVICE, GAN_GEN2_STATE_CHARACTERISTIC,
+    bluetooth::move_stream_v2,
+    cube::{CubeEvent, Move},
+    error::CubeError,
+    protocol::{
+        cipher::{CipherKeys, GAN_MANUFACTURER_ID, GANCubeVersion2Cipher},
+        gen2::{Command, Emulator},
+    },
+    retry::RetryPolicy,
+};
+
+const SALT: [u8; 6] = [0xab, 0x12, 0xcd, 0x34, 0xef, 0x56];
+
+/// A cube that sends `payloads` once subscribed to and records every write.
+#[derive(Debug, Clone)]
+struct MockPeripheral {
+    payloads: Vec<Vec<u8>>,
+    /// Subscribes that never finish before one succeeds.
+    hung_subscribes: Arc<AtomicUsize>,
+    subscribed: Arc<Mutex<Option<oneshot::Sender<()>>>>,
+    notified: Arc<Mutex<Option<oneshot::Receiver<()>>>>,
+    written: Arc<Mutex<Vec<Vec<u8>>>>,
+}
+
+impl MockPeripheral {
+    fn new(payloads: Vec<Vec<u8>>) -> Self {
+        let (tx, rx) = oneshot::channel();
+        Self {
+            payloads,
+            hung_subscribes: Arc::default(),
+            subscribed: Arc::new(Mutex::new(Some(tx))),
+            notified: Arc::new(Mutex::new(Some(rx))),
+            written: Arc::default(),
+        }
+    }
+
+    fn hang_subscribes(self, count: usize) -> Self {
+        self.hung_subscribes.store(count, Ordering::SeqCst);
+        self
+    }
+
+    fn written(&self) -> Vec<Vec<u8>> {
+        self.written.lock().unwrap().clone()
+    }
+}
+
+#[async_trait]
+impl Peripheral for MockPeripheral {
+    fn id(&self) -> PeripheralId {
+        unimplemented!("mock peripherals have no platform id")
+    }
+
+    fn address(&self) -> BDAddr {
+        BDAddr::from([0xab, 0x12, 0xcd, 0x34, 0xef, 0x56])
+    }
+
+    async fn properties(&self) -> Result<Option<PeripheralProperties>> {
+        let mut data = vec![0; 3];
+        data.extend(SALT);
+
+        Ok(Some(PeripheralProperties {
+            address: self.address(),
+            local_name: Some("GANi3".to_string()),
+            manufacturer_data: HashMap::from([(GAN_MANUFACTURER_ID, data)]),
+            ..Default::default()
+        }))
+    }
+
+    fn services(&self) -> BTreeSet<Service> {
+        BTreeSet::new()
+    }
+
+    async fn is_connected(&self) -> Result<bool> {
+        Ok(true)
+    }
+
+    async fn connect(&self) -> Result<()> {
+        Ok(())
+    }
+
+    async fn disconnect(&self) -> Result<()> {
+        Ok(())
+    }
+
+    async fn discover_services(&self) -> Result<()> {
+        Ok(())
+    }
+
+    async fn write(&self, _: &Characteristic, data: &[u8], _: WriteType) -> Result<()> {
+        self.written.lock().unwrap().push(data.to_vec());
+        Ok(())
+    }
+
+    async fn read(&self, _: &Characteristic) -> Result<Vec<u8>> {
+        Err(Error::NotSupported("read".to_string()))
+    }
+
+    async fn subscribe(&self, _: &Characteristic) -> Result<()> {
+        let hung = self
+            .hung_subscribes
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |count| {
+                count.checked_sub(1)
+            });
+        if hung.is_ok() {
+            std::future::pending::<()>().await;
+        }
+
+        if let Some(subscribed) = self.subscribed.lock().unwrap().take() {
+            let _ = subscribed.send(());
+        }
+        Ok(())
+    }
+
+    async fn unsubscribe(&self, _: &Characteristic) -> Result<()> {
+        Ok(())
+    }
+
+    async fn notifications(&self) -> Result<Pin<Box<dyn Stream<Item = ValueNotification> + Send>>> {
+        let notified = self
+            .notified
+            .lock()
+            .unwrap()
+            .take()
+            .ok_or_else(|| Error::NotSupported("a second notification stream".to_string()))?;
+        let notifications = self
+            .payloads
+            .clone()
+            .into_iter()
+            .map(|value| ValueNotification {
+                uuid: GAN_GEN2_STATE_CHARACTERISTIC,
+                value,
+            });
+
+        // Like a cube, nothing is sent until the state is subscribed to.
+        Ok(stream::once(notified)
+            .flat_map(move |_| stream::iter(notifications.clone()))
+            .boxed())
+    }
+
+    async fn write_descriptor(&self, _: &Descriptor, _: &[u8]) -> Result<()> {
+        Err(Error::NotSupported("write_descriptor".to_string()))
+    }
+
+    async fn read_descriptor(&self, _: &Descriptor) -> Result<Vec<u8>> {
+        Err(Error::NotSupported("read_descriptor".to_string()))
+    }
+}
+
+fn characteristic(uuid: uuid::Uuid, properties: CharPropFlags) -> Characteristic {
+    Characteristic {
+        uuid,
+        service_uuid: GAN_GEN2_SERVICE,
+        properties,
+        descriptors: BTreeSet::new(),
+    }
+}
+
+async fn try_connect(
+    cube: MockPeripheral,
+    keys: CipherKeys,
+    retry: RetryPolicy,
+) -> std::result::Result<Receiver<CubeEvent>, CubeError> {
+    move_stream_v2(
+        cube,
+        characteristic(GAN_GEN2_STATE_CHARACTERISTIC, CharPropFlags::NOTIFY),
+        characteristic(GAN_GEN2_COMMAND_CHARACTERISTIC, CharPropFlags::WRITE),
+        keys,
+        retry,
+        None,
+        CancellationToken::new(),
+    )
+    .await
+}
+
+async fn connect(cube: MockPeripheral, keys: CipherKeys) -> Receiver<CubeEvent> {
+    try_connect(cube, keys, RetryPolicy::default())
+        .await
+        .unwrap()
+}
+
+/// Collects events until the replayed notifications run out.
+async fn events_until_disconnected(mut events: Receiver<CubeEvent>) -> Vec<CubeEvent> {
+    let mut received = Vec::new();
+    loop {
+        let event = events.recv().await.unwrap();
+        received.push(event);
+        if event == CubeEvent::Disconnected {
+            return received;
+        }
+    }
+}
+
+fn move_packets(emulator: &mut Emulator, moves: &[Move]) -> Vec<Vec<u8>> {
+    moves
+        .iter()
+        .map(|m| {
+            emulator
+                .move_packet(*m, Duration::from_millis(100))
+                .unwrap()
+                .to_vec()
+        })
+        .collect()
+}
+
+#[tokio::test]
+async fn replays_moves() {
+    let mut emulator = Emulator::new(GANCubeVersion2Cipher::from_salt(SALT));
+    let mut payloads = move_packets(&mut emulator, &[Move::U, Move::R, Move::Up, Move::Fp]);
+    payloads.push(emulator.battery_packet(42).unwrap().to_vec());
+
+    let events = events_until_disconnected(
+        connect(MockPeripheral::new(payloads), CipherKeys::default()).await,
+    )
+    .await;
+
+    // The first move packet only sets the decoder's move counter.
+    assert_eq!(
+        events,
+        [
+            CubeEvent::Connected,
+            CubeEvent::Move(Move::R),
+            CubeEvent::Move(Move::Up),
+            CubeEvent::Move(Move::Fp),
+            CubeEvent::Battery(42),
+            CubeEvent::Disconnected,
+        ]
+    );
+}
+
+#[tokio::test]
+async fn skips_undecodable_packets() {
+    let mut emulator = Emulator::new(GANCubeVersion2Cipher::from_salt(SALT));
+    let mut payloads = move_packets(&mut emulator, &[Move::U, Move::R]);
+    payloads.push(vec![0; 5]);
+    payloads.extend(move_packets(&mut emulator, &[Move::L]));
+
+    let events = events_until_disconnected(
+        connect(MockPeripheral::new(payloads), CipherKeys::default()).await,
+    )
+    .await;
+
+    assert_eq!(
+        events,
+        [
+            CubeEvent::Connected,
+            CubeEvent::Move(Move::R),
+            CubeEvent::Move(Move::L),
+            CubeEvent::Disconnected,
+        ]
+    );
+}
+
+#[tokio::test]
+async fn catches_up_on_missed_packets() {
+    let mut emulator = Emulator::new(GANCubeVersion2Cipher::from_salt(SALT));
+    let mut payloads = move_packets(&mut emulator, &[Move::U, Move::R, Move::F, Move::D]);
+    // Only the newest packet arrives, it still lists the moves before it.
+    payloads.drain(1..3);
+
+    let events = events_until_disconnected(
+        connect(MockPeripheral::new(payloads), CipherKeys::default()).await,
+    )
+    .await;
+
+    assert_eq!(
+        events,
+        [
+            CubeEvent::Connected,
+            CubeEvent::Move(Move::R),
+            CubeEvent::Move(Move::F),
+            CubeEvent::Move(Move::D),
+            CubeEvent::Disconnected,
+        ]
+    );
+}
+
+#[tokio::test]
+async fn uses_custom_keys() {
+    let keys = CipherKeys {
+        key: [0x5a; 16],
+        iv: [0xa5; 16],
+    };
+    let mut emulator = Emulator::new(GANCubeVersion2Cipher::from_salt_with(keys, SALT));
+    let payloads = move_packets(&mut emulator, &[Move::U, Move::B]);
+
+    let events =
+        events_until_disconnected(connect(MockPeripheral::new(payloads.clone()), keys).await).await;
+    assert_eq!(events[1], CubeEvent::Move(Move::B));
+
+    // The same packets are garbage under GAN's keys.
+    let events = events_until_disconnected(
+        connect(MockPeripheral::new(payloads), CipherKeys::default()).await,
+    )
+    .await;
+    assert!(!events.contains(&CubeEvent::Move(Move::B)));
+}
+
+#[tokio::test]
+async fn requests_state_and_battery() {
+    let cube = MockPeripheral::new(Vec::new());
+    events_until_disconnected(connect(cube.clone(), CipherKeys::default()).await).await;
+
+    let emulator = Emulator::new(GANCubeVersion2Cipher::from_salt(SALT));
+    let commands: Vec<_> = cube
+        .written()
+        .iter()
+        .map(|packet| emulator.command(packet).unwrap())
+        .collect();
+
+    assert_eq!(
+        commands[..2],
+        [Some(Command::RequestState), Some(Command::RequestBattery)]
+    );
+}
+
+#[tokio::test(start_paused = true)]
+async fn retries_a_hung_subscribe() {
+    let mut emulator = Emulator::new(GANCubeVersion2Cipher::from_salt(SALT));
+    let payloads = move_packets(&mut emulator, &[Move::U, Move::R]);
+    let cube = MockPeripheral::new(payloads).hang_subscribes(2);
+
+    let events = events_until_disconnected(connect(cube, CipherKeys::default()).await).await;
+
+    assert_eq!(events[1], CubeEvent::Move(Move::R));
+}
+
+#[tokio::test(start_paused = true)]
+async fn gives_up_on_a_hung_subscribe() {
+    let cube = MockPeripheral::new(Vec::new()).hang_subscribes(usize::MAX);
+    let retry = RetryPolicy {
+        attempts: 2,
+        ..Default::default()
+    };
+
+    let error = try_connect(cube, CipherKeys::default(), retry)
+        .await
+        .unwrap_err();
+
+    let CubeError::GaveUp { attempts, last, .. } = error else {
+        panic!("expected to give up, got {error}");
+    };
+    assert_eq!(attempts, 2);
+    assert!(matches!(*last, CubeError::TimedOut { .. }));
+}
@@ -0,0 +1,56 @@
+#![cfg(feature = "config")]
+
+use triplicata::{
+    config::{Config, Key},
+    cube::{Face, Move},
+    movement::{Movement, MovementKey},
+};
+
+fn actions(movement: &Movement, trigger: &[Move]) -> Vec<String> {
+    let binds = movement.binds();
+    let bind = binds.iter().find(|bind| bind.trigger == trigger).unwrap();
+    bind.actions.iter().map(ToString::to_string).collect()
+}
+
+#[test]
+fn holds_the_last_face_turned() {
+    let movement = Movement::default();
+
+    assert_eq!(
+        actions(&movement, &[Move::U]),
+        [
+            "release Unicode('a')",
+            "release Unicode('s')",
+            "release Unicode('d')",
+            "press Unicode('w')",
+        ]
+    );
+    assert_eq!(actions(&movement, &[Move::Up]), ["release Unicode('w')"]);
+}
+
+#[test]
+fn stops_on_the_stop_trigger() {
+    let config: Config = r#"(
+        timeout: 500,
+        binds: [],
+        movement: Some((keys: [(face: R, key: right), (face: L, key: left)], stop: "F F'")),
+    )"#
+    .parse()
+    .unwrap();
+    let movement = config.movement.unwrap();
+    assert_eq!(
+        movement.keys[0],
+        MovementKey {
+            face: Face::R,
+            key: Key::RightArrow,
+        }
+    );
+
+    let binds = movement.binds();
+    assert_eq!(binds.len(), 5);
+    assert!(binds.iter().all(|bind| bind.groups == ["movement"]));
+    assert_eq!(
+        actions(&movement, &[Move::F, Move::Fp]),
+        ["release RightArrow", "release LeftArrow"]
+    );
+}
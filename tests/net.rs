@@ -0,0 +1,53 @@
+use triplicata::{
+    cube::{CubeState, Move},
+    net::Net,
+};
+
+/// The plain net of `state`, a line per row without the trailing space.
+fn drawn(state: &CubeState) -> Vec<String> {
+    Net::from(state)
+        .plain()
+        .to_string()
+        .lines()
+        .map(|line| line.trim_end().to_string())
+        .collect()
+}
+
+#[test]
+fn draws_the_solved_cube() {
+    assert_eq!(
+        drawn(&CubeState::SOLVED),
+        [
+            "      U U U",
+            "      U U U",
+            "      U U U",
+            "L L L F F F R R R B B B",
+            "L L L F F F R R R B B B",
+            "L L L F F F R R R B B B",
+            "      D D D",
+            "      D D D",
+            "      D D D",
+        ]
+    );
+}
+
+#[test]
+fn draws_a_turned_cube() {
+    let mut state = CubeState::SOLVED;
+    state.apply(Move::R);
+
+    assert_eq!(
+        drawn(&state),
+        [
+            "      U U F",
+            "      U U F",
+            "      U U F",
+            "L L L F F D R R R U B B",
+            "L L L F F D R R R U B B",
+            "L L L F F D R R R U B B",
+            "      D D B",
+            "      D D B",
+            "      D D B",
+        ]
+    );
+}
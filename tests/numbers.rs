@@ -0,0 +1,47 @@
+#![cfg(all(feature = "runtime", feature = "config"))]
+
+mod common;
+
+use futures::stream;
+use triplicata::algorithm::Algorithm;
+
+/// The actions `config` plays for `moves`, as displayed.
+async fn plays(config: &str, moves: &str) -> Vec<String> {
+    let moves = moves.parse::<Algorithm>().unwrap().to_moves().unwrap();
+    common::played(config, stream::iter(moves), |machine| machine).await
+}
+
+#[tokio::test]
+async fn types_digits() {
+    assert_eq!(
+        plays(
+            "(timeout: 500, binds: [], number_entry: Some(()))",
+            "R B' U' D"
+        )
+        .await,
+        ["Unicode('1')", "Unicode('0')", "Unicode('4')", "Return"]
+    );
+}
+
+#[tokio::test]
+async fn runs_actions_with_the_value() {
+    let config = r#"(
+        timeout: 500,
+        binds: [],
+        number_entry: Some((on_confirm: [Run("volume {value}")])),
+    )"#;
+
+    assert_eq!(plays(config, "F L L' D' D D").await, [r#"run "volume 57""#]);
+}
+
+#[tokio::test]
+async fn ends_on_confirming() {
+    let config = r#"(
+        timeout: 500,
+        binds: [],
+        number_entry: Some((toggle: "D D", on_confirm: [Run("workspace {value}")])),
+    )"#;
+
+    assert!(plays(config, "R D").await.is_empty());
+    assert_eq!(plays(config, "D U D R").await, [r#"run "workspace 3""#]);
+}
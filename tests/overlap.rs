@@ -0,0 +1,56 @@
+#![cfg(all(feature = "runtime", feature = "config"))]
+
+mod common;
+
+use futures::stream;
+use triplicata::{
+    cube::{CubeEvent, Move},
+    state_machine::OutputQueue,
+};
+
+const CONFIG: &str = r#"(
+    timeout: 500,
+    binds: [
+        (trigger: "R U", actions: [Run("save"), Run("close")], overlap: Coalesce),
+        (trigger: "F B", actions: [Run("build"), Run("test")]),
+        (trigger: "L D", actions: [Run("stop")], overlap: Replace),
+    ],
+)"#;
+
+/// The actions sent for `moves` and those the output plays of them, playing
+/// none until every move is turned.
+async fn played(moves: &[Move], queue: OutputQueue) -> (Vec<String>, Vec<String>) {
+    let events = stream::iter(moves.iter().map(|m| CubeEvent::Move(*m)));
+    let sent = common::played(CONFIG, events, |machine| {
+        machine.output_queue(queue.clone())
+    })
+    .await;
+
+    let mut played = Vec::new();
+    for action in &sent {
+        if !queue.skips_next() {
+            played.push(action.clone());
+        }
+        queue.played();
+    }
+    (sent, played)
+}
+
+#[tokio::test]
+async fn coalesces_firings_while_still_playing() {
+    let coalesced = [Move::R, Move::U, Move::R, Move::U];
+    let (sent, _) = played(&coalesced, OutputQueue::new()).await;
+    assert_eq!(sent, [r#"run "save""#, r#"run "close""#]);
+
+    let queued = [Move::F, Move::B, Move::F, Move::B];
+    let (sent, _) = played(&queued, OutputQueue::new()).await;
+    assert_eq!(sent.len(), 4);
+}
+
+#[tokio::test]
+async fn replaces_what_is_still_waiting() {
+    let (sent, played) = played(&[Move::F, Move::B, Move::L, Move::D], OutputQueue::new()).await;
+
+    assert_eq!(sent.len(), 3);
+    assert_eq!(played, [r#"run "stop""#]);
+}
@@ -0,0 +1,90 @@
+#![cfg(all(feature = "runtime", feature = "config"))]
+
+mod common;
+
+use futures::{StreamExt, stream};
+use tokio::sync::mpsc;
+use tokio_util::sync::CancellationToken;
+use triplicata::{
+    config::Config,
+    cube::{CubeEvent, Move},
+    state_machine::{KillSwitch, Pause, StateMachine},
+};
+
+const CONFIG: &str = r#"(
+    timeout: 500,
+    kill_gesture: "F F' F F'",
+    binds: [
+        (name: Some("Save"), trigger: "R U", actions: [Run("save")]),
+        (name: Some("Quit"), trigger: "L D", actions: [Run("quit")]),
+    ],
+)"#;
+
+async fn played(events: Vec<CubeEvent>, pause: Pause) -> Vec<String> {
+    common::played(CONFIG, stream::iter(events), |machine| machine.pause(pause)).await
+}
+
+#[test]
+fn toggles() {
+    let pause = Pause::new();
+    assert!(!pause.is_paused());
+    assert!(pause.toggle());
+    assert!(pause.clone().is_paused());
+    assert!(!pause.toggle());
+}
+
+#[tokio::test]
+async fn ignores_moves_while_paused() {
+    let moves = vec![CubeEvent::Move(Move::R), CubeEvent::Move(Move::U)];
+
+    assert_eq!(played(moves.clone(), Pause::new()).await, [r#"run "save""#]);
+
+    let pause = Pause::new();
+    pause.set(true);
+    assert!(played(moves, pause).await.is_empty());
+}
+
+#[tokio::test]
+async fn fires_binds_without_their_trigger() {
+    let pause = Pause::new();
+    pause.set(true);
+
+    assert_eq!(
+        played(vec![CubeEvent::Fire(1), CubeEvent::Fire(5)], pause).await,
+        [r#"run "quit""#]
+    );
+}
+
+#[tokio::test]
+async fn kill_gesture_releases_keys_and_pauses_even_while_paused() {
+    let kill = [Move::F, Move::Fp, Move::F, Move::Fp].map(CubeEvent::Move);
+
+    let pause = Pause::new();
+    let mut events = kill.to_vec();
+    events.extend([CubeEvent::Move(Move::R), CubeEvent::Move(Move::U)]);
+    assert_eq!(played(events, pause.clone()).await, ["release all"]);
+    assert!(pause.is_paused());
+
+    assert_eq!(played(kill.to_vec(), pause).await, ["release all"]);
+}
+
+#[tokio::test]
+async fn kill_switch_releases_keys_and_pauses() {
+    let config: Config = CONFIG.parse().unwrap();
+    let (tx, mut rx) = mpsc::unbounded_channel();
+    let (pause, kill_switch, cancel) = (Pause::new(), KillSwitch::new(), CancellationToken::new());
+    let machine = tokio::spawn(
+        StateMachine::new(stream::pending::<CubeEvent>().boxed(), config)
+            .pause(pause.clone())
+            .kill_switch(kill_switch.clone())
+            .run(tx, cancel.clone()),
+    );
+
+    kill_switch.pull();
+    assert_eq!(rx.recv().await.unwrap().to_string(), "release all");
+    assert!(pause.is_paused());
+    assert!(kill_switch.is_dropping());
+
+    cancel.cancel();
+    machine.await.unwrap();
+}
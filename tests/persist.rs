@@ -0,0 +1,148 @@
+#![cfg(feature = "persist")]
+
+use std::{fs, path::PathBuf, time::Duration};
+
+use triplicata::{
+    calibration::Calibrator,
+    cube::{CubeEvent, CubeState, Move, Quaternion},
+    persist::{SavedCube, StateKeeper, StateStore},
+};
+
+/// A store in a fresh file, unique to the test.
+fn store(name: &str) -> StateStore {
+    let path: PathBuf =
+        std::env::temp_dir().join(format!("triplicata-{}-{name}.json", std::process::id()));
+    let _ = fs::remove_file(&path);
+    StateStore::new(path)
+}
+
+fn scrambled() -> CubeState {
+    let mut state = CubeState::SOLVED;
+    state.apply(Move::R);
+    state.apply(Move::U);
+    state
+}
+
+#[test]
+fn restores_a_forgotten_state() {
+    let store = store("restores");
+    store
+        .save(
+            "cube",
+            SavedCube {
+                state: scrambled(),
+                move_count: 12,
+                reference: None,
+            },
+        )
+        .unwrap();
+
+    let mut keeper = StateKeeper::new(store, "cube".to_string());
+    let event = keeper.event(CubeEvent::StateSync(CubeState::SOLVED), Some(12));
+
+    assert_eq!(event, CubeEvent::StateSync(scrambled()));
+}
+
+#[test]
+fn trusts_the_cube_after_moves_elsewhere() {
+    let store = store("trusts");
+    store
+        .save(
+            "cube",
+            SavedCube {
+                state: scrambled(),
+                move_count: 12,
+                reference: None,
+            },
+        )
+        .unwrap();
+
+    let mut keeper = StateKeeper::new(store, "cube".to_string());
+    let event = keeper.event(CubeEvent::StateSync(CubeState::SOLVED), Some(15));
+
+    assert_eq!(event, CubeEvent::StateSync(CubeState::SOLVED));
+}
+
+#[test]
+fn saves_the_tracked_state() {
+    let store = store("saves");
+
+    let mut keeper = StateKeeper::new(store.clone(), "cube".to_string());
+    keeper.event(CubeEvent::StateSync(CubeState::SOLVED), Some(0));
+    keeper.event(CubeEvent::Move(Move::R), Some(1));
+    keeper.event(CubeEvent::Move(Move::U), Some(2));
+    keeper.save(Some(2));
+
+    assert_eq!(
+        store.load("cube").unwrap(),
+        Some(SavedCube {
+            state: scrambled(),
+            move_count: 2,
+            reference: None,
+        })
+    );
+    assert_eq!(store.load("other").unwrap(), None);
+}
+
+#[test]
+fn saves_while_connected_only_after_moves() {
+    let store = store("pending");
+
+    let mut keeper = StateKeeper::new(store.clone(), "cube".to_string());
+    keeper.event(CubeEvent::StateSync(CubeState::SOLVED), Some(0));
+    keeper.pending(Some(0)).unwrap().write();
+    assert_eq!(store.load("cube").unwrap().unwrap().move_count, 0);
+
+    keeper.event(CubeEvent::StateSync(CubeState::SOLVED), Some(0));
+    assert!(keeper.pending(Some(0)).is_none());
+
+    // Moved, but too soon after the last save.
+    keeper.event(CubeEvent::Move(Move::R), Some(1));
+    assert!(keeper.pending(Some(1)).is_none());
+
+    // Still saved once disconnected.
+    keeper.save(Some(1));
+    assert_eq!(store.load("cube").unwrap().unwrap().move_count, 1);
+}
+
+#[test]
+fn calibrates_and_saves_the_neutral_orientation() {
+    let store = store("calibrates").calibrate(Some(Calibrator::new(
+        vec![Move::D, Move::D],
+        Duration::ZERO,
+    )));
+    // Turned half way round the z axis.
+    let tilted = Quaternion {
+        x: 0.0,
+        y: 0.0,
+        z: 1.0,
+        w: 0.0,
+    };
+    let neutral = Quaternion {
+        x: 0.0,
+        y: 0.0,
+        z: 0.0,
+        w: 1.0,
+    };
+
+    let mut keeper = StateKeeper::new(store.clone(), "cube".to_string());
+    keeper.event(CubeEvent::StateSync(CubeState::SOLVED), Some(0));
+    keeper.event(CubeEvent::Move(Move::D), Some(1));
+    keeper.event(CubeEvent::Move(Move::D), Some(2));
+    keeper.event(CubeEvent::Orientation(tilted), Some(2));
+    keeper.event(CubeEvent::Orientation(tilted), Some(2));
+    assert_eq!(
+        keeper.event(CubeEvent::Orientation(tilted), Some(2)),
+        CubeEvent::Orientation(neutral)
+    );
+    keeper.save(Some(2));
+
+    let saved = store.load("cube").unwrap().unwrap();
+    assert_eq!(saved.reference, Some(tilted));
+
+    let mut keeper = StateKeeper::new(store, "cube".to_string());
+    assert_eq!(
+        keeper.event(CubeEvent::Orientation(tilted), Some(2)),
+        CubeEvent::Orientation(neutral)
+    );
+}
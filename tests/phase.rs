@@ -0,0 +1,95 @@
+#![cfg(feature = "solves")]
+
+use std::time::{Duration, Instant};
+
+use triplicata::{
+    cube::{CubeEvent, CubeState, Move},
+    phase::{Milestone, Phase, PhaseEnd},
+    solve::{Solve, SolveRecorder, Split},
+};
+
+/// The solve recorded from a cube scrambled with `scramble` and solved with
+/// `solution`, each move made the given milliseconds after the first.
+fn record(scramble: &[Move], solution: &[(Move, u64)]) -> Solve {
+    let mut state = CubeState::SOLVED;
+    for &m in scramble {
+        state.apply(m);
+    }
+
+    let mut recorder = SolveRecorder::new();
+    recorder.push(&CubeEvent::StateSync(state), Instant::now());
+
+    let start = Instant::now();
+    solution
+        .iter()
+        .find_map(|&(m, millis)| {
+            recorder.push(&CubeEvent::Move(m), start + Duration::from_millis(millis))
+        })
+        .unwrap()
+}
+
+fn phase(name: &str, end: PhaseEnd) -> Phase {
+    Phase {
+        name: name.to_string(),
+        end,
+    }
+}
+
+fn split(name: &str, millis: u64, moves: usize) -> Split {
+    Split {
+        name: name.to_string(),
+        duration: Duration::from_millis(millis),
+        moves,
+    }
+}
+
+#[test]
+fn ends_phases_by_moves_and_time() {
+    let solve = record(
+        &[Move::R, Move::U, Move::F, Move::D],
+        &[
+            (Move::Dp, 0),
+            (Move::Fp, 500),
+            (Move::Up, 1200),
+            (Move::Rp, 1500),
+        ],
+    );
+    let phases = [
+        phase("opening", PhaseEnd::Moves(2)),
+        phase("middle", PhaseEnd::Time(1000)),
+        phase("end", PhaseEnd::Milestone(Milestone::Solved)),
+    ];
+
+    assert_eq!(
+        solve.splits(&phases),
+        [
+            split("opening", 500, 2),
+            split("middle", 700, 1),
+            split("end", 300, 1),
+        ]
+    );
+}
+
+#[test]
+fn ends_several_milestones_with_one_move() {
+    // Undoing R leaves only F turned, which is a solved cross, first two
+    // layers and oriented last layer on F all at once.
+    let solve = record(&[Move::F, Move::R], &[(Move::Rp, 0), (Move::Fp, 500)]);
+    let phases = [
+        phase("cross", PhaseEnd::Milestone(Milestone::Cross)),
+        phase("f2l", PhaseEnd::Milestone(Milestone::F2l)),
+        phase("oll", PhaseEnd::Milestone(Milestone::Oll)),
+        phase("pll", PhaseEnd::Milestone(Milestone::Solved)),
+    ];
+
+    assert_eq!(solve.duration, Duration::from_millis(500));
+    assert_eq!(
+        solve.splits(&phases),
+        [
+            split("cross", 0, 1),
+            split("f2l", 0, 0),
+            split("oll", 0, 0),
+            split("pll", 500, 1),
+        ]
+    );
+}
@@ -0,0 +1,110 @@
+#![cfg(all(feature = "simulator", feature = "history", feature = "input"))]
+
+use std::time::Duration;
+
+use tokio::{task::JoinHandle, time::timeout};
+use tokio_util::sync::CancellationToken;
+use triplicata::{
+    Triplicata,
+    config::Action,
+    cube::Move,
+    error::{CubeError, Error, OutputError},
+    history::MoveHistory,
+    output::OutputBackend,
+    simulator::SimulatedCubeSource,
+    status::CubeStatus,
+};
+
+const CONFIG: &str = r#"(binds: [(trigger: "R", actions: [Run("crash")])])"#;
+
+/// An output whose first action brings its task down.
+struct Crashing;
+
+impl OutputBackend for Crashing {
+    fn execute(&mut self, _action: Action) -> Result<(), OutputError> {
+        panic!("the output crashed");
+    }
+}
+
+async fn build(scenario: &str, output: Option<Crashing>) -> Triplicata {
+    let builder = Triplicata::builder().config(CONFIG.parse().unwrap());
+    let builder = match output {
+        Some(output) => builder.output(output),
+        None => builder,
+    };
+    builder
+        .source(SimulatedCubeSource::new(scenario.parse().unwrap()))
+        .build()
+        .await
+        .unwrap()
+}
+
+/// Follows `triplicata` into `status` and `history` until `cancel` is
+/// cancelled, as the daemon does for every pipeline it builds.
+fn follow(
+    triplicata: &Triplicata,
+    status: &CubeStatus,
+    history: &MoveHistory,
+    cancel: &CancellationToken,
+) -> [JoinHandle<()>; 2] {
+    let (status, events, report) = (status.clone(), triplicata.event_stream(), None);
+    let following = {
+        let cancel = cancel.clone();
+        tokio::spawn(async move { status.follow(events, report, cancel).await })
+    };
+    let (history, events, cancel) = (history.clone(), triplicata.event_stream(), cancel.clone());
+    let recording = tokio::spawn(async move { history.record(events, cancel).await });
+    [following, recording]
+}
+
+async fn stop(triplicata: Triplicata, tasks: [JoinHandle<()>; 2], cancel: CancellationToken) {
+    cancel.cancel();
+    for task in tasks {
+        task.await.unwrap();
+    }
+    triplicata.shutdown().await;
+}
+
+#[tokio::test]
+async fn restarts_keeping_the_status_and_history() {
+    let status = CubeStatus::new();
+    let history = MoveHistory::new(10);
+
+    let mut triplicata = build("wait 100ms\nbattery 80%\nR", Some(Crashing)).await;
+    let cancel = CancellationToken::new();
+    let tasks = follow(&triplicata, &status, &history, &cancel);
+
+    let closed = timeout(Duration::from_secs(5), triplicata.closed())
+        .await
+        .unwrap();
+    assert!(matches!(closed, Err(Error::TaskStopped("output"))));
+    stop(triplicata, tasks, cancel).await;
+
+    let triplicata = build("wait 100ms\nF", None).await;
+    let cancel = CancellationToken::new();
+    let tasks = follow(&triplicata, &status, &history, &cancel);
+
+    timeout(Duration::from_secs(5), async {
+        while history.moves().len() < 2 {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+    })
+    .await
+    .unwrap();
+    stop(triplicata, tasks, cancel).await;
+
+    let moves: Vec<_> = history.moves().iter().map(|m| m.m).collect();
+    assert_eq!(moves, [Move::R, Move::F]);
+    assert_eq!(status.get().battery, Some(80));
+}
+
+#[tokio::test]
+async fn reports_a_disconnect_rather_than_a_stopped_task() {
+    let mut triplicata = build("wait 50ms\ndisconnect", None).await;
+
+    let closed = timeout(Duration::from_secs(5), triplicata.closed())
+        .await
+        .unwrap();
+    assert!(matches!(closed, Err(Error::Cube(CubeError::Disconnected))));
+    triplicata.shutdown().await;
+}
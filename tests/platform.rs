@@ -0,0 +1,66 @@
+#![cfg(feature = "config")]
+
+use triplicata::config::{Action, Config, resolve_actions};
+
+fn current(linux: &str, windows: &str, macos: &str) -> Vec<String> {
+    let command = if cfg!(target_os = "linux") {
+        linux
+    } else if cfg!(target_os = "windows") {
+        windows
+    } else if cfg!(target_os = "macos") {
+        macos
+    } else {
+        return Vec::new();
+    };
+    vec![format!("run {command:?}")]
+}
+
+#[test]
+fn resolves_the_current_platform() {
+    let mut config: Config = r#"(
+        timeout: 500,
+        binds: [(
+            trigger: "R U",
+            actions: [
+                Platform(
+                    linux: [Run("xdg-open .")],
+                    windows: [Run("explorer .")],
+                    macos: [Run("open .")],
+                ),
+                Delay(10),
+            ],
+        )],
+    )"#
+    .parse()
+    .unwrap();
+
+    config.resolve_platform_actions();
+    let actions: Vec<String> = config.binds[0]
+        .actions
+        .iter()
+        .map(ToString::to_string)
+        .collect();
+
+    let mut expected = current("xdg-open .", "explorer .", "open .");
+    expected.push("wait 10ms".to_string());
+    assert_eq!(actions, expected);
+}
+
+#[test]
+fn resolves_nested_platforms() {
+    let action = Action::Platform {
+        linux: vec![Action::Platform {
+            linux: vec![Action::Run("a".to_string())],
+            windows: Vec::new(),
+            macos: Vec::new(),
+        }],
+        windows: vec![Action::Run("b".to_string())],
+        macos: vec![Action::Run("c".to_string())],
+    };
+
+    let actions: Vec<String> = resolve_actions(vec![action])
+        .iter()
+        .map(ToString::to_string)
+        .collect();
+    assert_eq!(actions, current("a", "b", "c"));
+}
@@ -0,0 +1,88 @@
+#![cfg(all(feature = "runtime", feature = "config"))]
+
+mod common;
+
+use std::time::Duration;
+
+use futures::{StreamExt, stream};
+use triplicata::{
+    cube::{CubeEvent, Move},
+    rate_limit::{Period, RateLimit, RateLimiter, RatePolicy},
+};
+
+/// The actions played for `moves` within `wait` of turning them.
+async fn played(config: &str, moves: &[Move], wait: Duration) -> Vec<String> {
+    let events = stream::iter(moves.iter().map(|m| CubeEvent::Move(*m)))
+        .chain(stream::pending())
+        .take_until(Box::pin(tokio::time::sleep(wait)));
+
+    common::played(config, events, |machine| machine).await
+}
+
+#[test]
+fn drops_firings_over_the_limit() {
+    let limit = RateLimit {
+        max: 2,
+        per: Period::Second,
+        policy: RatePolicy::Drop,
+    };
+    let mut limiter = RateLimiter::new();
+    let at = |ms| Duration::from_millis(ms);
+
+    for now in [at(0), at(100)] {
+        assert_eq!(limiter.next_free(&limit, now), Some(now));
+        limiter.record(now);
+    }
+    assert_eq!(limiter.next_free(&limit, at(200)), None);
+    assert_eq!(limiter.next_free(&limit, at(1000)), Some(at(1000)));
+}
+
+#[test]
+fn queues_firings_until_the_limit_allows() {
+    let limit = RateLimit {
+        max: 1,
+        per: Period::Second,
+        policy: RatePolicy::Queue,
+    };
+    let mut limiter = RateLimiter::new();
+
+    for expected in [0, 1000, 2000] {
+        let at = limiter.next_free(&limit, Duration::ZERO).unwrap();
+        assert_eq!(at, Duration::from_millis(expected));
+        limiter.record(at);
+    }
+}
+
+#[tokio::test(start_paused = true)]
+async fn limits_each_bind_and_all_of_them() {
+    let moves = [Move::R, Move::U, Move::R, Move::U, Move::L, Move::D];
+
+    let own = r#"(
+        timeout: 500,
+        binds: [
+            (trigger: "R U", actions: [Run("save")], rate_limit: Some((max: 1, per: Minute))),
+            (trigger: "L D", actions: [Run("quit")]),
+        ],
+    )"#;
+    assert_eq!(
+        played(own, &moves, Duration::from_secs(1)).await,
+        [r#"run "save""#, r#"run "quit""#]
+    );
+
+    let global = r#"(
+        timeout: 500,
+        rate_limit: Some((max: 1, per: Second, policy: Queue)),
+        binds: [
+            (trigger: "R U", actions: [Run("save")]),
+            (trigger: "L D", actions: [Run("quit")]),
+        ],
+    )"#;
+    assert_eq!(
+        played(global, &moves, Duration::from_millis(500)).await,
+        [r#"run "save""#]
+    );
+    assert_eq!(
+        played(global, &moves, Duration::from_millis(2500)).await,
+        [r#"run "save""#, r#"run "save""#, r#"run "quit""#]
+    );
+}
@@ -0,0 +1,235 @@
+#![cfg(feature = "simulator")]
+
+use std::time::Duration;
+
+use tokio::{sync::broadcast::Receiver, time::Instant};
+use tokio_util::sync::CancellationToken;
+use triplicata::{
+    cube::{CubeEvent, Move},
+    error::ScenarioError,
+    simulator::{DEFAULT_PACE, Playback, Scenario, SessionRecorder, SimulatedCubeSource, Step},
+    source::CubeSource,
+};
+
+const SCENARIO: &str = "
+# A sexy move, a break and a dying battery.
+pace 150ms
+R U R' U'
+wait 2s
+battery 15%
+disconnect 1s
+F
+";
+
+async fn connect(scenario: &str, cancel: CancellationToken) -> Receiver<CubeEvent> {
+    SimulatedCubeSource::new(scenario.parse().unwrap())
+        .connect(cancel)
+        .await
+        .unwrap()
+}
+
+/// Events until the cube disconnects for good, with the time each arrived.
+async fn timeline(mut events: Receiver<CubeEvent>) -> Vec<(CubeEvent, Duration)> {
+    let start = Instant::now();
+    let mut received = Vec::new();
+    while let Ok(event) = events.recv().await {
+        received.push((event, start.elapsed()));
+    }
+    received
+}
+
+#[test]
+fn parses_steps() {
+    let scenario: Scenario = SCENARIO.parse().unwrap();
+
+    assert_eq!(
+        scenario.steps,
+        [
+            Step::Pace(Duration::from_millis(150)),
+            Step::Moves(vec![Move::R, Move::U, Move::Rp, Move::Up]),
+            Step::Wait(Duration::from_secs(2)),
+            Step::Battery(15),
+            Step::Disconnect(Some(Duration::from_secs(1))),
+            Step::Moves(vec![Move::F]),
+        ]
+    );
+}
+
+#[test]
+fn reports_the_invalid_line() {
+    for (scenario, line) in [
+        ("R U\nwait 2 minutes", 2),
+        ("\n\nbattery 120", 3),
+        ("R Q", 1),
+        ("R\nwait 1e30s", 2),
+        ("# rotations cannot be turned\nx", 2),
+    ] {
+        match scenario.parse::<Scenario>() {
+            Err(ScenarioError::Invalid { line: invalid, .. }) => assert_eq!(invalid, line),
+            other => panic!("{scenario:?} parsed as {other:?}"),
+        }
+    }
+}
+
+#[tokio::test(start_paused = true)]
+async fn plays_steps_in_time() {
+    let cancel = CancellationToken::new();
+    let events = connect(SCENARIO, cancel.clone()).await;
+
+    let playing = tokio::spawn(timeline(events));
+    tokio::time::sleep(Duration::from_secs(10)).await;
+    cancel.cancel();
+
+    let ms = Duration::from_millis;
+    assert_eq!(
+        playing.await.unwrap(),
+        [
+            (CubeEvent::Connected, ms(0)),
+            (CubeEvent::Move(Move::R), ms(150)),
+            (CubeEvent::Move(Move::U), ms(300)),
+            (CubeEvent::Move(Move::Rp), ms(450)),
+            (CubeEvent::Move(Move::Up), ms(600)),
+            (CubeEvent::Battery(15), ms(2600)),
+            (CubeEvent::Disconnected, ms(2600)),
+            (CubeEvent::Connected, ms(3600)),
+            (CubeEvent::Move(Move::F), ms(3750)),
+            (CubeEvent::Disconnected, ms(10000)),
+        ]
+    );
+}
+
+#[tokio::test(start_paused = true)]
+async fn disconnects_for_good() {
+    let events = connect("R\ndisconnect\nU", CancellationToken::new()).await;
+
+    assert_eq!(
+        timeline(events).await,
+        [
+            (CubeEvent::Connected, Duration::ZERO),
+            (CubeEvent::Move(Move::R), DEFAULT_PACE),
+            (CubeEvent::Disconnected, DEFAULT_PACE),
+        ]
+    );
+}
+
+#[tokio::test(start_paused = true)]
+async fn stops_when_cancelled() {
+    let cancel = CancellationToken::new();
+    let events = connect("R U R' U'", cancel.clone()).await;
+    cancel.cancel();
+
+    let events: Vec<_> = timeline(events)
+        .await
+        .into_iter()
+        .map(|(event, _)| event)
+        .collect();
+    assert_eq!(events, [CubeEvent::Connected, CubeEvent::Disconnected]);
+}
+
+#[tokio::test(start_paused = true)]
+async fn plays_at_the_playback_speed() {
+    let cancel = CancellationToken::new();
+    let events = SimulatedCubeSource::new("pace 200ms\nR U".parse().unwrap())
+        .playback(Playback::new(4.0))
+        .connect(cancel.clone())
+        .await
+        .unwrap();
+
+    let playing = tokio::spawn(timeline(events));
+    tokio::time::sleep(Duration::from_secs(1)).await;
+    cancel.cancel();
+
+    let received = playing.await.unwrap();
+    assert_eq!(
+        received[1],
+        (CubeEvent::Move(Move::R), Duration::from_millis(50))
+    );
+    assert_eq!(
+        received[2],
+        (CubeEvent::Move(Move::U), Duration::from_millis(100))
+    );
+}
+
+#[tokio::test(start_paused = true)]
+async fn slows_waits_too_long_to_hold() {
+    let cancel = CancellationToken::new();
+    let events = SimulatedCubeSource::new("wait 1e18s\nR".parse().unwrap())
+        .playback(Playback::new(0.01))
+        .connect(cancel.clone())
+        .await
+        .unwrap();
+
+    let playing = tokio::spawn(timeline(events));
+    tokio::time::sleep(Duration::from_secs(3600)).await;
+    cancel.cancel();
+
+    let events: Vec<_> = playing
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|(event, _)| event)
+        .collect();
+    assert_eq!(events, [CubeEvent::Connected, CubeEvent::Disconnected]);
+}
+
+#[tokio::test(start_paused = true)]
+async fn steps_through_a_paused_playback() {
+    let playback = Playback::new(1.0);
+    playback.set_paused(true);
+    let mut events = SimulatedCubeSource::new("R\nwait 5s\nU".parse().unwrap())
+        .playback(playback.clone())
+        .connect(CancellationToken::new())
+        .await
+        .unwrap();
+    assert_eq!(events.recv().await.unwrap(), CubeEvent::Connected);
+
+    tokio::time::sleep(Duration::from_secs(10)).await;
+    assert!(events.try_recv().is_err());
+
+    playback.step();
+    assert_eq!(events.recv().await.unwrap(), CubeEvent::Move(Move::R));
+    playback.step();
+    assert_eq!(events.recv().await.unwrap(), CubeEvent::Move(Move::U));
+    playback.finished().await;
+}
+
+#[test]
+fn records_sessions_as_scenarios() {
+    let start = Instant::now();
+    let ms = |ms| start + Duration::from_millis(ms);
+    let mut recorder = SessionRecorder::new(start);
+
+    let mut recorded = recorder.header();
+    for (event, at) in [
+        (CubeEvent::Connected, ms(0)),
+        (CubeEvent::Move(Move::R), ms(120)),
+        (CubeEvent::Move(Move::U), ms(120)),
+        (CubeEvent::Battery(15), ms(2000)),
+        (CubeEvent::Disconnected, ms(2500)),
+        (CubeEvent::Connected, ms(3500)),
+        (CubeEvent::Move(Move::F), ms(3600)),
+        (CubeEvent::Disconnected, ms(4000)),
+    ] {
+        recorded.extend(recorder.push(&event, at));
+    }
+    recorded.extend(recorder.finish());
+
+    let scenario: Scenario = recorded.parse().unwrap();
+    assert_eq!(
+        scenario.steps,
+        [
+            Step::Pace(Duration::ZERO),
+            Step::Wait(Duration::from_millis(120)),
+            Step::Moves(vec![Move::R]),
+            Step::Moves(vec![Move::U]),
+            Step::Wait(Duration::from_millis(1880)),
+            Step::Battery(15),
+            Step::Wait(Duration::from_millis(500)),
+            Step::Disconnect(Some(Duration::from_secs(1))),
+            Step::Wait(Duration::from_millis(100)),
+            Step::Moves(vec![Move::F]),
+            Step::Wait(Duration::from_millis(400)),
+            Step::Disconnect(None),
+        ]
+    );
+}
@@ -0,0 +1,75 @@
+#![cfg(feature = "config")]
+
+use std::time::Duration;
+
+use triplicata::{
+    cube::Quaternion,
+    smoothing::{OrientationFilter, Smoothing},
+};
+
+const IDENTITY: Quaternion = Quaternion {
+    x: 0.0,
+    y: 0.0,
+    z: 0.0,
+    w: 1.0,
+};
+
+/// A turn of `angle` radians about the z axis.
+fn about_z(angle: f32) -> Quaternion {
+    Quaternion {
+        x: 0.0,
+        y: 0.0,
+        z: (angle / 2.0).sin(),
+        w: (angle / 2.0).cos(),
+    }
+}
+
+fn angle(q: Quaternion) -> f32 {
+    2.0 * q.z.atan2(q.w)
+}
+
+fn ms(ms: u64) -> Duration {
+    Duration::from_millis(ms)
+}
+
+#[test]
+fn ema_moves_part_of_the_way() {
+    let mut filter = OrientationFilter::new(Smoothing::Ema { alpha: 0.5 });
+
+    assert_eq!(filter.push(IDENTITY, ms(0)), IDENTITY);
+    let smoothed = angle(filter.push(about_z(0.2), ms(10)));
+    assert!((smoothed - 0.1).abs() < 0.01, "{smoothed}");
+}
+
+#[test]
+fn smooths_the_same_orientation_either_sign() {
+    let mut filter = OrientationFilter::new(Smoothing::Ema { alpha: 0.5 });
+    filter.push(IDENTITY, ms(0));
+
+    let flipped = Quaternion {
+        w: -1.0,
+        ..IDENTITY
+    };
+    assert_eq!(filter.push(flipped, ms(10)), IDENTITY);
+}
+
+#[test]
+fn one_euro_steadies_jitter_but_follows_turns() {
+    let smoothing = Smoothing::OneEuro {
+        min_cutoff: 1.0,
+        beta: 5.0,
+        d_cutoff: 1.0,
+    };
+
+    let mut still = OrientationFilter::new(smoothing);
+    still.push(IDENTITY, ms(0));
+    let jitter = angle(still.push(about_z(0.01), ms(10)));
+    assert!(jitter < 0.001, "{jitter}");
+
+    let mut turning = OrientationFilter::new(smoothing);
+    let mut turned = 0.0;
+    for step in 0..=20 {
+        turned = angle(turning.push(about_z(step as f32 * 0.1), ms(step * 10)));
+    }
+    assert!(turned > 1.5, "{turned}");
+}
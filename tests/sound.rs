@@ -0,0 +1,46 @@
+#![cfg(feature = "sound")]
+
+use std::{collections::HashMap, time::Duration};
+
+use triplicata::{
+    config::{Bind, Cue},
+    sound::{cue_for, tone_wav},
+    state_machine::FiredBind,
+};
+
+#[test]
+fn tones_are_wav_files() {
+    let wav = tone_wav(440, Duration::from_millis(100));
+
+    assert_eq!(&wav[..4], b"RIFF");
+    assert_eq!(&wav[8..16], b"WAVEfmt ");
+    // 2205 samples of two bytes after the 44 byte header.
+    assert_eq!(wav.len(), 44 + 2205 * 2);
+    assert_eq!(
+        u32::from_le_bytes(wav[4..8].try_into().unwrap()),
+        36 + 2205 * 2
+    );
+}
+
+#[test]
+fn binds_fall_back_to_their_group_cue() {
+    let fired = |cue, groups: &[&str]| FiredBind {
+        bind: Bind {
+            cue,
+            groups: groups.iter().map(|group| group.to_string()).collect(),
+            ..Bind::default()
+        },
+        prefix: Vec::new(),
+    };
+    let cues = HashMap::from([("media".to_string(), Cue::Tone(660))]);
+
+    assert_eq!(
+        cue_for(&fired(Some(Cue::Tone(880)), &["media"]), &cues),
+        Some(&Cue::Tone(880))
+    );
+    assert_eq!(
+        cue_for(&fired(None, &["games", "media"]), &cues),
+        Some(&Cue::Tone(660))
+    );
+    assert_eq!(cue_for(&fired(None, &["games"]), &cues), None);
+}
@@ -0,0 +1,74 @@
+#![cfg(feature = "database")]
+
+use std::time::{Duration, UNIX_EPOCH};
+
+use triplicata::{
+    cube::Move,
+    database::{Penalty, StoredSolve},
+    solve::{Split, TimedMove},
+    stats::{Column, to_csv},
+};
+
+fn solve(id: i64, millis: u64, splits: &[(&str, u64, usize)]) -> StoredSolve {
+    StoredSolve {
+        id,
+        session: 1,
+        started: UNIX_EPOCH + Duration::from_secs(60),
+        duration: Duration::from_millis(millis),
+        scramble: "UUU,RRR".to_string(),
+        moves: vec![
+            TimedMove {
+                m: Move::R,
+                offset: Duration::ZERO,
+            },
+            TimedMove {
+                m: Move::U,
+                offset: Duration::from_millis(millis),
+            },
+        ],
+        splits: splits
+            .iter()
+            .map(|&(name, millis, moves)| Split {
+                name: name.to_string(),
+                duration: Duration::from_millis(millis),
+                moves,
+            })
+            .collect(),
+        penalty: None,
+    }
+}
+
+#[test]
+fn writes_the_default_columns() {
+    let mut dnf = solve(2, 1000, &[]);
+    dnf.penalty = Some(Penalty::Dnf);
+
+    let csv = to_csv(
+        &[solve(1, 2000, &[("cross", 500, 1), ("rest", 1500, 1)]), dnf],
+        &Column::DEFAULT,
+    );
+
+    assert_eq!(
+        csv,
+        "id,session,started,time,duration,penalty,moves,tps,cross time,cross moves,rest time,rest moves\n\
+         1,1,1970-01-01T00:01:00.000Z,2.000,2.000,,2,1.00,0.500,1,1.500,1\n\
+         2,1,1970-01-01T00:01:00.000Z,,1.000,DNF,2,2.00,,,,\n"
+    );
+}
+
+#[test]
+fn writes_the_chosen_columns_quoted() {
+    let mut plus_two = solve(3, 1000, &[]);
+    plus_two.penalty = Some(Penalty::PlusTwo);
+
+    let columns: Vec<Column> = ["time", "scramble", "id"]
+        .iter()
+        .map(|name| name.parse().unwrap())
+        .collect();
+
+    assert_eq!(
+        to_csv(&[plus_two], &columns),
+        "time,scramble,id\n3.000,\"UUU,RRR\",3\n"
+    );
+    assert!("color".parse::<Column>().is_err());
+}
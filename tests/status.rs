@@ -0,0 +1,19 @@
+#![cfg(feature = "runtime")]
+
+use triplicata::{cube::CubeEvent, status::CubeStatus};
+
+#[test]
+fn follows_connection_and_battery() {
+    let status = CubeStatus::new();
+    assert_eq!(status.get().to_string(), "disconnected");
+
+    status.push(&CubeEvent::Connected);
+    assert_eq!(status.get().to_string(), "connected, battery unknown");
+
+    status.push(&CubeEvent::Battery(80));
+    assert_eq!(status.get().to_string(), "connected, battery 80%");
+
+    status.push(&CubeEvent::Disconnected);
+    assert_eq!(status.get().battery, None);
+    assert!(!status.get().connected);
+}
@@ -0,0 +1,23 @@
+#![cfg(feature = "input")]
+
+use triplicata::{config::Key, error::OutputError, switches::Switches};
+
+#[test]
+fn counts_switches_from_one() {
+    let switches: Switches = ron::from_str("(keys: [space, enter, Unicode('1')])").unwrap();
+
+    assert_eq!(switches.key(1).unwrap(), Key::Space);
+    assert_eq!(switches.key(2).unwrap(), Key::Return);
+    assert_eq!(switches.key(3).unwrap(), Key::Unicode('1'));
+    assert!(matches!(switches.key(0), Err(OutputError::NoSwitch(0))));
+    assert!(matches!(switches.key(4), Err(OutputError::NoSwitch(4))));
+}
+
+#[test]
+fn defaults_to_space_and_enter() {
+    let switches: Switches = ron::from_str("()").unwrap();
+
+    assert_eq!(switches, Switches::default());
+    assert_eq!(switches.keys, [Key::Space, Key::Return]);
+    assert!(!switches.device);
+}
@@ -0,0 +1,86 @@
+#![cfg(feature = "udp")]
+
+use std::time::Duration;
+
+use futures::stream;
+use tokio::{net::UdpSocket, time::timeout};
+use tokio_util::sync::CancellationToken;
+use triplicata::{
+    cube::{CubeEvent, Move, Quaternion},
+    udp::{encode, send_events},
+};
+
+#[test]
+fn encodes_moves_as_osc() {
+    assert_eq!(
+        encode(&CubeEvent::Move(Move::Rp)).unwrap(),
+        [
+            b"/cube/move\0\0".as_slice(),
+            b",sii\0\0\0\0",
+            b"R'\0\0",
+            &1i32.to_be_bytes(),
+            &(-1i32).to_be_bytes(),
+        ]
+        .concat()
+    );
+}
+
+#[test]
+fn encodes_orientation_as_osc() {
+    let orientation = Quaternion {
+        w: 1.0,
+        x: 0.0,
+        y: -0.5,
+        z: 0.25,
+    };
+
+    assert_eq!(
+        encode(&CubeEvent::Orientation(orientation)).unwrap(),
+        [
+            b"/cube/orientation\0\0\0".as_slice(),
+            b",ffff\0\0\0",
+            &1f32.to_be_bytes(),
+            &0f32.to_be_bytes(),
+            &(-0.5f32).to_be_bytes(),
+            &0.25f32.to_be_bytes(),
+        ]
+        .concat()
+    );
+}
+
+#[test]
+fn pads_messages_without_arguments() {
+    assert_eq!(
+        encode(&CubeEvent::Connected).unwrap(),
+        b"/cube/connected\0,\0\0\0"
+    );
+    assert_eq!(encode(&CubeEvent::Lagged(3)), None);
+}
+
+#[tokio::test]
+async fn sends_a_datagram_per_event() {
+    let receiver = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+    let events = [
+        CubeEvent::Connected,
+        CubeEvent::Lagged(1),
+        CubeEvent::Move(Move::U),
+        CubeEvent::Battery(80),
+    ];
+
+    send_events(
+        receiver.local_addr().unwrap(),
+        stream::iter(events),
+        CancellationToken::new(),
+    )
+    .await
+    .unwrap();
+
+    let mut buffer = [0; 64];
+    for event in [events[0], events[2], events[3]] {
+        let length = timeout(Duration::from_secs(1), receiver.recv(&mut buffer))
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(buffer[..length], encode(&event).unwrap());
+    }
+}
@@ -0,0 +1,66 @@
+#![cfg(feature = "webhook")]
+
+use std::time::{Duration, UNIX_EPOCH};
+
+use serde_json::{Value, json};
+use triplicata::{
+    cube::{CubeEvent, Move},
+    webhook::{Payload, PayloadTracker, Webhook, WebhookEvent},
+};
+
+#[test]
+fn reports_solving_once_scrambled() {
+    let mut tracker = PayloadTracker::new();
+
+    assert_eq!(tracker.push(&CubeEvent::Move(Move::R)), None);
+    assert_eq!(tracker.push(&CubeEvent::Move(Move::U)), None);
+    assert_eq!(tracker.push(&CubeEvent::Move(Move::Up)), None);
+    assert_eq!(
+        tracker.push(&CubeEvent::Move(Move::Rp)),
+        Some(Payload::Solved)
+    );
+}
+
+#[test]
+fn reports_low_battery_until_charged() {
+    let mut tracker = PayloadTracker::new();
+
+    assert_eq!(tracker.push(&CubeEvent::Battery(50)), None);
+    assert_eq!(
+        tracker.push(&CubeEvent::Battery(15)),
+        Some(Payload::BatteryLow { level: 15 })
+    );
+    assert_eq!(tracker.push(&CubeEvent::Battery(10)), None);
+    assert_eq!(tracker.push(&CubeEvent::Battery(80)), None);
+    assert_eq!(
+        tracker.push(&CubeEvent::Battery(5)),
+        Some(Payload::BatteryLow { level: 5 })
+    );
+}
+
+#[test]
+fn sends_chosen_events() {
+    let webhook: Webhook =
+        ron::from_str(r#"(url: "http://localhost/hook", events: [Solved, BatteryLow])"#).unwrap();
+    assert!(webhook.wants(WebhookEvent::Solved));
+    assert!(!webhook.wants(Payload::Connected.event()));
+
+    let every: Webhook = ron::from_str(r#"(url: "http://localhost/hook")"#).unwrap();
+    assert!(every.wants(WebhookEvent::Fired));
+}
+
+#[test]
+fn bodies_are_tagged_by_event() {
+    let body: Value = serde_json::from_str(
+        &Payload::Fired {
+            bind: "Save".to_string(),
+        }
+        .to_json(UNIX_EPOCH + Duration::from_millis(1500)),
+    )
+    .unwrap();
+
+    assert_eq!(
+        body,
+        json!({"event": "fired", "bind": "Save", "time_ms": 1500})
+    );
+}
@@ -0,0 +1,9 @@
+use std::{env, path::PathBuf};
+
+fn main() {
+    let crate_dir = PathBuf::from(env::var("CARGO_MANIFEST_DIR").unwrap());
+
+    cbindgen::generate(&crate_dir)
+        .expect("could not generate bindings")
+        .write_to_file(crate_dir.join("include/triplicata.h"));
+}
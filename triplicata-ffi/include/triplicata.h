@@ -0,0 +1,166 @@
+#ifndef TRIPLICATA_H
+#define TRIPLICATA_H
+
+/* Generated by cbindgen from triplicata-ffi, do not edit. */
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+typedef enum TriplicataEventKind {
+  TRIPLICATA_EVENT_KIND_NONE,
+  TRIPLICATA_EVENT_KIND_MOVE,
+  TRIPLICATA_EVENT_KIND_STATE_SYNC,
+  TRIPLICATA_EVENT_KIND_BATTERY,
+  TRIPLICATA_EVENT_KIND_ORIENTATION,
+  TRIPLICATA_EVENT_KIND_CONNECTED,
+  TRIPLICATA_EVENT_KIND_DISCONNECTED,
+  TRIPLICATA_EVENT_KIND_LAGGED,
+  TRIPLICATA_EVENT_KIND_TIMER,
+  TRIPLICATA_EVENT_KIND_REDEMPTION,
+  TRIPLICATA_EVENT_KIND_PROFILE,
+  TRIPLICATA_EVENT_KIND_FIRE,
+} TriplicataEventKind;
+
+typedef struct TriplicataConfig TriplicataConfig;
+
+/**
+ * A connected cube. Owns the runtime driving its bluetooth connection.
+ */
+typedef struct TriplicataCube TriplicataCube;
+
+typedef struct TriplicataCubeState {
+  uint8_t corner_permutation[8];
+  uint8_t corner_orientation[8];
+  uint8_t edge_permutation[12];
+  uint8_t edge_orientation[12];
+} TriplicataCubeState;
+
+/**
+ * A cube event, only the fields matching `kind` are meaningful.
+ *
+ * `move_index` uses the order GAN cubes number moves in: U, U', R, R', F,
+ * F', D, D', L, L', B, B'. `orientation` is a quaternion as x, y, z, w.
+ * `dropped` is the number of events missed by a lagged receiver.
+ * `timer_state` counts disconnect, get set, hands off, running, stopped,
+ * idle, hands on and finished from 0, and `timer_ms` is the recorded time
+ * once a timer stops. `redemption` is the index of the redemption bind whose
+ * Twitch reward was redeemed. `profile` is the index of the profile the
+ * focused window switched to, and `bind` the index of the bind fired
+ * without its trigger.
+ */
+typedef struct TriplicataEvent {
+  enum TriplicataEventKind kind;
+  uint8_t move_index;
+  uint8_t battery;
+  float orientation[4];
+  struct TriplicataCubeState state;
+  uint64_t dropped;
+  uint8_t timer_state;
+  uint64_t timer_ms;
+  uint64_t redemption;
+  uint64_t profile;
+  uint64_t bind;
+} TriplicataEvent;
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+/**
+ * Returns the last error raised on this thread, or null. The string is owned
+ * by the library and valid until the next call on this thread.
+ */
+const char *triplicata_last_error(void);
+
+/**
+ * Scans for and connects to the first GAN cube found on the given bluetooth
+ * adapter, blocking until connected.
+ */
+struct TriplicataCube *triplicata_connect(size_t adapter);
+
+/**
+ * Disconnects the cube and frees it.
+ *
+ * # Safety
+ *
+ * `cube` must be null or a pointer returned by [`triplicata_connect`] that
+ * has not been freed.
+ */
+void triplicata_disconnect(struct TriplicataCube *cube);
+
+/**
+ * Writes the next pending event into `event` without blocking. Returns
+ * whether an event was written.
+ *
+ * # Safety
+ *
+ * `cube` must be a live pointer from [`triplicata_connect`] and `event` must
+ * be valid for writes.
+ */
+bool triplicata_poll_event(struct TriplicataCube *cube, struct TriplicataEvent *event);
+
+/**
+ * Blocks for up to `timeout_ms` milliseconds waiting for an event. Returns
+ * whether an event was written.
+ *
+ * # Safety
+ *
+ * `cube` must be a live pointer from [`triplicata_connect`] and `event` must
+ * be valid for writes.
+ */
+bool triplicata_wait_event(struct TriplicataCube *cube,
+                           uint64_t timeout_ms,
+                           struct TriplicataEvent *event);
+
+/**
+ * Loads a config file from `path`.
+ *
+ * # Safety
+ *
+ * `path` must be a valid nul terminated string.
+ */
+struct TriplicataConfig *triplicata_config_load(const char *path);
+
+/**
+ * Parses a config from its source text.
+ *
+ * # Safety
+ *
+ * `source` must be a valid nul terminated string.
+ */
+struct TriplicataConfig *triplicata_config_parse(const char *source);
+
+/**
+ * # Safety
+ *
+ * `config` must be a live pointer from [`triplicata_config_load`] or
+ * [`triplicata_config_parse`].
+ */
+size_t triplicata_config_bind_count(const struct TriplicataConfig *config);
+
+/**
+ * # Safety
+ *
+ * `config` must be a live pointer from [`triplicata_config_load`] or
+ * [`triplicata_config_parse`].
+ */
+uint64_t triplicata_config_timeout(const struct TriplicataConfig *config);
+
+/**
+ * Frees a config.
+ *
+ * # Safety
+ *
+ * `config` must be null or a pointer from [`triplicata_config_load`] or
+ * [`triplicata_config_parse`] that has not been freed.
+ */
+void triplicata_config_free(struct TriplicataConfig *config);
+
+#ifdef __cplusplus
+}  // extern "C"
+#endif  // __cplusplus
+
+#endif  /* TRIPLICATA_H */
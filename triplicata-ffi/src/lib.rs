@@ -0,0 +1,378 @@
+//! C bindings for embedding cube input in other applications. The generated
+//! header lives in `include/triplicata.h`.
+//!
+//! Functions returning a pointer return null on failure, after which
+//! [`triplicata_last_error`] describes what went wrong.
+
+use std::{
+    cell::RefCell,
+    ffi::{CStr, CString, c_char},
+    fmt::Display,
+    ptr,
+    time::Duration,
+};
+
+use tokio::{
+    runtime::Runtime,
+    sync::broadcast::{
+        Receiver,
+        error::{RecvError, TryRecvError},
+    },
+};
+use tokio_util::sync::CancellationToken;
+use triplicata::{
+    bluetooth::BluetoothCubeSource,
+    config::Config,
+    cube::{CubeEvent, CubeState},
+    source::CubeSource,
+};
+
+thread_local! {
+    static LAST_ERROR: RefCell<Option<CString>> = const { RefCell::new(None) };
+}
+
+fn set_last_error(error: impl Display) {
+    let message = CString::new(error.to_string().replace('\0', "")).unwrap_or_default();
+    LAST_ERROR.with(|last| *last.borrow_mut() = Some(message));
+}
+
+/// A connected cube. Owns the runtime driving its bluetooth connection.
+pub struct TriplicataCube {
+    runtime: Runtime,
+    events: Receiver<CubeEvent>,
+    cancel: CancellationToken,
+}
+
+pub struct TriplicataConfig(Config);
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum TriplicataEventKind {
+    #[default]
+    None,
+    Move,
+    StateSync,
+    Battery,
+    Orientation,
+    Connected,
+    Disconnected,
+    Lagged,
+    Timer,
+    Redemption,
+    Profile,
+    Fire,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default)]
+pub struct TriplicataCubeState {
+    pub corner_permutation: [u8; 8],
+    pub corner_orientation: [u8; 8],
+    pub edge_permutation: [u8; 12],
+    pub edge_orientation: [u8; 12],
+}
+
+/// A cube event, only the fields matching `kind` are meaningful.
+///
+/// `move_index` uses the order GAN cubes number moves in: U, U', R, R', F,
+/// F', D, D', L, L', B, B'. `orientation` is a quaternion as x, y, z, w.
+/// `dropped` is the number of events missed by a lagged receiver.
+/// `timer_state` counts disconnect, get set, hands off, running, stopped,
+/// idle, hands on and finished from 0, and `timer_ms` is the recorded time
+/// once a timer stops. `redemption` is the index of the redemption bind whose
+/// Twitch reward was redeemed. `profile` is the index of the profile the
+/// focused window switched to, and `bind` the index of the bind fired
+/// without its trigger.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default)]
+pub struct TriplicataEvent {
+    pub kind: TriplicataEventKind,
+    pub move_index: u8,
+    pub battery: u8,
+    pub orientation: [f32; 4],
+    pub state: TriplicataCubeState,
+    pub dropped: u64,
+    pub timer_state: u8,
+    pub timer_ms: u64,
+    pub redemption: u64,
+    pub profile: u64,
+    pub bind: u64,
+}
+
+impl From<CubeState> for TriplicataCubeState {
+    fn from(value: CubeState) -> Self {
+        Self {
+            corner_permutation: value.corner_permutation,
+            corner_orientation: value.corner_orientation,
+            edge_permutation: value.edge_permutation,
+            edge_orientation: value.edge_orientation,
+        }
+    }
+}
+
+impl From<CubeEvent> for TriplicataEvent {
+    fn from(value: CubeEvent) -> Self {
+        let mut event = Self::default();
+
+        match value {
+            CubeEvent::Move(m) => {
+                event.kind = TriplicataEventKind::Move;
+                event.move_index = m as u8;
+            }
+            CubeEvent::StateSync(state) => {
+                event.kind = TriplicataEventKind::StateSync;
+                event.state = state.into();
+            }
+            CubeEvent::Battery(battery) => {
+                event.kind = TriplicataEventKind::Battery;
+                event.battery = battery;
+            }
+            CubeEvent::Orientation(q) => {
+                event.kind = TriplicataEventKind::Orientation;
+                event.orientation = [q.x, q.y, q.z, q.w];
+            }
+            CubeEvent::Connected => event.kind = TriplicataEventKind::Connected,
+            CubeEvent::Disconnected => event.kind = TriplicataEventKind::Disconnected,
+            CubeEvent::Lagged(dropped) => {
+                event.kind = TriplicataEventKind::Lagged;
+                event.dropped = dropped;
+            }
+            CubeEvent::Timer(timer) => {
+                event.kind = TriplicataEventKind::Timer;
+                event.timer_state = timer.state as u8;
+                event.timer_ms = timer.time.map_or(0, |time| time.as_millis() as u64);
+            }
+            CubeEvent::Redemption(bind) => {
+                event.kind = TriplicataEventKind::Redemption;
+                event.redemption = bind as u64;
+            }
+            CubeEvent::Profile(profile) => {
+                event.kind = TriplicataEventKind::Profile;
+                event.profile = profile as u64;
+            }
+            CubeEvent::Fire(bind) => {
+                event.kind = TriplicataEventKind::Fire;
+                event.bind = bind as u64;
+            }
+        }
+
+        event
+    }
+}
+
+/// Returns the last error raised on this thread, or null. The string is owned
+/// by the library and valid until the next call on this thread.
+#[unsafe(no_mangle)]
+pub extern "C" fn triplicata_last_error() -> *const c_char {
+    LAST_ERROR.with(|last| {
+        last.borrow()
+            .as_ref()
+            .map_or(ptr::null(), |message| message.as_ptr())
+    })
+}
+
+/// Scans for and connects to the first GAN cube found on the given bluetooth
+/// adapter, blocking until connected.
+#[unsafe(no_mangle)]
+pub extern "C" fn triplicata_connect(adapter: usize) -> *mut TriplicataCube {
+    let runtime = match Runtime::new() {
+        Ok(runtime) => runtime,
+        Err(e) => {
+            set_last_error(e);
+            return ptr::null_mut();
+        }
+    };
+
+    let cancel = CancellationToken::new();
+    let source = BluetoothCubeSource::new().adapter(adapter);
+
+    match runtime.block_on(source.connect(cancel.clone())) {
+        Ok(events) => Box::into_raw(Box::new(TriplicataCube {
+            runtime,
+            events,
+            cancel,
+        })),
+        Err(e) => {
+            set_last_error(e);
+            ptr::null_mut()
+        }
+    }
+}
+
+/// Disconnects the cube and frees it.
+///
+/// # Safety
+///
+/// `cube` must be null or a pointer returned by [`triplicata_connect`] that
+/// has not been freed.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn triplicata_disconnect(cube: *mut TriplicataCube) {
+    if !cube.is_null() {
+        let mut cube = unsafe { Box::from_raw(cube) };
+        cube.cancel.cancel();
+
+        // Let the connection task see the cancellation and disconnect.
+        let _ = cube.runtime.block_on(async {
+            tokio::time::timeout(Duration::from_secs(1), async {
+                while !matches!(cube.events.recv().await, Err(RecvError::Closed)) {}
+            })
+            .await
+        });
+    }
+}
+
+fn next_event(
+    events: &mut Receiver<CubeEvent>,
+    event: &mut TriplicataEvent,
+) -> Result<bool, TryRecvError> {
+    let value = match events.try_recv() {
+        Ok(value) => value,
+        Err(TryRecvError::Lagged(count)) => CubeEvent::Lagged(count),
+        Err(TryRecvError::Empty) => return Ok(false),
+        Err(e) => return Err(e),
+    };
+
+    *event = value.into();
+    Ok(true)
+}
+
+/// Writes the next pending event into `event` without blocking. Returns
+/// whether an event was written.
+///
+/// # Safety
+///
+/// `cube` must be a live pointer from [`triplicata_connect`] and `event` must
+/// be valid for writes.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn triplicata_poll_event(
+    cube: *mut TriplicataCube,
+    event: *mut TriplicataEvent,
+) -> bool {
+    let (Some(cube), Some(event)) = (unsafe { cube.as_mut() }, unsafe { event.as_mut() }) else {
+        return false;
+    };
+
+    next_event(&mut cube.events, event).unwrap_or_else(|e| {
+        set_last_error(e);
+        false
+    })
+}
+
+/// Blocks for up to `timeout_ms` milliseconds waiting for an event. Returns
+/// whether an event was written.
+///
+/// # Safety
+///
+/// `cube` must be a live pointer from [`triplicata_connect`] and `event` must
+/// be valid for writes.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn triplicata_wait_event(
+    cube: *mut TriplicataCube,
+    timeout_ms: u64,
+    event: *mut TriplicataEvent,
+) -> bool {
+    let (Some(cube), Some(event)) = (unsafe { cube.as_mut() }, unsafe { event.as_mut() }) else {
+        return false;
+    };
+
+    let TriplicataCube {
+        runtime, events, ..
+    } = cube;
+
+    runtime.block_on(async {
+        let received = tokio::time::timeout(Duration::from_millis(timeout_ms), events.recv()).await;
+
+        match received {
+            Ok(Ok(value)) => {
+                *event = value.into();
+                true
+            }
+            Ok(Err(RecvError::Lagged(count))) => {
+                *event = CubeEvent::Lagged(count).into();
+                true
+            }
+            Ok(Err(e)) => {
+                set_last_error(e);
+                false
+            }
+            Err(_) => false,
+        }
+    })
+}
+
+fn config_into_raw(config: Result<Config, impl Display>) -> *mut TriplicataConfig {
+    match config {
+        Ok(config) => Box::into_raw(Box::new(TriplicataConfig(config))),
+        Err(e) => {
+            set_last_error(e);
+            ptr::null_mut()
+        }
+    }
+}
+
+/// Loads a config file from `path`.
+///
+/// # Safety
+///
+/// `path` must be a valid nul terminated string.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn triplicata_config_load(path: *const c_char) -> *mut TriplicataConfig {
+    if path.is_null() {
+        set_last_error("path is null");
+        return ptr::null_mut();
+    }
+
+    match unsafe { CStr::from_ptr(path) }.to_str() {
+        Ok(path) => config_into_raw(Config::load(path)),
+        Err(e) => config_into_raw(Err(e)),
+    }
+}
+
+/// Parses a config from its source text.
+///
+/// # Safety
+///
+/// `source` must be a valid nul terminated string.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn triplicata_config_parse(source: *const c_char) -> *mut TriplicataConfig {
+    if source.is_null() {
+        set_last_error("source is null");
+        return ptr::null_mut();
+    }
+
+    match unsafe { CStr::from_ptr(source) }.to_str() {
+        Ok(source) => config_into_raw(source.parse()),
+        Err(e) => config_into_raw(Err(e)),
+    }
+}
+
+/// # Safety
+///
+/// `config` must be a live pointer from [`triplicata_config_load`] or
+/// [`triplicata_config_parse`].
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn triplicata_config_bind_count(config: *const TriplicataConfig) -> usize {
+    unsafe { config.as_ref() }.map_or(0, |config| config.0.binds.len())
+}
+
+/// # Safety
+///
+/// `config` must be a live pointer from [`triplicata_config_load`] or
+/// [`triplicata_config_parse`].
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn triplicata_config_timeout(config: *const TriplicataConfig) -> u64 {
+    unsafe { config.as_ref() }.map_or(0, |config| config.0.timeout)
+}
+
+/// Frees a config.
+///
+/// # Safety
+///
+/// `config` must be null or a pointer from [`triplicata_config_load`] or
+/// [`triplicata_config_parse`] that has not been freed.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn triplicata_config_free(config: *mut TriplicataConfig) {
+    if !config.is_null() {
+        drop(unsafe { Box::from_raw(config) });
+    }
+}
@@ -0,0 +1,261 @@
+//! Python bindings exposing the cube event stream as an async iterator.
+//!
+//! ```python
+//! import asyncio, triplicata
+//!
+//! async def main():
+//!     cube = await triplicata.Cube.connect()
+//!     async for event in cube:
+//!         print(event)
+//!
+//! asyncio.run(main())
+//! ```
+
+use std::sync::Arc;
+
+use pyo3::{
+    exceptions::{PyRuntimeError, PyStopAsyncIteration, PyValueError},
+    prelude::*,
+};
+use tokio::sync::{
+    Mutex,
+    broadcast::{Receiver, error::RecvError},
+};
+use tokio_util::sync::CancellationToken;
+use triplicata::{
+    algorithm::Algorithm,
+    bluetooth::BluetoothCubeSource,
+    cube::{CubeEvent, CubeState},
+    source::CubeSource,
+};
+
+#[pyclass(frozen, eq, name = "CubeState")]
+#[derive(Clone, PartialEq)]
+struct PyCubeState(CubeState);
+
+#[pymethods]
+impl PyCubeState {
+    #[new]
+    fn new() -> Self {
+        Self(CubeState::SOLVED)
+    }
+
+    #[getter]
+    fn corner_permutation(&self) -> [u8; 8] {
+        self.0.corner_permutation
+    }
+
+    #[getter]
+    fn corner_orientation(&self) -> [u8; 8] {
+        self.0.corner_orientation
+    }
+
+    #[getter]
+    fn edge_permutation(&self) -> [u8; 12] {
+        self.0.edge_permutation
+    }
+
+    #[getter]
+    fn edge_orientation(&self) -> [u8; 12] {
+        self.0.edge_orientation
+    }
+
+    fn is_solved(&self) -> bool {
+        self.0.is_solved()
+    }
+
+    fn __repr__(&self) -> String {
+        format!("{:?}", self.0)
+    }
+}
+
+/// A single cube event. `kind` is one of `"move"`, `"state"`, `"battery"`,
+/// `"orientation"`, `"connected"`, `"disconnected"`, `"lagged"`, `"timer"`,
+/// `"redemption"`, `"profile"` or `"fire"`, and only the matching attributes
+/// are set.
+#[pyclass(frozen, get_all, name = "CubeEvent")]
+#[derive(Clone)]
+struct PyCubeEvent {
+    kind: &'static str,
+    #[pyo3(name = "move")]
+    turn: Option<String>,
+    state: Option<PyCubeState>,
+    battery: Option<u8>,
+    orientation: Option<(f32, f32, f32, f32)>,
+    dropped: Option<u64>,
+    timer_state: Option<String>,
+    /// Seconds recorded by a timer that stopped.
+    timer_time: Option<f64>,
+    /// The index of the redemption bind whose Twitch reward was redeemed.
+    redemption: Option<usize>,
+    /// The index of the profile the focused window switched to.
+    profile: Option<usize>,
+    /// The index of the bind fired without its trigger.
+    bind: Option<usize>,
+}
+
+impl From<CubeEvent> for PyCubeEvent {
+    fn from(value: CubeEvent) -> Self {
+        let mut event = Self {
+            kind: "",
+            turn: None,
+            state: None,
+            battery: None,
+            orientation: None,
+            dropped: None,
+            timer_state: None,
+            timer_time: None,
+            redemption: None,
+            profile: None,
+            bind: None,
+        };
+
+        match value {
+            CubeEvent::Move(m) => {
+                event.kind = "move";
+                event.turn = Some(m.to_notation());
+            }
+            CubeEvent::StateSync(state) => {
+                event.kind = "state";
+                event.state = Some(PyCubeState(state));
+            }
+            CubeEvent::Battery(battery) => {
+                event.kind = "battery";
+                event.battery = Some(battery);
+            }
+            CubeEvent::Orientation(q) => {
+                event.kind = "orientation";
+                event.orientation = Some((q.x, q.y, q.z, q.w));
+            }
+            CubeEvent::Connected => event.kind = "connected",
+            CubeEvent::Disconnected => event.kind = "disconnected",
+            CubeEvent::Lagged(dropped) => {
+                event.kind = "lagged";
+                event.dropped = Some(dropped);
+            }
+            CubeEvent::Timer(timer) => {
+                event.kind = "timer";
+                event.timer_state = Some(format!("{:?}", timer.state));
+                event.timer_time = timer.time.map(|time| time.as_secs_f64());
+            }
+            CubeEvent::Redemption(bind) => {
+                event.kind = "redemption";
+                event.redemption = Some(bind);
+            }
+            CubeEvent::Profile(profile) => {
+                event.kind = "profile";
+                event.profile = Some(profile);
+            }
+            CubeEvent::Fire(bind) => {
+                event.kind = "fire";
+                event.bind = Some(bind);
+            }
+        }
+
+        event
+    }
+}
+
+#[pymethods]
+impl PyCubeEvent {
+    fn __repr__(&self) -> String {
+        match self.kind {
+            "move" => format!("CubeEvent(move={:?})", self.turn.as_deref().unwrap_or("")),
+            "battery" => format!("CubeEvent(battery={})", self.battery.unwrap_or(0)),
+            "orientation" => format!("CubeEvent(orientation={:?})", self.orientation),
+            "lagged" => format!("CubeEvent(dropped={})", self.dropped.unwrap_or(0)),
+            "timer" => format!(
+                "CubeEvent(timer={}, time={:?})",
+                self.timer_state.as_deref().unwrap_or(""),
+                self.timer_time
+            ),
+            "redemption" => format!("CubeEvent(redemption={})", self.redemption.unwrap_or(0)),
+            "profile" => format!("CubeEvent(profile={})", self.profile.unwrap_or(0)),
+            "fire" => format!("CubeEvent(bind={})", self.bind.unwrap_or(0)),
+            kind => format!("CubeEvent({kind})"),
+        }
+    }
+}
+
+async fn next_event(events: Arc<Mutex<Receiver<CubeEvent>>>) -> Option<PyCubeEvent> {
+    let mut events = events.lock().await;
+
+    match events.recv().await {
+        Ok(event) => Some(event.into()),
+        Err(RecvError::Lagged(count)) => Some(CubeEvent::Lagged(count).into()),
+        Err(RecvError::Closed) => None,
+    }
+}
+
+/// A connected cube, iterate it with `async for` to receive events.
+#[pyclass(frozen)]
+struct Cube {
+    events: Arc<Mutex<Receiver<CubeEvent>>>,
+    cancel: CancellationToken,
+}
+
+#[pymethods]
+impl Cube {
+    /// Scans for and connects to the first GAN cube found on `adapter`.
+    #[staticmethod]
+    #[pyo3(signature = (adapter = 0))]
+    fn connect(py: Python<'_>, adapter: usize) -> PyResult<Bound<'_, PyAny>> {
+        pyo3_async_runtimes::tokio::future_into_py(py, async move {
+            let cancel = CancellationToken::new();
+            let events = BluetoothCubeSource::new()
+                .adapter(adapter)
+                .connect(cancel.clone())
+                .await
+                .map_err(|e| PyRuntimeError::new_err(e.to_string()))?;
+
+            Ok(Cube {
+                events: Arc::new(Mutex::new(events)),
+                cancel,
+            })
+        })
+    }
+
+    /// Disconnects the cube, ending iteration once pending events are read.
+    fn disconnect(&self) {
+        self.cancel.cancel();
+    }
+
+    /// Waits for the next event, returning `None` once the cube is gone.
+    fn next_event<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyAny>> {
+        let events = self.events.clone();
+        pyo3_async_runtimes::tokio::future_into_py(py, async move { Ok(next_event(events).await) })
+    }
+
+    fn __aiter__(slf: Py<Self>) -> Py<Self> {
+        slf
+    }
+
+    fn __anext__<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyAny>> {
+        let events = self.events.clone();
+        pyo3_async_runtimes::tokio::future_into_py(py, async move {
+            next_event(events)
+                .await
+                .ok_or_else(|| PyStopAsyncIteration::new_err(()))
+        })
+    }
+}
+
+/// Expands an algorithm in standard or SiGN notation into the face turns a
+/// cube would report.
+#[pyfunction]
+fn parse_algorithm(algorithm: &str) -> PyResult<Vec<String>> {
+    algorithm
+        .parse::<Algorithm>()
+        .and_then(|algorithm| algorithm.to_moves())
+        .map(|moves| moves.into_iter().map(|m| m.to_notation()).collect())
+        .map_err(|e| PyValueError::new_err(e.to_string()))
+}
+
+#[pymodule(name = "triplicata")]
+fn triplicata_module(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_class::<Cube>()?;
+    m.add_class::<PyCubeEvent>()?;
+    m.add_class::<PyCubeState>()?;
+    m.add_function(wrap_pyfunction!(parse_algorithm, m)?)?;
+    Ok(())
+}